@@ -0,0 +1,6 @@
+CREATE TABLE acknowledgements (
+  id SERIAL PRIMARY KEY,
+  event_key VARCHAR NOT NULL,
+  acknowledged_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  resolved_at TIMESTAMPTZ
+);
@@ -0,0 +1,4 @@
+CREATE TABLE last_alerted (
+  event_key VARCHAR PRIMARY KEY,
+  alerted_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,10 @@
+CREATE TABLE dead_letters (
+  id SERIAL PRIMARY KEY,
+  medium VARCHAR NOT NULL,
+  subject VARCHAR NOT NULL,
+  body TEXT NOT NULL,
+  error VARCHAR NOT NULL,
+  attempts INT NOT NULL,
+  failed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  replayed_at TIMESTAMPTZ
+);
@@ -0,0 +1 @@
+ALTER TABLE dead_letters ADD COLUMN text_body TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,6 @@
+CREATE TABLE outbox (
+  id SERIAL PRIMARY KEY,
+  event TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  delivered_at TIMESTAMPTZ
+);
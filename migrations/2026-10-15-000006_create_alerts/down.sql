@@ -0,0 +1,4 @@
+ALTER TABLE dead_letters DROP COLUMN event_keys;
+ALTER TABLE dead_letters DROP COLUMN event_type;
+
+DROP TABLE alerts;
@@ -0,0 +1,13 @@
+CREATE TABLE alerts (
+  id SERIAL PRIMARY KEY,
+  event_type VARCHAR,
+  event_keys TEXT[] NOT NULL,
+  medium VARCHAR NOT NULL,
+  subject VARCHAR NOT NULL,
+  outcome VARCHAR NOT NULL,
+  error VARCHAR,
+  sent_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+ALTER TABLE dead_letters ADD COLUMN event_type VARCHAR;
+ALTER TABLE dead_letters ADD COLUMN event_keys TEXT[] NOT NULL DEFAULT '{}';
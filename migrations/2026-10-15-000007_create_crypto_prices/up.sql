@@ -0,0 +1,10 @@
+CREATE TABLE crypto_prices (
+  id SERIAL PRIMARY KEY,
+  asset VARCHAR NOT NULL,
+  currency VARCHAR NOT NULL,
+  price DOUBLE PRECISION NOT NULL,
+  percent_change_24h DOUBLE PRECISION,
+  recorded_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX crypto_prices_asset_recorded_at ON crypto_prices (asset, recorded_at);
@@ -0,0 +1,8 @@
+CREATE TABLE github_seen (
+  id SERIAL PRIMARY KEY,
+  kind VARCHAR NOT NULL,
+  repository VARCHAR NOT NULL,
+  item_id VARCHAR NOT NULL,
+  seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  UNIQUE (kind, item_id)
+);
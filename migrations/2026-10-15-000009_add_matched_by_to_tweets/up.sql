@@ -0,0 +1 @@
+ALTER TABLE tweets ADD COLUMN matched_by VARCHAR NOT NULL DEFAULT 'keyword';
@@ -0,0 +1 @@
+ALTER TABLE tweets ADD COLUMN sentiment FLOAT8;
@@ -0,0 +1,5 @@
+CREATE TABLE api_cache (
+  key VARCHAR PRIMARY KEY,
+  body TEXT NOT NULL,
+  fetched_at TIMESTAMPTZ NOT NULL
+);
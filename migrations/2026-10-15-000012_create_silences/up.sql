@@ -0,0 +1,8 @@
+CREATE TABLE silences (
+  id SERIAL PRIMARY KEY,
+  event_type VARCHAR,
+  event_key VARCHAR,
+  comment VARCHAR,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1 @@
+DROP TABLE disk_usage_rollups;
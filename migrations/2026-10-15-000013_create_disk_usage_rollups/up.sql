@@ -0,0 +1,9 @@
+CREATE TABLE disk_usage_rollups (
+  id SERIAL PRIMARY KEY,
+  mount VARCHAR NOT NULL,
+  hour TIMESTAMPTZ NOT NULL,
+  average_percent_used FLOAT8 NOT NULL,
+  max_percent_used FLOAT8 NOT NULL,
+  samples BIGINT NOT NULL,
+  UNIQUE (mount, hour)
+);
@@ -0,0 +1,16 @@
+CREATE TABLE digests (
+  id SERIAL PRIMARY KEY,
+  sent_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE articles (
+  id SERIAL PRIMARY KEY,
+  digest_id INTEGER NOT NULL REFERENCES digests (id) ON DELETE CASCADE,
+  url VARCHAR NOT NULL,
+  title VARCHAR NOT NULL,
+  source VARCHAR NOT NULL,
+  summary TEXT NOT NULL,
+  published_date DATE NOT NULL
+);
+
+CREATE INDEX articles_url ON articles (url);
@@ -0,0 +1,11 @@
+CREATE TABLE task_runs (
+  id SERIAL PRIMARY KEY,
+  task VARCHAR NOT NULL,
+  runner VARCHAR NOT NULL,
+  succeeded BOOLEAN NOT NULL,
+  error TEXT,
+  duration_ms BIGINT NOT NULL,
+  started_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX task_runs_task_started_at ON task_runs (task, started_at);
@@ -0,0 +1,6 @@
+CREATE TABLE completed_tasks (
+  task VARCHAR NOT NULL,
+  scheduled_for TIMESTAMPTZ NOT NULL,
+  completed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (task, scheduled_for)
+);
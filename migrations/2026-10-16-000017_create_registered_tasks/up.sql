@@ -0,0 +1,5 @@
+CREATE TABLE registered_tasks (
+  id SERIAL PRIMARY KEY,
+  config TEXT NOT NULL,
+  registered_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
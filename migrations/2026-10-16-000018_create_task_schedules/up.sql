@@ -0,0 +1,4 @@
+CREATE TABLE task_schedules (
+  task VARCHAR PRIMARY KEY,
+  next_run TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,3 @@
+DROP INDEX tasks_task_key_sent_at;
+
+ALTER TABLE tasks DROP COLUMN task_key;
@@ -0,0 +1,3 @@
+ALTER TABLE tasks ADD COLUMN task_key VARCHAR;
+
+CREATE INDEX tasks_task_key_sent_at ON tasks (task_key, sent_at);
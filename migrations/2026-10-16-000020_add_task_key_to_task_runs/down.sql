@@ -0,0 +1,3 @@
+DROP INDEX task_runs_task_key_started_at;
+
+ALTER TABLE task_runs DROP COLUMN task_key;
@@ -0,0 +1,3 @@
+ALTER TABLE task_runs ADD COLUMN task_key VARCHAR;
+
+CREATE INDEX task_runs_task_key_started_at ON task_runs (task_key, started_at);
@@ -0,0 +1 @@
+ALTER TABLE task_schedules RENAME COLUMN task_key TO task;
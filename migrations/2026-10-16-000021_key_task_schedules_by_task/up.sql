@@ -0,0 +1,5 @@
+-- schedules were saved under task messages, which several tasks can
+-- share, so they can't be matched to a task
+DELETE FROM task_schedules;
+
+ALTER TABLE task_schedules RENAME COLUMN task TO task_key;
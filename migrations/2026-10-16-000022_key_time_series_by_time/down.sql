@@ -0,0 +1,2 @@
+ALTER TABLE crypto_prices DROP CONSTRAINT crypto_prices_pkey, ADD PRIMARY KEY (id);
+ALTER TABLE disk_usage DROP CONSTRAINT disk_usage_pkey, ADD PRIMARY KEY (id);
@@ -0,0 +1,4 @@
+-- timescale needs the time column in every unique index of a
+-- hypertable, so the time series tables are keyed by it too
+ALTER TABLE disk_usage DROP CONSTRAINT disk_usage_pkey, ADD PRIMARY KEY (id, recorded_at);
+ALTER TABLE crypto_prices DROP CONSTRAINT crypto_prices_pkey, ADD PRIMARY KEY (id, recorded_at);
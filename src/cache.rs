@@ -0,0 +1,67 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    config::config,
+    db::{database, models},
+    error::Result,
+};
+
+/// Responses from upstream apis are cached in the database so that
+/// repeated fetches, including those after a restart, don't count
+/// against rate limits. `request` identifies the response within the
+/// provider, and `default_ttl` is how long it is cached for unless the
+/// provider's ttl is configured. A ttl of zero disables caching
+pub fn cached<T, F>(provider: &str, request: &str, default_ttl: Duration, fetch: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Result<T>,
+{
+    let ttl = config()
+        .cache
+        .and_then(|cache| cache.ttls.get(provider).cloned())
+        .unwrap_or(default_ttl);
+    if ttl == Duration::from_secs(0) {
+        return fetch();
+    }
+
+    let key = cache_key(provider, request);
+    let now = Utc::now().naive_utc();
+    match database().cached_response(&key) {
+        Ok(Some(entry)) if now - entry.fetched_at < chrono::Duration::from_std(ttl).unwrap() => {
+            match serde_json::from_str(&entry.body) {
+                Ok(response) => return Ok(response),
+                Err(e) => log::warn!("Error reading cached {} response: {}", provider, e),
+            }
+        }
+        Ok(_) => (),
+        Err(e) => log::warn!("Error loading cached {} response: {}", provider, e),
+    }
+
+    let response = fetch()?;
+    serde_json::to_string(&response)
+        .map_err(Into::into)
+        .and_then(|body| {
+            database().upsert_cached_response(models::CachedResponse {
+                key,
+                body,
+                fetched_at: now,
+            })
+        })
+        .unwrap_or_else(|e| log::warn!("Error caching {} response: {}", provider, e));
+
+    Ok(response)
+}
+
+/// Requests are hashed so that api keys in them aren't stored
+fn cache_key(provider: &str, request: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    request.hash(&mut hasher);
+    format!("{}:{:x}", provider, hasher.finish())
+}
@@ -1,16 +1,25 @@
-use std::{fs::File, io::Read, path::PathBuf, str::FromStr, sync::Mutex, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::File,
+    io::Read,
+    path::PathBuf,
+    str::FromStr,
+    sync::Mutex,
+    time::Duration,
+};
 
-use chrono::Local;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Utc};
 use cron::Schedule as CronSchedule;
 use lazy_static::lazy_static;
 use nytrs::request::{MostPopularPeriod, ShareType};
-use serde::Deserialize;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
     constants,
-    error::Result,
+    error::{Error, Result},
     services::{
-        broadcast::{BroadcastEventType, BroadcastMedium},
+        broadcast::{BroadcastEventType, BroadcastMedium, Severity},
         scheduler::{ScheduledStreamMessage, ScheduledTaskMessage},
     },
 };
@@ -38,132 +47,1079 @@ fn config_file() -> Result<PathBuf> {
 
 /// Initialize the CONFIG object from the config file
 pub fn initialize_from_file() -> Result<()> {
+    initialize_from(read_file()?);
+
+    Ok(())
+}
+
+/// Read and validate the config file, without applying it
+pub fn read_file() -> Result<Config> {
     let mut contents = String::new();
 
     let mut config_file = File::open(config_file()?)?;
     config_file.read_to_string(&mut contents)?;
 
     let config: Config = toml::from_str(&contents)?;
+    config.validate()?;
 
-    initialize_from(config);
-
-    Ok(())
+    Ok(config)
 }
 
 pub fn initialize_from(config: Config) {
     *CONFIG.lock().unwrap() = Some(config);
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// How the connection to the SMTP server is secured
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SmtpTls {
+    /// Plain text, usually on port 25
+    None,
+    /// Upgrade to TLS with STARTTLS, usually on port 587
+    Starttls,
+    /// Connect with TLS from the start, usually on port 465
+    Implicit,
+}
+
+impl SmtpTls {
+    pub fn default_port(self) -> u16 {
+        match self {
+            SmtpTls::None => 25,
+            SmtpTls::Starttls => 587,
+            SmtpTls::Implicit => 465,
+        }
+    }
+}
+
+/// SMTP settings for email alerts. `tls` defaults to implicit, and
+/// `port` to the usual port for the `tls` mode
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct EmailConfig {
     pub smtp_host: String,
+    pub port: Option<u16>,
+    pub tls: Option<SmtpTls>,
+    /// The name to introduce ourselves to the server with in `EHLO`
+    pub hello_name: Option<String>,
+    pub timeout: Option<Duration>,
     pub username: String,
-    pub password: String,
+    /// Either a password or `oauth2` is required
+    pub password: Option<String>,
+    pub oauth2: Option<OAuth2Config>,
     pub recipients: Vec<String>,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// Authenticate to the SMTP server with XOAUTH2, using access tokens
+/// from `token_url` (e.g. `https://oauth2.googleapis.com/token`)
+/// refreshed with `refresh_token`
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct OAuth2Config {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub refresh_token: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct BroadcastConfig {
     pub email: Option<EmailConfig>,
     pub alerts: Vec<AlertConfig>,
+    pub maintenance_windows: Option<Vec<MaintenanceWindowConfig>>,
+    pub retry: Option<RetryConfig>,
+    pub dead_letter_replay_interval: Option<Duration>,
+    pub quiet_hours: Option<Vec<QuietHoursConfig>>,
+    /// Write pending events to the database so that they survive a
+    /// crash
+    pub durable_outbox: Option<bool>,
+    pub outbox: Option<OutboxConfig>,
+}
+
+/// What happens to a new event when the outbox is full
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Refuse the new event
+    Reject,
+    /// Drop the oldest of the least severe queued events
+    DropOldest,
+    /// Replace a queued event with the same key, refusing the new
+    /// event if there is none
+    CoalesceByKey,
+}
+
+/// The size of the outbox and what happens when it fills up. Defaults
+/// to rejecting new events once 100,000 are queued
+#[derive(Clone, Deserialize, Serialize, Debug, Default)]
+pub struct OutboxConfig {
+    pub capacity: Option<usize>,
+    pub overflow: Option<OverflowPolicy>,
+}
+
+/// How failed deliveries are retried. The backoff doubles after each
+/// failed attempt, up to `max_backoff`
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryConfig {
+    /// How long to wait before retrying after the given attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let multiplier = 2_u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .checked_mul(multiplier)
+            .map(|backoff| backoff.min(self.max_backoff))
+            .unwrap_or(self.max_backoff)
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+        }
+    }
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// A period during which alerts are suppressed, either a daily time
+/// range from `start` to `end` (local time), or a window of `duration`
+/// beginning at every occurrence of `cron`. Windows apply to every
+/// event unless limited to an `event` type or a single event `key`
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct MaintenanceWindowConfig {
+    pub event: Option<BroadcastEventType>,
+    pub key: Option<String>,
+    pub start: Option<NaiveTime>,
+    pub end: Option<NaiveTime>,
+    pub cron: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+/// A daily time range from `start` to `end` in `timezone` (e.g.
+/// `America/New_York`) during which alerts of the given `severities`
+/// are held and sent as a summary once the range is over. Defaults to
+/// holding every alert that isn't critical
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct QuietHoursConfig {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub timezone: String,
+    pub severities: Option<Vec<Severity>>,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct FilesystemConfig {
     pub mount: PathBuf,
     pub available_space_alert_above: f64,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct SystemMonitorConfig {
     pub filesystems: Vec<FilesystemConfig>,
     pub tick_ms: u64,
+    /// Minutes of recorded disk usage sent to websocket clients when
+    /// they connect, 10 by default
+    pub backfill_minutes: Option<i64>,
+    /// Roll disk usage older than this many days up into hourly
+    /// averages, and delete the samples
+    pub rollup_after_days: Option<i64>,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct NewYorkTimesConfig {
     pub api_key: String,
+    #[serde(serialize_with = "serialize_period")]
     pub most_popular_viewed_period: Option<MostPopularPeriod>,
+    #[serde(serialize_with = "serialize_period")]
     pub most_popular_emailed_days: Option<MostPopularPeriod>,
+    #[serde(serialize_with = "serialize_period")]
     pub most_popular_shared_period: Option<MostPopularPeriod>,
+    #[serde(serialize_with = "serialize_share_types")]
     pub most_popular_shared_mediums: Vec<ShareType>,
+    /// Top Stories sections to include, e.g. `technology`
+    pub top_stories_sections: Option<Vec<String>>,
+}
+
+/// nytrs types can only be deserialized, so they are written out by
+/// name
+fn serialize_period<S: Serializer>(
+    period: &Option<MostPopularPeriod>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    period
+        .as_ref()
+        .map(|period| format!("{:?}", period))
+        .serialize(serializer)
 }
 
-#[derive(Clone, Deserialize, Debug)]
+fn serialize_share_types<S: Serializer>(
+    share_types: &[ShareType],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    share_types
+        .iter()
+        .map(|share_type| format!("{:?}", share_type))
+        .collect::<Vec<_>>()
+        .serialize(serializer)
+}
+
+/// Units of short durations, and their length in seconds
+const SHORT_DURATION_UNITS: &[(&str, u64)] =
+    &[("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60), ("s", 1)];
+
+/// The longest short duration, about 100 years, so that any short
+/// duration can be added to the current time
+const MAX_SHORT_DURATION: Duration = Duration::from_secs(36500 * 24 * 60 * 60);
+
+/// Parse a duration written as a number and a unit, one of `s`, `m`,
+/// `h` or `d`, e.g. `15m`, of at most `36500d`
+fn parse_short_duration(duration: &str) -> Result<Duration> {
+    let invalid = || Error::invalid_config(format!("invalid duration {:?}", duration));
+    let duration = duration.trim();
+    let unit_start = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = duration.split_at(unit_start);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    SHORT_DURATION_UNITS
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .and_then(|(_, seconds)| amount.checked_mul(*seconds))
+        .map(Duration::from_secs)
+        .filter(|duration| *duration <= MAX_SHORT_DURATION)
+        .ok_or_else(invalid)
+}
+
+/// Write a duration in the largest unit that it is a whole number of
+pub fn format_short_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    SHORT_DURATION_UNITS
+        .iter()
+        .find(|(_, length)| seconds % length == 0)
+        .map(|(name, length)| format!("{}{}", seconds / length, name))
+        .unwrap_or_else(|| format!("{}s", seconds))
+}
+
+fn deserialize_short_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Duration>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|duration| parse_short_duration(&duration).map_err(de::Error::custom))
+        .transpose()
+}
+
+fn serialize_short_duration<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    duration.map(format_short_duration).serialize(serializer)
+}
+
+/// Articles from The Guardian's content api, a section of the latest
+/// articles for each of `sections` (e.g. `technology`), limited to
+/// articles with all of `tags` (e.g. `technology/artificialintelligenceai`)
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct GuardianConfig {
+    pub api_key: String,
+    pub sections: Option<Vec<String>>,
+    pub tags: Option<Vec<String>>,
+    /// The number of articles in each section
+    pub page_size: Option<u32>,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct NewsConfig {
     pub new_york_times: Option<NewYorkTimesConfig>,
+    pub guardian: Option<GuardianConfig>,
+    /// Urls of RSS or Atom feeds
+    pub feeds: Option<Vec<String>>,
+    pub reddit: Option<RedditConfig>,
+    pub weather: Option<WeatherConfig>,
+    pub calendar: Option<CalendarConfig>,
+    pub youtube: Option<YouTubeConfig>,
+}
+
+/// YouTube channels, by id. Uploads to `channels` are included in the
+/// newscast, and uploads to `alert_channels` are checked for every
+/// `poll_interval` (15 minutes by default) and broadcast as they appear
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct YouTubeConfig {
+    /// YouTube Data API key
+    pub api_key: String,
+    #[serde(default)]
+    pub channels: Vec<String>,
+    pub alert_channels: Option<Vec<String>>,
+    pub poll_interval: Option<Duration>,
+}
+
+/// How posts in a subreddit are sorted
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RedditSort {
+    Hot,
+    New,
+    Top,
+    Rising,
+}
+
+/// Displays the sort as it is written in the config and in reddit
+/// urls, e.g. `hot`
+impl fmt::Display for RedditSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        name.trim_matches('"').fmt(f)
+    }
+}
+
+/// Posts from these subreddits, sorted by `sort` (hot by default), are
+/// included in the newscast if their score is at least `min_score`
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct RedditConfig {
+    pub subreddits: Vec<String>,
+    pub sort: Option<RedditSort>,
+    pub min_score: Option<i64>,
+    /// The number of posts to fetch from each subreddit
+    pub limit: Option<u32>,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WeatherUnits {
+    Metric,
+    Imperial,
+}
+
+impl WeatherUnits {
+    pub fn temperature_symbol(self) -> &'static str {
+        match self {
+            WeatherUnits::Metric => "°C",
+            WeatherUnits::Imperial => "°F",
+        }
+    }
+}
+
+/// Displays the units as they are written in the config and in
+/// OpenWeatherMap requests, e.g. `metric`
+impl fmt::Display for WeatherUnits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        name.trim_matches('"').fmt(f)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct WeatherLocationConfig {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Today's forecast for each location is included in the newscast.
+/// With `alert_on_warnings`, weather warnings for the locations are
+/// checked every `warning_check_interval` (15 minutes by default) and
+/// broadcast as soon as they are issued
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct WeatherConfig {
+    /// OpenWeatherMap api key
+    pub api_key: String,
+    pub locations: Vec<WeatherLocationConfig>,
+    /// Metric by default
+    pub units: Option<WeatherUnits>,
+    pub alert_on_warnings: Option<bool>,
+    pub warning_check_interval: Option<Duration>,
+}
+
+/// Today's and tomorrow's events from iCal `urls` are included in the
+/// newscast. With `remind_minutes_before`, the calendars are checked
+/// every `reminder_check_interval` (1 minute by default) and a reminder
+/// is sent that many minutes before each event whose summary contains
+/// one of `remind_of`, or before every event if `remind_of` isn't set
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct CalendarConfig {
+    pub urls: Vec<String>,
+    pub remind_minutes_before: Option<i64>,
+    pub remind_of: Option<Vec<String>>,
+    pub reminder_check_interval: Option<Duration>,
+}
+
+/// Stock prices from Finnhub. Closing prices are included in the
+/// newscast with `include_in_digest`, and with `move_alert_percent`
+/// the tickers are checked every `poll_interval` (5 minutes by
+/// default) and alerted when they move more than that percent from
+/// their previous close
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct MarketsConfig {
+    /// Finnhub api key
+    pub api_key: String,
+    pub tickers: Vec<String>,
+    pub include_in_digest: Option<bool>,
+    pub move_alert_percent: Option<f64>,
+    pub poll_interval: Option<Duration>,
+}
+
+/// Alert when an asset's price is at or `above`, or at or `below` a
+/// threshold, or has changed by `change_alert_percent` in 24 hours
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct CryptoAssetConfig {
+    /// CoinGecko id of the asset, e.g. `bitcoin`
+    pub id: String,
+    pub above: Option<f64>,
+    pub below: Option<f64>,
+    pub change_alert_percent: Option<f64>,
+}
+
+/// Cryptocurrency prices from CoinGecko, checked and recorded every
+/// `poll_interval` (5 minutes by default) in `currency` (`usd` by
+/// default)
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct CryptoConfig {
+    pub currency: Option<String>,
+    pub assets: Vec<CryptoAssetConfig>,
+    pub poll_interval: Option<Duration>,
+}
+
+/// Releases in `repositories` (e.g. `rust-lang/rust`) are broadcast
+/// as they are published, along with tags that aren't part of a
+/// release if `tags` is set, and unread notifications for the owner of
+/// `token` if `notifications` is set. GitHub is polled every
+/// `poll_interval` (15 minutes by default)
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct GitHubConfig {
+    pub token: Option<String>,
+    pub repositories: Vec<String>,
+    pub tags: Option<bool>,
+    pub notifications: Option<bool>,
+    pub poll_interval: Option<Duration>,
+}
+
+/// Podcast feeds (`feeds` are RSS urls), checked for new episodes
+/// every `poll_interval` (1 hour by default)
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct PodcastsConfig {
+    pub feeds: Vec<String>,
+    pub poll_interval: Option<Duration>,
+}
+
+/// An IMAP mailbox, checked every `poll_interval` (5 minutes by
+/// default). Only messages matching the IMAP `search` criteria (e.g.
+/// `SINCE 1-Jan-2020`) are counted, and unread ones matching one of
+/// `watches` are alerted. The number of unread messages is alerted
+/// when it passes `max_unread`
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct MailboxConfig {
+    pub host: String,
+    /// 993 by default
+    pub port: Option<u16>,
+    pub username: String,
+    pub password: String,
+    /// `INBOX` by default
+    pub mailbox: Option<String>,
+    pub search: Option<String>,
+    #[serde(default)]
+    pub watches: Vec<MailWatchConfig>,
+    pub max_unread: Option<usize>,
+    pub poll_interval: Option<Duration>,
+}
+
+/// Messages whose sender contains `from` and whose subject contains
+/// `subject`, ignoring case
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct MailWatchConfig {
+    pub name: String,
+    pub from: Option<String>,
+    pub subject: Option<String>,
+}
+
+/// A group of terms and user ids to track. With `max_count`, the group
+/// is alerted when more than that many tweets arrive within `window` (1
+/// minute by default)
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct TwitterTerms {
     pub group_name: String,
+    #[serde(default)]
     pub terms: Vec<String>,
+    /// Ids of users whose tweets are tracked
+    #[serde(default)]
+    pub follow: Vec<u64>,
+    /// Overrides the languages in `TwitterConfig` for this group
+    pub languages: Option<Vec<String>>,
+    /// Alert when the average sentiment of the group's last
+    /// `sentiment_sample_size` tweets (20 by default) falls below this,
+    /// on a scale from -1 (negative) to 1 (positive)
+    pub sentiment_alert_below: Option<f64>,
+    pub sentiment_sample_size: Option<usize>,
+    pub max_count: Option<i64>,
+    pub window: Option<Duration>,
+    /// Only tweets with coordinates inside this box are recorded
+    pub bounding_box: Option<BoundingBox>,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// A region bounded by lines of longitude (`west` and `east`) and
+/// latitude (`south` and `north`)
+#[derive(Clone, Copy, Deserialize, Serialize, Debug)]
+pub struct BoundingBox {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+}
+
+impl BoundingBox {
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        let within_longitude = if self.west <= self.east {
+            self.west <= longitude && longitude <= self.east
+        } else {
+            // the box crosses the antimeridian
+            self.west <= longitude || longitude <= self.east
+        };
+        within_longitude && self.south <= latitude && latitude <= self.north
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct TwitterConfig {
     pub consumer_key: String,
     pub consumer_secret: String,
     pub access_key: String,
     pub access_secret: String,
     pub terms: Vec<TwitterTerms>,
+    /// Codes of the languages to stream tweets in, e.g. `en` (the
+    /// default). Tweets in any language are streamed if this is empty
+    pub languages: Option<Vec<String>>,
+    /// Score the sentiment of every tweet. Tweets in groups with a
+    /// `sentiment_alert_below` are always scored
+    pub score_sentiment: Option<bool>,
+    /// Include the last day's tweet activity in the newscast
+    pub include_in_digest: Option<bool>,
+    /// Which tweets are kept. Every tweet is kept by default
+    pub retention: Option<TweetRetentionConfig>,
+}
+
+/// Tweets older than `max_age_days`, and the oldest tweets beyond the
+/// most recent `max_rows`, are deleted
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct TweetRetentionConfig {
+    pub max_age_days: Option<i64>,
+    pub max_rows: Option<i64>,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct DatabaseConfig {
     pub host: String,
     pub port: u16,
     pub database: String,
     pub username: String,
     pub password: String,
+    /// Run pending migrations on startup
+    pub run_migrations: Option<bool>,
+    /// Whether and how the connection is secured with TLS. Left to
+    /// libpq (`prefer`) by default
+    pub ssl_mode: Option<PostgresSslMode>,
+    /// The root certificate that the server's certificate is verified
+    /// against in the `verify-ca` and `verify-full` modes
+    pub ssl_root_cert: Option<PathBuf>,
+    /// Use the timescale extension for the time series tables, if it
+    /// is installed on the server
+    pub timescale: Option<bool>,
+    /// How often the connection is checked, every 30 seconds by default
+    pub health_check_interval: Option<Duration>,
+    /// The longest wait between attempts to reconnect while the
+    /// database is unreachable, 5 minutes by default
+    pub max_reconnect_backoff: Option<Duration>,
+    /// Broadcast a database-unreachable event once the database has
+    /// been unreachable this long, 5 minutes by default
+    pub alert_unreachable_after: Option<Duration>,
+}
+
+/// How the connection to Postgres is secured, as in libpq's `sslmode`
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PostgresSslMode {
+    Disable,
+    Allow,
+    Prefer,
+    Require,
+    /// Require TLS, and verify the server's certificate
+    VerifyCa,
+    /// Require TLS, and verify the server's certificate and host name
+    VerifyFull,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// Displays the mode as libpq expects it, e.g. `verify-full`
+impl fmt::Display for PostgresSslMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        name.trim_matches('"').fmt(f)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct ScheduledStreamConfig {
     pub message: ScheduledStreamMessage,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// A task run on a `cron` schedule, once `at` a time, or `every`
+/// interval, e.g. `15m`
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct ScheduledTaskConfig {
-    pub cron: String,
+    /// Identifies the task in the history of its runs. Unnamed tasks
+    /// are identified by their message and schedule, so changing the
+    /// schedule of an unnamed task starts a new history
+    pub name: Option<String>,
+    pub cron: Option<String>,
+    pub at: Option<DateTime<Utc>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_short_duration",
+        serialize_with = "serialize_short_duration"
+    )]
+    pub every: Option<Duration>,
+    /// Delay each run by a random duration up to this long, e.g. `30s`,
+    /// so that pulse instances sharing a schedule don't all run it at
+    /// the same moment
+    #[serde(
+        default,
+        deserialize_with = "deserialize_short_duration",
+        serialize_with = "serialize_short_duration"
+    )]
+    pub jitter: Option<Duration>,
+    /// Alert if a run hasn't finished after this long, e.g. `10m`
+    #[serde(
+        default,
+        deserialize_with = "deserialize_short_duration",
+        serialize_with = "serialize_short_duration"
+    )]
+    pub timeout: Option<Duration>,
+    /// What to do about a run that was missed while pulse was down.
+    /// Missed runs are skipped by default
+    pub catch_up: Option<CatchUpPolicy>,
+    /// Also run the task as soon as pulse starts, e.g. to send news
+    /// after a redeploy
+    pub run_on_start: Option<bool>,
+    /// Try again when a task runner fails the task, until it has made
+    /// `max_attempts` attempts
+    pub retry: Option<TaskRetryConfig>,
+    /// The names of the services the task is sent to, e.g. `news`.
+    /// Sent to every service by default
+    pub runners: Option<Vec<String>>,
+    /// Skip the task on blackout days
+    pub blackout: Option<bool>,
+    /// Disabled tasks keep their configuration but never run. Enabled
+    /// by default
+    pub enabled: Option<bool>,
     pub message: ScheduledTaskMessage,
 }
 
+/// Retries of a task that a task runner failed. Each retry waits
+/// `initial_backoff`, `30s` by default, doubling after each failed
+/// attempt up to `max_backoff`, `10m` by default
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct TaskRetryConfig {
+    pub max_attempts: u32,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_short_duration",
+        serialize_with = "serialize_short_duration"
+    )]
+    pub initial_backoff: Option<Duration>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_short_duration",
+        serialize_with = "serialize_short_duration"
+    )]
+    pub max_backoff: Option<Duration>,
+}
+
+impl TaskRetryConfig {
+    /// How long to wait before retrying after the given attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        RetryConfig {
+            max_attempts: self.max_attempts,
+            initial_backoff: self.initial_backoff.unwrap_or(Duration::from_secs(30)),
+            max_backoff: self.max_backoff.unwrap_or(Duration::from_secs(10 * 60)),
+        }
+        .backoff(attempt)
+    }
+}
+
+/// Days on which tasks with `blackout = true` are skipped, e.g. planned
+/// maintenance days. Blackout days are listed in `dates`, or are the
+/// days that events in the iCal `calendar_urls` start on. Calendars are
+/// fetched again every `refresh_interval`, `1h` by default
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct BlackoutConfig {
+    pub dates: Option<Vec<NaiveDate>>,
+    pub calendar_urls: Option<Vec<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_short_duration",
+        serialize_with = "serialize_short_duration"
+    )]
+    pub refresh_interval: Option<Duration>,
+}
+
+/// What to do about runs of a recurring task that were missed
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CatchUpPolicy {
+    /// Wait for the next scheduled run
+    Skip,
+    /// Run the task once when pulse starts, however many runs were
+    /// missed
+    RunOnce,
+}
+
 impl ScheduledTaskConfig {
-    pub fn duration_until_next(&self) -> Duration {
-        // TODO: validate the cron syntax before it gets here
-        let cron_schedule = CronSchedule::from_str(&self.cron).ok().unwrap();
-        let now = Local::now();
-        let next = cron_schedule.upcoming(Local).next().unwrap();
-        let duration_until = next.signed_duration_since(now);
-        Duration::from_millis(duration_until.num_milliseconds() as u64)
+    /// The next time this task is scheduled to run. One-shot tasks are
+    /// scheduled for their time even once it has passed
+    pub fn next_run(&self) -> DateTime<Local> {
+        if let Some(at) = self.at {
+            return at.with_timezone(&Local);
+        }
+        // intervals are limited to what can be added to the current
+        // time when the config is read
+        if let Some(every) = self.every {
+            return Local::now() + chrono::Duration::from_std(every).unwrap();
+        }
+
+        // other tasks have a cron schedule, whose syntax is checked when
+        // the config is read
+        let cron_schedule = CronSchedule::from_str(self.cron.as_ref().unwrap())
+            .ok()
+            .unwrap();
+        cron_schedule.upcoming(Local).next().unwrap()
+    }
+
+    /// When this recurring task was first scheduled to run after
+    /// `time`. One-shot tasks don't recur
+    pub fn run_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if let Some(every) = self.every {
+            return chrono::Duration::from_std(every)
+                .ok()
+                .and_then(|every| time.checked_add_signed(every));
+        }
+
+        let cron_schedule = CronSchedule::from_str(self.cron.as_ref()?).ok()?;
+        cron_schedule.after(&time).next()
+    }
+
+    /// Whether this task runs once, rather than on a schedule
+    pub fn is_one_shot(&self) -> bool {
+        self.at.is_some()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// What the runs of this task are recorded under: its name, or its
+    /// message and schedule, e.g. `fetch-news cron:0 0 7 * * * *`
+    pub fn key(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let message = serde_json::to_string(&self.message).unwrap_or_default();
+        let mut key = message.trim_matches('"').to_string();
+        if let Some(cron) = &self.cron {
+            key.push_str(&format!(" cron:{}", cron));
+        }
+        if let Some(at) = self.at {
+            key.push_str(&format!(" at:{}", at.to_rfc3339()));
+        }
+        if let Some(every) = self.every {
+            key.push_str(&format!(" every:{}", format_short_duration(every)));
+        }
+        if let Some(runners) = &self.runners {
+            key.push_str(&format!(" runners:{}", runners.join(",")));
+        }
+        key
+    }
+
+    /// Check what can't be checked while deserializing
+    pub fn validate(&self) -> Result<()> {
+        let schedules = [self.cron.is_some(), self.at.is_some(), self.every.is_some()];
+        if schedules.iter().filter(|schedule| **schedule).count() != 1 {
+            return Err(Error::invalid_config(format!(
+                "task {:?} needs exactly one of a cron schedule, an at time or an every interval",
+                self.message
+            )));
+        }
+        if let Some(cron) = &self.cron {
+            CronSchedule::from_str(cron)?;
+        }
+        if self.every == Some(Duration::from_secs(0)) {
+            return Err(Error::invalid_config(format!(
+                "task {:?} can't run every 0 seconds",
+                self.message
+            )));
+        }
+        if self
+            .retry
+            .as_ref()
+            .map_or(false, |retry| retry.max_attempts == 0)
+        {
+            return Err(Error::invalid_config(format!(
+                "task {:?} needs at least one attempt",
+                self.message
+            )));
+        }
+        if self.is_one_shot() && self.run_on_start == Some(true) {
+            return Err(Error::invalid_config(format!(
+                "one-shot task {:?} can't also run on start",
+                self.message
+            )));
+        }
+
+        Ok(())
     }
 }
 
-#[derive(Clone, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum AlertType {
     Digest,
     Alarm,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// Overrides how an alert is sent for events of a given severity
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct SeverityRouteConfig {
+    pub severity: Severity,
+    pub mediums: Vec<BroadcastMedium>,
+    pub alert_type: Option<AlertType>,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct AlertConfig {
     pub alert_interval: Option<Duration>,
     pub event: BroadcastEventType,
     pub mediums: Vec<BroadcastMedium>,
     pub alert_type: AlertType,
+    pub severity_routes: Option<Vec<SeverityRouteConfig>>,
+    /// Send alarms for events of this type that fire together as a
+    /// single alert
+    pub rollup: Option<bool>,
+    /// Template for the subject line of alerts, see
+    /// `broadcast::template::render` for the available variables
+    pub subject_template: Option<String>,
+}
+
+impl AlertConfig {
+    /// The configuration to use for an event of the given severity,
+    /// with the mediums and alert type of a matching severity route
+    /// applied
+    pub fn for_severity(&self, severity: Severity) -> AlertConfig {
+        let route = self
+            .severity_routes
+            .iter()
+            .flatten()
+            .find(|route| route.severity == severity);
+
+        match route {
+            Some(route) => AlertConfig {
+                mediums: route.mediums.clone(),
+                alert_type: route
+                    .alert_type
+                    .clone()
+                    .unwrap_or_else(|| self.alert_type.clone()),
+                ..self.clone()
+            },
+            None => self.clone(),
+        }
+    }
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct Config {
     pub system_monitor: Option<SystemMonitorConfig>,
     pub news: Option<NewsConfig>,
     pub tasks: Vec<ScheduledTaskConfig>,
+    pub blackout: Option<BlackoutConfig>,
     pub streams: Vec<ScheduledStreamConfig>,
     pub broadcast: BroadcastConfig,
-    pub database: DatabaseConfig,
+    /// Data is kept in memory, and lost on restart, if there is no
+    /// database
+    pub database: Option<DatabaseConfig>,
     pub twitter: Option<TwitterConfig>,
+    pub markets: Option<MarketsConfig>,
+    pub crypto: Option<CryptoConfig>,
+    pub github: Option<GitHubConfig>,
+    pub mailbox: Option<MailboxConfig>,
+    pub podcasts: Option<PodcastsConfig>,
+    pub cache: Option<CacheConfig>,
+    pub auth: Option<AuthConfig>,
+    pub cors: Option<CorsConfig>,
+    pub compression: Option<CompressionConfig>,
+    pub rate_limit: Option<RateLimitConfig>,
+    pub webapp: Option<WebappConfig>,
+    /// Serve a graphql api at `/api/graphql`
+    pub graphql: Option<bool>,
+}
+
+/// Credentials that clients must present. Websocket clients need one
+/// of `websocket_tokens`, if any are configured. Api clients need one
+/// of `api_keys` or a `basic_auth` login, if either is configured
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct AuthConfig {
+    pub websocket_tokens: Option<Vec<String>>,
+    pub api_keys: Option<Vec<String>>,
+    pub basic_auth: Option<Vec<BasicAuthConfig>>,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct BasicAuthConfig {
+    pub username: String,
+    pub password: String,
+}
+
+/// Cross-origin requests allowed to the api and websocket, from
+/// `allowed_origins` (e.g. `https://dashboard.example.com`, or `*` for
+/// any origin) with `allowed_methods` (`GET`, `POST` and `DELETE` by
+/// default). Preflight responses are cached for `max_age` seconds
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Option<Vec<String>>,
+    pub max_age: Option<usize>,
+}
+
+/// Responses are compressed with the best encoding the client accepts,
+/// preferring `encoding`, unless `enabled` is false
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct CompressionConfig {
+    pub enabled: Option<bool>,
+    pub encoding: Option<CompressionEncoding>,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompressionEncoding {
+    /// Whichever the client prefers
+    Auto,
+    Br,
+    Gzip,
+    Deflate,
+}
+
+/// Each client IP may make `requests` requests to the api and websocket
+/// every `period` seconds. Further requests are rejected until the
+/// period is over
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct RateLimitConfig {
+    pub requests: usize,
+    pub period: u64,
+}
+
+/// The webapp is served from `path`, `./webapp/dist/webapp/` by
+/// default, unless `enabled` is false, e.g. for headless deployments
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct WebappConfig {
+    pub enabled: Option<bool>,
+    pub path: Option<PathBuf>,
+}
+
+/// Replaces a secret in the redacted config
+const REDACTED: &str = "[redacted]";
+
+impl Config {
+    /// Check what can't be checked while deserializing
+    pub fn validate(&self) -> Result<()> {
+        let mut task_keys = HashSet::new();
+        for task in &self.tasks {
+            task.validate()?;
+            if !task_keys.insert(task.key()) {
+                return Err(Error::invalid_config(format!(
+                    "more than one task is recorded as {:?}, give them distinct names",
+                    task.key()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A copy of this config with passwords, api keys, tokens and
+    /// calendar urls replaced, safe to show outside of the host
+    pub fn redacted(&self) -> Config {
+        let mut config = self.clone();
+
+        if let Some(email) = config.broadcast.email.as_mut() {
+            if let Some(password) = email.password.as_mut() {
+                redact(password);
+            }
+            if let Some(oauth2) = email.oauth2.as_mut() {
+                redact(&mut oauth2.client_secret);
+                redact(&mut oauth2.refresh_token);
+            }
+        }
+        if let Some(database) = config.database.as_mut() {
+            redact(&mut database.password);
+        }
+        if let Some(news) = config.news.as_mut() {
+            if let Some(new_york_times) = news.new_york_times.as_mut() {
+                redact(&mut new_york_times.api_key);
+            }
+            if let Some(guardian) = news.guardian.as_mut() {
+                redact(&mut guardian.api_key);
+            }
+            if let Some(weather) = news.weather.as_mut() {
+                redact(&mut weather.api_key);
+            }
+            if let Some(youtube) = news.youtube.as_mut() {
+                redact(&mut youtube.api_key);
+            }
+            // private iCal urls carry their own access token
+            if let Some(calendar) = news.calendar.as_mut() {
+                calendar.urls.iter_mut().for_each(redact);
+            }
+        }
+        if let Some(blackout) = config.blackout.as_mut() {
+            blackout.calendar_urls.iter_mut().flatten().for_each(redact);
+        }
+        if let Some(twitter) = config.twitter.as_mut() {
+            redact(&mut twitter.consumer_key);
+            redact(&mut twitter.consumer_secret);
+            redact(&mut twitter.access_key);
+            redact(&mut twitter.access_secret);
+        }
+        if let Some(markets) = config.markets.as_mut() {
+            redact(&mut markets.api_key);
+        }
+        if let Some(token) = config
+            .github
+            .as_mut()
+            .and_then(|github| github.token.as_mut())
+        {
+            redact(token);
+        }
+        if let Some(mailbox) = config.mailbox.as_mut() {
+            redact(&mut mailbox.password);
+        }
+        if let Some(auth) = config.auth.as_mut() {
+            auth.websocket_tokens.iter_mut().flatten().for_each(redact);
+            auth.api_keys.iter_mut().flatten().for_each(redact);
+            for login in auth.basic_auth.iter_mut().flatten() {
+                redact(&mut login.password);
+            }
+        }
+
+        config
+    }
+}
+
+fn redact(secret: &mut String) {
+    *secret = REDACTED.to_string();
+}
+
+/// How long responses from each provider are cached for, overriding
+/// the provider's default. Providers are `guardian`, `new-york-times`,
+/// `reddit`, `weather`, `markets`, `crypto` and `youtube`
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct CacheConfig {
+    pub ttls: HashMap<String, Duration>,
 }
 
 impl Default for Config {
@@ -173,18 +1129,101 @@ impl Default for Config {
             news: None,
             streams: vec![],
             tasks: vec![],
+            blackout: None,
             broadcast: BroadcastConfig {
                 email: None,
                 alerts: vec![],
+                maintenance_windows: None,
+                retry: None,
+                dead_letter_replay_interval: None,
+                quiet_hours: None,
+                durable_outbox: None,
+                outbox: None,
             },
-            database: DatabaseConfig {
+            database: Some(DatabaseConfig {
                 host: "localhost".to_string(),
                 port: 5432,
                 database: "pulse".to_string(),
                 username: "postgres".to_string(),
                 password: "postgres".to_string(),
-            },
+                run_migrations: None,
+                ssl_mode: None,
+                ssl_root_cert: None,
+                timescale: None,
+                health_check_interval: None,
+                max_reconnect_backoff: None,
+                alert_unreachable_after: None,
+            }),
             twitter: None,
+            markets: None,
+            crypto: None,
+            github: None,
+            mailbox: None,
+            podcasts: None,
+            cache: None,
+            auth: None,
+            cors: None,
+            compression: None,
+            rate_limit: None,
+            webapp: None,
+            graphql: None,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redacted_config_hides_secrets_and_calendar_urls() {
+        let calendar_url = "https://calendar.example.com/private-token/basic.ics";
+        let mut config = Config::default();
+        config.news = Some(NewsConfig {
+            new_york_times: None,
+            guardian: None,
+            feeds: None,
+            reddit: None,
+            weather: None,
+            calendar: Some(CalendarConfig {
+                urls: vec![calendar_url.to_string()],
+                remind_minutes_before: None,
+                remind_of: None,
+                reminder_check_interval: None,
+            }),
+            youtube: None,
+        });
+        config.blackout = Some(BlackoutConfig {
+            dates: None,
+            calendar_urls: Some(vec![calendar_url.to_string()]),
+            refresh_interval: None,
+        });
+        config.auth = Some(AuthConfig {
+            websocket_tokens: Some(vec!["websocket-token".to_string()]),
+            api_keys: Some(vec!["api-key".to_string()]),
+            basic_auth: Some(vec![BasicAuthConfig {
+                username: "admin".to_string(),
+                password: "hunter2".to_string(),
+            }]),
+        });
+
+        let redacted = config.redacted();
+        assert_eq!(redacted.database.unwrap().password, REDACTED);
+        assert_eq!(
+            redacted.news.unwrap().calendar.unwrap().urls,
+            vec![REDACTED]
+        );
+        assert_eq!(
+            redacted.blackout.unwrap().calendar_urls,
+            Some(vec![REDACTED.to_string()])
+        );
+        let auth = redacted.auth.unwrap();
+        assert_eq!(auth.websocket_tokens, Some(vec![REDACTED.to_string()]));
+        assert_eq!(auth.api_keys, Some(vec![REDACTED.to_string()]));
+        let login = &auth.basic_auth.unwrap()[0];
+        assert_eq!(
+            (login.username.as_str(), login.password.as_str()),
+            ("admin", REDACTED)
+        );
+    }
+}
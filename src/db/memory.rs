@@ -0,0 +1,999 @@
+use std::{cell::RefCell, collections::HashMap, hash::Hash};
+
+use chrono::{NaiveDateTime, Timelike, Utc};
+
+use super::{models, DatabaseInner, Page, SortOrder};
+use crate::error::Result;
+
+/// The most rows kept in each history table. The oldest rows are
+/// dropped when a table is full, so that a long running daemon doesn't
+/// grow without bound. Tables of state, like the outbox or the items
+/// already seen, aren't capped since dropping rows would lose it
+const MAX_ROWS: usize = 10_000;
+
+#[derive(Default)]
+struct Tables {
+    next_id: i32,
+    tasks: Vec<models::Task>,
+    task_runs: Vec<models::TaskRun>,
+    completed_tasks: Vec<models::CompletedTask>,
+    registered_tasks: Vec<models::RegisteredTask>,
+    task_schedules: HashMap<String, NaiveDateTime>,
+    disk_usage: Vec<models::DiskUsage>,
+    disk_usage_rollups: Vec<models::DiskUsageRollup>,
+    tweets: Vec<models::Tweet>,
+    digests: Vec<models::Digest>,
+    articles: Vec<models::Article>,
+    acknowledgements: Vec<models::Acknowledgement>,
+    silences: Vec<models::Silence>,
+    last_alerted: HashMap<String, NaiveDateTime>,
+    alerts: Vec<models::Alert>,
+    dead_letters: Vec<models::DeadLetter>,
+    outbox: Vec<models::OutboxEvent>,
+    crypto_prices: Vec<models::CryptoPrice>,
+    github_seen: Vec<models::GitHubSeen>,
+    api_cache: HashMap<String, models::CachedResponse>,
+}
+
+impl Tables {
+    fn next_id(&mut self) -> i32 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+/// Keeps everything in memory, for running without a database server.
+/// Nothing survives a restart
+#[derive(Default)]
+pub struct MemoryDatabase {
+    tables: RefCell<Tables>,
+}
+
+/// Add a row to a history table, dropping the oldest row if the table
+/// is full
+fn push_history<T>(table: &mut Vec<T>, row: T) {
+    if table.len() >= MAX_ROWS {
+        table.remove(0);
+    }
+    table.push(row);
+}
+
+/// Sort rows by when they were recorded, with ties broken by id, and
+/// take the page
+fn paginate<T>(mut rows: Vec<T>, key: impl Fn(&T) -> (NaiveDateTime, i32), page: Page) -> Vec<T> {
+    rows.sort_by_key(|row| key(row));
+    if page.order == SortOrder::Desc {
+        rows.reverse();
+    }
+    rows.into_iter()
+        .skip(page.offset as usize)
+        .take(page.limit.map_or(usize::MAX, |limit| limit as usize))
+        .collect()
+}
+
+fn within(time: NaiveDateTime, from: Option<NaiveDateTime>, to: Option<NaiveDateTime>) -> bool {
+    from.map_or(true, |from| time >= from) && to.map_or(true, |to| time <= to)
+}
+
+/// Whether `text` contains `search`, ignoring case
+fn contains(text: &str, search: &str) -> bool {
+    text.to_lowercase().contains(&search.to_lowercase())
+}
+
+/// Count rows by a key, sorted by the key
+fn counts<K: Ord + Hash>(keys: impl Iterator<Item = K>) -> Vec<(K, i64)> {
+    let mut counts: HashMap<K, i64> = HashMap::new();
+    for key in keys {
+        *counts.entry(key).or_default() += 1;
+    }
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort();
+    counts
+}
+
+impl DatabaseInner for MemoryDatabase {
+    fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn insert_task(&self, task: models::NewTask) -> Result<models::Task> {
+        let mut tables = self.tables.borrow_mut();
+        let task = models::Task {
+            id: tables.next_id(),
+            task: task.task,
+            sent_at: Utc::now().naive_utc(),
+            task_key: task.task_key,
+        };
+        push_history(&mut tables.tasks, task.clone());
+        Ok(task)
+    }
+
+    fn search_tasks(
+        &self,
+        task: Option<String>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        page: Page,
+    ) -> Result<Vec<models::Task>> {
+        let rows = self
+            .tables
+            .borrow()
+            .tasks
+            .iter()
+            .filter(|row| task.as_ref().map_or(true, |task| &row.task == task))
+            .filter(|row| within(row.sent_at, from, to))
+            .cloned()
+            .collect();
+        Ok(paginate(rows, |row| (row.sent_at, row.id), page))
+    }
+
+    fn tasks_since(&self, since: NaiveDateTime) -> Result<Vec<models::Task>> {
+        self.search_tasks(None, Some(since), None, Page::all(SortOrder::Asc))
+    }
+
+    fn last_task(&self, task_key: &str) -> Result<Option<models::Task>> {
+        let rows = self
+            .tables
+            .borrow()
+            .tasks
+            .iter()
+            .filter(|row| row.task_key.as_deref() == Some(task_key))
+            .cloned()
+            .collect();
+        Ok(paginate(rows, |row| (row.sent_at, row.id), Page::latest(1))
+            .into_iter()
+            .next())
+    }
+
+    fn insert_completed_task(&self, task: models::NewCompletedTask) -> Result<()> {
+        let mut tables = self.tables.borrow_mut();
+        let exists = tables
+            .completed_tasks
+            .iter()
+            .any(|row| row.task == task.task && row.scheduled_for == task.scheduled_for);
+        if !exists {
+            tables.completed_tasks.push(models::CompletedTask {
+                task: task.task,
+                scheduled_for: task.scheduled_for,
+                completed_at: Utc::now().naive_utc(),
+            });
+        }
+        Ok(())
+    }
+
+    fn completed_tasks(&self) -> Result<Vec<models::CompletedTask>> {
+        Ok(self.tables.borrow().completed_tasks.clone())
+    }
+
+    fn insert_registered_task(
+        &self,
+        task: models::NewRegisteredTask,
+    ) -> Result<models::RegisteredTask> {
+        let mut tables = self.tables.borrow_mut();
+        let task = models::RegisteredTask {
+            id: tables.next_id(),
+            config: task.config,
+            registered_at: Utc::now().naive_utc(),
+        };
+        tables.registered_tasks.push(task.clone());
+        Ok(task)
+    }
+
+    fn registered_tasks(&self) -> Result<Vec<models::RegisteredTask>> {
+        Ok(self.tables.borrow().registered_tasks.clone())
+    }
+
+    fn upsert_task_schedule(&self, schedule: models::TaskSchedule) -> Result<()> {
+        self.tables
+            .borrow_mut()
+            .task_schedules
+            .insert(schedule.task_key, schedule.next_run);
+        Ok(())
+    }
+
+    fn task_schedules(&self) -> Result<Vec<models::TaskSchedule>> {
+        Ok(self
+            .tables
+            .borrow()
+            .task_schedules
+            .iter()
+            .map(|(task_key, next_run)| models::TaskSchedule {
+                task_key: task_key.clone(),
+                next_run: *next_run,
+            })
+            .collect())
+    }
+
+    fn insert_task_run(&self, run: models::NewTaskRun) -> Result<models::TaskRun> {
+        let mut tables = self.tables.borrow_mut();
+        let run = models::TaskRun {
+            id: tables.next_id(),
+            task: run.task,
+            runner: run.runner,
+            succeeded: run.succeeded,
+            error: run.error,
+            duration_ms: run.duration_ms,
+            started_at: run.started_at,
+            task_key: run.task_key,
+        };
+        push_history(&mut tables.task_runs, run.clone());
+        Ok(run)
+    }
+
+    fn search_task_runs(&self, task: Option<String>, page: Page) -> Result<Vec<models::TaskRun>> {
+        let rows = self
+            .tables
+            .borrow()
+            .task_runs
+            .iter()
+            .filter(|row| task.as_ref().map_or(true, |task| &row.task == task))
+            .cloned()
+            .collect();
+        Ok(paginate(rows, |row| (row.started_at, row.id), page))
+    }
+
+    fn latest_task_runs(&self) -> Result<Vec<models::TaskRun>> {
+        let mut latest: HashMap<String, models::TaskRun> = HashMap::new();
+        for row in &self.tables.borrow().task_runs {
+            let task_key = match &row.task_key {
+                Some(task_key) => task_key,
+                None => continue,
+            };
+            if latest.get(task_key).map_or(true, |latest| {
+                (latest.started_at, latest.id) <= (row.started_at, row.id)
+            }) {
+                latest.insert(task_key.clone(), row.clone());
+            }
+        }
+        let mut latest = latest.into_iter().map(|(_, row)| row).collect::<Vec<_>>();
+        latest.sort_by(|a, b| a.task_key.cmp(&b.task_key));
+        Ok(latest)
+    }
+
+    fn task_counts(&self) -> Result<Vec<(String, i64)>> {
+        let tables = self.tables.borrow();
+        Ok(counts(tables.tasks.iter().map(|row| row.task.clone())))
+    }
+
+    fn insert_disk_usage(&self, disk_usage: models::NewDiskUsage) -> Result<models::DiskUsage> {
+        let mut tables = self.tables.borrow_mut();
+        let disk_usage = models::DiskUsage {
+            id: tables.next_id(),
+            mount: disk_usage.mount,
+            percent_disk_used: disk_usage.percent_disk_used,
+            recorded_at: Utc::now().naive_utc(),
+        };
+        push_history(&mut tables.disk_usage, disk_usage.clone());
+        Ok(disk_usage)
+    }
+
+    fn disk_usage_since(
+        &self,
+        mount: &str,
+        since: NaiveDateTime,
+    ) -> Result<Vec<models::DiskUsage>> {
+        let rows = self
+            .tables
+            .borrow()
+            .disk_usage
+            .iter()
+            .filter(|row| row.mount == mount && row.recorded_at >= since)
+            .cloned()
+            .collect();
+        Ok(paginate(
+            rows,
+            |row| (row.recorded_at, row.id),
+            Page::all(SortOrder::Asc),
+        ))
+    }
+
+    fn latest_disk_usage(&self) -> Result<Vec<models::DiskUsage>> {
+        let mut latest: HashMap<String, models::DiskUsage> = HashMap::new();
+        for row in &self.tables.borrow().disk_usage {
+            if latest
+                .get(&row.mount)
+                .map_or(true, |latest| latest.recorded_at <= row.recorded_at)
+            {
+                latest.insert(row.mount.clone(), row.clone());
+            }
+        }
+        let mut latest = latest.into_iter().map(|(_, row)| row).collect::<Vec<_>>();
+        latest.sort_by(|a, b| a.mount.cmp(&b.mount));
+        Ok(latest)
+    }
+
+    fn disk_usage_between(
+        &self,
+        mount: Option<String>,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        page: Page,
+    ) -> Result<Vec<models::DiskUsage>> {
+        let rows = self
+            .tables
+            .borrow()
+            .disk_usage
+            .iter()
+            .filter(|row| mount.as_ref().map_or(true, |mount| &row.mount == mount))
+            .filter(|row| within(row.recorded_at, Some(from), Some(to)))
+            .cloned()
+            .collect();
+        Ok(paginate(rows, |row| (row.recorded_at, row.id), page))
+    }
+
+    fn roll_up_disk_usage(&self, before: NaiveDateTime) -> Result<usize> {
+        let mut tables = self.tables.borrow_mut();
+        let (old, recent): (Vec<_>, Vec<_>) = tables
+            .disk_usage
+            .drain(..)
+            .partition(|row| row.recorded_at < before);
+        tables.disk_usage = recent;
+
+        let samples = old.len();
+        for row in old {
+            let hour = row.recorded_at.date().and_hms(row.recorded_at.hour(), 0, 0);
+            let existing = tables
+                .disk_usage_rollups
+                .iter_mut()
+                .find(|rollup| rollup.mount == row.mount && rollup.hour == hour);
+            match existing {
+                Some(rollup) => {
+                    rollup.average_percent_used = (rollup.average_percent_used
+                        * rollup.samples as f64
+                        + row.percent_disk_used)
+                        / (rollup.samples + 1) as f64;
+                    rollup.max_percent_used = rollup.max_percent_used.max(row.percent_disk_used);
+                    rollup.samples += 1;
+                }
+                None => {
+                    let rollup = models::DiskUsageRollup {
+                        id: tables.next_id(),
+                        mount: row.mount,
+                        hour,
+                        average_percent_used: row.percent_disk_used,
+                        max_percent_used: row.percent_disk_used,
+                        samples: 1,
+                    };
+                    push_history(&mut tables.disk_usage_rollups, rollup);
+                }
+            }
+        }
+        Ok(samples)
+    }
+
+    fn disk_usage_rollups_between(
+        &self,
+        mount: Option<String>,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        page: Page,
+    ) -> Result<Vec<models::DiskUsageRollup>> {
+        let rows = self
+            .tables
+            .borrow()
+            .disk_usage_rollups
+            .iter()
+            .filter(|row| mount.as_ref().map_or(true, |mount| &row.mount == mount))
+            .filter(|row| within(row.hour, Some(from), Some(to)))
+            .cloned()
+            .collect();
+        Ok(paginate(rows, |row| (row.hour, row.id), page))
+    }
+
+    fn insert_tweet(&self, tweet: models::NewTweet) -> Result<models::Tweet> {
+        let mut tables = self.tables.borrow_mut();
+        let tweet = models::Tweet {
+            id: tables.next_id(),
+            twitter_tweet_id: tweet.twitter_tweet_id,
+            group_name: tweet.group_name,
+            latitude: tweet.latitude,
+            longitude: tweet.longitude,
+            favorite_count: tweet.favorite_count,
+            retweet_count: tweet.retweet_count,
+            username: tweet.username,
+            lang: tweet.lang,
+            text: tweet.text,
+            tweeted_at: tweet.tweeted_at,
+            matched_by: tweet.matched_by,
+            sentiment: tweet.sentiment,
+        };
+        push_history(&mut tables.tweets, tweet.clone());
+        Ok(tweet)
+    }
+
+    fn tweets_for_group_between(
+        &self,
+        group_name: &str,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Vec<models::Tweet>> {
+        self.search_tweets(
+            Some(group_name.to_string()),
+            None,
+            Some(from),
+            Some(to),
+            Page::all(SortOrder::Asc),
+        )
+    }
+
+    fn tweet_counts(&self) -> Result<Vec<(String, i64)>> {
+        let tables = self.tables.borrow();
+        Ok(counts(
+            tables.tweets.iter().map(|row| row.group_name.clone()),
+        ))
+    }
+
+    fn delete_old_tweets(&self, before: Option<NaiveDateTime>, keep: Option<i64>) -> Result<usize> {
+        let mut tables = self.tables.borrow_mut();
+        let count = tables.tweets.len();
+        if let Some(before) = before {
+            tables.tweets.retain(|row| row.tweeted_at >= before);
+        }
+        if let Some(keep) = keep {
+            tables.tweets.sort_by_key(|row| (row.tweeted_at, row.id));
+            let excess = tables.tweets.len().saturating_sub(keep.max(0) as usize);
+            tables.tweets.drain(..excess);
+        }
+        Ok(count - tables.tweets.len())
+    }
+
+    fn insert_digest(&self, articles: Vec<models::NewArticle>) -> Result<models::Digest> {
+        let mut tables = self.tables.borrow_mut();
+        let digest = models::Digest {
+            id: tables.next_id(),
+            sent_at: Utc::now().naive_utc(),
+        };
+        push_history(&mut tables.digests, digest.clone());
+        for article in articles {
+            let article = models::Article {
+                id: tables.next_id(),
+                digest_id: digest.id,
+                url: article.url,
+                title: article.title,
+                source: article.source,
+                summary: article.summary,
+                published_date: article.published_date,
+            };
+            push_history(&mut tables.articles, article);
+        }
+        Ok(digest)
+    }
+
+    fn search_articles(
+        &self,
+        digest_id: Option<i32>,
+        source: Option<String>,
+        text: Option<String>,
+        page: Page,
+    ) -> Result<Vec<models::Article>> {
+        let mut rows = self
+            .tables
+            .borrow()
+            .articles
+            .iter()
+            .filter(|row| digest_id.map_or(true, |digest_id| row.digest_id == digest_id))
+            .filter(|row| source.as_ref().map_or(true, |source| &row.source == source))
+            .filter(|row| {
+                text.as_ref().map_or(true, |text| {
+                    contains(&row.title, text) || contains(&row.summary, text)
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| row.id);
+        if page.order == SortOrder::Desc {
+            rows.reverse();
+        }
+        Ok(rows
+            .into_iter()
+            .skip(page.offset as usize)
+            .take(page.limit.map_or(usize::MAX, |limit| limit as usize))
+            .collect())
+    }
+
+    fn search_tweets(
+        &self,
+        group_name: Option<String>,
+        text: Option<String>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        page: Page,
+    ) -> Result<Vec<models::Tweet>> {
+        let rows = self
+            .tables
+            .borrow()
+            .tweets
+            .iter()
+            .filter(|row| {
+                group_name
+                    .as_ref()
+                    .map_or(true, |group_name| &row.group_name == group_name)
+            })
+            .filter(|row| text.as_ref().map_or(true, |text| contains(&row.text, text)))
+            .filter(|row| within(row.tweeted_at, from, to))
+            .cloned()
+            .collect();
+        Ok(paginate(rows, |row| (row.tweeted_at, row.id), page))
+    }
+
+    fn insert_acknowledgement(
+        &self,
+        acknowledgement: models::NewAcknowledgement,
+    ) -> Result<models::Acknowledgement> {
+        let mut tables = self.tables.borrow_mut();
+        let acknowledgement = models::Acknowledgement {
+            id: tables.next_id(),
+            event_key: acknowledgement.event_key,
+            acknowledged_at: Utc::now().naive_utc(),
+            resolved_at: None,
+        };
+        tables.acknowledgements.push(acknowledgement.clone());
+        Ok(acknowledgement)
+    }
+
+    fn active_acknowledgements(&self) -> Result<Vec<models::Acknowledgement>> {
+        Ok(self
+            .tables
+            .borrow()
+            .acknowledgements
+            .iter()
+            .filter(|row| row.resolved_at.is_none())
+            .cloned()
+            .collect())
+    }
+
+    fn resolve_acknowledgement(&self, event_key: &str) -> Result<()> {
+        let now = Utc::now().naive_utc();
+        for row in self.tables.borrow_mut().acknowledgements.iter_mut() {
+            if row.event_key == event_key && row.resolved_at.is_none() {
+                row.resolved_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_silence(&self, silence: models::NewSilence) -> Result<models::Silence> {
+        let mut tables = self.tables.borrow_mut();
+        let silence = models::Silence {
+            id: tables.next_id(),
+            event_type: silence.event_type,
+            event_key: silence.event_key,
+            comment: silence.comment,
+            created_at: Utc::now().naive_utc(),
+            expires_at: silence.expires_at,
+        };
+        tables.silences.push(silence.clone());
+        Ok(silence)
+    }
+
+    fn active_silences(&self) -> Result<Vec<models::Silence>> {
+        let now = Utc::now().naive_utc();
+        Ok(self
+            .tables
+            .borrow()
+            .silences
+            .iter()
+            .filter(|row| row.expires_at > now)
+            .cloned()
+            .collect())
+    }
+
+    fn delete_silence(&self, id: i32) -> Result<()> {
+        self.tables.borrow_mut().silences.retain(|row| row.id != id);
+        Ok(())
+    }
+
+    fn upsert_last_alerted(&self, alerted: models::LastAlerted) -> Result<()> {
+        self.tables
+            .borrow_mut()
+            .last_alerted
+            .insert(alerted.event_key, alerted.alerted_at);
+        Ok(())
+    }
+
+    fn last_alerted(&self) -> Result<Vec<models::LastAlerted>> {
+        Ok(self
+            .tables
+            .borrow()
+            .last_alerted
+            .iter()
+            .map(|(event_key, alerted_at)| models::LastAlerted::new(event_key.clone(), *alerted_at))
+            .collect())
+    }
+
+    fn insert_alert(&self, alert: models::NewAlert) -> Result<models::Alert> {
+        let mut tables = self.tables.borrow_mut();
+        let alert = models::Alert {
+            id: tables.next_id(),
+            event_type: alert.event_type,
+            event_keys: alert.event_keys,
+            medium: alert.medium,
+            subject: alert.subject,
+            outcome: alert.outcome,
+            error: alert.error,
+            sent_at: Utc::now().naive_utc(),
+        };
+        push_history(&mut tables.alerts, alert.clone());
+        Ok(alert)
+    }
+
+    fn search_alerts(
+        &self,
+        event_type: Option<String>,
+        outcome: Option<String>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        page: Page,
+    ) -> Result<Vec<models::Alert>> {
+        let rows = self
+            .tables
+            .borrow()
+            .alerts
+            .iter()
+            .filter(|row| event_type.is_none() || row.event_type == event_type)
+            .filter(|row| {
+                outcome
+                    .as_ref()
+                    .map_or(true, |outcome| &row.outcome == outcome)
+            })
+            .filter(|row| within(row.sent_at, from, to))
+            .cloned()
+            .collect();
+        Ok(paginate(rows, |row| (row.sent_at, row.id), page))
+    }
+
+    fn sent_alert_counts(&self) -> Result<Vec<(Option<String>, i64)>> {
+        let tables = self.tables.borrow();
+        Ok(counts(
+            tables
+                .alerts
+                .iter()
+                .filter(|row| row.outcome == "sent")
+                .map(|row| row.event_type.clone()),
+        ))
+    }
+
+    fn insert_dead_letter(&self, dead_letter: models::NewDeadLetter) -> Result<models::DeadLetter> {
+        let mut tables = self.tables.borrow_mut();
+        let dead_letter = models::DeadLetter {
+            id: tables.next_id(),
+            medium: dead_letter.medium,
+            subject: dead_letter.subject,
+            body: dead_letter.body,
+            error: dead_letter.error,
+            attempts: dead_letter.attempts,
+            failed_at: Utc::now().naive_utc(),
+            replayed_at: None,
+            text_body: dead_letter.text_body,
+            event_type: dead_letter.event_type,
+            event_keys: dead_letter.event_keys,
+        };
+        tables.dead_letters.push(dead_letter.clone());
+        Ok(dead_letter)
+    }
+
+    fn pending_dead_letters(&self) -> Result<Vec<models::DeadLetter>> {
+        Ok(self
+            .tables
+            .borrow()
+            .dead_letters
+            .iter()
+            .filter(|row| row.replayed_at.is_none())
+            .cloned()
+            .collect())
+    }
+
+    fn mark_dead_letter_replayed(&self, id: i32) -> Result<()> {
+        let now = Utc::now().naive_utc();
+        for row in self.tables.borrow_mut().dead_letters.iter_mut() {
+            if row.id == id {
+                row.replayed_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_outbox_event(&self, event: models::NewOutboxEvent) -> Result<models::OutboxEvent> {
+        let mut tables = self.tables.borrow_mut();
+        let event = models::OutboxEvent {
+            id: tables.next_id(),
+            event: event.event,
+            created_at: Utc::now().naive_utc(),
+            delivered_at: None,
+        };
+        tables.outbox.push(event.clone());
+        Ok(event)
+    }
+
+    fn pending_outbox_events(&self) -> Result<Vec<models::OutboxEvent>> {
+        Ok(self.tables.borrow().outbox.clone())
+    }
+
+    /// Delivered events are dropped, since they are never read again
+    fn mark_outbox_events_delivered(&self, ids: &[i32]) -> Result<()> {
+        self.tables
+            .borrow_mut()
+            .outbox
+            .retain(|row| !ids.contains(&row.id));
+        Ok(())
+    }
+
+    fn insert_crypto_price(&self, price: models::NewCryptoPrice) -> Result<models::CryptoPrice> {
+        let mut tables = self.tables.borrow_mut();
+        let price = models::CryptoPrice {
+            id: tables.next_id(),
+            asset: price.asset,
+            currency: price.currency,
+            price: price.price,
+            percent_change_24h: price.percent_change_24h,
+            recorded_at: Utc::now().naive_utc(),
+        };
+        push_history(&mut tables.crypto_prices, price.clone());
+        Ok(price)
+    }
+
+    fn crypto_prices_since(
+        &self,
+        asset: Option<String>,
+        since: NaiveDateTime,
+    ) -> Result<Vec<models::CryptoPrice>> {
+        Ok(self
+            .tables
+            .borrow()
+            .crypto_prices
+            .iter()
+            .filter(|row| asset.as_ref().map_or(true, |asset| &row.asset == asset))
+            .filter(|row| row.recorded_at >= since)
+            .cloned()
+            .collect())
+    }
+
+    fn insert_github_seen(&self, seen: models::NewGitHubSeen) -> Result<()> {
+        let mut tables = self.tables.borrow_mut();
+        let exists = tables.github_seen.iter().any(|row| {
+            row.kind == seen.kind
+                && row.repository == seen.repository
+                && row.item_id == seen.item_id
+        });
+        if !exists {
+            let seen = models::GitHubSeen {
+                id: tables.next_id(),
+                kind: seen.kind,
+                repository: seen.repository,
+                item_id: seen.item_id,
+                seen_at: Utc::now().naive_utc(),
+            };
+            tables.github_seen.push(seen);
+        }
+        Ok(())
+    }
+
+    fn github_seen(&self) -> Result<Vec<models::GitHubSeen>> {
+        Ok(self.tables.borrow().github_seen.clone())
+    }
+
+    fn cached_response(&self, key: &str) -> Result<Option<models::CachedResponse>> {
+        Ok(self.tables.borrow().api_cache.get(key).cloned())
+    }
+
+    fn upsert_cached_response(&self, response: models::CachedResponse) -> Result<()> {
+        self.tables
+            .borrow_mut()
+            .api_cache
+            .insert(response.key.clone(), response);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn disk_usage(database: &MemoryDatabase, percent_disk_used: f64) -> models::DiskUsage {
+        database
+            .insert_disk_usage(models::NewDiskUsage::new("/", percent_disk_used))
+            .unwrap()
+    }
+
+    #[test]
+    fn memory_database_pages_rows_in_order() {
+        let database = MemoryDatabase::default();
+        let rows = (0..5)
+            .map(|i| disk_usage(&database, f64::from(i)))
+            .collect::<Vec<_>>();
+        let from = rows[0].recorded_at;
+        let to = rows[4].recorded_at;
+
+        let page = database
+            .disk_usage_between(
+                None,
+                from,
+                to,
+                Page {
+                    limit: Some(2),
+                    offset: 1,
+                    order: SortOrder::Desc,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            page.iter().map(|row| row.id).collect::<Vec<_>>(),
+            vec![rows[3].id, rows[2].id]
+        );
+    }
+
+    fn tweet(
+        database: &MemoryDatabase,
+        group_name: &str,
+        tweeted_at: NaiveDateTime,
+    ) -> models::Tweet {
+        database
+            .insert_tweet(models::NewTweet {
+                twitter_tweet_id: tweeted_at.timestamp().to_string(),
+                group_name: group_name.to_string(),
+                latitude: None,
+                longitude: None,
+                favorite_count: 0,
+                retweet_count: 0,
+                username: None,
+                lang: None,
+                text: "text".to_string(),
+                tweeted_at,
+                matched_by: "keyword".to_string(),
+                sentiment: None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn memory_database_lists_a_groups_tweets_between_times() {
+        let database = MemoryDatabase::default();
+        let hour = |hour: i64| NaiveDateTime::from_timestamp(0, 0) + chrono::Duration::hours(hour);
+        let late = tweet(&database, "rust", hour(3));
+        tweet(&database, "rust", hour(0));
+        let early = tweet(&database, "rust", hour(1));
+        tweet(&database, "golang", hour(2));
+        tweet(&database, "rust", hour(4));
+
+        let tweets = database
+            .tweets_for_group_between("rust", hour(1), hour(3))
+            .unwrap();
+
+        // both ends are included, oldest first
+        assert_eq!(
+            tweets.iter().map(|row| row.id).collect::<Vec<_>>(),
+            vec![early.id, late.id]
+        );
+    }
+
+    #[test]
+    fn memory_database_limits_tweet_searches() {
+        let database = MemoryDatabase::default();
+        let hour = |hour: i64| NaiveDateTime::from_timestamp(0, 0) + chrono::Duration::hours(hour);
+        let rows = (0..4)
+            .map(|i| tweet(&database, "rust", hour(i)))
+            .collect::<Vec<_>>();
+
+        let latest = database
+            .search_tweets(Some("rust".to_string()), None, None, None, Page::latest(2))
+            .unwrap();
+
+        assert_eq!(
+            latest.iter().map(|row| row.id).collect::<Vec<_>>(),
+            vec![rows[3].id, rows[2].id]
+        );
+    }
+
+    #[test]
+    fn memory_database_lists_tasks_since_a_time() {
+        let database = MemoryDatabase::default();
+        let hour = |hour: i64| NaiveDateTime::from_timestamp(0, 0) + chrono::Duration::hours(hour);
+        // tasks are recorded as sent now, so backdate them
+        let sent_at = vec![hour(2), hour(0), hour(1)];
+        for sent_at in &sent_at {
+            database
+                .insert_task(models::NewTask::new("news", "news"))
+                .unwrap();
+            database
+                .tables
+                .borrow_mut()
+                .tasks
+                .last_mut()
+                .unwrap()
+                .sent_at = *sent_at;
+        }
+
+        let tasks = database.tasks_since(hour(1)).unwrap();
+
+        assert_eq!(
+            tasks.iter().map(|row| row.sent_at).collect::<Vec<_>>(),
+            vec![hour(1), hour(2)]
+        );
+    }
+
+    #[test]
+    fn memory_database_rolls_up_old_disk_usage() {
+        let database = MemoryDatabase::default();
+        disk_usage(&database, 40.0);
+        disk_usage(&database, 60.0);
+
+        let samples = database
+            .roll_up_disk_usage(Utc::now().naive_utc() + chrono::Duration::seconds(1))
+            .unwrap();
+
+        assert_eq!(samples, 2);
+        assert!(database.tables.borrow().disk_usage.is_empty());
+        let rollups = database.tables.borrow().disk_usage_rollups.clone();
+        let samples = rollups.iter().map(|rollup| rollup.samples).sum::<i64>();
+        assert_eq!(samples, 2);
+        assert!(rollups.iter().all(|rollup| rollup.max_percent_used <= 60.0));
+    }
+
+    #[test]
+    fn memory_database_drops_the_oldest_rows_when_full() {
+        let database = MemoryDatabase::default();
+        let first = disk_usage(&database, 0.0);
+        for _ in 0..MAX_ROWS {
+            disk_usage(&database, 1.0);
+        }
+
+        let tables = database.tables.borrow();
+        assert_eq!(tables.disk_usage.len(), MAX_ROWS);
+        assert!(tables.disk_usage.iter().all(|row| row.id != first.id));
+    }
+
+    #[test]
+    fn memory_database_keeps_every_row_of_state() {
+        let database = MemoryDatabase::default();
+        for _ in 0..=MAX_ROWS {
+            database
+                .insert_outbox_event(models::NewOutboxEvent {
+                    event: "{}".to_string(),
+                })
+                .unwrap();
+        }
+
+        assert_eq!(
+            database.pending_outbox_events().unwrap().len(),
+            MAX_ROWS + 1
+        );
+    }
+
+    #[test]
+    fn memory_database_finds_the_latest_run_of_each_task() {
+        let database = MemoryDatabase::default();
+        let started_at =
+            |hour: i64| NaiveDateTime::from_timestamp(0, 0) + chrono::Duration::hours(hour);
+        let run = |task_key: Option<&str>, hour, succeeded| {
+            database
+                .insert_task_run(models::NewTaskRun {
+                    task: "task".to_string(),
+                    runner: "runner".to_string(),
+                    succeeded,
+                    error: None,
+                    duration_ms: 0,
+                    started_at: started_at(hour),
+                    task_key: task_key.map(ToString::to_string),
+                })
+                .unwrap()
+        };
+        run(Some("b"), 2, false);
+        run(Some("a"), 1, false);
+        let latest_b = run(Some("b"), 3, true);
+        run(Some("b"), 0, false);
+        let latest_a = run(Some("a"), 1, true);
+        run(None, 4, false);
+
+        let latest = database.latest_task_runs().unwrap();
+
+        assert_eq!(
+            latest.iter().map(|run| run.id).collect::<Vec<_>>(),
+            vec![latest_a.id, latest_b.id]
+        );
+    }
+}
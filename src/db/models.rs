@@ -1,30 +1,108 @@
 use actix::Message;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::{Insertable, Queryable};
 use egg_mode::tweet::Tweet as EggModeTweet;
 use serde::{Deserialize, Serialize};
 
-use crate::schema::{disk_usage, tasks, tweets};
+use crate::schema::{
+    acknowledgements, alerts, api_cache, articles, completed_tasks, crypto_prices, dead_letters,
+    disk_usage, disk_usage_rollups, github_seen, last_alerted, outbox, registered_tasks, silences,
+    task_runs, task_schedules, tasks, tweets,
+};
 
 #[derive(Queryable, Clone, Debug)]
 pub struct Task {
     pub id: i32,
     pub task: String,
     pub sent_at: NaiveDateTime,
+    /// The key of the scheduled task that was run, see
+    /// `ScheduledTaskConfig::key`
+    pub task_key: Option<String>,
 }
 
 #[derive(Insertable)]
 #[table_name = "tasks"]
 pub struct NewTask {
     pub task: String,
+    pub task_key: Option<String>,
 }
 
 impl NewTask {
-    pub fn new<S: Into<String>>(task: S) -> Self {
-        NewTask { task: task.into() }
+    pub fn new<S: Into<String>>(task: S, task_key: S) -> Self {
+        NewTask {
+            task: task.into(),
+            task_key: Some(task_key.into()),
+        }
     }
 }
 
+/// A one-shot task that has run, and won't run again
+#[derive(Queryable, Clone, Debug)]
+pub struct CompletedTask {
+    pub task: String,
+    pub scheduled_for: NaiveDateTime,
+    pub completed_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "completed_tasks"]
+pub struct NewCompletedTask {
+    pub task: String,
+    pub scheduled_for: NaiveDateTime,
+}
+
+/// When a task is next scheduled to run, so that runs missed while
+/// pulse was down can be caught up on
+#[derive(Queryable, Insertable, Clone, Debug)]
+#[table_name = "task_schedules"]
+pub struct TaskSchedule {
+    /// See `ScheduledTaskConfig::key`
+    pub task_key: String,
+    pub next_run: NaiveDateTime,
+}
+
+/// A task added to the scheduler at runtime, as its config serialized
+/// to JSON
+#[derive(Queryable, Clone, Debug)]
+pub struct RegisteredTask {
+    pub id: i32,
+    pub config: String,
+    pub registered_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "registered_tasks"]
+pub struct NewRegisteredTask {
+    pub config: String,
+}
+
+/// How a task runner handled a scheduled task
+#[derive(Queryable, Clone, Debug)]
+pub struct TaskRun {
+    pub id: i32,
+    pub task: String,
+    pub runner: String,
+    pub succeeded: bool,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub started_at: NaiveDateTime,
+    /// The key of the scheduled task that was run, see
+    /// `ScheduledTaskConfig::key`
+    pub task_key: Option<String>,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "task_runs"]
+pub struct NewTaskRun {
+    pub task: String,
+    pub runner: String,
+    pub succeeded: bool,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub started_at: NaiveDateTime,
+    pub task_key: Option<String>,
+}
+
 #[derive(Queryable, Clone, Debug, Message, Serialize, Deserialize)]
 #[rtype(result = "()")]
 #[serde(rename_all = "snake_case")]
@@ -41,6 +119,18 @@ impl Into<String> for DiskUsage {
     }
 }
 
+/// Disk usage samples older than the rollup age, averaged over an hour
+#[derive(Queryable, Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DiskUsageRollup {
+    pub id: i32,
+    pub mount: String,
+    pub hour: NaiveDateTime,
+    pub average_percent_used: f64,
+    pub max_percent_used: f64,
+    pub samples: i64,
+}
+
 #[derive(Debug, Insertable, Clone)]
 #[table_name = "disk_usage"]
 pub struct NewDiskUsage {
@@ -72,6 +162,11 @@ pub struct Tweet {
     pub lang: Option<String>,
     pub text: String,
     pub tweeted_at: NaiveDateTime,
+    /// `author` if the tweet was tracked because of who wrote it,
+    /// otherwise `keyword`
+    pub matched_by: String,
+    /// From -1 (negative) to 1 (positive), if sentiment is scored
+    pub sentiment: Option<f64>,
 }
 
 impl Into<String> for Tweet {
@@ -93,10 +188,23 @@ pub struct NewTweet {
     pub lang: Option<String>,
     pub text: String,
     pub tweeted_at: NaiveDateTime,
+    /// `author` if the tweet was tracked because of who wrote it,
+    /// otherwise `keyword`
+    pub matched_by: String,
+    /// From -1 (negative) to 1 (positive), if sentiment is scored
+    pub sentiment: Option<f64>,
 }
 
 impl NewTweet {
-    pub fn from_egg_mode_tweet(group_name: String, egg_mode_tweet: EggModeTweet) -> Self {
+    pub fn from_egg_mode_tweet(
+        group_name: String,
+        followed: &[u64],
+        egg_mode_tweet: EggModeTweet,
+    ) -> Self {
+        let matched_by = match &egg_mode_tweet.user {
+            Some(user) if followed.contains(&user.id) => "author",
+            _ => "keyword",
+        };
         Self {
             twitter_tweet_id: egg_mode_tweet.id.to_string(),
             group_name,
@@ -108,6 +216,220 @@ impl NewTweet {
             lang: egg_mode_tweet.lang,
             text: egg_mode_tweet.text,
             tweeted_at: egg_mode_tweet.created_at.naive_utc(),
+            matched_by: matched_by.to_string(),
+            sentiment: None,
         }
     }
 }
+
+#[derive(Queryable, Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Acknowledgement {
+    pub id: i32,
+    pub event_key: String,
+    pub acknowledged_at: NaiveDateTime,
+    pub resolved_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "acknowledgements"]
+pub struct NewAcknowledgement {
+    pub event_key: String,
+}
+
+impl NewAcknowledgement {
+    pub fn new<S: Into<String>>(event_key: S) -> Self {
+        NewAcknowledgement {
+            event_key: event_key.into(),
+        }
+    }
+}
+
+/// Alerts for events of `event_type`, or with `event_key`, are muted
+/// until the silence expires
+#[derive(Queryable, Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Silence {
+    pub id: i32,
+    pub event_type: Option<String>,
+    pub event_key: Option<String>,
+    pub comment: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "silences"]
+pub struct NewSilence {
+    pub event_type: Option<String>,
+    pub event_key: Option<String>,
+    pub comment: Option<String>,
+    pub expires_at: NaiveDateTime,
+}
+
+/// The last time an alert was sent for an event
+#[derive(Queryable, Insertable, Clone, Debug)]
+#[table_name = "last_alerted"]
+pub struct LastAlerted {
+    pub event_key: String,
+    pub alerted_at: NaiveDateTime,
+}
+
+impl LastAlerted {
+    pub fn new<S: Into<String>>(event_key: S, alerted_at: NaiveDateTime) -> Self {
+        LastAlerted {
+            event_key: event_key.into(),
+            alerted_at,
+        }
+    }
+}
+
+/// An alert that was sent, or that failed to send after every retry
+#[derive(Queryable, Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Alert {
+    pub id: i32,
+    pub event_type: Option<String>,
+    pub event_keys: Vec<String>,
+    pub medium: String,
+    pub subject: String,
+    pub outcome: String,
+    pub error: Option<String>,
+    pub sent_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "alerts"]
+pub struct NewAlert {
+    pub event_type: Option<String>,
+    pub event_keys: Vec<String>,
+    pub medium: String,
+    pub subject: String,
+    pub outcome: String,
+    pub error: Option<String>,
+}
+
+/// A delivery that failed even after being retried
+#[derive(Queryable, Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DeadLetter {
+    pub id: i32,
+    pub medium: String,
+    pub subject: String,
+    pub body: String,
+    pub error: String,
+    pub attempts: i32,
+    pub failed_at: NaiveDateTime,
+    pub replayed_at: Option<NaiveDateTime>,
+    pub text_body: String,
+    pub event_type: Option<String>,
+    pub event_keys: Vec<String>,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "dead_letters"]
+pub struct NewDeadLetter {
+    pub medium: String,
+    pub subject: String,
+    pub body: String,
+    pub text_body: String,
+    pub error: String,
+    pub attempts: i32,
+    pub event_type: Option<String>,
+    pub event_keys: Vec<String>,
+}
+
+/// An event written to the durable outbox, as json
+#[derive(Queryable, Clone, Debug)]
+pub struct OutboxEvent {
+    pub id: i32,
+    pub event: String,
+    pub created_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "outbox"]
+pub struct NewOutboxEvent {
+    pub event: String,
+}
+
+/// A cryptocurrency price, recorded each time prices are checked
+#[derive(Queryable, Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CryptoPrice {
+    pub id: i32,
+    pub asset: String,
+    pub currency: String,
+    pub price: f64,
+    pub percent_change_24h: Option<f64>,
+    pub recorded_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "crypto_prices"]
+pub struct NewCryptoPrice {
+    pub asset: String,
+    pub currency: String,
+    pub price: f64,
+    pub percent_change_24h: Option<f64>,
+}
+
+/// A github release, tag or notification that has already been
+/// broadcast. `kind` is one of `release`, `tag` or `notification`
+#[derive(Queryable, Clone, Debug)]
+pub struct GitHubSeen {
+    pub id: i32,
+    pub kind: String,
+    pub repository: String,
+    pub item_id: String,
+    pub seen_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "github_seen"]
+pub struct NewGitHubSeen {
+    pub kind: String,
+    pub repository: String,
+    pub item_id: String,
+}
+
+/// A response from an upstream api, serialized as json
+#[derive(Queryable, Insertable, Clone, Debug)]
+#[table_name = "api_cache"]
+pub struct CachedResponse {
+    pub key: String,
+    pub body: String,
+    pub fetched_at: NaiveDateTime,
+}
+
+/// A newscast that was sent, whose articles are archived
+#[derive(Queryable, Clone, Debug)]
+pub struct Digest {
+    pub id: i32,
+    pub sent_at: NaiveDateTime,
+}
+
+/// An article included in a newscast. `source` is the title of the
+/// section that it appeared in
+#[derive(Queryable, Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Article {
+    pub id: i32,
+    pub digest_id: i32,
+    pub url: String,
+    pub title: String,
+    pub source: String,
+    pub summary: String,
+    pub published_date: NaiveDate,
+}
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "articles"]
+pub struct NewArticle {
+    pub url: String,
+    pub title: String,
+    pub source: String,
+    pub summary: String,
+    pub published_date: NaiveDate,
+}
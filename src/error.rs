@@ -2,6 +2,11 @@ pub mod future;
 
 use std::{fmt, io, path::PathBuf, result};
 
+use actix_web::{
+    error::BlockingError,
+    http::{header, StatusCode},
+    HttpResponse, ResponseError,
+};
 use failure::{Backtrace, Context, Fail};
 
 pub type Result<T> = result::Result<T, Error>;
@@ -12,10 +17,10 @@ pub struct Error {
 }
 
 impl Error {
-    // /// Return the kind of this error.
-    // pub fn kind(&self) -> &ErrorKind {
-    //     self.ctx.get_context()
-    // }
+    /// Return the kind of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        self.ctx.get_context()
+    }
 
     pub fn invalid_unicode_path(path: PathBuf) -> Self {
         ErrorKind::InvalidUnicodePath { path }.into()
@@ -24,6 +29,142 @@ impl Error {
     pub fn unconfigured_email() -> Self {
         ErrorKind::UnconfiguredEmail.into()
     }
+
+    pub fn unconfigured_medium<S: Into<String>>(medium: S) -> Self {
+        ErrorKind::UnconfiguredMedium {
+            medium: medium.into(),
+        }
+        .into()
+    }
+
+    pub fn unconfigured_broadcast() -> Self {
+        ErrorKind::UnconfiguredBroadcast.into()
+    }
+
+    pub fn unknown_alert<S: Into<String>>(event_key: S) -> Self {
+        ErrorKind::UnknownAlert {
+            event_key: event_key.into(),
+        }
+        .into()
+    }
+
+    pub fn unknown_silence(id: i32) -> Self {
+        ErrorKind::UnknownSilence { id }.into()
+    }
+
+    pub fn invalid_request<S: Into<String>>(error: S) -> Self {
+        ErrorKind::InvalidRequest {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn unauthorized() -> Self {
+        ErrorKind::Unauthorized.into()
+    }
+
+    pub fn rate_limited(retry_after: u64) -> Self {
+        ErrorKind::RateLimited { retry_after }.into()
+    }
+
+    pub fn unknown_task(id: usize) -> Self {
+        ErrorKind::UnknownTask { id }.into()
+    }
+
+    pub fn outbox_full() -> Self {
+        ErrorKind::OutboxFull.into()
+    }
+
+    pub fn oauth2<S: Into<String>>(error: S) -> Self {
+        ErrorKind::OAuth2Error {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn calendar<S: Into<String>>(error: S) -> Self {
+        ErrorKind::CalendarError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn feed<S: Into<String>>(error: S) -> Self {
+        ErrorKind::FeedError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn reddit<S: Into<String>>(error: S) -> Self {
+        ErrorKind::RedditError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn weather<S: Into<String>>(error: S) -> Self {
+        ErrorKind::WeatherError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn market_data<S: Into<String>>(error: S) -> Self {
+        ErrorKind::MarketDataError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn podcast<S: Into<String>>(error: S) -> Self {
+        ErrorKind::PodcastError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn imap<S: Into<String>>(error: S) -> Self {
+        ErrorKind::ImapError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn github<S: Into<String>>(error: S) -> Self {
+        ErrorKind::GitHubError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn new_york_times<S: Into<String>>(error: S) -> Self {
+        ErrorKind::NewYorkTimesError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn youtube<S: Into<String>>(error: S) -> Self {
+        ErrorKind::YouTubeError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn guardian<S: Into<String>>(error: S) -> Self {
+        ErrorKind::GuardianError {
+            error: error.into(),
+        }
+        .into()
+    }
+
+    pub fn invalid_config<S: Into<String>>(error: S) -> Self {
+        ErrorKind::InvalidConfig {
+            error: error.into(),
+        }
+        .into()
+    }
 }
 
 impl Fail for Error {
@@ -42,6 +183,37 @@ impl fmt::Display for Error {
     }
 }
 
+/// Allow errors to be returned from http handlers
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self.kind() {
+            ErrorKind::UnknownAlert { .. }
+            | ErrorKind::UnknownSilence { .. }
+            | ErrorKind::UnknownTask { .. } => StatusCode::NOT_FOUND,
+            ErrorKind::InvalidRequest { .. }
+            | ErrorKind::InvalidConfig { .. }
+            | ErrorKind::TomlError { .. }
+            | ErrorKind::CronError { .. }
+            | ErrorKind::UnconfiguredMedium { .. } => StatusCode::BAD_REQUEST,
+            ErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorKind::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ErrorKind::UnconfiguredBroadcast => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Rate limited clients are told how many seconds to wait
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let ErrorKind::RateLimited { retry_after } = self.kind() {
+            response.header(header::RETRY_AFTER, retry_after.to_string());
+        }
+        response
+            .content_type("text/plain; charset=utf-8")
+            .body(self.to_string())
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Fail)]
 pub enum ErrorKind {
     #[fail(display = "invalid unicode path: {:?}", path)]
@@ -50,17 +222,47 @@ pub enum ErrorKind {
     #[fail(display = "email is not configured")]
     UnconfiguredEmail,
 
+    #[fail(display = "{} is not configured", medium)]
+    UnconfiguredMedium { medium: String },
+
+    #[fail(display = "broadcast is not configured")]
+    UnconfiguredBroadcast,
+
+    #[fail(display = "no active alert with key: {}", event_key)]
+    UnknownAlert { event_key: String },
+
+    #[fail(display = "no active silence with id: {}", id)]
+    UnknownSilence { id: i32 },
+
+    #[fail(display = "invalid request: {}", error)]
+    InvalidRequest { error: String },
+
+    #[fail(display = "missing or invalid credentials")]
+    Unauthorized,
+
+    #[fail(display = "too many requests, retry after {} seconds", retry_after)]
+    RateLimited { retry_after: u64 },
+
+    #[fail(display = "no scheduled task with id: {}", id)]
+    UnknownTask { id: usize },
+
+    #[fail(display = "invalid configuration: {}", error)]
+    InvalidConfig { error: String },
+
     #[fail(display = "error sending email: {}", error)]
     EmailError { error: String },
 
+    #[fail(display = "oauth2 error: {}", error)]
+    OAuth2Error { error: String },
+
     #[fail(display = "no user home directory found")]
     NoHomeDirectory,
 
     #[fail(display = "chrono error: {}", error)]
     ChronoError { error: String },
 
-    #[fail(display = "crossbeam error: {}", error)]
-    CrossbeamError { error: String },
+    #[fail(display = "the broadcast outbox is full")]
+    OutboxFull,
 
     #[fail(display = "twitter error: {}", error)]
     TwitterError { error: String },
@@ -74,12 +276,48 @@ pub enum ErrorKind {
     #[fail(display = "new york times error: {}", error)]
     NewYorkTimesError { error: String },
 
+    #[fail(display = "reddit error: {}", error)]
+    RedditError { error: String },
+
+    #[fail(display = "weather error: {}", error)]
+    WeatherError { error: String },
+
+    #[fail(display = "market data error: {}", error)]
+    MarketDataError { error: String },
+
+    #[fail(display = "podcast error: {}", error)]
+    PodcastError { error: String },
+
+    #[fail(display = "imap error: {}", error)]
+    ImapError { error: String },
+
+    #[fail(display = "github error: {}", error)]
+    GitHubError { error: String },
+
+    #[fail(display = "youtube error: {}", error)]
+    YouTubeError { error: String },
+
+    #[fail(display = "guardian error: {}", error)]
+    GuardianError { error: String },
+
+    #[fail(display = "calendar error: {}", error)]
+    CalendarError { error: String },
+
+    #[fail(display = "feed error: {}", error)]
+    FeedError { error: String },
+
     #[fail(display = "error parsing cron expression: {}", error)]
     CronError { error: String },
 
     #[fail(display = "actix send error: {}", error)]
     ActixSendError { error: String },
 
+    #[fail(display = "actix mailbox error: {}", error)]
+    ActixMailboxError { error: String },
+
+    #[fail(display = "blocking operation was canceled")]
+    BlockingCanceled,
+
     #[fail(display = "io error: {}", error)]
     IoError { error: String },
 
@@ -88,6 +326,9 @@ pub enum ErrorKind {
 
     #[fail(display = "database query error: {}", error)]
     DatabaseQueryError { error: String },
+
+    #[fail(display = "database migration error: {}", error)]
+    DatabaseMigrationError { error: String },
 }
 
 impl From<ErrorKind> for Error {
@@ -135,6 +376,15 @@ impl From<diesel::result::Error> for Error {
     }
 }
 
+/// map from migration errors
+impl From<diesel_migrations::RunMigrationsError> for Error {
+    fn from(error: diesel_migrations::RunMigrationsError) -> Error {
+        Error::from(Context::new(ErrorKind::DatabaseMigrationError {
+            error: error.to_string(),
+        }))
+    }
+}
+
 /// map from toml errors
 impl From<cron::error::Error> for Error {
     fn from(error: cron::error::Error) -> Error {
@@ -162,15 +412,25 @@ impl<T> From<actix::prelude::SendError<T>> for Error {
     }
 }
 
-/// map from crossbeam errors
-impl<T> From<crossbeam::queue::PushError<T>> for Error {
-    fn from(error: crossbeam::queue::PushError<T>) -> Error {
-        Error::from(Context::new(ErrorKind::CrossbeamError {
+/// map from actix mailbox errors
+impl From<actix::MailboxError> for Error {
+    fn from(error: actix::MailboxError) -> Error {
+        Error::from(Context::new(ErrorKind::ActixMailboxError {
             error: error.to_string(),
         }))
     }
 }
 
+/// map from errors in blocking http handler operations
+impl From<BlockingError<Error>> for Error {
+    fn from(error: BlockingError<Error>) -> Error {
+        match error {
+            BlockingError::Error(error) => error,
+            BlockingError::Canceled => ErrorKind::BlockingCanceled.into(),
+        }
+    }
+}
+
 /// map from email errors
 impl From<lettre::smtp::error::Error> for Error {
     fn from(error: lettre::smtp::error::Error) -> Error {
@@ -180,6 +440,24 @@ impl From<lettre::smtp::error::Error> for Error {
     }
 }
 
+/// map from tls errors
+impl From<native_tls::Error> for Error {
+    fn from(error: native_tls::Error) -> Error {
+        Error::from(Context::new(ErrorKind::EmailError {
+            error: error.to_string(),
+        }))
+    }
+}
+
+/// map from email building errors
+impl From<lettre_email::error::Error> for Error {
+    fn from(error: lettre_email::error::Error) -> Error {
+        Error::from(Context::new(ErrorKind::EmailError {
+            error: error.to_string(),
+        }))
+    }
+}
+
 /// map from twitter errors
 impl From<egg_mode::error::Error> for Error {
     fn from(error: egg_mode::error::Error) -> Error {
@@ -1,3 +1,4 @@
+mod cache;
 mod config;
 mod constants;
 mod db;
@@ -10,19 +11,27 @@ mod services;
 // https://github.com/diesel-rs/diesel/pull/1956
 #[macro_use]
 extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
 
 use std::env;
 
-use actix::{Actor, Addr};
-use actix_files::Files;
+use actix::{Actor, Addr, Arbiter};
 use actix_web::{middleware, web, App, HttpServer};
-use actix_web_actors::ws;
 
 use crate::{
     error::Result,
-    routes::Ws,
     services::{
-        broadcast::Broadcast, news::News, scheduler::Scheduler, system::SystemMonitor,
+        broadcast::{Broadcast, Outbox},
+        crypto::Crypto,
+        database::DatabaseMonitor,
+        github::GitHub,
+        mailbox::Mailbox,
+        markets::Markets,
+        news::News,
+        podcasts::Podcasts,
+        scheduler::Scheduler,
+        system::SystemMonitor,
         twitter::Twitter,
     },
 };
@@ -30,39 +39,69 @@ use crate::{
 #[actix_rt::main]
 async fn main() -> Result<()> {
     env::set_var("RUST_LOG", "actix_server=info,actix_web=info,pulse=info");
-    pretty_env_logger::init();
+    routes::request_log::init_logger();
 
     config::initialize_from_file()?;
-    db::initialize_postgres()?;
-    log::info!("Database connection initialized");
+    db::initialize()?;
+    log::info!("Database initialized");
+
+    // Events for broadcast are queued in the outbox by other services
+    let outbox = Outbox::from_config(&config::config().broadcast.outbox.unwrap_or_default());
+    DatabaseMonitor::new(outbox.clone()).map(|d| d.start());
 
-    // Only start broadcast and twitter actors if they have been configured
-    Broadcast::new()?.map(|b| b.start());
-    Twitter::new().map(|t| t.start());
+    // Only start broadcast and twitter actors if they have been
+    // configured. Broadcast gets its own arbiter so that it keeps
+    // draining the outbox while producers are blocked on a full one
+    let broadcast = Broadcast::new(outbox.clone())?
+        .map(|b| Broadcast::start_in_arbiter(&Arbiter::new(), |_| b));
+    let twitter = Twitter::new(outbox.clone()).map(|t| t.start());
+    Markets::new(outbox.clone()).map(|m| m.start());
+    Crypto::new(outbox.clone()).map(|c| c.start());
+    GitHub::new(outbox.clone()).map(|g| g.start());
+    Mailbox::new(outbox.clone()).map(|m| m.start());
+    Podcasts::new(outbox.clone()).map(|p| p.start());
 
-    let monitor = SystemMonitor::new().start();
+    let monitor = SystemMonitor::new(outbox.clone()).start();
 
-    let news_addr = News::new().start();
-    let mut scheduler = Scheduler::new();
-    scheduler.add_task_runner(Addr::recipient(news_addr));
-    scheduler.start();
+    let news = News::new(outbox.clone()).start();
+    let mut scheduler = Scheduler::new(outbox.clone());
+    scheduler.add_task_runner("news", Addr::recipient(news.clone()));
+    let scheduler = scheduler.start();
     log::info!("Scheduler started");
 
+    let rate_limit = routes::rate_limit::RateLimit::new(config::config().rate_limit);
     HttpServer::new(move || {
         App::new()
+            .wrap(routes::cors::middleware(config::config().cors.as_ref()))
             .wrap(middleware::DefaultHeaders::new().header("X-Version", "0.2"))
-            .wrap(middleware::Compress::default())
-            .wrap(middleware::Logger::default())
-            .data(monitor.clone())
-            .service(web::resource("/ws").to(
-                |request, stream: web::Payload, monitor: web::Data<Addr<SystemMonitor>>| async move {
-                    ws::start(Ws::new(monitor.as_ref().clone()), &request, stream)
-                }
+            .wrap(routes::compression::middleware(
+                config::config().compression.as_ref(),
             ))
-            .service(Files::new("/", "./webapp/dist/webapp/").index_file("index.html"))
+            .wrap(routes::request_log::RequestLog)
+            .data(monitor.clone())
+            .data(broadcast.clone())
+            .data(outbox.clone())
+            .data(twitter.clone())
+            .data(news.clone())
+            .data(scheduler.clone())
+            .configure(routes::health::configure)
+            .configure(routes::metrics::configure)
+            .service(
+                web::scope("/api")
+                    .wrap(routes::auth::ApiAuth)
+                    .wrap(rate_limit.clone())
+                    .configure(routes::api::configure)
+                    .configure(routes::graphql::configure),
+            )
+            .service(
+                web::resource("/ws")
+                    .wrap(rate_limit.clone())
+                    .to(routes::ws::index),
+            )
+            .configure(routes::webapp::configure)
     })
-        .bind("0.0.0.0:8088")?
-        .run()
-        .await
-        .map_err(Into::into)
+    .bind("0.0.0.0:8088")?
+    .run()
+    .await
+    .map_err(Into::into)
 }
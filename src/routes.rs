@@ -1,3 +1,14 @@
-mod ws;
-
-pub use ws::Ws;
+pub mod api;
+pub mod auth;
+pub mod compression;
+pub mod cors;
+pub mod export;
+pub mod graphql;
+pub mod health;
+pub mod metrics;
+pub mod openapi;
+pub mod rate_limit;
+pub mod request_log;
+pub mod sse;
+pub mod webapp;
+pub mod ws;
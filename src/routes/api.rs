@@ -0,0 +1,667 @@
+use actix::Addr;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{export, openapi, sse};
+use crate::{
+    config,
+    db::{database, Page, SortOrder},
+    error::{Error, Result},
+    services::{
+        broadcast::{
+            self, Acknowledge, AddSilence, Broadcast, BroadcastEvent, BroadcastEventType, Outbox,
+            RemoveSilence, ReplayDeadLetters, Severity,
+        },
+        news::News,
+        scheduler::{GetSchedule, Pause, RegisterTask, Resume, Scheduler, SetEnabled},
+        system::SystemMonitor,
+        ReloadConfig,
+    },
+};
+
+/// Register all routes of the http api
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/acknowledgements")
+            .route(web::get().to(acknowledgements))
+            .route(web::post().to(acknowledge)),
+    )
+    .service(web::resource("/alerts").route(web::get().to(alerts)))
+    .service(web::resource("/articles").route(web::get().to(articles)))
+    .service(web::resource("/config").route(web::get().to(running_config)))
+    .service(web::resource("/config/reload").route(web::post().to(reload_config)))
+    .service(web::resource("/crypto-prices").route(web::get().to(crypto_prices)))
+    .service(web::resource("/dead-letters").route(web::get().to(dead_letters)))
+    .service(web::resource("/dead-letters/replay").route(web::post().to(replay_dead_letters)))
+    .service(web::resource("/disk-usage").route(web::get().to(disk_usage)))
+    .service(web::resource("/disk-usage/rollups").route(web::get().to(disk_usage_rollups)))
+    .service(web::resource("/events").route(web::post().to(push_event)))
+    .service(web::resource("/events/stream").route(web::get().to(sse::events_stream)))
+    .service(web::resource("/export").route(web::get().to(export::export)))
+    .service(web::resource("/ingest").route(web::post().to(ingest)))
+    .service(web::resource("/openapi.json").route(web::get().to(openapi::document)))
+    .service(web::resource("/outbox").route(web::get().to(outbox)))
+    .service(web::resource("/schedule").route(web::get().to(schedule)))
+    .service(web::resource("/schedule/pause").route(web::post().to(pause_scheduler)))
+    .service(web::resource("/schedule/resume").route(web::post().to(resume_scheduler)))
+    .service(web::resource("/schedule/tasks").route(web::post().to(register_task)))
+    .service(web::resource("/schedule/tasks/{id}/pause").route(web::post().to(pause_task)))
+    .service(web::resource("/schedule/tasks/{id}/resume").route(web::post().to(resume_task)))
+    .service(web::resource("/schedule/tasks/{id}/enable").route(web::post().to(enable_task)))
+    .service(web::resource("/schedule/tasks/{id}/disable").route(web::post().to(disable_task)))
+    .service(
+        web::resource("/silences")
+            .route(web::get().to(silences))
+            .route(web::post().to(add_silence)),
+    )
+    .service(web::resource("/silences/{id}").route(web::delete().to(remove_silence)))
+    .service(web::resource("/task-runs").route(web::get().to(task_runs)))
+    .service(web::resource("/tasks").route(web::get().to(tasks)))
+    .service(web::resource("/tweets").route(web::get().to(tweets)));
+}
+
+/// The most rows a list may be limited to
+const MAX_PAGE_LIMIT: i64 = 1000;
+
+/// Pagination and sorting, shared by every list endpoint
+#[derive(Deserialize)]
+struct PageQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+    order: Option<SortOrder>,
+}
+
+impl PageQuery {
+    /// The page of rows to load, with the endpoint's defaults for what
+    /// wasn't given
+    fn page(&self, default_limit: Option<i64>, default_order: SortOrder) -> Result<Page> {
+        let limit = self.limit.or(default_limit);
+        let offset = self.offset.unwrap_or(0);
+        if limit.map_or(false, |limit| limit < 0) || offset < 0 {
+            return Err(Error::invalid_request(
+                "limit and offset must not be negative",
+            ));
+        }
+
+        Ok(Page {
+            limit: limit.map(|limit| limit.min(MAX_PAGE_LIMIT)),
+            offset,
+            order: self.order.unwrap_or(default_order),
+        })
+    }
+}
+
+/// Get the broadcast actor, if it has been configured
+fn broadcast_addr(broadcast: &web::Data<Option<Addr<Broadcast>>>) -> Result<&Addr<Broadcast>> {
+    broadcast
+        .get_ref()
+        .as_ref()
+        .ok_or_else(Error::unconfigured_broadcast)
+}
+
+#[derive(Deserialize)]
+struct AcknowledgeRequest {
+    event_key: String,
+}
+
+/// List acknowledged alerts that have not yet resolved
+async fn acknowledgements() -> Result<HttpResponse> {
+    let acknowledgements = web::block(|| database().active_acknowledgements()).await?;
+    Ok(HttpResponse::Ok().json(acknowledgements))
+}
+
+/// Acknowledge an active alert, so that it is not re-triggered until
+/// it resolves
+async fn acknowledge(
+    broadcast: web::Data<Option<Addr<Broadcast>>>,
+    request: web::Json<AcknowledgeRequest>,
+) -> Result<HttpResponse> {
+    broadcast_addr(&broadcast)?
+        .send(Acknowledge(request.into_inner().event_key.into()))
+        .await??;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// The number of alerts listed when no limit is given
+const DEFAULT_ALERTS_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+struct AlertsQuery {
+    event_type: Option<String>,
+    outcome: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+/// List the most recent alerts that were sent or failed to send,
+/// optionally only those for one type of event, with one outcome, or
+/// within a range
+async fn alerts(
+    query: web::Query<AlertsQuery>,
+    page: web::Query<PageQuery>,
+) -> Result<HttpResponse> {
+    let AlertsQuery {
+        event_type,
+        outcome,
+        from,
+        to,
+    } = query.into_inner();
+    let page = page.page(Some(DEFAULT_ALERTS_LIMIT), SortOrder::Desc)?;
+    let alerts = web::block(move || {
+        database().search_alerts(
+            event_type,
+            outcome,
+            from.map(|from| from.naive_utc()),
+            to.map(|to| to.naive_utc()),
+            page,
+        )
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(alerts))
+}
+
+/// Articles listed when no limit is given
+const DEFAULT_ARTICLES_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+struct ArticlesQuery {
+    digest: Option<i32>,
+    source: Option<String>,
+    search: Option<String>,
+}
+
+/// List the articles archived from past newscasts, most recent first
+async fn articles(
+    query: web::Query<ArticlesQuery>,
+    page: web::Query<PageQuery>,
+) -> Result<HttpResponse> {
+    let ArticlesQuery {
+        digest,
+        source,
+        search,
+    } = query.into_inner();
+    let page = page.page(Some(DEFAULT_ARTICLES_LIMIT), SortOrder::Desc)?;
+    let articles =
+        web::block(move || database().search_articles(digest, source, search, page)).await?;
+    Ok(HttpResponse::Ok().json(articles))
+}
+
+/// The config the daemon is running with, with secrets redacted
+async fn running_config() -> HttpResponse {
+    HttpResponse::Ok().json(config::config().redacted())
+}
+
+/// Re-read the config file and apply it to the running services. The
+/// config is rejected, and nothing is applied, if it is invalid.
+/// Server settings and schedules only change on restart
+async fn reload_config(
+    monitor: web::Data<Addr<SystemMonitor>>,
+    broadcast: web::Data<Option<Addr<Broadcast>>>,
+    news: web::Data<Addr<News>>,
+) -> Result<HttpResponse> {
+    let config = web::block(config::read_file).await?;
+
+    // broadcast goes first, since it is the only one that can reject
+    // the config
+    if let Some(broadcast) = broadcast.get_ref() {
+        broadcast.send(ReloadConfig(config.clone())).await??;
+    }
+    monitor.send(ReloadConfig(config.clone())).await??;
+    news.send(ReloadConfig(config.clone())).await??;
+
+    config::initialize_from(config);
+    log::info!("Config reloaded");
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Hours of crypto price history listed when no range is given
+const DEFAULT_CRYPTO_PRICE_HOURS: i64 = 24;
+
+/// The most hours of crypto price history that can be listed, a year
+const MAX_CRYPTO_PRICE_HOURS: i64 = 365 * 24;
+
+#[derive(Deserialize)]
+struct CryptoPricesQuery {
+    asset: Option<String>,
+    hours: Option<i64>,
+}
+
+/// List the crypto prices recorded in the last `hours`, up to a year,
+/// optionally only those for one asset
+async fn crypto_prices(query: web::Query<CryptoPricesQuery>) -> Result<HttpResponse> {
+    let CryptoPricesQuery { asset, hours } = query.into_inner();
+    let hours = hours.unwrap_or(DEFAULT_CRYPTO_PRICE_HOURS);
+    if hours <= 0 {
+        return Err(Error::invalid_request("hours must be positive"));
+    }
+    let since = Utc::now()
+        .naive_utc()
+        .checked_sub_signed(chrono::Duration::hours(hours.min(MAX_CRYPTO_PRICE_HOURS)))
+        .ok_or_else(|| Error::invalid_request("hours is out of range"))?;
+    let prices = web::block(move || database().crypto_prices_since(asset, since)).await?;
+    Ok(HttpResponse::Ok().json(prices))
+}
+
+#[derive(Serialize)]
+struct ReplayResponse {
+    replayed: usize,
+}
+
+/// List deliveries that failed and have not been successfully
+/// replayed
+async fn dead_letters() -> Result<HttpResponse> {
+    let dead_letters = web::block(|| database().pending_dead_letters()).await?;
+    Ok(HttpResponse::Ok().json(dead_letters))
+}
+
+/// Attempt every delivery in the dead letter queue again
+async fn replay_dead_letters(
+    broadcast: web::Data<Option<Addr<Broadcast>>>,
+) -> Result<HttpResponse> {
+    let replayed = broadcast_addr(&broadcast)?
+        .send(ReplayDeadLetters)
+        .await??;
+
+    Ok(HttpResponse::Ok().json(ReplayResponse { replayed }))
+}
+
+/// Hours of disk usage history listed when no start is given
+const DEFAULT_DISK_USAGE_HOURS: i64 = 24;
+
+#[derive(Deserialize)]
+struct DiskUsageQuery {
+    mount: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+/// List the disk usage recorded between `from` and `to` (the last day
+/// by default), optionally only for one mount
+async fn disk_usage(
+    query: web::Query<DiskUsageQuery>,
+    page: web::Query<PageQuery>,
+) -> Result<HttpResponse> {
+    let DiskUsageQuery { mount, from, to } = query.into_inner();
+    let to = to.unwrap_or_else(Utc::now);
+    let from = from.unwrap_or_else(|| to - chrono::Duration::hours(DEFAULT_DISK_USAGE_HOURS));
+    let page = page.page(None, SortOrder::Asc)?;
+    let disk_usage = web::block(move || {
+        database().disk_usage_between(mount, from.naive_utc(), to.naive_utc(), page)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(disk_usage))
+}
+
+/// Days of hourly disk usage rollups listed when no start is given
+const DEFAULT_DISK_USAGE_ROLLUP_DAYS: i64 = 30;
+
+/// List the hourly disk usage rollups between `from` and `to` (the
+/// last 30 days by default), optionally only for one mount
+async fn disk_usage_rollups(
+    query: web::Query<DiskUsageQuery>,
+    page: web::Query<PageQuery>,
+) -> Result<HttpResponse> {
+    let DiskUsageQuery { mount, from, to } = query.into_inner();
+    let to = to.unwrap_or_else(Utc::now);
+    let from = from.unwrap_or_else(|| to - chrono::Duration::days(DEFAULT_DISK_USAGE_ROLLUP_DAYS));
+    let page = page.page(None, SortOrder::Asc)?;
+    let rollups = web::block(move || {
+        database().disk_usage_rollups_between(mount, from.naive_utc(), to.naive_utc(), page)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(rollups))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EventRequest {
+    title: String,
+    body: Option<String>,
+    severity: Severity,
+    key: String,
+}
+
+#[derive(Serialize)]
+struct EventResponse {
+    event_key: String,
+}
+
+/// Broadcast a custom event. It follows the alert configured for
+/// `custom-event`, and events with the same key are deduplicated
+async fn push_event(
+    request: web::Json<EventRequest>,
+    outbox: web::Data<Outbox>,
+) -> Result<HttpResponse> {
+    let EventRequest {
+        title,
+        body,
+        severity,
+        key,
+    } = request.into_inner();
+    if title.trim().is_empty() || key.trim().is_empty() {
+        return Err(Error::invalid_request("title and key must not be empty"));
+    }
+
+    let event = BroadcastEvent::CustomEvent {
+        title,
+        body: body.unwrap_or_default(),
+        severity,
+        key,
+    };
+    let event_key = event.event_key().to_string();
+    let outbox = outbox.get_ref().clone();
+    web::block(move || outbox.push(event)).await?;
+
+    Ok(HttpResponse::Accepted().json(EventResponse { event_key }))
+}
+
+#[derive(Deserialize)]
+struct IngestQuery {
+    source: Option<String>,
+}
+
+#[derive(Serialize)]
+struct IngestResponse {
+    accepted: usize,
+}
+
+/// Accept events from outside of pulse, e.g. Alertmanager
+/// notifications, GitHub webhooks or messages from scripts, and
+/// broadcast them as generic messages
+async fn ingest(
+    request: HttpRequest,
+    query: web::Query<IngestQuery>,
+    payload: web::Json<serde_json::Value>,
+    outbox: web::Data<Outbox>,
+) -> Result<HttpResponse> {
+    let github_event = request
+        .headers()
+        .get("X-GitHub-Event")
+        .and_then(|value| value.to_str().ok())
+        .map(ToString::to_string);
+    let events = broadcast::ingest(
+        query.into_inner().source,
+        github_event,
+        payload.into_inner(),
+    )?;
+
+    let accepted = events.len();
+    let outbox = outbox.get_ref().clone();
+    web::block(move || {
+        events
+            .into_iter()
+            .map(|event| outbox.push(event))
+            .collect::<Result<Vec<_>>>()
+    })
+    .await?;
+
+    Ok(HttpResponse::Accepted().json(IngestResponse { accepted }))
+}
+
+/// The depth of the broadcast outbox and how many events overflowed it
+async fn outbox(outbox: web::Data<Outbox>) -> HttpResponse {
+    HttpResponse::Ok().json(outbox.stats())
+}
+
+/// The scheduled tasks, when they next run and whether they are
+/// paused
+async fn schedule(scheduler: web::Data<Addr<Scheduler>>) -> Result<HttpResponse> {
+    let schedule = scheduler.send(GetSchedule).await?;
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+/// Stop running every scheduled task until the scheduler is resumed
+async fn pause_scheduler(scheduler: web::Data<Addr<Scheduler>>) -> Result<HttpResponse> {
+    scheduler.send(Pause(None)).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn resume_scheduler(scheduler: web::Data<Addr<Scheduler>>) -> Result<HttpResponse> {
+    scheduler.send(Resume(None)).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Serialize)]
+struct RegisteredTask {
+    id: usize,
+}
+
+/// Add a scheduled task without restarting. It is saved, so it keeps
+/// running after a restart
+async fn register_task(
+    scheduler: web::Data<Addr<Scheduler>>,
+    task: web::Json<config::ScheduledTaskConfig>,
+) -> Result<HttpResponse> {
+    let id = scheduler.send(RegisterTask(task.into_inner())).await??;
+    Ok(HttpResponse::Created().json(RegisteredTask { id }))
+}
+
+/// Stop running one scheduled task until it is resumed
+async fn pause_task(
+    scheduler: web::Data<Addr<Scheduler>>,
+    id: web::Path<usize>,
+) -> Result<HttpResponse> {
+    scheduler.send(Pause(Some(id.into_inner()))).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn resume_task(
+    scheduler: web::Data<Addr<Scheduler>>,
+    id: web::Path<usize>,
+) -> Result<HttpResponse> {
+    scheduler.send(Resume(Some(id.into_inner()))).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Turn a disabled task back on, scheduling its next run
+async fn enable_task(
+    scheduler: web::Data<Addr<Scheduler>>,
+    id: web::Path<usize>,
+) -> Result<HttpResponse> {
+    let id = id.into_inner();
+    scheduler.send(SetEnabled { id, enabled: true }).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Turn a task off until it is enabled again
+async fn disable_task(
+    scheduler: web::Data<Addr<Scheduler>>,
+    id: web::Path<usize>,
+) -> Result<HttpResponse> {
+    let id = id.into_inner();
+    scheduler.send(SetEnabled { id, enabled: false }).await??;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// The longest a silence can last, a year
+const MAX_SILENCE_MINUTES: u64 = 365 * 24 * 60;
+
+#[derive(Deserialize)]
+struct SilenceRequest {
+    event_type: Option<BroadcastEventType>,
+    event_key: Option<String>,
+    minutes: u64,
+    comment: Option<String>,
+}
+
+/// List silences that have not yet expired
+async fn silences() -> Result<HttpResponse> {
+    let silences = web::block(|| database().active_silences()).await?;
+    Ok(HttpResponse::Ok().json(silences))
+}
+
+/// Mute alerts for an event type or a single event key for some
+/// number of minutes
+async fn add_silence(
+    broadcast: web::Data<Option<Addr<Broadcast>>>,
+    request: web::Json<SilenceRequest>,
+) -> Result<HttpResponse> {
+    let SilenceRequest {
+        event_type,
+        event_key,
+        minutes,
+        comment,
+    } = request.into_inner();
+    let seconds = minutes
+        .checked_mul(60)
+        .filter(|_| minutes <= MAX_SILENCE_MINUTES)
+        .ok_or_else(|| {
+            Error::invalid_request(format!(
+                "a silence can last at most {} minutes",
+                MAX_SILENCE_MINUTES
+            ))
+        })?;
+    let silence = broadcast_addr(&broadcast)?
+        .send(AddSilence {
+            event_type,
+            event_key,
+            duration: std::time::Duration::from_secs(seconds),
+            comment,
+        })
+        .await??;
+
+    Ok(HttpResponse::Created().json(silence))
+}
+
+/// Stop muting the alerts of a silence before it expires
+async fn remove_silence(
+    broadcast: web::Data<Option<Addr<Broadcast>>>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse> {
+    broadcast_addr(&broadcast)?
+        .send(RemoveSilence(id.into_inner()))
+        .await??;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// The number of task runs listed when no limit is given
+const DEFAULT_TASKS_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+struct TasksQuery {
+    task: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct TaskRun {
+    id: i32,
+    task: String,
+    sent_at: DateTime<Utc>,
+}
+
+/// List the most recent scheduled task runs, optionally only those for
+/// one task, e.g. `fetch-news`, or within a range
+async fn tasks(query: web::Query<TasksQuery>, page: web::Query<PageQuery>) -> Result<HttpResponse> {
+    let TasksQuery { task, from, to } = query.into_inner();
+    // tasks are recorded as json strings
+    let task = task.map(|task| format!("\"{}\"", task));
+    let page = page.page(Some(DEFAULT_TASKS_LIMIT), SortOrder::Desc)?;
+    let tasks = web::block(move || {
+        database().search_tasks(
+            task,
+            from.map(|from| from.naive_utc()),
+            to.map(|to| to.naive_utc()),
+            page,
+        )
+    })
+    .await?;
+
+    let tasks: Vec<_> = tasks
+        .into_iter()
+        .map(|task| TaskRun {
+            id: task.id,
+            task: task.task.trim_matches('"').to_string(),
+            sent_at: DateTime::from_utc(task.sent_at, Utc),
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(tasks))
+}
+
+/// The number of task runs listed when no limit is given
+const DEFAULT_TASK_RUNS_LIMIT: i64 = 10;
+
+#[derive(Deserialize)]
+struct TaskRunsQuery {
+    task: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TaskRunOutcome {
+    id: i32,
+    task: String,
+    runner: String,
+    succeeded: bool,
+    error: Option<String>,
+    duration_ms: i64,
+    started_at: DateTime<Utc>,
+}
+
+/// List how task runners handled the most recent runs of scheduled
+/// tasks, optionally only those for one task, e.g. `fetch-news`
+async fn task_runs(
+    query: web::Query<TaskRunsQuery>,
+    page: web::Query<PageQuery>,
+) -> Result<HttpResponse> {
+    // tasks are recorded as json strings
+    let task = query.into_inner().task.map(|task| format!("\"{}\"", task));
+    let page = page.page(Some(DEFAULT_TASK_RUNS_LIMIT), SortOrder::Desc)?;
+    let runs = web::block(move || database().search_task_runs(task, page)).await?;
+
+    let runs: Vec<_> = runs
+        .into_iter()
+        .map(|run| TaskRunOutcome {
+            id: run.id,
+            task: run.task.trim_matches('"').to_string(),
+            runner: run.runner,
+            succeeded: run.succeeded,
+            error: run.error,
+            duration_ms: run.duration_ms,
+            started_at: DateTime::from_utc(run.started_at, Utc),
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(runs))
+}
+
+/// The number of tweets listed when no limit is given
+const DEFAULT_TWEETS_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+struct TweetsQuery {
+    group: Option<String>,
+    search: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+/// List the most recent tweets recorded by the twitter service,
+/// optionally only those for one group, within a range, or containing
+/// some text
+async fn tweets(
+    query: web::Query<TweetsQuery>,
+    page: web::Query<PageQuery>,
+) -> Result<HttpResponse> {
+    let TweetsQuery {
+        group,
+        search,
+        from,
+        to,
+    } = query.into_inner();
+    let page = page.page(Some(DEFAULT_TWEETS_LIMIT), SortOrder::Desc)?;
+    let tweets = web::block(move || {
+        database().search_tweets(
+            group,
+            search,
+            from.map(|from| from.naive_utc()),
+            to.map(|to| to.naive_utc()),
+            page,
+        )
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(tweets))
+}
@@ -0,0 +1,203 @@
+use std::task::{Context, Poll};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::header,
+};
+use futures::future::{err, ok, Either, Ready};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    config::{config, AuthConfig},
+    error::Error,
+};
+
+/// Requires api requests to carry one of the configured api keys, in
+/// an `X-Api-Key` header or as a bearer token, or a configured basic
+/// auth login. Every request is allowed if neither is configured
+pub struct ApiAuth;
+
+impl<S, B> Transform<S> for ApiAuth
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = ApiAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ApiAuthMiddleware { service })
+    }
+}
+
+pub struct ApiAuthMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service for ApiAuthMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: ServiceRequest) -> Self::Future {
+        let authorized = match config().auth {
+            Some(auth) => authorized(&auth, &request),
+            None => true,
+        };
+
+        if authorized {
+            Either::Left(self.service.call(request))
+        } else {
+            log::warn!("Rejecting unauthorized api request to {}", request.path());
+            Either::Right(err(Error::unauthorized().into()))
+        }
+    }
+}
+
+fn authorized(auth: &AuthConfig, request: &ServiceRequest) -> bool {
+    if auth.api_keys.is_none() && auth.basic_auth.is_none() {
+        return true;
+    }
+
+    let api_key = request
+        .headers()
+        .get("X-Api-Key")
+        .and_then(|value| value.to_str().ok());
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let bearer = authorization.and_then(|value| strip_scheme(value, "Bearer"));
+    let basic = authorization.and_then(|value| strip_scheme(value, "Basic"));
+
+    let valid_api_key = auth.api_keys.iter().flatten().any(|key| {
+        api_key.map_or(false, |api_key| secure_eq(api_key, key))
+            || bearer.map_or(false, |bearer| secure_eq(bearer, key))
+    });
+
+    valid_api_key || basic.map_or(false, |basic| valid_basic_auth(auth, basic))
+}
+
+/// The credentials of an `Authorization` header using `scheme`
+fn strip_scheme<'a>(authorization: &'a str, scheme: &str) -> Option<&'a str> {
+    let mut parts = authorization.splitn(2, ' ');
+    match (parts.next(), parts.next()) {
+        (Some(s), Some(credentials)) if s.eq_ignore_ascii_case(scheme) => Some(credentials.trim()),
+        _ => None,
+    }
+}
+
+/// Whether base64 encoded `username:password` credentials match one of
+/// the configured logins
+fn valid_basic_auth(auth: &AuthConfig, credentials: &str) -> bool {
+    let decoded = match base64::decode(credentials)
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+    {
+        Some(decoded) => decoded,
+        None => return false,
+    };
+    let mut parts = decoded.splitn(2, ':');
+    let (username, password) = match (parts.next(), parts.next()) {
+        (Some(username), Some(password)) => (username, password),
+        _ => return false,
+    };
+
+    auth.basic_auth.iter().flatten().any(|login| {
+        // both are compared, so that the time taken doesn't reveal
+        // whether the username exists
+        let username_matches = login.username.as_bytes().ct_eq(username.as_bytes());
+        let password_matches = login.password.as_bytes().ct_eq(password.as_bytes());
+        (username_matches & password_matches).into()
+    })
+}
+
+/// Compare credentials in constant time, so that the time taken
+/// doesn't reveal how much of a guess was right
+fn secure_eq(given: &str, expected: &str) -> bool {
+    given.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+
+    use super::*;
+    use crate::config::{self, BasicAuthConfig, Config};
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    /// The status of a request to an api guarded by `ApiAuth`
+    async fn status(request: test::TestRequest) -> StatusCode {
+        let mut app =
+            test::init_service(App::new().wrap(ApiAuth).route("/", web::get().to(ok))).await;
+        match app.call(request.to_request()).await {
+            Ok(response) => response.status(),
+            Err(e) => e.as_response_error().status_code(),
+        }
+    }
+
+    /// The config is global, so every case runs in one test
+    #[actix_rt::test]
+    async fn api_auth_requires_configured_credentials() {
+        config::initialize_from(Config::default());
+        assert_eq!(status(test::TestRequest::get()).await, StatusCode::OK);
+
+        config::initialize_from(Config {
+            auth: Some(AuthConfig {
+                websocket_tokens: None,
+                api_keys: Some(vec!["api-key".to_string()]),
+                basic_auth: Some(vec![BasicAuthConfig {
+                    username: "admin".to_string(),
+                    password: "hunter2".to_string(),
+                }]),
+            }),
+            ..Config::default()
+        });
+        let basic = |login: &str| format!("Basic {}", base64::encode(login));
+
+        assert_eq!(
+            status(test::TestRequest::get()).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(test::TestRequest::get().header("X-Api-Key", "wrong-key")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(test::TestRequest::get().header("X-Api-Key", "api-key")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(test::TestRequest::get().header(header::AUTHORIZATION, "Bearer api-key")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(test::TestRequest::get().header(header::AUTHORIZATION, basic("admin:hunter2")))
+                .await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(test::TestRequest::get().header(header::AUTHORIZATION, basic("admin:hunter3")))
+                .await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use actix_web::{
+    http::ContentEncoding,
+    middleware::{Compress, Condition},
+};
+
+use crate::config::{CompressionConfig, CompressionEncoding};
+
+/// Compress responses with the best encoding the client accepts,
+/// unless compression has been turned off
+pub fn middleware(config: Option<&CompressionConfig>) -> Condition<Compress> {
+    let enabled = config.and_then(|config| config.enabled).unwrap_or(true);
+    let encoding = match config.and_then(|config| config.encoding) {
+        Some(CompressionEncoding::Br) => ContentEncoding::Br,
+        Some(CompressionEncoding::Gzip) => ContentEncoding::Gzip,
+        Some(CompressionEncoding::Deflate) => ContentEncoding::Deflate,
+        Some(CompressionEncoding::Auto) | None => ContentEncoding::Auto,
+    };
+
+    Condition::new(enabled, Compress::new(encoding))
+}
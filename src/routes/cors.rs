@@ -0,0 +1,39 @@
+use actix_cors::{Cors, CorsFactory};
+use actix_web::{http::header, middleware::Condition};
+
+use crate::config::CorsConfig;
+
+/// Methods allowed in cross-origin requests unless configured otherwise
+const DEFAULT_ALLOWED_METHODS: &[&str] = &["GET", "POST", "DELETE"];
+
+/// Allow cross-origin requests as configured, or none at all if cors
+/// isn't configured
+pub fn middleware(config: Option<&CorsConfig>) -> Condition<CorsFactory> {
+    let cors = match config {
+        Some(config) => {
+            let mut cors = Cors::new();
+            // cors allows any origin unless origins are given
+            for origin in &config.allowed_origins {
+                if origin != "*" {
+                    cors = cors.allowed_origin(origin);
+                }
+            }
+            cors = match &config.allowed_methods {
+                Some(methods) => cors.allowed_methods(methods.iter().map(String::as_str)),
+                None => cors.allowed_methods(DEFAULT_ALLOWED_METHODS.iter().cloned()),
+            };
+            cors = cors.allowed_headers(vec![
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                header::HeaderName::from_static("x-api-key"),
+            ]);
+            if let Some(max_age) = config.max_age {
+                cors = cors.max_age(max_age);
+            }
+            cors
+        }
+        None => Cors::new(),
+    };
+
+    Condition::new(config.is_some(), cors.finish())
+}
@@ -0,0 +1,332 @@
+use actix_web::{http::header, web, HttpResponse};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures::{future, stream, StreamExt};
+use serde::Deserialize;
+
+use crate::{
+    db::{database, models, Page, SortOrder},
+    error::{Error, Result},
+};
+
+/// Rows loaded from the database at a time while exporting
+const EXPORT_PAGE_SIZE: i64 = 1000;
+
+/// The tables that can be exported
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ExportTable {
+    Alerts,
+    DiskUsage,
+    DiskUsageRollups,
+    Tasks,
+    Tweets,
+}
+
+impl ExportTable {
+    fn name(self) -> &'static str {
+        match self {
+            ExportTable::Alerts => "alerts",
+            ExportTable::DiskUsage => "disk_usage",
+            ExportTable::DiskUsageRollups => "disk_usage_rollups",
+            ExportTable::Tasks => "tasks",
+            ExportTable::Tweets => "tweets",
+        }
+    }
+
+    fn header(self) -> &'static [&'static str] {
+        match self {
+            ExportTable::Alerts => models::Alert::HEADER,
+            ExportTable::DiskUsage => models::DiskUsage::HEADER,
+            ExportTable::DiskUsageRollups => models::DiskUsageRollup::HEADER,
+            ExportTable::Tasks => models::Task::HEADER,
+            ExportTable::Tweets => models::Tweet::HEADER,
+        }
+    }
+
+    /// One page of the table's rows recorded between `from` and `to`,
+    /// as csv lines, oldest first
+    fn load_page(self, from: NaiveDateTime, to: NaiveDateTime, offset: i64) -> Result<Vec<String>> {
+        let page = Page {
+            limit: Some(EXPORT_PAGE_SIZE),
+            offset,
+            order: SortOrder::Asc,
+        };
+        let database = database();
+        Ok(match self {
+            ExportTable::Alerts => {
+                csv_rows(database.search_alerts(None, None, Some(from), Some(to), page)?)
+            }
+            ExportTable::DiskUsage => csv_rows(database.disk_usage_between(None, from, to, page)?),
+            ExportTable::DiskUsageRollups => {
+                csv_rows(database.disk_usage_rollups_between(None, from, to, page)?)
+            }
+            ExportTable::Tasks => {
+                csv_rows(database.search_tasks(None, Some(from), Some(to), page)?)
+            }
+            ExportTable::Tweets => {
+                csv_rows(database.search_tweets(None, None, Some(from), Some(to), page)?)
+            }
+        })
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    Csv,
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    table: ExportTable,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    format: Option<ExportFormat>,
+}
+
+/// Stream the rows of a table recorded between `from` (the beginning
+/// by default) and `to` (now by default), oldest first. Rows are loaded
+/// a page at a time, so large tables aren't held in memory
+pub async fn export(query: web::Query<ExportQuery>) -> Result<HttpResponse> {
+    let ExportQuery {
+        table,
+        from,
+        to,
+        format,
+    } = query.into_inner();
+    let to = to.unwrap_or_else(Utc::now).naive_utc();
+    let from = from.map_or_else(
+        || NaiveDateTime::from_timestamp(0, 0),
+        |from| from.naive_utc(),
+    );
+    let (content_type, extension) = match format.unwrap_or(ExportFormat::Csv) {
+        ExportFormat::Csv => ("text/csv", "csv"),
+    };
+
+    let columns = csv_line(table.header().iter().map(|column| column.to_string()));
+    let rows = stream::try_unfold(Some(0), move |offset| async move {
+        let offset = match offset {
+            Some(offset) => offset,
+            None => return Ok::<_, Error>(None),
+        };
+        let lines = web::block(move || table.load_page(from, to, offset)).await?;
+        let next_offset = if (lines.len() as i64) < EXPORT_PAGE_SIZE {
+            None
+        } else {
+            Some(offset + EXPORT_PAGE_SIZE)
+        };
+        Ok(Some((web::Bytes::from(lines.concat()), next_offset)))
+    });
+    let body = stream::once(future::ok::<_, Error>(web::Bytes::from(columns))).chain(rows);
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.{}\"", table.name(), extension),
+        )
+        .streaming(Box::pin(body)))
+}
+
+/// A row of an exported table
+trait CsvRow {
+    /// The names of the row's columns
+    const HEADER: &'static [&'static str];
+
+    fn fields(self) -> Vec<String>;
+}
+
+impl CsvRow for models::Alert {
+    const HEADER: &'static [&'static str] = &[
+        "id",
+        "event_type",
+        "event_keys",
+        "medium",
+        "subject",
+        "outcome",
+        "error",
+        "sent_at",
+    ];
+
+    fn fields(self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.event_type.unwrap_or_default(),
+            self.event_keys.join(" "),
+            self.medium,
+            self.subject,
+            self.outcome,
+            self.error.unwrap_or_default(),
+            timestamp(self.sent_at),
+        ]
+    }
+}
+
+impl CsvRow for models::DiskUsage {
+    const HEADER: &'static [&'static str] = &["id", "mount", "percent_disk_used", "recorded_at"];
+
+    fn fields(self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.mount,
+            self.percent_disk_used.to_string(),
+            timestamp(self.recorded_at),
+        ]
+    }
+}
+
+impl CsvRow for models::DiskUsageRollup {
+    const HEADER: &'static [&'static str] = &[
+        "id",
+        "mount",
+        "hour",
+        "average_percent_used",
+        "max_percent_used",
+        "samples",
+    ];
+
+    fn fields(self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.mount,
+            timestamp(self.hour),
+            self.average_percent_used.to_string(),
+            self.max_percent_used.to_string(),
+            self.samples.to_string(),
+        ]
+    }
+}
+
+impl CsvRow for models::Task {
+    const HEADER: &'static [&'static str] = &["id", "task", "sent_at"];
+
+    fn fields(self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.task.trim_matches('"').to_string(),
+            timestamp(self.sent_at),
+        ]
+    }
+}
+
+impl CsvRow for models::Tweet {
+    const HEADER: &'static [&'static str] = &[
+        "id",
+        "twitter_tweet_id",
+        "group_name",
+        "latitude",
+        "longitude",
+        "favorite_count",
+        "retweet_count",
+        "username",
+        "lang",
+        "text",
+        "tweeted_at",
+        "matched_by",
+        "sentiment",
+    ];
+
+    fn fields(self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.twitter_tweet_id,
+            self.group_name,
+            optional(self.latitude),
+            optional(self.longitude),
+            self.favorite_count.to_string(),
+            self.retweet_count.to_string(),
+            self.username.unwrap_or_default(),
+            self.lang.unwrap_or_default(),
+            self.text,
+            timestamp(self.tweeted_at),
+            self.matched_by,
+            optional(self.sentiment),
+        ]
+    }
+}
+
+fn csv_rows<R: CsvRow>(rows: Vec<R>) -> Vec<String> {
+    rows.into_iter()
+        .map(|row| csv_line(row.fields().into_iter()))
+        .collect()
+}
+
+/// Join fields into a line, quoting fields that contain separators,
+/// quotes or line breaks
+fn csv_line(fields: impl Iterator<Item = String>) -> String {
+    let fields = fields
+        .map(|field| {
+            if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>();
+    format!("{}\r\n", fields.join(","))
+}
+
+fn timestamp(time: NaiveDateTime) -> String {
+    DateTime::<Utc>::from_utc(time, Utc).to_rfc3339()
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test, App};
+
+    use super::*;
+    use crate::db::{self, Database, MemoryDatabase};
+
+    #[test]
+    fn export_header_names_the_columns() {
+        let header = csv_line(
+            ExportTable::DiskUsage
+                .header()
+                .iter()
+                .map(|column| column.to_string()),
+        );
+
+        assert_eq!(header, "id,mount,percent_disk_used,recorded_at\r\n");
+    }
+
+    #[test]
+    fn export_quotes_fields_with_separators_quotes_and_line_breaks() {
+        let fields = vec![
+            "plain",
+            "a,b",
+            "say \"hi\"",
+            "two\nlines",
+            "carriage\rreturn",
+            "",
+        ];
+
+        let line = csv_line(fields.into_iter().map(ToString::to_string));
+
+        assert_eq!(
+            line,
+            "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",\"carriage\rreturn\",\r\n"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn export_of_an_empty_range_is_only_the_header() {
+        db::initialize_from(Database::new(MemoryDatabase::default()));
+        let mut app = test::init_service(App::new().route("/export", web::get().to(export))).await;
+        let request = test::TestRequest::get()
+            .uri("/export?table=disk_usage_rollups&to=1970-01-02T00:00:00Z")
+            .to_request();
+
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = test::read_body(response).await;
+
+        assert_eq!(
+            body,
+            web::Bytes::from("id,mount,hour,average_percent_used,max_percent_used,samples\r\n")
+        );
+    }
+}
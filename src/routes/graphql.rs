@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use juniper::{
+    http::{graphiql::graphiql_source, GraphQLRequest},
+    EmptyMutation, FieldResult, GraphQLObject, RootNode,
+};
+
+use crate::{
+    config::config,
+    db::{database, models, Page, SortOrder},
+    error::{Error, Result},
+};
+
+/// The number of rows listed when no limit is given
+const DEFAULT_LIMIT: i32 = 100;
+
+/// The most rows a list may be limited to
+const MAX_LIMIT: i32 = 1000;
+
+/// Hours of disk usage history listed when no start is given
+const DEFAULT_DISK_USAGE_HOURS: i64 = 24;
+
+type Schema = RootNode<'static, Query, EmptyMutation<()>>;
+
+/// Register the graphql api and its explorer, if graphql is enabled
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    if !config().graphql.unwrap_or(false) {
+        return;
+    }
+
+    cfg.data(Arc::new(Schema::new(Query, EmptyMutation::new())))
+        .service(web::resource("/graphql").route(web::post().to(graphql)))
+        .service(web::resource("/graphiql").route(web::get().to(graphiql)));
+}
+
+async fn graphql(
+    schema: web::Data<Arc<Schema>>,
+    request: web::Json<GraphQLRequest>,
+) -> Result<HttpResponse> {
+    let schema = schema.get_ref().clone();
+    let response = web::block(move || {
+        let response = request.execute(&schema, &());
+        serde_json::to_string(&response).map_err(Error::from)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .body(response))
+}
+
+/// An in-browser explorer for the graphql api
+async fn graphiql() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(graphiql_source("/api/graphql"))
+}
+
+fn utc(timestamp: NaiveDateTime) -> DateTime<Utc> {
+    DateTime::from_utc(timestamp, Utc)
+}
+
+/// The number of rows to list, between 1 and `MAX_LIMIT`
+fn row_limit(limit: Option<i32>) -> i64 {
+    i64::from(limit.unwrap_or(DEFAULT_LIMIT).max(1).min(MAX_LIMIT))
+}
+
+#[derive(GraphQLObject)]
+struct DiskUsage {
+    mount: String,
+    percent_disk_used: f64,
+    recorded_at: DateTime<Utc>,
+}
+
+impl From<models::DiskUsage> for DiskUsage {
+    fn from(disk_usage: models::DiskUsage) -> Self {
+        Self {
+            mount: disk_usage.mount,
+            percent_disk_used: disk_usage.percent_disk_used,
+            recorded_at: utc(disk_usage.recorded_at),
+        }
+    }
+}
+
+/// A monitored mount, with its disk usage history
+struct Mount {
+    latest: models::DiskUsage,
+}
+
+#[juniper::object]
+impl Mount {
+    fn mount(&self) -> &str {
+        &self.latest.mount
+    }
+
+    fn percent_disk_used(&self) -> f64 {
+        self.latest.percent_disk_used
+    }
+
+    /// Disk usage recorded between `from` and `to`, the last day by
+    /// default
+    fn history(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> FieldResult<Vec<DiskUsage>> {
+        let to = to.unwrap_or_else(Utc::now);
+        let from = from.unwrap_or_else(|| to - chrono::Duration::hours(DEFAULT_DISK_USAGE_HOURS));
+        let history = database().disk_usage_between(
+            Some(self.latest.mount.clone()),
+            from.naive_utc(),
+            to.naive_utc(),
+            Page::all(SortOrder::Asc),
+        )?;
+        Ok(history.into_iter().map(Into::into).collect())
+    }
+}
+
+#[derive(GraphQLObject)]
+struct Tweet {
+    twitter_tweet_id: String,
+    group_name: String,
+    username: Option<String>,
+    text: String,
+    favorite_count: i32,
+    retweet_count: i32,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    sentiment: Option<f64>,
+    tweeted_at: DateTime<Utc>,
+}
+
+impl From<models::Tweet> for Tweet {
+    fn from(tweet: models::Tweet) -> Self {
+        Self {
+            twitter_tweet_id: tweet.twitter_tweet_id,
+            group_name: tweet.group_name,
+            username: tweet.username,
+            text: tweet.text,
+            favorite_count: tweet.favorite_count,
+            retweet_count: tweet.retweet_count,
+            latitude: tweet.latitude,
+            longitude: tweet.longitude,
+            sentiment: tweet.sentiment,
+            tweeted_at: utc(tweet.tweeted_at),
+        }
+    }
+}
+
+/// A group of tracked twitter terms, with its tweets
+struct TwitterGroup {
+    name: String,
+    tweet_count: i64,
+}
+
+#[juniper::object]
+impl TwitterGroup {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn tweet_count(&self) -> i32 {
+        self.tweet_count as i32
+    }
+
+    /// The group's most recent tweets, optionally within a range or
+    /// containing some text
+    fn tweets(
+        &self,
+        search: Option<String>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: Option<i32>,
+    ) -> FieldResult<Vec<Tweet>> {
+        let tweets = database().search_tweets(
+            Some(self.name.clone()),
+            search,
+            from.map(|from| from.naive_utc()),
+            to.map(|to| to.naive_utc()),
+            Page::latest(row_limit(limit)),
+        )?;
+        Ok(tweets.into_iter().map(Into::into).collect())
+    }
+}
+
+#[derive(GraphQLObject)]
+struct Acknowledgement {
+    event_key: String,
+    acknowledged_at: DateTime<Utc>,
+}
+
+/// An alert that was sent, or that failed to send
+struct Alert {
+    alert: models::Alert,
+}
+
+#[juniper::object]
+impl Alert {
+    fn event_type(&self) -> Option<&str> {
+        self.alert.event_type.as_deref()
+    }
+
+    fn event_keys(&self) -> Vec<String> {
+        self.alert.event_keys.clone()
+    }
+
+    fn medium(&self) -> &str {
+        &self.alert.medium
+    }
+
+    fn subject(&self) -> &str {
+        &self.alert.subject
+    }
+
+    /// `sent` or `failed`
+    fn outcome(&self) -> &str {
+        &self.alert.outcome
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.alert.error.as_deref()
+    }
+
+    fn sent_at(&self) -> DateTime<Utc> {
+        utc(self.alert.sent_at)
+    }
+
+    /// Active acknowledgements of the alert's events
+    fn acknowledgements(&self) -> FieldResult<Vec<Acknowledgement>> {
+        let acknowledgements = database().active_acknowledgements()?;
+        Ok(acknowledgements
+            .into_iter()
+            .filter(|acknowledgement| self.alert.event_keys.contains(&acknowledgement.event_key))
+            .map(|acknowledgement| Acknowledgement {
+                event_key: acknowledgement.event_key,
+                acknowledged_at: utc(acknowledgement.acknowledged_at),
+            })
+            .collect())
+    }
+}
+
+#[derive(GraphQLObject)]
+struct Task {
+    task: String,
+    sent_at: DateTime<Utc>,
+}
+
+pub struct Query;
+
+#[juniper::object]
+impl Query {
+    /// Monitored mounts, with their latest disk usage
+    fn mounts() -> FieldResult<Vec<Mount>> {
+        let latest = database().latest_disk_usage()?;
+        Ok(latest.into_iter().map(|latest| Mount { latest }).collect())
+    }
+
+    /// Groups that tweets have been recorded for
+    fn twitter_groups() -> FieldResult<Vec<TwitterGroup>> {
+        let counts = database().tweet_counts()?;
+        Ok(counts
+            .into_iter()
+            .map(|(name, tweet_count)| TwitterGroup { name, tweet_count })
+            .collect())
+    }
+
+    /// The most recent alerts, optionally only for one type of event
+    fn alerts(event_type: Option<String>, limit: Option<i32>) -> FieldResult<Vec<Alert>> {
+        let alerts = database().search_alerts(
+            event_type,
+            None,
+            None,
+            None,
+            Page::latest(row_limit(limit)),
+        )?;
+        Ok(alerts.into_iter().map(|alert| Alert { alert }).collect())
+    }
+
+    /// The most recent scheduled task runs, optionally only for one
+    /// task, e.g. `fetch-news`
+    fn tasks(task: Option<String>, limit: Option<i32>) -> FieldResult<Vec<Task>> {
+        // tasks are recorded as json strings
+        let task = task.map(|task| format!("\"{}\"", task));
+        let tasks = database().search_tasks(task, None, None, Page::latest(row_limit(limit)))?;
+        Ok(tasks
+            .into_iter()
+            .map(|task| Task {
+                task: task.task.trim_matches('"').to_string(),
+                sent_at: utc(task.sent_at),
+            })
+            .collect())
+    }
+}
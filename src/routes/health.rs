@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use actix::{Actor, Addr, Handler};
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+use crate::{
+    db::database,
+    services::{broadcast::Broadcast, system::SystemMonitor, twitter::Twitter, Ping},
+};
+
+/// How long an actor has to answer a ping before it is considered
+/// unresponsive
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Register the health check routes, which are served outside of the
+/// api
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/healthz").route(web::get().to(healthz)))
+        .service(web::resource("/readyz").route(web::get().to(readyz)));
+}
+
+#[derive(Serialize)]
+struct Liveness {
+    alive: bool,
+}
+
+/// Whether the core actors are still running. Broadcast only counts if
+/// it has been configured
+async fn healthz(
+    monitor: web::Data<Addr<SystemMonitor>>,
+    broadcast: web::Data<Option<Addr<Broadcast>>>,
+) -> HttpResponse {
+    let alive = monitor.connected() && broadcast.get_ref().as_ref().map_or(true, Addr::connected);
+
+    if alive {
+        HttpResponse::Ok().json(Liveness { alive })
+    } else {
+        HttpResponse::ServiceUnavailable().json(Liveness { alive })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum DependencyStatus {
+    Ready,
+    Unavailable,
+    /// Not configured, so not required to be ready
+    Unconfigured,
+}
+
+#[derive(Serialize)]
+struct Dependency {
+    name: &'static str,
+    status: DependencyStatus,
+    error: Option<String>,
+}
+
+impl Dependency {
+    fn ready(&self) -> bool {
+        match self.status {
+            DependencyStatus::Ready | DependencyStatus::Unconfigured => true,
+            DependencyStatus::Unavailable => false,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Readiness {
+    ready: bool,
+    dependencies: Vec<Dependency>,
+}
+
+/// Whether an actor, if it has been configured, answers a ping
+async fn ping<A>(name: &'static str, addr: Option<&Addr<A>>) -> Dependency
+where
+    A: Actor + Handler<Ping>,
+    A::Context: actix::dev::ToEnvelope<A, Ping>,
+{
+    let (status, error) = match addr {
+        Some(addr) => match addr.send(Ping).timeout(PING_TIMEOUT).await {
+            Ok(()) => (DependencyStatus::Ready, None),
+            Err(e) => (DependencyStatus::Unavailable, Some(e.to_string())),
+        },
+        None => (DependencyStatus::Unconfigured, None),
+    };
+
+    Dependency {
+        name,
+        status,
+        error,
+    }
+}
+
+/// Whether postgres and the configured actors are responsive, with the
+/// status of each
+async fn readyz(
+    monitor: web::Data<Addr<SystemMonitor>>,
+    broadcast: web::Data<Option<Addr<Broadcast>>>,
+    twitter: web::Data<Option<Addr<Twitter>>>,
+) -> HttpResponse {
+    let postgres = match web::block(|| database().ping()).await {
+        Ok(()) => Dependency {
+            name: "postgres",
+            status: DependencyStatus::Ready,
+            error: None,
+        },
+        Err(e) => Dependency {
+            name: "postgres",
+            status: DependencyStatus::Unavailable,
+            error: Some(e.to_string()),
+        },
+    };
+
+    let dependencies = vec![
+        postgres,
+        ping("system-monitor", Some(monitor.get_ref())).await,
+        ping("broadcast", broadcast.get_ref().as_ref()).await,
+        ping("twitter", twitter.get_ref().as_ref()).await,
+    ];
+    let ready = dependencies.iter().all(Dependency::ready);
+
+    let readiness = Readiness {
+        ready,
+        dependencies,
+    };
+    if ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
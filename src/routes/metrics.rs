@@ -0,0 +1,146 @@
+use std::fmt::Write;
+
+use actix::Addr;
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    db::database,
+    error::{Error, Result},
+    services::{
+        broadcast::Outbox,
+        scheduler::{GetLag, Scheduler},
+    },
+};
+
+/// Register the prometheus metrics route, which is served outside of
+/// the api
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/metrics").route(web::get().to(metrics)));
+}
+
+/// Metrics in the prometheus text format
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    /// Write a metric along with a sample for each set of labels
+    fn metric<'a, I>(&mut self, name: &str, kind: &str, help: &str, samples: I)
+    where
+        I: IntoIterator<Item = (Vec<(&'a str, &'a str)>, f64)>,
+    {
+        writeln!(self.0, "# HELP {} {}", name, help).unwrap();
+        writeln!(self.0, "# TYPE {} {}", name, kind).unwrap();
+        for (labels, value) in samples {
+            let labels = labels
+                .into_iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+                .collect::<Vec<_>>();
+            if labels.is_empty() {
+                writeln!(self.0, "{} {}", name, value).unwrap();
+            } else {
+                writeln!(self.0, "{}{{{}}} {}", name, labels.join(","), value).unwrap();
+            }
+        }
+    }
+}
+
+/// Escape a label value for the prometheus text format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Disk usage, the outbox, alerts, tweets, task runs and scheduler lag
+/// in the prometheus text format
+async fn metrics(
+    outbox: web::Data<Outbox>,
+    scheduler: web::Data<Addr<Scheduler>>,
+) -> Result<HttpResponse> {
+    let (disk_usage, alert_counts, tweet_counts, task_counts) = web::block(|| {
+        let database = database();
+        Ok::<_, Error>((
+            database.latest_disk_usage()?,
+            database.sent_alert_counts()?,
+            database.tweet_counts()?,
+            database.task_counts()?,
+        ))
+    })
+    .await?;
+    let outbox = outbox.stats();
+    let lag = scheduler.send(GetLag).await?;
+
+    let mut exposition = Exposition::default();
+    exposition.metric(
+        "pulse_disk_used_percent",
+        "gauge",
+        "Percent of the disk used at each mount, as last recorded",
+        disk_usage.iter().map(|usage| {
+            (
+                vec![("mount", usage.mount.as_str())],
+                usage.percent_disk_used,
+            )
+        }),
+    );
+    exposition.metric(
+        "pulse_outbox_depth",
+        "gauge",
+        "Events waiting in the outbox to be broadcast",
+        vec![(vec![], outbox.depth as f64)],
+    );
+    exposition.metric(
+        "pulse_outbox_capacity",
+        "gauge",
+        "Events the outbox can hold",
+        vec![(vec![], outbox.capacity as f64)],
+    );
+    exposition.metric(
+        "pulse_outbox_overflowed_total",
+        "counter",
+        "Events dropped, coalesced or rejected because the outbox was full",
+        vec![
+            (vec![("reason", "dropped")], outbox.dropped as f64),
+            (vec![("reason", "coalesced")], outbox.coalesced as f64),
+            (vec![("reason", "rejected")], outbox.rejected as f64),
+        ],
+    );
+    exposition.metric(
+        "pulse_alerts_sent_total",
+        "counter",
+        "Alerts sent for each type of event",
+        alert_counts.iter().map(|(event_type, count)| {
+            (
+                vec![("event_type", event_type.as_deref().unwrap_or(""))],
+                *count as f64,
+            )
+        }),
+    );
+    exposition.metric(
+        "pulse_tweets_recorded_total",
+        "counter",
+        "Tweets recorded for each group",
+        tweet_counts
+            .iter()
+            .map(|(group_name, count)| (vec![("group", group_name.as_str())], *count as f64)),
+    );
+    exposition.metric(
+        "pulse_task_runs_total",
+        "counter",
+        "Times each scheduled task has run",
+        task_counts.iter().map(|(task, count)| {
+            // tasks are recorded as json strings
+            (vec![("task", task.trim_matches('"'))], *count as f64)
+        }),
+    );
+    exposition.metric(
+        "pulse_scheduler_lag_seconds",
+        "gauge",
+        "How late the most recent scheduled task ran",
+        lag.map(|lag| (vec![], lag.as_secs_f64())),
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(exposition.0))
+}
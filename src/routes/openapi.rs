@@ -0,0 +1,41 @@
+use actix_web::HttpResponse;
+
+/// The OpenAPI document describing the http api. Keep it in step with
+/// the routes in `api.rs` and `graphql.rs`, which the tests check
+const OPENAPI: &str = include_str!("openapi.json");
+
+/// Serve the OpenAPI document, for generating api clients
+pub async fn document() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(OPENAPI)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The paths of the resources registered in a routes module
+    fn resources(source: &str) -> Vec<&str> {
+        source
+            .split("web::resource(\"")
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next())
+            .collect()
+    }
+
+    #[test]
+    fn openapi_documents_every_api_route() {
+        let document: serde_json::Value = serde_json::from_str(OPENAPI).unwrap();
+        let paths = document["paths"].as_object().unwrap();
+
+        let routes = resources(include_str!("api.rs"))
+            .into_iter()
+            .chain(resources(include_str!("graphql.rs")))
+            .collect::<Vec<_>>();
+        assert!(routes.contains(&"/graphql"));
+        for route in routes {
+            assert!(paths.contains_key(route), "{} is not documented", route);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use futures::future::{err, ok, Either, Ready};
+
+use crate::{config::RateLimitConfig, error::Error};
+
+/// Limits how many requests each client IP may make per period. Every
+/// request is allowed if no limit is configured. Clones share their
+/// counts, so one limiter can be used by every worker
+#[derive(Clone)]
+pub struct RateLimit {
+    inner: Option<Arc<RateLimiter>>,
+}
+
+impl RateLimit {
+    pub fn new(config: Option<RateLimitConfig>) -> Self {
+        Self {
+            inner: config.map(|config| {
+                Arc::new(RateLimiter {
+                    requests: config.requests,
+                    period: Duration::from_secs(config.period),
+                    windows: Mutex::new(HashMap::new()),
+                })
+            }),
+        }
+    }
+}
+
+/// Counts requests from each IP in fixed windows
+struct RateLimiter {
+    requests: usize,
+    period: Duration,
+    windows: Mutex<HashMap<IpAddr, Window>>,
+}
+
+struct Window {
+    started: Instant,
+    requests: usize,
+}
+
+impl RateLimiter {
+    /// Count a request from this IP. Requests over the limit are
+    /// rejected with the time until the IP's window is over
+    fn allow(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut windows = self.windows.lock().unwrap();
+        let period = self.period;
+        if !windows.contains_key(&ip) {
+            // Forget clients whose windows are over, so the counts
+            // don't grow with every IP ever seen
+            windows.retain(|_, window| now.duration_since(window.started) < period);
+        }
+
+        let window = windows.entry(ip).or_insert(Window {
+            started: now,
+            requests: 0,
+        });
+        if now.duration_since(window.started) >= period {
+            window.started = now;
+            window.requests = 0;
+        }
+
+        window.requests += 1;
+        if window.requests <= self.requests {
+            Ok(())
+        } else {
+            Err(period - now.duration_since(window.started))
+        }
+    }
+}
+
+impl<S, B> Transform<S> for RateLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RateLimitMiddleware {
+            service,
+            limiter: self.inner.clone(),
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: S,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl<S, B> Service for RateLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: ServiceRequest) -> Self::Future {
+        let allowed = match (&self.limiter, request.peer_addr()) {
+            (Some(limiter), Some(peer)) => limiter.allow(peer.ip(), Instant::now()),
+            _ => Ok(()),
+        };
+
+        match allowed {
+            Ok(()) => Either::Left(self.service.call(request)),
+            Err(retry_after) => {
+                log::warn!("Rate limiting request to {}", request.path());
+                // rounded up, so that clients don't retry too early
+                let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                Either::Right(err(Error::rate_limited(retry_after).into()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{
+        http::{header, StatusCode},
+        test, web, App, HttpResponse,
+    };
+
+    use super::*;
+
+    fn limiter(requests: usize, period: u64) -> Arc<RateLimiter> {
+        RateLimit::new(Some(RateLimitConfig { requests, period }))
+            .inner
+            .unwrap()
+    }
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn rate_limiter_allows_a_burst_up_to_the_limit() {
+        let limiter = limiter(3, 60);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.allow(ip("10.0.0.1"), now).is_ok());
+        }
+        assert_eq!(
+            limiter.allow(ip("10.0.0.1"), now + Duration::from_secs(20)),
+            Err(Duration::from_secs(40))
+        );
+    }
+
+    #[test]
+    fn rate_limiter_allows_requests_again_once_the_period_is_over() {
+        let limiter = limiter(1, 60);
+        let now = Instant::now();
+
+        assert!(limiter.allow(ip("10.0.0.1"), now).is_ok());
+        assert!(limiter.allow(ip("10.0.0.1"), now).is_err());
+        assert!(limiter
+            .allow(ip("10.0.0.1"), now + Duration::from_secs(60))
+            .is_ok());
+    }
+
+    #[test]
+    fn rate_limiter_counts_each_ip_separately() {
+        let limiter = limiter(1, 60);
+        let now = Instant::now();
+
+        assert!(limiter.allow(ip("10.0.0.1"), now).is_ok());
+        assert!(limiter.allow(ip("10.0.0.1"), now).is_err());
+        assert!(limiter.allow(ip("10.0.0.2"), now).is_ok());
+        assert!(limiter.allow(ip("::1"), now).is_ok());
+    }
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn rate_limit_rejects_requests_over_the_limit_with_retry_after() {
+        let rate_limit = RateLimit::new(Some(RateLimitConfig {
+            requests: 1,
+            period: 60,
+        }));
+        let mut app =
+            test::init_service(App::new().wrap(rate_limit).route("/", web::get().to(ok))).await;
+        let request = || {
+            test::TestRequest::get()
+                .peer_addr("10.0.0.1:4000".parse().unwrap())
+                .to_request()
+        };
+
+        let response = app.call(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = match app.call(request()).await {
+            Ok(_) => panic!("the second request was allowed"),
+            Err(e) => e.as_response_error().error_response(),
+        };
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "60");
+    }
+}
@@ -0,0 +1,286 @@
+use std::{
+    cell::RefCell,
+    env,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    HttpMessage,
+};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use log::{Log, Metadata, Record};
+
+/// The header a request id is read from, and returned in
+const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longer request ids from clients are replaced
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+thread_local! {
+    /// The id of the request being handled on this thread, if any
+    static CURRENT_REQUEST_ID: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// The id of a request, available from its extensions
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+/// Start logging. Lines logged while a request is being handled are
+/// prefixed with its id
+pub fn init_logger() {
+    let mut builder = pretty_env_logger::formatted_builder();
+    if let Ok(filters) = env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    let logger = builder.build();
+
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(RequestIdLogger {
+        inner: Box::new(logger),
+    }))
+    .expect("Logger was initialized twice");
+}
+
+struct RequestIdLogger {
+    inner: Box<dyn Log>,
+}
+
+impl Log for RequestIdLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        CURRENT_REQUEST_ID.with(|request_id| match &*request_id.borrow() {
+            Some(request_id) => self.inner.log(
+                &Record::builder()
+                    .args(format_args!(
+                        "[request_id={}] {}",
+                        request_id,
+                        record.args()
+                    ))
+                    .metadata(record.metadata().clone())
+                    .module_path(record.module_path())
+                    .file(record.file())
+                    .line(record.line())
+                    .build(),
+            ),
+            None => self.inner.log(record),
+        })
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+/// Whether a request id from a client is safe to log and echo back
+fn valid_request_id(request_id: &str) -> bool {
+    !request_id.is_empty()
+        && request_id.len() <= MAX_REQUEST_ID_LEN
+        && request_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Gives every request an id, taken from its `X-Request-Id` header or
+/// generated, which is returned in the response and logged with its
+/// method, path, status and duration
+pub struct RequestLog;
+
+impl<S, B> Transform<S> for RequestLog
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = RequestLogMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestLogMiddleware { service })
+    }
+}
+
+pub struct RequestLogMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service for RequestLogMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: ServiceRequest) -> Self::Future {
+        let request_id = request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|request_id| valid_request_id(request_id))
+            .map(ToString::to_string)
+            .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()));
+        request
+            .extensions_mut()
+            .insert(RequestId(request_id.clone()));
+
+        let method = request.method().to_string();
+        let path = request.path().to_string();
+        let started = Instant::now();
+        let response = WithRequestId {
+            request_id: request_id.clone(),
+            inner: Box::pin(self.service.call(request)),
+        };
+
+        Box::pin(async move {
+            let response = response.await;
+            let status = match &response {
+                Ok(response) => response.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            log::info!(
+                target: "pulse::access",
+                "request_id={} method={} path={} status={} duration_ms={}",
+                request_id,
+                method,
+                path,
+                status.as_u16(),
+                started.elapsed().as_millis()
+            );
+
+            match response {
+                Ok(mut response) => {
+                    set_request_id(response.headers_mut(), &request_id);
+                    Ok(response)
+                }
+                // requests rejected by an inner middleware, as unauthorized
+                // or over the rate limit, are turned into their response
+                // here so that it carries the request id too
+                Err(e) => {
+                    let mut response = e.as_response_error().error_response();
+                    set_request_id(response.headers_mut(), &request_id);
+                    Err(response.into())
+                }
+            }
+        })
+    }
+}
+
+/// Return the request id in a response's headers
+fn set_request_id(headers: &mut HeaderMap, request_id: &str) {
+    if let Ok(value) = HeaderValue::from_str(request_id) {
+        headers.insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+}
+
+/// Polls a future with its request id set as the current one, so that
+/// everything logged while handling the request carries it
+struct WithRequestId<F> {
+    request_id: String,
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for WithRequestId<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let previous =
+            CURRENT_REQUEST_ID.with(|current| current.replace(Some(this.request_id.clone())));
+        let poll = this.inner.as_mut().poll(cx);
+        CURRENT_REQUEST_ID.with(|current| *current.borrow_mut() = previous);
+        poll
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+
+    use super::*;
+    use crate::{config::RateLimitConfig, routes::rate_limit::RateLimit};
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    /// The request id returned for a request
+    async fn returned_request_id(request: test::TestRequest) -> String {
+        let mut app =
+            test::init_service(App::new().wrap(RequestLog).route("/", web::get().to(ok))).await;
+        let response = test::call_service(&mut app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[actix_rt::test]
+    async fn request_log_generates_a_request_id() {
+        let request_id = returned_request_id(test::TestRequest::get()).await;
+
+        assert_eq!(request_id.len(), 16);
+        assert!(request_id.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[actix_rt::test]
+    async fn request_log_echoes_a_valid_incoming_request_id() {
+        let request = test::TestRequest::get().header(REQUEST_ID_HEADER, "client-request_1");
+        assert_eq!(returned_request_id(request).await, "client-request_1");
+
+        let request = test::TestRequest::get().header(REQUEST_ID_HEADER, "not a request id");
+        assert_ne!(returned_request_id(request).await, "not a request id");
+    }
+
+    #[actix_rt::test]
+    async fn request_log_sets_the_request_id_on_rejected_requests() {
+        // no requests are allowed, so every request is rejected by the
+        // inner middleware
+        let rate_limit = RateLimit::new(Some(RateLimitConfig {
+            requests: 0,
+            period: 60,
+        }));
+        let mut app = test::init_service(
+            App::new()
+                .wrap(rate_limit)
+                .wrap(RequestLog)
+                .route("/", web::get().to(ok)),
+        )
+        .await;
+        let request = test::TestRequest::get()
+            .header(REQUEST_ID_HEADER, "rejected-request")
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .to_request();
+
+        let response = match app.call(request).await {
+            Ok(_) => panic!("the request was allowed"),
+            Err(e) => e.as_response_error().error_response(),
+        };
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            response.headers().get(REQUEST_ID_HEADER).unwrap(),
+            "rejected-request"
+        );
+    }
+}
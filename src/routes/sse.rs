@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use actix::prelude::*;
+use actix_web::{dev::BodyEncoding, http::ContentEncoding, web, web::Bytes, HttpResponse};
+use futures::{channel::mpsc, StreamExt};
+
+use crate::services::{
+    broadcast::{EventPushed, Outbox},
+    system::{MonitorUpdate, Subscribe, SystemMonitor, Unsubscribe},
+};
+
+/// How often a comment is sent to keep idle connections open
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Stream monitor updates and broadcast events as server-sent events,
+/// named after the monitor stream (e.g. `disk_usage`) or `broadcast`
+pub async fn events_stream(
+    monitor: web::Data<Addr<SystemMonitor>>,
+    outbox: web::Data<Outbox>,
+) -> HttpResponse {
+    let (sender, receiver) = mpsc::unbounded();
+    let stream = EventStream {
+        sender,
+        monitor: monitor.get_ref().clone(),
+        subscriber_id: None,
+    }
+    .start();
+    outbox.listen(stream.recipient());
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header("Cache-Control", "no-cache")
+        // compression would hold events back until enough of them
+        // were buffered
+        .encoding(ContentEncoding::Identity)
+        .streaming(receiver.map(Ok::<_, actix_web::Error>))
+}
+
+/// Forwards updates to a client until it disconnects
+struct EventStream {
+    sender: mpsc::UnboundedSender<Bytes>,
+    monitor: Addr<SystemMonitor>,
+    subscriber_id: Option<usize>,
+}
+
+impl EventStream {
+    /// Send a chunk to the client, stopping once it has disconnected
+    fn send(&self, chunk: String, ctx: &mut Context<Self>) {
+        if self.sender.unbounded_send(Bytes::from(chunk)).is_err() {
+            ctx.stop();
+        }
+    }
+
+    fn send_event<T: serde::Serialize>(&self, event: &str, data: &T, ctx: &mut Context<Self>) {
+        match serde_json::to_string(data) {
+            Ok(data) => self.send(format!("event: {}\ndata: {}\n\n", event, data), ctx),
+            Err(e) => log::error!("Error serializing server-sent event: {}", e),
+        }
+    }
+}
+
+impl Actor for EventStream {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        self.monitor
+            .send(Subscribe(ctx.address().recipient()))
+            .into_actor(self)
+            .map(|res, act, _| act.subscriber_id = res.ok())
+            .wait(ctx);
+
+        ctx.run_interval(KEEPALIVE_INTERVAL, |this, ctx| {
+            this.send(": keepalive\n\n".to_string(), ctx)
+        });
+    }
+
+    fn stopped(&mut self, _: &mut Context<Self>) {
+        if let Some(id) = self.subscriber_id {
+            self.monitor.do_send(Unsubscribe(id));
+        }
+    }
+}
+
+impl Handler<MonitorUpdate> for EventStream {
+    type Result = ();
+
+    fn handle(&mut self, update: MonitorUpdate, ctx: &mut Context<Self>) {
+        let stream = serde_json::to_value(update.stream())
+            .ok()
+            .and_then(|stream| stream.as_str().map(ToString::to_string))
+            .unwrap_or_default();
+        self.send_event(&stream, &update, ctx)
+    }
+}
+
+impl Handler<EventPushed> for EventStream {
+    type Result = ();
+
+    fn handle(&mut self, EventPushed(event): EventPushed, ctx: &mut Context<Self>) {
+        self.send_event("broadcast", &event, ctx)
+    }
+}
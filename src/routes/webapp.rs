@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use actix_files::Files;
+use actix_web::web;
+
+use crate::config::config;
+
+/// Where the webapp is built to, if no path is configured
+const DEFAULT_WEBAPP_PATH: &str = "./webapp/dist/webapp/";
+
+/// Serve the webapp at the root, unless it has been disabled. This
+/// must be registered last, since it matches every path
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let webapp = config().webapp;
+    if !webapp
+        .as_ref()
+        .and_then(|webapp| webapp.enabled)
+        .unwrap_or(true)
+    {
+        return;
+    }
+
+    let path = webapp
+        .and_then(|webapp| webapp.path)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_WEBAPP_PATH));
+    cfg.service(Files::new("/", path).index_file("index.html"));
+}
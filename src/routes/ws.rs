@@ -1,22 +1,103 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
 
 use actix::prelude::*;
 use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, StreamHandler};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    db::models,
-    services::system::{Subscribe, SystemMonitor, Unsubscribe},
+    config::config,
+    db::{database, Page, SortOrder},
+    services::system::{
+        MonitorStream, MonitorUpdate, SetStreams, Subscribe, SystemMonitor, Unsubscribe,
+    },
 };
 
+/// The version of the envelope outgoing messages are wrapped in
+const ENVELOPE_VERSION: u32 = 1;
+
+/// Minutes of disk usage history sent to clients when they connect,
+/// if none are configured
+const DEFAULT_BACKFILL_MINUTES: i64 = 10;
+
 /// How frequently we send heartbeats to the client
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 /// Maximum time we'll wait for a ping from the client before timing out
 const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
 
+#[derive(Deserialize)]
+pub struct WsQuery {
+    token: Option<String>,
+}
+
+/// Start a websocket for a client. If websocket tokens are configured,
+/// the client must give one in the `token` query parameter or in its
+/// first message
+pub async fn index(
+    request: HttpRequest,
+    stream: web::Payload,
+    query: web::Query<WsQuery>,
+    monitor: web::Data<Addr<SystemMonitor>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let authenticated = authorized(query.token.as_deref());
+    ws::start(
+        Ws::new(monitor.get_ref().clone(), authenticated),
+        &request,
+        stream,
+    )
+}
+
+/// Whether a client with this token may receive updates. Every client
+/// may if no tokens are configured
+fn authorized(token: Option<&str>) -> bool {
+    match config().auth.and_then(|auth| auth.websocket_tokens) {
+        Some(tokens) => token.map_or(false, |token| tokens.iter().any(|t| t == token)),
+        None => true,
+    }
+}
+
+/// Sent by clients to authenticate with a `token`, or to choose which
+/// streams they receive, e.g. `{"subscribe": ["disk_usage"]}`. Clients
+/// receive every stream until they subscribe
+#[derive(Deserialize)]
+struct ClientMessage {
+    token: Option<String>,
+    subscribe: Option<Vec<String>>,
+}
+
+/// Every message sent to clients is wrapped in a versioned envelope
+/// tagged with its type, e.g. `{"type": "disk_usage", "v": 1, "data":
+/// {...}}`
+#[derive(Serialize)]
+struct Envelope<T> {
+    #[serde(flatten)]
+    message: T,
+    v: u32,
+}
+
+/// Replies to client messages
+#[derive(Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+enum Reply {
+    Authenticated(bool),
+    /// The streams a client is subscribed to, after ignoring the ones
+    /// that don't exist
+    Subscribed(Vec<MonitorStream>),
+    Error(String),
+}
+
 pub struct Ws {
     system_monitor: Addr<SystemMonitor>,
     subscriber_id: Option<usize>,
+    /// Clients only receive updates once they are authenticated
+    authenticated: bool,
+    /// The streams the client chose, if it has chosen
+    streams: Option<HashSet<MonitorStream>>,
 
     /// Client must send ping at least once per CLIENT_TIMEOUT
     last_heartbeat: Instant,
@@ -27,12 +108,9 @@ impl Actor for Ws {
 
     /// Start the heartbeat process on actor start
     fn started(&mut self, ctx: &mut Self::Context) {
-        // subscribe to system updates
-        self.system_monitor
-            .send(Subscribe(Addr::recipient(ctx.address())))
-            .into_actor(self)
-            .map(move |res, act, _| act.subscriber_id = Some(res.unwrap()))
-            .wait(ctx);
+        if self.authenticated {
+            self.subscribe(ctx);
+        }
 
         self.heartbeat(ctx);
     }
@@ -48,7 +126,7 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Ws {
             ws::Message::Pong(_) => {
                 self.last_heartbeat = Instant::now();
             }
-            ws::Message::Text(text) => ctx.text(text),
+            ws::Message::Text(text) => self.handle_client_message(&text, ctx),
             ws::Message::Binary(bin) => ctx.binary(bin),
             ws::Message::Close(_) => self.disconnect(ctx),
             ws::Message::Continuation(_) => (),
@@ -58,14 +136,94 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Ws {
 }
 
 impl Ws {
-    pub fn new(system_monitor: Addr<SystemMonitor>) -> Self {
+    pub fn new(system_monitor: Addr<SystemMonitor>, authenticated: bool) -> Self {
         Self {
             system_monitor,
             subscriber_id: None,
+            authenticated,
+            streams: None,
             last_heartbeat: Instant::now(),
         }
     }
 
+    /// Subscribe to system updates, limited to the streams the client
+    /// chose if it has chosen. Recent history is sent first
+    fn subscribe(&self, ctx: &mut <Self as Actor>::Context) {
+        self.backfill(ctx);
+        self.system_monitor
+            .send(Subscribe(Addr::recipient(ctx.address())))
+            .into_actor(self)
+            .map(move |res, act, _| {
+                act.subscriber_id = Some(res.unwrap());
+                act.set_streams();
+            })
+            .wait(ctx);
+    }
+
+    /// Send the disk usage recorded in the last few minutes, so that
+    /// clients have history to show before live updates start
+    fn backfill(&self, ctx: &mut <Self as Actor>::Context) {
+        let minutes = config()
+            .system_monitor
+            .and_then(|system_monitor| system_monitor.backfill_minutes)
+            .unwrap_or(DEFAULT_BACKFILL_MINUTES);
+        if minutes <= 0 {
+            return;
+        }
+
+        let to = Utc::now().naive_utc();
+        let from = to - chrono::Duration::minutes(minutes);
+        web::block(move || {
+            database().disk_usage_between(None, from, to, Page::all(SortOrder::Asc))
+        })
+        .into_actor(self)
+        .map(|res, act, ctx| match res {
+            Ok(history) => {
+                for disk_usage in history {
+                    act.send_update(MonitorUpdate::DiskUsage(disk_usage), ctx);
+                }
+            }
+            Err(e) => log::error!("Error loading disk usage history: {}", e),
+        })
+        .wait(ctx);
+    }
+
+    /// Send an update if the client is subscribed to its stream
+    fn send_update(&self, update: MonitorUpdate, ctx: &mut <Self as Actor>::Context) {
+        let subscribed = self
+            .streams
+            .as_ref()
+            .map_or(true, |streams| streams.contains(&update.stream()));
+        if subscribed {
+            self.send(update, ctx);
+        }
+    }
+
+    fn set_streams(&self) {
+        if let (Some(subscriber_id), Some(streams)) = (self.subscriber_id, &self.streams) {
+            self.system_monitor.do_send(SetStreams {
+                subscriber_id,
+                streams: streams.clone(),
+            });
+        }
+    }
+
+    /// Send a message to the client in a versioned envelope
+    fn send<T: Serialize>(&self, message: T, ctx: &mut <Self as Actor>::Context) {
+        let envelope = Envelope {
+            message,
+            v: ENVELOPE_VERSION,
+        };
+        match serde_json::to_string(&envelope) {
+            Ok(text) => ctx.text(text),
+            Err(e) => log::error!("Error serializing websocket message: {}", e),
+        }
+    }
+
+    fn send_error(&self, error: String, ctx: &mut <Self as Actor>::Context) {
+        self.send(Reply::Error(error), ctx);
+    }
+
     /// Send ping to client every second and determine whether we've
     /// timed out
     fn heartbeat(&self, ctx: &mut <Self as Actor>::Context) {
@@ -80,9 +238,46 @@ impl Ws {
         });
     }
 
-    /// Send system status updates to the client
-    fn send_update(&self, update: models::DiskUsage, ctx: &mut <Self as Actor>::Context) {
-        ctx.text(update)
+    /// Authenticate the client, or subscribe to the streams it asked
+    /// for. Unauthenticated clients are disconnected unless their
+    /// first message has a valid token
+    fn handle_client_message(&mut self, text: &str, ctx: &mut <Self as Actor>::Context) {
+        let message = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(message) => message,
+            Err(e) => {
+                self.send_error(e.to_string(), ctx);
+                return;
+            }
+        };
+
+        if !self.authenticated {
+            if !authorized(message.token.as_deref()) {
+                log::warn!("Websocket client failed to authenticate, disconnecting");
+                self.send_error("unauthorized".to_string(), ctx);
+                self.disconnect(ctx);
+                return;
+            }
+
+            self.authenticated = true;
+            self.subscribe(ctx);
+            self.send(Reply::Authenticated(true), ctx);
+        }
+
+        let subscribe = match message.subscribe {
+            Some(subscribe) => subscribe,
+            None => return,
+        };
+        let mut streams = vec![];
+        for name in subscribe {
+            match serde_json::from_value(serde_json::Value::String(name.clone())) {
+                Ok(stream) => streams.push(stream),
+                Err(_) => log::debug!("Ignoring subscription to unknown stream {}", name),
+            }
+        }
+
+        self.streams = Some(streams.iter().cloned().collect());
+        self.set_streams();
+        self.send(Reply::Subscribed(streams), ctx);
     }
 
     fn disconnect(&self, ctx: &mut <Self as Actor>::Context) {
@@ -93,10 +288,10 @@ impl Ws {
     }
 }
 
-impl Handler<models::DiskUsage> for Ws {
+impl Handler<MonitorUpdate> for Ws {
     type Result = ();
 
-    fn handle(&mut self, update: models::DiskUsage, ctx: &mut Self::Context) {
+    fn handle(&mut self, update: MonitorUpdate, ctx: &mut Self::Context) {
         self.send_update(update, ctx)
     }
 }
@@ -1,5 +1,89 @@
 table! {
-    disk_usage (id) {
+    acknowledgements (id) {
+        id -> Int4,
+        event_key -> Varchar,
+        acknowledged_at -> Timestamptz,
+        resolved_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    articles (id) {
+        id -> Int4,
+        digest_id -> Int4,
+        url -> Varchar,
+        title -> Varchar,
+        source -> Varchar,
+        summary -> Text,
+        published_date -> Date,
+    }
+}
+
+table! {
+    alerts (id) {
+        id -> Int4,
+        event_type -> Nullable<Varchar>,
+        event_keys -> Array<Text>,
+        medium -> Varchar,
+        subject -> Varchar,
+        outcome -> Varchar,
+        error -> Nullable<Varchar>,
+        sent_at -> Timestamptz,
+    }
+}
+
+table! {
+    api_cache (key) {
+        key -> Varchar,
+        body -> Text,
+        fetched_at -> Timestamptz,
+    }
+}
+
+table! {
+    completed_tasks (task, scheduled_for) {
+        task -> Varchar,
+        scheduled_for -> Timestamptz,
+        completed_at -> Timestamptz,
+    }
+}
+
+table! {
+    crypto_prices (id, recorded_at) {
+        id -> Int4,
+        asset -> Varchar,
+        currency -> Varchar,
+        price -> Float8,
+        percent_change_24h -> Nullable<Float8>,
+        recorded_at -> Timestamptz,
+    }
+}
+
+table! {
+    dead_letters (id) {
+        id -> Int4,
+        medium -> Varchar,
+        subject -> Varchar,
+        body -> Text,
+        error -> Varchar,
+        attempts -> Int4,
+        failed_at -> Timestamptz,
+        replayed_at -> Nullable<Timestamptz>,
+        text_body -> Text,
+        event_type -> Nullable<Varchar>,
+        event_keys -> Array<Text>,
+    }
+}
+
+table! {
+    digests (id) {
+        id -> Int4,
+        sent_at -> Timestamptz,
+    }
+}
+
+table! {
+    disk_usage (id, recorded_at) {
         id -> Int4,
         mount -> Varchar,
         percent_disk_used -> Float8,
@@ -7,11 +91,88 @@ table! {
     }
 }
 
+table! {
+    disk_usage_rollups (id) {
+        id -> Int4,
+        mount -> Varchar,
+        hour -> Timestamptz,
+        average_percent_used -> Float8,
+        max_percent_used -> Float8,
+        samples -> Int8,
+    }
+}
+
+table! {
+    github_seen (id) {
+        id -> Int4,
+        kind -> Varchar,
+        repository -> Varchar,
+        item_id -> Varchar,
+        seen_at -> Timestamptz,
+    }
+}
+
+table! {
+    last_alerted (event_key) {
+        event_key -> Varchar,
+        alerted_at -> Timestamptz,
+    }
+}
+
+table! {
+    outbox (id) {
+        id -> Int4,
+        event -> Text,
+        created_at -> Timestamptz,
+        delivered_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    registered_tasks (id) {
+        id -> Int4,
+        config -> Text,
+        registered_at -> Timestamptz,
+    }
+}
+
+table! {
+    silences (id) {
+        id -> Int4,
+        event_type -> Nullable<Varchar>,
+        event_key -> Nullable<Varchar>,
+        comment -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
+table! {
+    task_schedules (task_key) {
+        task_key -> Varchar,
+        next_run -> Timestamptz,
+    }
+}
+
 table! {
     tasks (id) {
         id -> Int4,
         task -> Varchar,
         sent_at -> Timestamptz,
+        task_key -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    task_runs (id) {
+        id -> Int4,
+        task -> Varchar,
+        runner -> Varchar,
+        succeeded -> Bool,
+        error -> Nullable<Text>,
+        duration_ms -> Int8,
+        started_at -> Timestamptz,
+        task_key -> Nullable<Varchar>,
     }
 }
 
@@ -28,7 +189,31 @@ table! {
         lang -> Nullable<Varchar>,
         text -> Varchar,
         tweeted_at -> Timestamptz,
+        matched_by -> Varchar,
+        sentiment -> Nullable<Float8>,
     }
 }
 
-allow_tables_to_appear_in_same_query!(disk_usage, tasks, tweets,);
+joinable!(articles -> digests (digest_id));
+
+allow_tables_to_appear_in_same_query!(
+    acknowledgements,
+    alerts,
+    api_cache,
+    articles,
+    completed_tasks,
+    crypto_prices,
+    dead_letters,
+    digests,
+    disk_usage,
+    disk_usage_rollups,
+    github_seen,
+    last_alerted,
+    outbox,
+    registered_tasks,
+    silences,
+    task_runs,
+    task_schedules,
+    tasks,
+    tweets,
+);
@@ -1,5 +1,42 @@
 pub mod broadcast;
+pub mod crypto;
+pub mod database;
+pub mod github;
+pub mod mailbox;
+pub mod markets;
 pub mod news;
+pub mod podcasts;
 pub mod scheduler;
 pub mod system;
 pub mod twitter;
+
+use actix::Message;
+
+use crate::{config::Config, error::Result};
+
+/// How long requests to upstream apis wait to connect, and then for a
+/// response, before they fail
+const REQUEST_TIMEOUT_MS: u64 = 30_000;
+
+/// A GET request to an upstream api, which fails rather than hanging
+/// if the api stops responding
+pub fn http_get(url: &str) -> ureq::Request {
+    let mut request = ureq::get(url);
+    request
+        .timeout_connect(REQUEST_TIMEOUT_MS)
+        .timeout_read(REQUEST_TIMEOUT_MS);
+    request
+}
+
+/// Check that an actor is responsive
+pub struct Ping;
+impl Message for Ping {
+    type Result = ();
+}
+
+/// Apply a reloaded config to a running actor. Actors that can't
+/// apply the config leave their current one in place
+pub struct ReloadConfig(pub Config);
+impl Message for ReloadConfig {
+    type Result = Result<()>;
+}
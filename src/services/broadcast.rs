@@ -1,223 +1,2531 @@
+mod chart;
+mod delivery;
+mod digest;
 mod email;
 mod events;
+mod ingest;
+mod maintenance;
+mod medium;
+mod oauth2;
+mod outbox;
+mod quiet_hours;
+mod template;
 pub use events::*;
+pub use ingest::ingest;
 
 use std::{
-    collections::HashMap,
-    sync::{Mutex, MutexGuard},
+    collections::{HashMap, HashSet},
     time::{Duration, Instant},
 };
 
 use actix::prelude::*;
-use crossbeam::queue::ArrayQueue;
-use lazy_static::lazy_static;
+use chrono::{NaiveDateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
 
 use crate::{
-    config::{config, AlertConfig, AlertType, EmailConfig},
+    config::{config, AlertConfig, AlertType, RetryConfig},
+    db::{self, models},
     error::{Error, Result},
+    services::{Ping, ReloadConfig},
 };
+use delivery::Delivery;
+use digest::Digest;
+use maintenance::MaintenanceWindow;
+pub use medium::{Medium, Mediums};
+pub use outbox::{EventPushed, Outbox, OutboxEntry, OutboxReady, OutboxStats};
+use quiet_hours::QuietHours;
 
-type LastAlerted = HashMap<BroadcastEventKey, Instant>;
+type LastAlerted = HashMap<BroadcastEventKey, NaiveDateTime>;
 
-lazy_static! {
-    pub static ref OUTBOX: ArrayQueue<BroadcastEvent> = ArrayQueue::new(100_000);
-    static ref LAST_ALERTED: Mutex<LastAlerted> = Mutex::new(HashMap::new());
+/// The maximum number of events waiting to be broadcast
+pub const OUTBOX_CAPACITY: usize = 100_000;
+
+/// How often to check for digests and quiet hour summaries that are
+/// due. Events are broadcast as soon as they arrive in the outbox
+const BROADCAST_TICK_INTERVAL: u64 = 500;
+
+/// An acknowledged alert is considered resolved once no events have
+/// been seen for it for this long
+const ACKNOWLEDGEMENT_RESOLVED_AFTER: Duration = Duration::from_secs(300);
+
+/// Hours of disk usage history to chart in high disk usage alerts
+const DISK_USAGE_CHART_HOURS: i64 = 24;
+
+trait BroadcastPorts {
+    fn medium(&self, name: &BroadcastMedium) -> Option<&dyn Medium>;
+    fn get_next_event(&self) -> Option<OutboxEntry>;
+    fn mark_delivered(&self, outbox_ids: &[i32]) -> Result<()>;
+    fn subscribe(&self, subscriber: Recipient<OutboxReady>);
+    fn load_last_alerted(&self) -> BoxFuture<'static, Result<LastAlerted>>;
+    fn persist_last_alerted(
+        &self,
+        event_key: &BroadcastEventKey,
+        alerted_at: NaiveDateTime,
+    ) -> Result<()>;
+    fn acknowledged_alerts(&self) -> BoxFuture<'static, Result<Vec<BroadcastEventKey>>>;
+    fn acknowledge_alert(&self, event_key: &BroadcastEventKey) -> Result<()>;
+    fn resolve_alert(&self, event_key: &BroadcastEventKey) -> Result<()>;
+    fn insert_silence(
+        &self,
+        silence: models::NewSilence,
+    ) -> BoxFuture<'static, Result<models::Silence>>;
+    fn active_silences(&self) -> BoxFuture<'static, Result<Vec<models::Silence>>>;
+    fn delete_silence(&self, id: i32) -> Result<()>;
+    fn record_alert(&self, delivery: &Delivery, error: Option<&Error>) -> Result<()>;
+    fn insert_dead_letter(&self, delivery: &Delivery, error: &Error) -> Result<()>;
+    fn dead_letters(&self) -> BoxFuture<'static, Result<Vec<models::DeadLetter>>>;
+    fn mark_dead_letter_replayed(&self, dead_letter_id: i32) -> Result<()>;
+    fn disk_usage_history(
+        &self,
+        mount: &str,
+        since: NaiveDateTime,
+    ) -> BoxFuture<'static, Result<Vec<models::DiskUsage>>>;
+}
+
+struct LiveBroadcastPorts {
+    mediums: Mediums,
+    outbox: Outbox,
+}
+impl LiveBroadcastPorts {
+    fn new(mediums: Mediums, outbox: Outbox) -> Self {
+        Self { mediums, outbox }
+    }
+}
+impl BroadcastPorts for LiveBroadcastPorts {
+    fn medium(&self, name: &BroadcastMedium) -> Option<&dyn Medium> {
+        self.mediums.get(name)
+    }
+
+    fn get_next_event(&self) -> Option<OutboxEntry> {
+        self.outbox.pop()
+    }
+
+    fn mark_delivered(&self, outbox_ids: &[i32]) -> Result<()> {
+        let outbox_ids = outbox_ids.to_vec();
+        db::spawn_write("marking outbox events delivered", move |database| {
+            database.mark_outbox_events_delivered(&outbox_ids)
+        });
+        Ok(())
+    }
+
+    fn subscribe(&self, subscriber: Recipient<OutboxReady>) {
+        self.outbox.subscribe(subscriber)
+    }
+
+    fn load_last_alerted(&self) -> BoxFuture<'static, Result<LastAlerted>> {
+        db::run(|database| {
+            database.last_alerted().map(|last_alerted| {
+                last_alerted
+                    .into_iter()
+                    .map(|alerted| (alerted.event_key.into(), alerted.alerted_at))
+                    .collect()
+            })
+        })
+        .boxed()
+    }
+
+    fn persist_last_alerted(
+        &self,
+        event_key: &BroadcastEventKey,
+        alerted_at: NaiveDateTime,
+    ) -> Result<()> {
+        let last_alerted = models::LastAlerted::new(event_key.to_string(), alerted_at);
+        db::spawn_write(
+            "recording when an event was last alerted",
+            move |database| database.upsert_last_alerted(last_alerted),
+        );
+        Ok(())
+    }
+
+    fn acknowledged_alerts(&self) -> BoxFuture<'static, Result<Vec<BroadcastEventKey>>> {
+        db::run(|database| {
+            database.active_acknowledgements().map(|acknowledgements| {
+                acknowledgements
+                    .into_iter()
+                    .map(|acknowledgement| acknowledgement.event_key.into())
+                    .collect()
+            })
+        })
+        .boxed()
+    }
+
+    fn acknowledge_alert(&self, event_key: &BroadcastEventKey) -> Result<()> {
+        let acknowledgement = models::NewAcknowledgement::new(event_key.to_string());
+        db::spawn_write("recording an acknowledgement", move |database| {
+            database.insert_acknowledgement(acknowledgement).map(|_| ())
+        });
+        Ok(())
+    }
+
+    fn resolve_alert(&self, event_key: &BroadcastEventKey) -> Result<()> {
+        let event_key = event_key.to_string();
+        db::spawn_write("resolving an acknowledgement", move |database| {
+            database.resolve_acknowledgement(&event_key)
+        });
+        Ok(())
+    }
+
+    fn insert_silence(
+        &self,
+        silence: models::NewSilence,
+    ) -> BoxFuture<'static, Result<models::Silence>> {
+        db::run(move |database| database.insert_silence(silence)).boxed()
+    }
+
+    fn active_silences(&self) -> BoxFuture<'static, Result<Vec<models::Silence>>> {
+        db::run(|database| database.active_silences()).boxed()
+    }
+
+    fn delete_silence(&self, id: i32) -> Result<()> {
+        db::spawn_write("deleting a silence", move |database| {
+            database.delete_silence(id)
+        });
+        Ok(())
+    }
+
+    fn record_alert(&self, delivery: &Delivery, error: Option<&Error>) -> Result<()> {
+        let alert = models::NewAlert {
+            event_type: delivery.event_type.as_ref().map(ToString::to_string),
+            event_keys: event_keys(delivery),
+            medium: delivery.medium.to_string(),
+            subject: delivery.subject.clone(),
+            outcome: if error.is_none() { "sent" } else { "failed" }.to_string(),
+            error: error.map(ToString::to_string),
+        };
+        db::spawn_write("recording an alert", move |database| {
+            database.insert_alert(alert).map(|_| ())
+        });
+        Ok(())
+    }
+
+    fn insert_dead_letter(&self, delivery: &Delivery, error: &Error) -> Result<()> {
+        let dead_letter = models::NewDeadLetter {
+            medium: delivery.medium.to_string(),
+            subject: delivery.subject.clone(),
+            body: delivery.body.html.clone(),
+            text_body: delivery.body.text.clone(),
+            error: error.to_string(),
+            attempts: delivery.attempt as i32,
+            event_type: delivery.event_type.as_ref().map(ToString::to_string),
+            event_keys: event_keys(delivery),
+        };
+        db::spawn_write("recording a dead letter", move |database| {
+            database.insert_dead_letter(dead_letter).map(|_| ())
+        });
+        Ok(())
+    }
+
+    fn dead_letters(&self) -> BoxFuture<'static, Result<Vec<models::DeadLetter>>> {
+        db::run(|database| database.pending_dead_letters()).boxed()
+    }
+
+    fn mark_dead_letter_replayed(&self, dead_letter_id: i32) -> Result<()> {
+        db::spawn_write("marking a dead letter replayed", move |database| {
+            database.mark_dead_letter_replayed(dead_letter_id)
+        });
+        Ok(())
+    }
+
+    fn disk_usage_history(
+        &self,
+        mount: &str,
+        since: NaiveDateTime,
+    ) -> BoxFuture<'static, Result<Vec<models::DiskUsage>>> {
+        let mount = mount.to_string();
+        db::run(move |database| database.disk_usage_since(&mount, since)).boxed()
+    }
+}
+
+fn event_keys(delivery: &Delivery) -> Vec<String> {
+    delivery
+        .event_keys
+        .iter()
+        .map(|event_key| event_key.to_string())
+        .collect()
+}
+
+pub struct Broadcast {
+    alerts: HashMap<BroadcastEventType, AlertConfig>,
+    digests: HashMap<(BroadcastEventType, Severity), Digest>,
+    maintenance_windows: Vec<MaintenanceWindow>,
+    quiet_hours: Vec<QuietHours>,
+    /// Alerts held during quiet hours, to be sent once they are over
+    held: HashMap<(BroadcastEventType, Severity), Digest>,
+    last_alerted: LastAlerted,
+    acknowledged: HashSet<BroadcastEventKey>,
+    last_seen: HashMap<BroadcastEventKey, Instant>,
+    silences: Vec<models::Silence>,
+    retry: RetryConfig,
+    dead_letter_replay_interval: Option<Duration>,
+    ports: Box<dyn BroadcastPorts + Send + Sync>,
+}
+
+impl Broadcast {
+    pub fn new(outbox: Outbox) -> Result<Option<Self>> {
+        let config = config().broadcast;
+        if config.alerts.is_empty() {
+            Ok(None)
+        } else {
+            let mediums = Mediums::from_config(&config)?;
+            mediums.validate(&config)?;
+
+            if config.durable_outbox.unwrap_or(false) {
+                let recovered = outbox.make_durable()?;
+                log::info!("Recovered {} pending events from the outbox", recovered);
+            }
+
+            Ok(Some(Self {
+                alerts: config
+                    .alerts
+                    .iter()
+                    .map(|alert| (alert.event.clone(), alert.clone()))
+                    .collect(),
+                digests: HashMap::new(),
+                maintenance_windows: config
+                    .maintenance_windows
+                    .iter()
+                    .flatten()
+                    .map(MaintenanceWindow::new)
+                    .collect::<Result<Vec<_>>>()?,
+                quiet_hours: config
+                    .quiet_hours
+                    .iter()
+                    .flatten()
+                    .map(QuietHours::new)
+                    .collect::<Result<Vec<_>>>()?,
+                held: HashMap::new(),
+                last_alerted: HashMap::new(),
+                acknowledged: HashSet::new(),
+                last_seen: HashMap::new(),
+                silences: vec![],
+                retry: config.retry.unwrap_or_default(),
+                dead_letter_replay_interval: config.dead_letter_replay_interval,
+                ports: Box::new(LiveBroadcastPorts::new(mediums, outbox)),
+            }))
+        }
+    }
+
+    #[cfg(test)]
+    fn test(
+        alerts: HashMap<BroadcastEventType, AlertConfig>,
+        ports: Box<dyn BroadcastPorts + Send + Sync>,
+    ) -> Self {
+        Self {
+            alerts,
+            digests: HashMap::new(),
+            maintenance_windows: vec![],
+            quiet_hours: vec![],
+            held: HashMap::new(),
+            last_alerted: HashMap::new(),
+            acknowledged: HashSet::new(),
+            last_seen: HashMap::new(),
+            silences: vec![],
+            retry: RetryConfig::default(),
+            dead_letter_replay_interval: None,
+            ports,
+        }
+    }
+
+    #[cfg(test)]
+    fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    #[cfg(test)]
+    fn with_maintenance_windows(mut self, maintenance_windows: Vec<MaintenanceWindow>) -> Self {
+        self.maintenance_windows = maintenance_windows;
+        self
+    }
+
+    #[cfg(test)]
+    fn with_quiet_hours(mut self, quiet_hours: Vec<QuietHours>) -> Self {
+        self.quiet_hours = quiet_hours;
+        self
+    }
+
+    /// Handle every event waiting in the outbox, then send any digests
+    /// that are due
+    fn tick(&mut self, ctx: &mut Context<Self>) {
+        self.resolve_acknowledged_alerts();
+
+        let now = Utc::now().naive_utc();
+        self.silences.retain(|silence| silence.expires_at > now);
+
+        let mut rollups: HashMap<(BroadcastEventType, Severity), (AlertConfig, Vec<OutboxEntry>)> =
+            HashMap::new();
+
+        while let Some(OutboxEntry { id, event }) = self.ports.get_next_event() {
+            log::debug!("Broadcast received message: {:?}", event.event_type());
+            self.last_seen.insert(event.event_key(), Instant::now());
+
+            if self
+                .maintenance_windows
+                .iter()
+                .any(|window| window.suppresses(&event))
+            {
+                log::info!(
+                    "Suppressing alert for {} during maintenance window",
+                    event.event_key()
+                );
+                self.mark_delivered(id);
+                continue;
+            }
+
+            if let Some(silence) = self
+                .silences
+                .iter()
+                .find(|silence| silences(silence, &event))
+            {
+                log::info!(
+                    "Silencing alert for {} until {}",
+                    event.event_key(),
+                    silence.expires_at
+                );
+                self.mark_delivered(id);
+                continue;
+            }
+
+            // get the configuration for this message, if it exists,
+            // routed according to the message's severity
+            let alert_config = self
+                .alerts
+                .get(&event.event_type())
+                .map(|alert_config| alert_config.for_severity(event.severity()));
+
+            match alert_config {
+                Some(alert_config) if self.is_quiet(event.severity()) => {
+                    self.hold(alert_config, event, id)
+                }
+                Some(alert_config) => match alert_config.alert_type {
+                    AlertType::Alarm if alert_config.rollup.unwrap_or(false) => rollups
+                        .entry((event.event_type(), event.severity()))
+                        .or_insert_with(|| (alert_config, vec![]))
+                        .1
+                        .push(OutboxEntry { id, event }),
+                    AlertType::Alarm => {
+                        self.alarm(ctx, &alert_config, event);
+                        self.mark_delivered(id);
+                    }
+                    AlertType::Digest => self
+                        .digests
+                        .entry((event.event_type(), event.severity()))
+                        .or_insert_with(|| Digest::new(alert_config))
+                        .add(event, id),
+                },
+                None => {
+                    log::debug!(
+                        "Not alerting: {:?}. No alert is configured",
+                        event.event_type()
+                    );
+                    self.mark_delivered(id);
+                }
+            }
+        }
+
+        for (_, (alert_config, entries)) in rollups {
+            let outbox_ids = entries
+                .iter()
+                .filter_map(|entry| entry.id)
+                .collect::<Vec<_>>();
+            let events = entries.into_iter().map(|entry| entry.event).collect();
+            self.rollup(ctx, &alert_config, events);
+            self.mark_delivered(outbox_ids);
+        }
+
+        self.flush_digests(ctx);
+        self.flush_held(ctx);
+    }
+
+    /// Hold an alert until quiet hours are over. Alarms that are
+    /// acknowledged or were sent recently are dropped rather than held,
+    /// as they would be outside of quiet hours
+    fn hold(&mut self, alert_config: AlertConfig, event: BroadcastEvent, id: Option<i32>) {
+        if alert_config.alert_type == AlertType::Alarm
+            && !self.alarm_due(&alert_config, &event.event_key())
+        {
+            self.mark_delivered(id);
+            return;
+        }
+
+        log::debug!("Holding alert for {} during quiet hours", event.event_key());
+        self.held
+            .entry((event.event_type(), event.severity()))
+            .or_insert_with(|| Digest::new(alert_config))
+            .add(event, id)
+    }
+
+    fn is_quiet(&self, severity: Severity) -> bool {
+        self.quiet_hours
+            .iter()
+            .any(|quiet_hours| quiet_hours.holds(severity))
+    }
+
+    /// Resolve acknowledged alerts that have stopped firing, so that
+    /// they alert again if they come back
+    fn resolve_acknowledged_alerts(&mut self) {
+        let now = Instant::now();
+        let last_seen = &self.last_seen;
+        let resolved = self
+            .acknowledged
+            .iter()
+            .filter(|event_key| {
+                last_seen
+                    .get(event_key)
+                    .map(|seen| now.duration_since(*seen) > ACKNOWLEDGEMENT_RESOLVED_AFTER)
+                    .unwrap_or(true)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for event_key in resolved {
+            log::info!("Acknowledged alert {} has resolved", event_key);
+            self.acknowledged.remove(&event_key);
+            self.ports
+                .resolve_alert(&event_key)
+                .unwrap_or_else(|e| log::error!("Error resolving alert {}: {}", event_key, e));
+        }
+    }
+
+    /// Send an alert for this event immediately, unless we've already
+    /// alerted for it within the configured window or it has been
+    /// acknowledged
+    fn alarm(
+        &mut self,
+        ctx: &mut Context<Self>,
+        alert_config: &AlertConfig,
+        event: BroadcastEvent,
+    ) {
+        let event_key = event.event_key();
+        if !self.alarm_due(alert_config, &event_key) {
+            return;
+        }
+
+        log::debug!("Sending alert for : {:?}", event);
+        let retriggered = self.last_alerted.contains_key(&event_key);
+
+        let (subject, body) = event.subject_and_body();
+        let subject = render_subject(alert_config, event.severity(), &subject, retriggered);
+        match self.load_chart(&event) {
+            Some(chart) => {
+                let alert_config = alert_config.clone();
+                let event_key = event_key.clone();
+                chart
+                    .into_actor(self)
+                    .map(move |chart, this, ctx| {
+                        this.send_alarm(ctx, &alert_config, event_key, subject, body, chart)
+                    })
+                    .spawn(ctx);
+            }
+            None => self.send_alarm(ctx, alert_config, event_key.clone(), subject, body, None),
+        }
+
+        self.record_alerted(event_key, Utc::now().naive_utc());
+    }
+
+    /// Send an alarm for a single event, with its chart if it has one
+    fn send_alarm(
+        &mut self,
+        ctx: &mut Context<Self>,
+        alert_config: &AlertConfig,
+        event_key: BroadcastEventKey,
+        subject: String,
+        mut body: Body,
+        chart: Option<Attachment>,
+    ) {
+        if let Some(chart) = chart {
+            body.push_paragraph("Disk usage over the last day is charted in the attachment");
+            body.attachments.push(chart);
+        }
+        body.push_paragraph(&format!("Acknowledge this alert with key: {}", event_key));
+        self.send(ctx, alert_config, vec![event_key], subject, body);
+    }
+
+    /// Send a single alert for every event of one type and severity
+    /// that fired in the same tick, summarizing them in a list
+    fn rollup(
+        &mut self,
+        ctx: &mut Context<Self>,
+        alert_config: &AlertConfig,
+        events: Vec<BroadcastEvent>,
+    ) {
+        let mut due = events
+            .into_iter()
+            .filter(|event| self.alarm_due(alert_config, &event.event_key()))
+            .collect::<Vec<_>>();
+
+        if due.len() == 1 {
+            self.alarm(ctx, alert_config, due.remove(0));
+        } else if !due.is_empty() {
+            log::debug!("Sending rollup of {} alerts", due.len());
+            let mut rollup = Digest::new(alert_config.clone());
+            for event in due {
+                rollup.add(event, None);
+            }
+
+            let event_keys = rollup.event_keys();
+            let (subject, mut body) = rollup.subject_and_body();
+            body.push_paragraph(&format!(
+                "Acknowledge these alerts with keys: {}",
+                event_keys
+                    .iter()
+                    .map(|event_key| event_key.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            let subject = render_subject(alert_config, rollup.severity(), &subject, false);
+            self.send(ctx, alert_config, event_keys, subject, body);
+
+            let alerted_at = Utc::now().naive_utc();
+            for event in &rollup.events {
+                self.record_alerted(event.event_key(), alerted_at);
+            }
+        }
+    }
+
+    /// Whether to alert for this event now. Alerts that are acknowledged
+    /// or were sent within the configured interval are skipped
+    fn alarm_due(&self, alert_config: &AlertConfig, event_key: &BroadcastEventKey) -> bool {
+        if self.acknowledged.contains(event_key) {
+            log::debug!("Not alerting: {:?}. Alert is acknowledged", event_key);
+            return false;
+        }
+
+        let last_alerted = self.last_alerted.get(event_key).cloned();
+
+        let recently_alerted = match (alert_config.alert_interval, last_alerted) {
+            (Some(interval), Some(alerted_at)) => Utc::now()
+                .naive_utc()
+                .signed_duration_since(alerted_at)
+                .to_std()
+                .map(|elapsed| elapsed <= interval)
+                // the last alert is in the future, so the clock must
+                // have changed. Treat it as recent
+                .unwrap_or(true),
+            _ => false,
+        };
+        if recently_alerted {
+            log::debug!("Not alerting: {:?}. Alerted recently", event_key);
+        }
+
+        !recently_alerted
+    }
+
+    /// Load a chart of recent disk usage for high disk usage alerts.
+    /// If the history can't be loaded the alert is sent without it
+    fn load_chart(&self, event: &BroadcastEvent) -> Option<BoxFuture<'static, Option<Attachment>>> {
+        if let BroadcastEvent::HighDiskUsage {
+            filesystem_mount, ..
+        } = event
+        {
+            let mount = filesystem_mount.clone();
+            let since = Utc::now().naive_utc() - chrono::Duration::hours(DISK_USAGE_CHART_HOURS);
+            let chart =
+                self.ports
+                    .disk_usage_history(filesystem_mount, since)
+                    .map(move |history| match history {
+                        Ok(history) => chart::disk_usage_chart(&mount, &history),
+                        Err(e) => {
+                            log::error!("Error loading disk usage history: {}", e);
+                            None
+                        }
+                    });
+            Some(chart.boxed())
+        } else {
+            None
+        }
+    }
+
+    /// Send every digest whose window has elapsed. Digests without a
+    /// configured alert interval are sent on every tick
+    fn flush_digests(&mut self, ctx: &mut Context<Self>) {
+        let now = Instant::now();
+        let due = self
+            .digests
+            .iter()
+            .filter(|(_, digest)| {
+                digest
+                    .alert_config
+                    .alert_interval
+                    .map(|interval| now.duration_since(digest.started) >= interval)
+                    .unwrap_or(true)
+            })
+            .map(|(digest_key, _)| digest_key.clone())
+            .collect::<Vec<_>>();
+
+        for digest_key in due {
+            if let Some(digest) = self.digests.remove(&digest_key) {
+                log::debug!(
+                    "Sending digest of {} {:?} events",
+                    digest.events.len(),
+                    digest_key
+                );
+
+                self.send_digest(ctx, &digest, "");
+            }
+        }
+    }
+
+    /// Send a summary of the alerts held for every severity whose
+    /// quiet hours are over
+    fn flush_held(&mut self, ctx: &mut Context<Self>) {
+        let due = self
+            .held
+            .keys()
+            .filter(|(_, severity)| !self.is_quiet(*severity))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for held_key in due {
+            if let Some(mut digest) = self.held.remove(&held_key) {
+                // alarms acknowledged while they were held aren't
+                // summarized
+                if digest.alert_config.alert_type == AlertType::Alarm {
+                    let alert_config = digest.alert_config.clone();
+                    digest
+                        .events
+                        .retain(|event| self.alarm_due(&alert_config, &event.event_key()));
+                }
+                if digest.events.is_empty() {
+                    self.mark_delivered(digest.outbox_ids);
+                    continue;
+                }
+
+                log::info!(
+                    "Sending summary of {} {:?} events held during quiet hours",
+                    digest.events.len(),
+                    held_key
+                );
+
+                self.send_digest(ctx, &digest, "Quiet Hours Summary: ");
+            }
+        }
+    }
+
+    fn send_digest(&mut self, ctx: &mut Context<Self>, digest: &Digest, prefix: &str) {
+        let (subject, body) = digest.subject_and_body();
+        let subject = render_subject(
+            &digest.alert_config,
+            digest.severity(),
+            &format!("{}{}", prefix, subject),
+            false,
+        );
+        self.send(
+            ctx,
+            &digest.alert_config,
+            digest.event_keys(),
+            subject,
+            body,
+        );
+
+        let alerted_at = Utc::now().naive_utc();
+        for event in &digest.events {
+            self.record_alerted(event.event_key(), alerted_at);
+        }
+        self.mark_delivered(digest.outbox_ids.iter().cloned());
+    }
+
+    /// Mark events from the durable outbox as delivered, so that they
+    /// aren't broadcast again on restart
+    fn mark_delivered<I: IntoIterator<Item = i32>>(&self, outbox_ids: I) {
+        let outbox_ids = outbox_ids.into_iter().collect::<Vec<_>>();
+        if !outbox_ids.is_empty() {
+            self.ports
+                .mark_delivered(&outbox_ids)
+                .unwrap_or_else(|e| log::error!("Error marking outbox events delivered: {}", e));
+        }
+    }
+
+    /// Record the time we last alerted for an event, both in memory
+    /// and in the database so that it survives restarts
+    fn record_alerted(&mut self, event_key: BroadcastEventKey, alerted_at: NaiveDateTime) {
+        self.ports
+            .persist_last_alerted(&event_key, alerted_at)
+            .unwrap_or_else(|e| log::error!("Error persisting last alerted time: {}", e));
+        self.last_alerted.insert(event_key, alerted_at);
+    }
+
+    fn send(
+        &mut self,
+        ctx: &mut Context<Self>,
+        alert_config: &AlertConfig,
+        event_keys: Vec<BroadcastEventKey>,
+        subject: String,
+        body: Body,
+    ) {
+        for medium in &alert_config.mediums {
+            self.deliver(
+                ctx,
+                Delivery::new(
+                    medium.clone(),
+                    subject.clone(),
+                    body.clone(),
+                    Some(alert_config.event.clone()),
+                    event_keys.clone(),
+                ),
+            );
+        }
+    }
+
+    /// Deliver a message over its medium, retrying with exponential
+    /// backoff if it fails. Deliveries that still fail after the
+    /// maximum number of attempts go to the dead letter queue
+    fn deliver(&mut self, ctx: &mut Context<Self>, delivery: Delivery) {
+        let result = self
+            .ports
+            .medium(&delivery.medium)
+            .ok_or_else(|| Error::unconfigured_medium(delivery.medium.to_string()))
+            .and_then(|medium| medium.deliver(&delivery));
+
+        match result {
+            Ok(()) => {
+                self.record_alert(&delivery, None);
+                if let Some(dead_letter_id) = delivery.dead_letter_id {
+                    self.ports
+                        .mark_dead_letter_replayed(dead_letter_id)
+                        .unwrap_or_else(|e| {
+                            log::error!("Error marking dead letter as replayed: {}", e)
+                        });
+                }
+            }
+            Err(e) => self.retry_delivery(ctx, delivery, e),
+        }
+    }
+
+    fn retry_delivery(&self, ctx: &mut Context<Self>, delivery: Delivery, e: Error) {
+        if delivery.attempt < self.retry.max_attempts {
+            let backoff = self.retry.backoff(delivery.attempt);
+            log::warn!(
+                "Error delivering {:?} by {:?} on attempt {}, retrying in {:?}: {}",
+                delivery.subject,
+                delivery.medium,
+                delivery.attempt,
+                backoff,
+                e
+            );
+            ctx.run_later(backoff, move |this, ctx| {
+                this.deliver(ctx, delivery.next_attempt())
+            });
+        } else {
+            log::error!(
+                "Giving up on delivering {:?} by {:?} after {} attempts: {}",
+                delivery.subject,
+                delivery.medium,
+                delivery.attempt,
+                e
+            );
+            self.record_alert(&delivery, Some(&e));
+
+            // replayed deliveries are still in the dead letter queue
+            if delivery.dead_letter_id.is_none() {
+                self.ports
+                    .insert_dead_letter(&delivery, &e)
+                    .unwrap_or_else(|e| {
+                        log::error!("Error adding {:?} to dead letters: {}", delivery, e)
+                    });
+            }
+        }
+    }
+
+    /// Record the outcome of a delivery in the alert history
+    fn record_alert(&self, delivery: &Delivery, error: Option<&Error>) {
+        self.ports
+            .record_alert(delivery, error)
+            .unwrap_or_else(|e| log::error!("Error recording alert history: {}", e));
+    }
+
+    /// Attempt to deliver everything in the dead letter queue again
+    fn replay_dead_letters(&self) -> impl ActorFuture<Output = Result<usize>, Actor = Self> {
+        self.ports
+            .dead_letters()
+            .into_actor(self)
+            .map(|dead_letters, this, ctx| {
+                let deliveries = dead_letters?
+                    .into_iter()
+                    .map(Delivery::from_dead_letter)
+                    .collect::<Result<Vec<_>>>()?;
+
+                let count = deliveries.len();
+                if count > 0 {
+                    log::info!("Replaying {} dead letters", count);
+                }
+                for delivery in deliveries {
+                    this.deliver(ctx, delivery);
+                }
+
+                Ok(count)
+            })
+    }
+}
+
+impl Broadcast {
+    /// Apply the alert state recorded in the database. State that
+    /// can't be loaded starts out empty
+    fn load_alert_state(
+        &mut self,
+        last_alerted: Result<LastAlerted>,
+        acknowledged: Result<Vec<BroadcastEventKey>>,
+        silences: Result<Vec<models::Silence>>,
+    ) {
+        match last_alerted {
+            Ok(last_alerted) => self.last_alerted.extend(last_alerted),
+            Err(e) => log::error!("Error loading last alerted times: {}", e),
+        }
+
+        match acknowledged {
+            Ok(acknowledged) => {
+                // treat acknowledged alerts as still firing until we
+                // hear otherwise
+                let now = Instant::now();
+                for event_key in acknowledged {
+                    self.last_seen.insert(event_key.clone(), now);
+                    self.acknowledged.insert(event_key);
+                }
+            }
+            Err(e) => log::error!("Error loading acknowledged alerts: {}", e),
+        }
+
+        match silences {
+            Ok(silences) => self.silences = silences,
+            Err(e) => log::error!("Error loading silences: {}", e),
+        }
+    }
+}
+
+/// Whether a silence mutes alerts for this event
+fn silences(silence: &models::Silence, event: &BroadcastEvent) -> bool {
+    silence
+        .event_type
+        .as_ref()
+        .map(|event_type| *event_type == event.event_type().to_string())
+        .unwrap_or(true)
+        && silence
+            .event_key
+            .as_ref()
+            .map(|event_key| *event_key == event.event_key().to_string())
+            .unwrap_or(true)
+}
+
+/// The subject line for an alert, rendered from its configured template
+fn render_subject(
+    alert_config: &AlertConfig,
+    severity: Severity,
+    subject: &str,
+    retriggered: bool,
+) -> String {
+    template::render(
+        alert_config
+            .subject_template
+            .as_deref()
+            .unwrap_or(template::DEFAULT_SUBJECT_TEMPLATE),
+        &alert_config.event,
+        severity,
+        subject,
+        retriggered,
+    )
+}
+
+impl Actor for Broadcast {
+    type Context = Context<Self>;
+
+    /// Load persisted alert state and start a tick for the broadcast
+    /// actor
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        // events wait until the alert state has loaded
+        futures::future::join3(
+            self.ports.load_last_alerted(),
+            self.ports.acknowledged_alerts(),
+            self.ports.active_silences(),
+        )
+        .into_actor(self)
+        .map(|(last_alerted, acknowledged, silences), this, _| {
+            this.load_alert_state(last_alerted, acknowledged, silences)
+        })
+        .wait(ctx);
+
+        self.ports.subscribe(ctx.address().recipient());
+        ctx.run_interval(
+            Duration::from_millis(BROADCAST_TICK_INTERVAL),
+            move |this, ctx| this.tick(ctx),
+        );
+
+        if let Some(interval) = self.dead_letter_replay_interval {
+            ctx.run_interval(interval, move |this, ctx| {
+                this.replay_dead_letters()
+                    .map(|replayed, _, _| {
+                        replayed
+                            .map(|_| ())
+                            .unwrap_or_else(|e| log::error!("Error replaying dead letters: {}", e))
+                    })
+                    .spawn(ctx)
+            });
+        }
+    }
 }
 
-const BROADCAST_TICK_INTERVAL: u64 = 500;
+impl Handler<OutboxReady> for Broadcast {
+    type Result = ();
+
+    fn handle(&mut self, _: OutboxReady, ctx: &mut Context<Self>) {
+        self.tick(ctx)
+    }
+}
+
+impl Handler<Ping> for Broadcast {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Context<Self>) {}
+}
+
+/// Apply new alerts, maintenance windows, quiet hours and retries.
+/// Mediums are only set up on start, so the config is rejected if its
+/// alerts use a medium that isn't running
+impl Handler<ReloadConfig> for Broadcast {
+    type Result = Result<()>;
+
+    fn handle(&mut self, ReloadConfig(config): ReloadConfig, _: &mut Context<Self>) -> Result<()> {
+        let config = config.broadcast;
+        let used = config.alerts.iter().flat_map(|alert| {
+            alert.mediums.iter().chain(
+                alert
+                    .severity_routes
+                    .iter()
+                    .flatten()
+                    .flat_map(|route| route.mediums.iter()),
+            )
+        });
+        for name in used {
+            if self.ports.medium(name).is_none() {
+                return Err(Error::unconfigured_medium(name.to_string()));
+            }
+        }
+        let maintenance_windows = config
+            .maintenance_windows
+            .iter()
+            .flatten()
+            .map(MaintenanceWindow::new)
+            .collect::<Result<Vec<_>>>()?;
+        let quiet_hours = config
+            .quiet_hours
+            .iter()
+            .flatten()
+            .map(QuietHours::new)
+            .collect::<Result<Vec<_>>>()?;
+
+        self.alerts = config
+            .alerts
+            .iter()
+            .map(|alert| (alert.event.clone(), alert.clone()))
+            .collect();
+        self.maintenance_windows = maintenance_windows;
+        self.quiet_hours = quiet_hours;
+        self.retry = config.retry.unwrap_or_default();
+        log::info!("Broadcast config reloaded");
+
+        Ok(())
+    }
+}
+
+/// Stop re-triggering an active alert until it resolves
+pub struct Acknowledge(pub BroadcastEventKey);
+impl Message for Acknowledge {
+    type Result = Result<()>;
+}
+
+impl Handler<Acknowledge> for Broadcast {
+    type Result = Result<()>;
+
+    fn handle(&mut self, msg: Acknowledge, _: &mut Context<Self>) -> Self::Result {
+        let Acknowledge(event_key) = msg;
+        if !self.last_alerted.contains_key(&event_key) {
+            return Err(Error::unknown_alert(event_key.to_string()));
+        }
+
+        if !self.acknowledged.contains(&event_key) {
+            self.ports.acknowledge_alert(&event_key)?;
+            self.last_seen.insert(event_key.clone(), Instant::now());
+            self.acknowledged.insert(event_key);
+        }
+
+        Ok(())
+    }
+}
+
+/// Mute alerts for events of `event_type`, or with `event_key`, for
+/// `duration`
+pub struct AddSilence {
+    pub event_type: Option<BroadcastEventType>,
+    pub event_key: Option<String>,
+    pub duration: Duration,
+    pub comment: Option<String>,
+}
+impl Message for AddSilence {
+    type Result = Result<models::Silence>;
+}
+
+impl Handler<AddSilence> for Broadcast {
+    type Result = ResponseActFuture<Self, Result<models::Silence>>;
+
+    fn handle(&mut self, msg: AddSilence, _: &mut Context<Self>) -> Self::Result {
+        let silence = match new_silence(msg) {
+            Ok(silence) => silence,
+            Err(e) => return Box::new(futures::future::err(e).into_actor(self)),
+        };
+
+        Box::new(
+            self.ports
+                .insert_silence(silence)
+                .into_actor(self)
+                .map(|silence, this, _| {
+                    let silence = silence?;
+                    this.silences.push(silence.clone());
+                    Ok(silence)
+                }),
+        )
+    }
+}
+
+/// The silence to record for a request, which must mute something and
+/// expire
+fn new_silence(msg: AddSilence) -> Result<models::NewSilence> {
+    if msg.event_type.is_none() && msg.event_key.is_none() {
+        return Err(Error::invalid_request(
+            "a silence needs an event type or an event key",
+        ));
+    }
+    let out_of_range = || Error::invalid_request("silence duration is out of range");
+    let duration = chrono::Duration::from_std(msg.duration).map_err(|_| out_of_range())?;
+    let expires_at = Utc::now()
+        .naive_utc()
+        .checked_add_signed(duration)
+        .ok_or_else(out_of_range)?;
+
+    Ok(models::NewSilence {
+        event_type: msg.event_type.as_ref().map(ToString::to_string),
+        event_key: msg.event_key,
+        comment: msg.comment,
+        expires_at,
+    })
+}
+
+/// Stop muting the alerts of an active silence
+pub struct RemoveSilence(pub i32);
+impl Message for RemoveSilence {
+    type Result = Result<()>;
+}
+
+impl Handler<RemoveSilence> for Broadcast {
+    type Result = Result<()>;
+
+    fn handle(&mut self, RemoveSilence(id): RemoveSilence, _: &mut Context<Self>) -> Self::Result {
+        if !self.silences.iter().any(|silence| silence.id == id) {
+            return Err(Error::unknown_silence(id));
+        }
+
+        self.ports.delete_silence(id)?;
+        self.silences.retain(|silence| silence.id != id);
+
+        Ok(())
+    }
+}
+
+/// Attempt to deliver everything in the dead letter queue again,
+/// returning the number of deliveries replayed
+pub struct ReplayDeadLetters;
+impl Message for ReplayDeadLetters {
+    type Result = Result<usize>;
+}
+
+impl Handler<ReplayDeadLetters> for Broadcast {
+    type Result = ResponseActFuture<Self, Result<usize>>;
+
+    fn handle(&mut self, _: ReplayDeadLetters, _: &mut Context<Self>) -> Self::Result {
+        Box::new(self.replay_dead_letters())
+    }
+}
+
+#[macro_use]
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::{
+        config::{
+            AlertType, Config, MaintenanceWindowConfig, OverflowPolicy, QuietHoursConfig,
+            SeverityRouteConfig,
+        },
+        error::{ErrorKind, Result},
+        services::broadcast::events::BroadcastEventType,
+    };
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    struct TestEmailMedium {
+        sent_emails: Arc<Mutex<Vec<(String, Body)>>>,
+        failing_sends: Mutex<u32>,
+    }
+    impl Medium for TestEmailMedium {
+        fn deliver(&self, delivery: &Delivery) -> Result<()> {
+            let mut failing_sends = self.failing_sends.lock().unwrap();
+            if *failing_sends > 0 {
+                *failing_sends -= 1;
+                return Err(ErrorKind::EmailError {
+                    error: "connection refused".to_string(),
+                }
+                .into());
+            }
+
+            self.sent_emails
+                .lock()
+                .unwrap()
+                .push((delivery.subject.clone(), delivery.body.clone()));
+            Ok(())
+        }
+    }
+
+    struct TestBroadcastPorts {
+        email: TestEmailMedium,
+        events_buffer: Arc<Mutex<Vec<BroadcastEvent>>>,
+        persisted_last_alerted: Arc<Mutex<LastAlerted>>,
+        dead_letters: Arc<Mutex<Vec<models::DeadLetter>>>,
+        delivered: Arc<Mutex<Vec<i32>>>,
+        alert_history: Arc<Mutex<Vec<(String, Option<String>)>>>,
+        outbox: Option<Outbox>,
+        disk_usage_history: Vec<models::DiskUsage>,
+    }
+    impl TestBroadcastPorts {
+        pub fn new() -> Self {
+            Self {
+                email: TestEmailMedium {
+                    sent_emails: Arc::new(Mutex::new(vec![])),
+                    failing_sends: Mutex::new(0),
+                },
+                events_buffer: Arc::new(Mutex::new(vec![])),
+                persisted_last_alerted: Arc::new(Mutex::new(HashMap::new())),
+                dead_letters: Arc::new(Mutex::new(vec![])),
+                delivered: Arc::new(Mutex::new(vec![])),
+                alert_history: Arc::new(Mutex::new(vec![])),
+                outbox: None,
+                disk_usage_history: vec![],
+            }
+        }
+
+        /// Take events from a real outbox instead of the events buffer
+        pub fn with_outbox(mut self, outbox: Outbox) -> Self {
+            self.outbox = Some(outbox);
+            self
+        }
+
+        pub fn with_alert_history(
+            mut self,
+            alert_history: Arc<Mutex<Vec<(String, Option<String>)>>>,
+        ) -> Self {
+            self.alert_history = alert_history;
+            self
+        }
+
+        pub fn with_delivered(mut self, delivered: Arc<Mutex<Vec<i32>>>) -> Self {
+            self.delivered = delivered;
+            self
+        }
+
+        pub fn with_disk_usage_history(
+            mut self,
+            disk_usage_history: Vec<models::DiskUsage>,
+        ) -> Self {
+            self.disk_usage_history = disk_usage_history;
+            self
+        }
+
+        pub fn with_dead_letters(
+            mut self,
+            dead_letters: Arc<Mutex<Vec<models::DeadLetter>>>,
+        ) -> Self {
+            self.dead_letters = dead_letters;
+            self
+        }
+
+        /// Fail this many sends before succeeding
+        pub fn with_failing_sends(self, failing_sends: u32) -> Self {
+            *self.email.failing_sends.lock().unwrap() = failing_sends;
+            self
+        }
+
+        pub fn with_persisted_last_alerted(
+            mut self,
+            persisted_last_alerted: Arc<Mutex<LastAlerted>>,
+        ) -> Self {
+            self.persisted_last_alerted = persisted_last_alerted;
+            self
+        }
+
+        pub fn with_sent_emails(mut self, sent_emails: Arc<Mutex<Vec<(String, Body)>>>) -> Self {
+            self.email.sent_emails = sent_emails;
+            self
+        }
+
+        pub fn with_events_buffer(
+            mut self,
+            events_buffer: Arc<Mutex<Vec<BroadcastEvent>>>,
+        ) -> Self {
+            self.events_buffer = events_buffer;
+            self
+        }
+    }
+    impl BroadcastPorts for TestBroadcastPorts {
+        fn medium(&self, name: &BroadcastMedium) -> Option<&dyn Medium> {
+            match name {
+                BroadcastMedium::Email => Some(&self.email),
+            }
+        }
+
+        /// Events are given the id of their position in the buffer,
+        /// as if they came from a durable outbox
+        fn get_next_event(&self) -> Option<OutboxEntry> {
+            if let Some(outbox) = &self.outbox {
+                return outbox.pop();
+            }
+
+            let mut events_buffer = self.events_buffer.lock().unwrap();
+            events_buffer.pop().map(|event| OutboxEntry {
+                id: Some(events_buffer.len() as i32),
+                event,
+            })
+        }
+
+        fn mark_delivered(&self, outbox_ids: &[i32]) -> Result<()> {
+            self.delivered.lock().unwrap().extend(outbox_ids);
+            Ok(())
+        }
+
+        fn subscribe(&self, subscriber: Recipient<OutboxReady>) {
+            if let Some(outbox) = &self.outbox {
+                outbox.subscribe(subscriber)
+            }
+        }
+
+        fn load_last_alerted(&self) -> BoxFuture<'static, Result<LastAlerted>> {
+            futures::future::ok(self.persisted_last_alerted.lock().unwrap().clone()).boxed()
+        }
+
+        fn persist_last_alerted(
+            &self,
+            event_key: &BroadcastEventKey,
+            alerted_at: NaiveDateTime,
+        ) -> Result<()> {
+            self.persisted_last_alerted
+                .lock()
+                .unwrap()
+                .insert(event_key.clone(), alerted_at);
+            Ok(())
+        }
+
+        fn acknowledged_alerts(&self) -> BoxFuture<'static, Result<Vec<BroadcastEventKey>>> {
+            futures::future::ok(vec![]).boxed()
+        }
+
+        fn acknowledge_alert(&self, _: &BroadcastEventKey) -> Result<()> {
+            Ok(())
+        }
+
+        fn resolve_alert(&self, _: &BroadcastEventKey) -> Result<()> {
+            Ok(())
+        }
+
+        fn insert_silence(
+            &self,
+            silence: models::NewSilence,
+        ) -> BoxFuture<'static, Result<models::Silence>> {
+            futures::future::ok(models::Silence {
+                id: 0,
+                event_type: silence.event_type,
+                event_key: silence.event_key,
+                comment: silence.comment,
+                created_at: Utc::now().naive_utc(),
+                expires_at: silence.expires_at,
+            })
+            .boxed()
+        }
+
+        fn active_silences(&self) -> BoxFuture<'static, Result<Vec<models::Silence>>> {
+            futures::future::ok(vec![]).boxed()
+        }
+
+        fn delete_silence(&self, _: i32) -> Result<()> {
+            Ok(())
+        }
+
+        fn record_alert(&self, delivery: &Delivery, error: Option<&Error>) -> Result<()> {
+            self.alert_history
+                .lock()
+                .unwrap()
+                .push((delivery.subject.clone(), error.map(ToString::to_string)));
+            Ok(())
+        }
+
+        fn insert_dead_letter(&self, delivery: &Delivery, error: &Error) -> Result<()> {
+            let mut dead_letters = self.dead_letters.lock().unwrap();
+            let id = dead_letters.len() as i32;
+            dead_letters.push(test_dead_letter(id, delivery, error));
+            Ok(())
+        }
+
+        fn dead_letters(&self) -> BoxFuture<'static, Result<Vec<models::DeadLetter>>> {
+            let dead_letters = self
+                .dead_letters
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|dead_letter| dead_letter.replayed_at.is_none())
+                .cloned()
+                .collect();
+            futures::future::ok(dead_letters).boxed()
+        }
+
+        fn mark_dead_letter_replayed(&self, dead_letter_id: i32) -> Result<()> {
+            for dead_letter in self.dead_letters.lock().unwrap().iter_mut() {
+                if dead_letter.id == dead_letter_id {
+                    dead_letter.replayed_at = Some(Utc::now().naive_utc());
+                }
+            }
+            Ok(())
+        }
+
+        fn disk_usage_history(
+            &self,
+            mount: &str,
+            since: NaiveDateTime,
+        ) -> BoxFuture<'static, Result<Vec<models::DiskUsage>>> {
+            let history = self
+                .disk_usage_history
+                .iter()
+                .filter(|disk_usage| disk_usage.mount == mount && disk_usage.recorded_at >= since)
+                .cloned()
+                .collect();
+            futures::future::ok(history).boxed()
+        }
+    }
+
+    fn test_dead_letter(id: i32, delivery: &Delivery, error: &Error) -> models::DeadLetter {
+        models::DeadLetter {
+            id,
+            medium: delivery.medium.to_string(),
+            subject: delivery.subject.clone(),
+            body: delivery.body.html.clone(),
+            error: error.to_string(),
+            attempts: delivery.attempt as i32,
+            failed_at: Utc::now().naive_utc(),
+            replayed_at: None,
+            text_body: delivery.body.text.clone(),
+            event_type: delivery.event_type.as_ref().map(ToString::to_string),
+            event_keys: event_keys(delivery),
+        }
+    }
+
+    #[test]
+    fn broadcast_sends_an_email() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: Some(Duration::from_millis(100)),
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let event = BroadcastEvent::HighDiskUsage {
+            filesystem_mount: "/".to_string(),
+            current_usage: 100.00,
+            max_usage: 50.00,
+        };
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(vec![event])))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        assert_eq!(sent_emails.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn broadcast_sends_multiple_emails() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: Some(Duration::from_millis(100)),
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let events = vec![
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/".to_string(),
+                current_usage: 100.00,
+                max_usage: 50.00,
+            },
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/mnt/test".to_string(),
+                current_usage: 100.00,
+                max_usage: 50.00,
+            },
+        ];
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(events)))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+        assert_eq!(sent_emails.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn broadcast_ignores_alerts_if_an_alert_was_just_sent() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: Some(Duration::from_millis(100)),
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        // simulate 10 identical events in a row
+        let event = BroadcastEvent::HighDiskUsage {
+            filesystem_mount: "/".to_string(),
+            current_usage: 100.00,
+            max_usage: 50.00,
+        };
+        let events: Arc<Mutex<Vec<BroadcastEvent>>> = Arc::new(Mutex::new(vec![]));
+        let events_clone = Arc::clone(&events);
+        for _ in 1..10 {
+            events.lock().unwrap().push(event.clone());
+        }
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::clone(&events))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+
+            // push another one, this one should get alerted on
+            // now that we're past the alert interval
+            events_clone.lock().unwrap().push(event.clone());
+
+            thread::sleep(Duration::from_millis(BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        assert_eq!(sent_emails.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn broadcast_batches_digest_events_into_one_email() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: None,
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Digest,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let events = vec![
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/".to_string(),
+                current_usage: 100.00,
+                max_usage: 50.00,
+            },
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/mnt/test".to_string(),
+                current_usage: 100.00,
+                max_usage: 50.00,
+            },
+        ];
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(events)))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        let sent_emails = sent_emails.lock().unwrap();
+        assert_eq!(sent_emails.len(), 1);
+        assert!(sent_emails[0].0.contains("Digest (2 events)"));
+        assert!(sent_emails[0].1.html.contains("/mnt/test"));
+        assert!(sent_emails[0].1.text.contains("/mnt/test"));
+        assert!(!sent_emails[0].1.text.contains('<'));
+    }
+
+    #[test]
+    fn broadcast_holds_digest_events_until_the_window_elapses() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: Some(Duration::from_secs(60)),
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Digest,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let event = BroadcastEvent::HighDiskUsage {
+            filesystem_mount: "/".to_string(),
+            current_usage: 100.00,
+            max_usage: 50.00,
+        };
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(vec![event])))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        assert!(sent_emails.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn broadcast_routes_alerts_by_severity() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: None,
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: Some(vec![SeverityRouteConfig {
+                    severity: Severity::Warning,
+                    mediums: vec![],
+                    alert_type: None,
+                }]),
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let events = vec![
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/".to_string(),
+                current_usage: 60.00,
+                max_usage: 50.00,
+            },
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/mnt/test".to_string(),
+                current_usage: 99.00,
+                max_usage: 50.00,
+            },
+        ];
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(events)))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        // only the critical event is routed to email
+        let sent_emails = sent_emails.lock().unwrap();
+        assert_eq!(sent_emails.len(), 1);
+        assert!(sent_emails[0].1.html.contains("/mnt/test"));
+    }
+
+    #[test]
+    fn broadcast_suppresses_alerts_during_maintenance_windows() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: None,
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        // a window that is always active, for the root filesystem only
+        let maintenance_window = MaintenanceWindow::new(&MaintenanceWindowConfig {
+            event: None,
+            key: Some("high-disk-usage:/".to_string()),
+            start: None,
+            end: None,
+            cron: Some("* * * * * * *".to_string()),
+            duration: Some(Duration::from_secs(3600)),
+        })
+        .unwrap();
+
+        let events = vec![
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/".to_string(),
+                current_usage: 100.00,
+                max_usage: 50.00,
+            },
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/mnt/test".to_string(),
+                current_usage: 100.00,
+                max_usage: 50.00,
+            },
+        ];
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(events)))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        Broadcast::test(alerts, Box::new(ports))
+            .with_maintenance_windows(vec![maintenance_window])
+            .start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        let sent_emails = sent_emails.lock().unwrap();
+        assert_eq!(sent_emails.len(), 1);
+        assert!(sent_emails[0].1.html.contains("/mnt/test"));
+    }
+
+    #[test]
+    fn broadcast_does_not_retrigger_acknowledged_alerts() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: Some(Duration::from_millis(100)),
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let event = BroadcastEvent::HighDiskUsage {
+            filesystem_mount: "/".to_string(),
+            current_usage: 100.00,
+            max_usage: 50.00,
+        };
+        let events = Arc::new(Mutex::new(vec![event.clone()]));
+        let events_clone = Arc::clone(&events);
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::clone(&events))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        let broadcast = Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+
+            // acknowledge the alert, then push the event again once
+            // we're past the alert interval
+            broadcast.do_send(Acknowledge(event.event_key()));
+            events_clone.lock().unwrap().push(event.clone());
+
+            thread::sleep(Duration::from_millis(BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        assert_eq!(sent_emails.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn broadcast_does_not_alert_for_silenced_events() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: None,
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let event = BroadcastEvent::HighDiskUsage {
+            filesystem_mount: "/".to_string(),
+            current_usage: 100.00,
+            max_usage: 50.00,
+        };
+        let events = Arc::new(Mutex::new(vec![]));
+        let events_clone = Arc::clone(&events);
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::clone(&events))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        let broadcast = Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            broadcast.do_send(AddSilence {
+                event_type: None,
+                event_key: Some(event.event_key().to_string()),
+                duration: Duration::from_secs(3600),
+                comment: Some("replacing the disk".to_string()),
+            });
+            thread::sleep(Duration::from_millis(50));
+            events_clone.lock().unwrap().push(event);
+
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        assert!(sent_emails.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn broadcast_rejects_silences_that_never_expire() {
+        let system = System::new("test");
+
+        let broadcast =
+            Broadcast::test(HashMap::new(), Box::new(TestBroadcastPorts::new())).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            let silence = futures::executor::block_on(broadcast.send(AddSilence {
+                event_type: Some(BroadcastEventType::HighDiskUsage),
+                event_key: None,
+                duration: Duration::from_secs(u64::max_value() / 1000),
+                comment: None,
+            }));
+            assert!(silence.unwrap().is_err());
+            current.stop()
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn broadcast_restores_persisted_last_alerted_times() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: Some(Duration::from_secs(3600)),
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let event = BroadcastEvent::HighDiskUsage {
+            filesystem_mount: "/".to_string(),
+            current_usage: 100.00,
+            max_usage: 50.00,
+        };
+
+        // simulate an alert sent just before a restart
+        let persisted_last_alerted = Arc::new(Mutex::new(
+            vec![(event.event_key(), Utc::now().naive_utc())]
+                .into_iter()
+                .collect(),
+        ));
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(vec![event])))
+            .with_sent_emails(Arc::clone(&sent_emails))
+            .with_persisted_last_alerted(Arc::clone(&persisted_last_alerted));
+
+        Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        assert!(sent_emails.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn broadcast_retries_failed_sends() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: None,
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let event = BroadcastEvent::HighDiskUsage {
+            filesystem_mount: "/".to_string(),
+            current_usage: 100.00,
+            max_usage: 50.00,
+        };
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(vec![event])))
+            .with_sent_emails(Arc::clone(&sent_emails))
+            .with_failing_sends(2);
+
+        Broadcast::test(alerts, Box::new(ports))
+            .with_retry(RetryConfig {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(100),
+            })
+            .start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        assert_eq!(sent_emails.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn retry_backoff_doubles_up_to_the_max() {
+        let retry = RetryConfig {
+            max_attempts: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+        };
+
+        assert_eq!(retry.backoff(1), Duration::from_secs(1));
+        assert_eq!(retry.backoff(2), Duration::from_secs(2));
+        assert_eq!(retry.backoff(3), Duration::from_secs(4));
+        assert_eq!(retry.backoff(4), Duration::from_secs(5));
+        assert_eq!(retry.backoff(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn broadcast_sends_undeliverable_alerts_to_dead_letters() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: None,
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let event = BroadcastEvent::HighDiskUsage {
+            filesystem_mount: "/".to_string(),
+            current_usage: 100.00,
+            max_usage: 50.00,
+        };
+
+        let system = System::new("test");
+
+        let dead_letters = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(vec![event])))
+            .with_dead_letters(Arc::clone(&dead_letters))
+            .with_failing_sends(2);
+
+        Broadcast::test(alerts, Box::new(ports))
+            .with_retry(RetryConfig {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(100),
+            })
+            .start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        let dead_letters = dead_letters.lock().unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].attempts, 2);
+    }
+
+    #[test]
+    fn broadcast_replays_dead_letters() {
+        let delivery = Delivery::new(
+            BroadcastMedium::Email,
+            "[PULSE] High Disk Usage".to_string(),
+            Body::plain("Filesystem mounted at / has 100.00% disk usage".to_string()),
+            Some(BroadcastEventType::HighDiskUsage),
+            vec!["high-disk-usage:/".to_string().into()],
+        );
+        let error: Error = ErrorKind::EmailError {
+            error: "connection refused".to_string(),
+        }
+        .into();
+        let dead_letters = Arc::new(Mutex::new(vec![test_dead_letter(0, &delivery, &error)]));
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_sent_emails(Arc::clone(&sent_emails))
+            .with_dead_letters(Arc::clone(&dead_letters));
+
+        let broadcast = Broadcast::test(HashMap::new(), Box::new(ports)).start();
+
+        actix_rt::spawn(async move {
+            let replayed = broadcast.send(ReplayDeadLetters).await.unwrap().unwrap();
+            assert_eq!(replayed, 1);
+
+            System::current().stop();
+        });
+
+        system.run().unwrap();
+
+        assert_eq!(sent_emails.lock().unwrap().len(), 1);
+        assert!(dead_letters.lock().unwrap()[0].replayed_at.is_some());
+    }
+
+    #[test]
+    fn broadcast_attaches_disk_usage_charts() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: None,
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let event = BroadcastEvent::HighDiskUsage {
+            filesystem_mount: "/".to_string(),
+            current_usage: 100.00,
+            max_usage: 50.00,
+        };
+
+        let now = Utc::now().naive_utc();
+        let disk_usage_history = vec![
+            (1, now - chrono::Duration::hours(2), 60.0),
+            (2, now - chrono::Duration::hours(1), 80.0),
+            (3, now, 100.0),
+        ]
+        .into_iter()
+        .map(|(id, recorded_at, percent_disk_used)| models::DiskUsage {
+            id,
+            mount: "/".to_string(),
+            percent_disk_used,
+            recorded_at,
+        })
+        .collect();
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(vec![event])))
+            .with_sent_emails(Arc::clone(&sent_emails))
+            .with_disk_usage_history(disk_usage_history);
+
+        Broadcast::test(alerts, Box::new(ports)).start();
 
-trait BroadcastPorts {
-    fn send_email(&self, subject: String, body: String) -> Result<()>;
-    fn get_next_event(&self) -> Option<BroadcastEvent>;
-    fn lock_last_alerted(&self) -> MutexGuard<LastAlerted>;
-}
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
 
-struct LiveBroadcastPorts {
-    email_config: EmailConfig,
-}
-impl BroadcastPorts for LiveBroadcastPorts {
-    fn send_email(&self, subject: String, body: String) -> Result<()> {
-        email::send_email(&self.email_config, subject, body)
-    }
+        system.run().unwrap();
 
-    fn get_next_event(&self) -> Option<BroadcastEvent> {
-        OUTBOX.pop().ok()
+        let sent_emails = sent_emails.lock().unwrap();
+        assert_eq!(sent_emails.len(), 1);
+        let attachments = &sent_emails[0].1.attachments;
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].content_type, mime::IMAGE_SVG);
+        assert!(String::from_utf8_lossy(&attachments[0].data).contains("<polyline"));
     }
 
-    fn lock_last_alerted(&self) -> MutexGuard<LastAlerted> {
-        LAST_ALERTED.lock().unwrap()
-    }
-}
+    #[test]
+    fn broadcast_holds_non_critical_alerts_during_quiet_hours() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: None,
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
 
-pub struct Broadcast {
-    alerts: HashMap<BroadcastEventType, AlertConfig>,
-    ports: Box<dyn BroadcastPorts + Send + Sync>,
-}
+        // quiet hours that are active now
+        let now = Utc::now().time();
+        let quiet_hours = QuietHours::new(&QuietHoursConfig {
+            start: now - chrono::Duration::hours(1),
+            end: now + chrono::Duration::hours(1),
+            timezone: "UTC".to_string(),
+            severities: None,
+        })
+        .unwrap();
 
-impl Broadcast {
-    pub fn new() -> Result<Option<Self>> {
-        let config = config().broadcast;
-        if config.alerts.is_empty() {
-            Ok(None)
-        } else if let Some(email_config) = config.email {
-            Ok(Some(Self {
-                alerts: config
-                    .alerts
-                    .iter()
-                    .map(|alert| (alert.event.clone(), alert.clone()))
-                    .collect(),
-                ports: Box::new(LiveBroadcastPorts { email_config }),
-            }))
-        } else {
-            Err(Error::unconfigured_email())
-        }
+        let events = vec![
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/".to_string(),
+                current_usage: 100.00,
+                max_usage: 50.00,
+            },
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/mnt/test".to_string(),
+                current_usage: 80.00,
+                max_usage: 50.00,
+            },
+        ];
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(events)))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        Broadcast::test(alerts, Box::new(ports))
+            .with_quiet_hours(vec![quiet_hours])
+            .start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        // only the critical alert is sent
+        let sent_emails = sent_emails.lock().unwrap();
+        assert_eq!(sent_emails.len(), 1);
+        assert!(sent_emails[0].1.html.contains("Filesystem mounted at / "));
     }
 
-    #[cfg(test)]
-    fn test(
-        alerts: HashMap<BroadcastEventType, AlertConfig>,
-        ports: Box<dyn BroadcastPorts + Send + Sync>,
-    ) -> Self {
-        Self { alerts, ports }
+    #[test]
+    fn broadcast_only_holds_alarms_that_are_due() {
+        let alert_config = AlertConfig {
+            alert_interval: Some(Duration::from_secs(60 * 60)),
+            event: BroadcastEventType::HighDiskUsage,
+            mediums: vec![BroadcastMedium::Email],
+            alert_type: AlertType::Alarm,
+            severity_routes: None,
+            rollup: None,
+            subject_template: None,
+        };
+        let high_disk_usage = |filesystem_mount: &str| BroadcastEvent::HighDiskUsage {
+            filesystem_mount: filesystem_mount.to_string(),
+            current_usage: 80.00,
+            max_usage: 50.00,
+        };
+        let acknowledged = high_disk_usage("/mnt/acknowledged");
+        let recent = high_disk_usage("/mnt/recent");
+        let due = high_disk_usage("/mnt/due");
+
+        let delivered = Arc::new(Mutex::new(vec![]));
+        let ports = TestBroadcastPorts::new().with_delivered(Arc::clone(&delivered));
+        let mut broadcast = Broadcast::test(HashMap::new(), Box::new(ports));
+        broadcast.acknowledged.insert(acknowledged.event_key());
+        broadcast
+            .last_alerted
+            .insert(recent.event_key(), Utc::now().naive_utc());
+
+        broadcast.hold(alert_config.clone(), acknowledged, Some(1));
+        broadcast.hold(alert_config.clone(), recent, Some(2));
+        broadcast.hold(alert_config, due.clone(), Some(3));
+
+        let held = broadcast
+            .held
+            .values()
+            .flat_map(|digest| digest.event_keys())
+            .collect::<Vec<_>>();
+        assert_eq!(held, vec![due.event_key()]);
+        assert_eq!(*delivered.lock().unwrap(), vec![1, 2]);
     }
-}
 
-impl Actor for Broadcast {
-    type Context = Context<Self>;
+    #[test]
+    fn outbox_pops_the_most_severe_events_first() {
+        let outbox = Outbox::new(3);
 
-    /// Start a tick for the broadcast actor
-    fn started(&mut self, ctx: &mut Context<Self>) {
-        ctx.run_interval(
-            Duration::from_millis(BROADCAST_TICK_INTERVAL),
-            move |this, _| {
-                while let Some(message) = this.ports.get_next_event() {
-                    log::debug!("Broadcast received message: {:?}", message.event_type());
-
-                    let alerts_map = this.alerts.clone();
-
-                    let message_id = message.event_key();
-                    let message_type = message.event_type();
-
-                    let mut locked_last_alerted = this.ports.lock_last_alerted();
-                    let last_alerted = locked_last_alerted.get(&message_id);
-
-                    // get the configuration for this message, if it exists
-                    match alerts_map.get(&message_type) {
-                        Some(alert_config)
-                            // only need to alert if we haven't already
-                            // alerted within the configured window
-                            if alert_config.alert_interval.is_none() || last_alerted
-                                .map(|instant| {
-                                    Instant::now().duration_since(*instant)
-                                        > alert_config.alert_interval.unwrap()
-                                })
-                                .unwrap_or(true) =>
-                        {
-                            log::debug!("Sending alert for : {:?}", message);
-                            let prefix = if last_alerted.is_none() || alert_config.alert_type == AlertType::Digest {
-                                "[PULSE]"
-                            } else {
-                                "[PULSE] Retriggered:"
-                            };
-
-                            let (subject, body) = message.subject_and_body();
-                            for medium in &alert_config.mediums {
-                                match medium {
-                                    BroadcastMedium::Email => {
-                                        this.ports.send_email(
-                                                format!("{} {}", prefix, subject.clone()),
-                                                body.clone(),
-                                            )
-                                            .map_err(|_| ())
-                                            .unwrap();
-                                    }
-                                }
-                            }
-                            this.alerts.insert(
-                                message_type,
-                                alert_config.clone(),
-                            );
-                            locked_last_alerted.insert(
-                                message_id,
-                                Instant::now()
-                            );
-                        }
-                        _ => {
-                            log::debug!(
-                                "Not alerting: {:?}. Alerts map entry: {:?}",
-                                message.event_type(), alerts_map.get(&message_type)
-                            );
-                        },
-                    }
-                }
-            },
+        let newscast = BroadcastEvent::Newscast {
+            articles: vec![],
+            weather: vec![],
+            calendar: vec![],
+            stocks: vec![],
+            twitter: vec![],
+        };
+        let high_disk_usage =
+            |filesystem_mount: &str, current_usage: f64| BroadcastEvent::HighDiskUsage {
+                filesystem_mount: filesystem_mount.to_string(),
+                current_usage,
+                max_usage: 50.00,
+            };
+
+        outbox.push(newscast).unwrap();
+        outbox.push(high_disk_usage("/mnt/warning", 80.00)).unwrap();
+        outbox
+            .push(high_disk_usage("/mnt/critical", 100.00))
+            .unwrap();
+        assert_eq!(
+            outbox
+                .push(high_disk_usage("/", 100.00))
+                .unwrap_err()
+                .kind(),
+            &ErrorKind::OutboxFull
+        );
+
+        let event_keys = std::iter::from_fn(|| outbox.pop())
+            .map(|entry| entry.event.event_key().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            event_keys,
+            vec![
+                "high-disk-usage:/mnt/critical",
+                "high-disk-usage:/mnt/warning",
+                "newscast"
+            ]
         );
     }
-}
 
-#[macro_use]
-#[cfg(test)]
-pub mod test {
-    use super::*;
-    use crate::{
-        config::AlertType, error::Result, services::broadcast::events::BroadcastEventType,
-    };
-    use std::{
-        sync::{Arc, Mutex},
-        thread,
-    };
+    #[test]
+    fn outbox_applies_overflow_policies() {
+        let high_disk_usage =
+            |filesystem_mount: &str, current_usage: f64| BroadcastEvent::HighDiskUsage {
+                filesystem_mount: filesystem_mount.to_string(),
+                current_usage,
+                max_usage: 50.00,
+            };
+        let drain = |outbox: &Outbox| {
+            std::iter::from_fn(|| outbox.pop())
+                .map(|entry| entry.event.event_key().to_string())
+                .collect::<Vec<_>>()
+        };
 
-    struct TestBroadcastPorts {
-        sent_emails: Arc<Mutex<Vec<(String, String)>>>,
-        events_buffer: Arc<Mutex<Vec<BroadcastEvent>>>,
-        last_alerted: Arc<Mutex<LastAlerted>>,
+        let outbox = Outbox::new(2).with_overflow(OverflowPolicy::DropOldest);
+        outbox.push(high_disk_usage("/mnt/old", 80.00)).unwrap();
+        outbox.push(high_disk_usage("/mnt/new", 80.00)).unwrap();
+        outbox
+            .push(high_disk_usage("/mnt/critical", 100.00))
+            .unwrap();
+        assert_eq!(outbox.stats().dropped, 1);
+        assert_eq!(
+            drain(&outbox),
+            vec!["high-disk-usage:/mnt/critical", "high-disk-usage:/mnt/new"]
+        );
+
+        let outbox = Outbox::new(2).with_overflow(OverflowPolicy::CoalesceByKey);
+        outbox.push(high_disk_usage("/", 80.00)).unwrap();
+        outbox.push(high_disk_usage("/mnt", 80.00)).unwrap();
+        outbox.push(high_disk_usage("/", 100.00)).unwrap();
+        assert!(outbox.push(high_disk_usage("/home", 80.00)).is_err());
+        let stats = outbox.stats();
+        assert_eq!((stats.depth, stats.coalesced, stats.rejected), (2, 1, 1));
+        let current_usage = std::iter::from_fn(|| outbox.pop())
+            .map(|entry| match entry.event {
+                BroadcastEvent::HighDiskUsage { current_usage, .. } => current_usage,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(current_usage, vec![100.00, 80.00]);
     }
-    impl TestBroadcastPorts {
-        pub fn new() -> Self {
-            Self {
-                sent_emails: Arc::new(Mutex::new(vec![])),
-                events_buffer: Arc::new(Mutex::new(vec![])),
-                last_alerted: Arc::new(Mutex::new(HashMap::new())),
-            }
-        }
 
-        pub fn with_sent_emails(mut self, sent_emails: Arc<Mutex<Vec<(String, String)>>>) -> Self {
-            self.sent_emails = sent_emails;
-            self
-        }
+    #[test]
+    fn broadcast_marks_outbox_events_delivered() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![
+            (
+                BroadcastEventType::HighDiskUsage,
+                AlertConfig {
+                    alert_interval: None,
+                    event: BroadcastEventType::HighDiskUsage,
+                    mediums: vec![BroadcastMedium::Email],
+                    alert_type: AlertType::Alarm,
+                    severity_routes: None,
+                    rollup: None,
+                    subject_template: None,
+                },
+            ),
+            (
+                BroadcastEventType::Newscast,
+                AlertConfig {
+                    alert_interval: Some(Duration::from_secs(3600)),
+                    event: BroadcastEventType::Newscast,
+                    mediums: vec![BroadcastMedium::Email],
+                    alert_type: AlertType::Digest,
+                    severity_routes: None,
+                    rollup: None,
+                    subject_template: None,
+                },
+            ),
+        ]
+        .into_iter()
+        .collect();
 
-        pub fn with_events_buffer(
-            mut self,
-            events_buffer: Arc<Mutex<Vec<BroadcastEvent>>>,
-        ) -> Self {
-            self.events_buffer = events_buffer;
-            self
-        }
+        let events = vec![
+            BroadcastEvent::Newscast {
+                articles: vec![],
+                weather: vec![],
+                calendar: vec![],
+                stocks: vec![],
+                twitter: vec![],
+            },
+            BroadcastEvent::HighDiskUsage {
+                filesystem_mount: "/".to_string(),
+                current_usage: 100.00,
+                max_usage: 50.00,
+            },
+        ];
+
+        let system = System::new("test");
+
+        let delivered = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(events)))
+            .with_delivered(Arc::clone(&delivered));
+
+        Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        // the newscast is still waiting in its digest
+        assert_eq!(*delivered.lock().unwrap(), vec![1]);
     }
-    impl BroadcastPorts for TestBroadcastPorts {
-        fn send_email(&self, subject: String, body: String) -> Result<()> {
-            self.sent_emails.lock().unwrap().push((subject, body));
-            Ok(())
-        }
 
-        fn get_next_event(&self) -> Option<BroadcastEvent> {
-            self.events_buffer.lock().unwrap().pop()
-        }
+    #[test]
+    fn broadcast_rolls_up_alarms_that_fire_together() {
+        let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
+            BroadcastEventType::HighDiskUsage,
+            AlertConfig {
+                alert_interval: None,
+                event: BroadcastEventType::HighDiskUsage,
+                mediums: vec![BroadcastMedium::Email],
+                alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: Some(true),
+                subject_template: None,
+            },
+        )]
+        .into_iter()
+        .collect();
 
-        fn lock_last_alerted(&self) -> MutexGuard<LastAlerted> {
-            self.last_alerted.lock().unwrap()
-        }
+        let events = vec!["/", "/mnt/one", "/mnt/two"]
+            .into_iter()
+            .map(|filesystem_mount| BroadcastEvent::HighDiskUsage {
+                filesystem_mount: filesystem_mount.to_string(),
+                current_usage: 100.00,
+                max_usage: 50.00,
+            })
+            .collect();
+
+        let system = System::new("test");
+
+        let sent_emails = Arc::new(Mutex::new(vec![]));
+
+        let ports = TestBroadcastPorts::new()
+            .with_events_buffer(Arc::new(Mutex::new(events)))
+            .with_sent_emails(Arc::clone(&sent_emails));
+
+        Broadcast::test(alerts, Box::new(ports)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
+            current.stop()
+        });
+
+        system.run().unwrap();
+
+        let sent_emails = sent_emails.lock().unwrap();
+        assert_eq!(sent_emails.len(), 1);
+        assert!(sent_emails[0].0.contains("(3 events)"));
+        assert!(sent_emails[0].1.text.contains("high-disk-usage:/mnt/two"));
     }
 
     #[test]
-    fn broadcast_sends_an_email() {
+    fn broadcast_renders_subject_templates() {
         let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
             BroadcastEventType::HighDiskUsage,
             AlertConfig {
-                alert_interval: Some(Duration::from_millis(100)),
+                alert_interval: None,
                 event: BroadcastEventType::HighDiskUsage,
                 mediums: vec![BroadcastMedium::Email],
                 alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: Some(
+                    "[{severity}] {event_type} {retriggered}{subject}".to_string(),
+                ),
             },
         )]
         .into_iter()
@@ -247,18 +2555,26 @@ pub mod test {
 
         system.run().unwrap();
 
-        assert_eq!(sent_emails.lock().unwrap().len(), 1);
+        let sent_emails = sent_emails.lock().unwrap();
+        assert_eq!(sent_emails.len(), 1);
+        assert_eq!(
+            sent_emails[0].0,
+            "[critical] high-disk-usage High Disk Usage"
+        );
     }
 
     #[test]
-    fn broadcast_sends_multiple_emails() {
+    fn broadcast_records_alert_history() {
         let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
             BroadcastEventType::HighDiskUsage,
             AlertConfig {
-                alert_interval: Some(Duration::from_millis(100)),
+                alert_interval: None,
                 event: BroadcastEventType::HighDiskUsage,
                 mediums: vec![BroadcastMedium::Email],
                 alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
             },
         )]
         .into_iter()
@@ -279,13 +2595,20 @@ pub mod test {
 
         let system = System::new("test");
 
-        let sent_emails = Arc::new(Mutex::new(vec![]));
+        let alert_history = Arc::new(Mutex::new(vec![]));
 
+        // the first send fails and is not retried
         let ports = TestBroadcastPorts::new()
             .with_events_buffer(Arc::new(Mutex::new(events)))
-            .with_sent_emails(Arc::clone(&sent_emails));
+            .with_alert_history(Arc::clone(&alert_history))
+            .with_failing_sends(1);
 
-        Broadcast::test(alerts, Box::new(ports)).start();
+        Broadcast::test(alerts, Box::new(ports))
+            .with_retry(RetryConfig {
+                max_attempts: 1,
+                ..RetryConfig::default()
+            })
+            .start();
 
         let current = System::current();
         thread::spawn(move || {
@@ -294,59 +2617,128 @@ pub mod test {
         });
 
         system.run().unwrap();
-        assert_eq!(sent_emails.lock().unwrap().len(), 2);
+
+        let alert_history = alert_history.lock().unwrap();
+        assert_eq!(alert_history.len(), 2);
+        assert!(alert_history[0].1.is_some());
+        assert!(alert_history[1].1.is_none());
     }
 
     #[test]
-    fn broadcast_ignores_alerts_if_an_alert_was_just_sent() {
+    fn broadcast_sends_events_as_soon_as_they_arrive() {
         let alerts: HashMap<BroadcastEventType, AlertConfig> = vec![(
             BroadcastEventType::HighDiskUsage,
             AlertConfig {
-                alert_interval: Some(Duration::from_millis(100)),
+                alert_interval: None,
                 event: BroadcastEventType::HighDiskUsage,
                 mediums: vec![BroadcastMedium::Email],
                 alert_type: AlertType::Alarm,
+                severity_routes: None,
+                rollup: None,
+                subject_template: None,
             },
         )]
         .into_iter()
         .collect();
 
-        // simulate 10 identical events in a row
-        let event = BroadcastEvent::HighDiskUsage {
-            filesystem_mount: "/".to_string(),
-            current_usage: 100.00,
-            max_usage: 50.00,
-        };
-        let events: Arc<Mutex<Vec<BroadcastEvent>>> = Arc::new(Mutex::new(vec![]));
-        let events_clone = Arc::clone(&events);
-        for _ in 1..10 {
-            events.lock().unwrap().push(event.clone());
-        }
-
         let system = System::new("test");
 
+        let outbox = Outbox::new(OUTBOX_CAPACITY);
         let sent_emails = Arc::new(Mutex::new(vec![]));
 
         let ports = TestBroadcastPorts::new()
-            .with_events_buffer(Arc::clone(&events))
+            .with_outbox(outbox.clone())
             .with_sent_emails(Arc::clone(&sent_emails));
 
         Broadcast::test(alerts, Box::new(ports)).start();
 
+        actix_rt::spawn(async move {
+            outbox
+                .push(BroadcastEvent::HighDiskUsage {
+                    filesystem_mount: "/".to_string(),
+                    current_usage: 100.00,
+                    max_usage: 50.00,
+                })
+                .unwrap();
+        });
+
+        // stop well before the first tick
         let current = System::current();
         thread::spawn(move || {
-            thread::sleep(Duration::from_millis(50 + BROADCAST_TICK_INTERVAL));
-
-            // push another one, this one should get alerted on
-            // now that we're past the alert interval
-            events_clone.lock().unwrap().push(event.clone());
-
-            thread::sleep(Duration::from_millis(BROADCAST_TICK_INTERVAL));
+            thread::sleep(Duration::from_millis(BROADCAST_TICK_INTERVAL / 5));
             current.stop()
         });
 
         system.run().unwrap();
 
-        assert_eq!(sent_emails.lock().unwrap().len(), 2);
+        assert_eq!(sent_emails.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn mediums_must_be_registered_for_every_alert() {
+        let mut config = Config::default().broadcast;
+        config.alerts = vec![AlertConfig {
+            alert_interval: None,
+            event: BroadcastEventType::HighDiskUsage,
+            mediums: vec![BroadcastMedium::Email],
+            alert_type: AlertType::Alarm,
+            severity_routes: None,
+            rollup: None,
+            subject_template: None,
+        }];
+
+        let mut mediums = Mediums::default();
+        assert_eq!(
+            mediums.validate(&config).unwrap_err().kind(),
+            &ErrorKind::UnconfiguredMedium {
+                medium: "email".to_string()
+            }
+        );
+
+        mediums.register(
+            BroadcastMedium::Email,
+            Box::new(TestEmailMedium {
+                sent_emails: Arc::new(Mutex::new(vec![])),
+                failing_sends: Mutex::new(0),
+            }),
+        );
+        assert!(mediums.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn firing_alertmanager_alerts_are_ingested_as_generic_messages() {
+        let payload = serde_json::json!({
+            "receiver": "pulse",
+            "status": "firing",
+            "alerts": [
+                {
+                    "status": "firing",
+                    "labels": {"alertname": "InstanceDown", "severity": "critical"},
+                    "annotations": {"summary": "db-1 is down"},
+                    "fingerprint": "abc123"
+                },
+                {
+                    "status": "resolved",
+                    "labels": {"alertname": "HighLoad"},
+                    "annotations": {}
+                }
+            ]
+        });
+
+        let events = ingest(None, None, payload).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].severity(), Severity::Critical);
+        assert_eq!(
+            events[0].event_key().to_string(),
+            "generic-message:alertmanager:abc123"
+        );
+        assert_eq!(
+            events[0].subject_and_body(),
+            (
+                "alertmanager: InstanceDown".to_string(),
+                Body::plain("db-1 is down".to_string())
+            )
+        );
     }
 }
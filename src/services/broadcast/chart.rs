@@ -0,0 +1,70 @@
+use chrono::NaiveDateTime;
+
+use crate::db::models;
+
+use super::events::Attachment;
+
+const WIDTH: f64 = 600.0;
+const HEIGHT: f64 = 200.0;
+const PADDING: f64 = 30.0;
+
+/// Render disk usage over time for a single mount as an SVG line
+/// chart, scaled from 0% to 100%. Returns `None` if there are too few
+/// measurements to draw a line
+pub fn disk_usage_chart(mount: &str, history: &[models::DiskUsage]) -> Option<Attachment> {
+    let first = history.first()?.recorded_at;
+    let last = history.last()?.recorded_at;
+    if history.len() < 2 || first == last {
+        return None;
+    }
+
+    let x = |recorded_at: NaiveDateTime| {
+        let elapsed = recorded_at.signed_duration_since(first).num_seconds() as f64;
+        let total = last.signed_duration_since(first).num_seconds() as f64;
+        PADDING + elapsed / total * (WIDTH - 2.0 * PADDING)
+    };
+    let y = |percent: f64| HEIGHT - PADDING - percent / 100.0 * (HEIGHT - 2.0 * PADDING);
+
+    let points = history
+        .iter()
+        .map(|disk_usage| {
+            format!(
+                "{:.1},{:.1}",
+                x(disk_usage.recorded_at),
+                y(disk_usage.percent_disk_used)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="sans-serif" font-size="12">
+  <rect width="{width}" height="{height}" fill="#fff"/>
+  <text x="{padding}" y="20">Disk usage on {mount}</text>
+  <line x1="{padding}" y1="{top}" x2="{right}" y2="{top}" stroke="#ddd"/>
+  <line x1="{padding}" y1="{bottom}" x2="{right}" y2="{bottom}" stroke="#999"/>
+  <text x="{right}" y="{top}" text-anchor="end" dy="-3">100%</text>
+  <text x="{right}" y="{bottom}" text-anchor="end" dy="-3">0%</text>
+  <text x="{padding}" y="{height}" dy="-8">{first}</text>
+  <text x="{right}" y="{height}" dy="-8" text-anchor="end">{last}</text>
+  <polyline points="{points}" fill="none" stroke="#c33" stroke-width="2"/>
+</svg>
+"##,
+        width = WIDTH,
+        height = HEIGHT,
+        padding = PADDING,
+        top = y(100.0),
+        bottom = y(0.0),
+        right = WIDTH - PADDING,
+        mount = mount,
+        first = first.format("%Y-%m-%d %H:%M"),
+        last = last.format("%Y-%m-%d %H:%M"),
+        points = points
+    );
+
+    Some(Attachment {
+        filename: "disk-usage.svg".to_string(),
+        content_type: mime::IMAGE_SVG,
+        data: svg.into_bytes(),
+    })
+}
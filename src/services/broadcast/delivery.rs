@@ -0,0 +1,73 @@
+use crate::{db::models, error::Result};
+
+use super::events::{Body, BroadcastEventKey, BroadcastEventType, BroadcastMedium};
+
+/// A message to be sent over a single medium
+#[derive(Clone, Debug)]
+pub struct Delivery {
+    pub medium: BroadcastMedium,
+    pub subject: String,
+    pub body: Body,
+    /// The type of event this message alerts for, unknown for some
+    /// replayed deliveries
+    pub event_type: Option<BroadcastEventType>,
+    /// The events this message alerts for
+    pub event_keys: Vec<BroadcastEventKey>,
+    /// Starts at 1 for the first attempt
+    pub attempt: u32,
+    /// Set when replaying a delivery from the dead letter queue
+    pub dead_letter_id: Option<i32>,
+}
+
+impl Delivery {
+    pub fn new(
+        medium: BroadcastMedium,
+        subject: String,
+        body: Body,
+        event_type: Option<BroadcastEventType>,
+        event_keys: Vec<BroadcastEventKey>,
+    ) -> Self {
+        Self {
+            medium,
+            subject,
+            body,
+            event_type,
+            event_keys,
+            attempt: 1,
+            dead_letter_id: None,
+        }
+    }
+
+    /// Replay a delivery from the dead letter queue
+    pub fn from_dead_letter(dead_letter: models::DeadLetter) -> Result<Self> {
+        Ok(Self {
+            dead_letter_id: Some(dead_letter.id),
+            ..Self::new(
+                dead_letter.medium.parse()?,
+                dead_letter.subject,
+                Body {
+                    text: dead_letter.text_body,
+                    html: dead_letter.body,
+                    // attachments aren't kept in the dead letter queue
+                    attachments: vec![],
+                },
+                dead_letter
+                    .event_type
+                    .map(|event_type| event_type.parse())
+                    .transpose()?,
+                dead_letter
+                    .event_keys
+                    .into_iter()
+                    .map(BroadcastEventKey::from)
+                    .collect(),
+            )
+        })
+    }
+
+    pub fn next_attempt(self) -> Self {
+        Self {
+            attempt: self.attempt + 1,
+            ..self
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Instant;
+
+use crate::config::AlertConfig;
+
+use super::events::{Body, BroadcastEvent, BroadcastEventKey, Severity};
+
+/// Events of a single type accumulated over the window of a digest
+/// alert, to be sent together as one email
+pub struct Digest {
+    pub alert_config: AlertConfig,
+    pub started: Instant,
+    pub events: Vec<BroadcastEvent>,
+    /// Ids of the durable outbox events in this digest
+    pub outbox_ids: Vec<i32>,
+}
+
+impl Digest {
+    pub fn new(alert_config: AlertConfig) -> Self {
+        Self {
+            alert_config,
+            started: Instant::now(),
+            events: vec![],
+            outbox_ids: vec![],
+        }
+    }
+
+    /// Add an event to the digest. An event with the same key as one
+    /// that is already in the digest replaces it, so that repeated
+    /// events are only reported once
+    pub fn add(&mut self, event: BroadcastEvent, outbox_id: Option<i32>) {
+        self.outbox_ids.extend(outbox_id);
+
+        let event_key = event.event_key();
+        match self.events.iter().position(|e| e.event_key() == event_key) {
+            Some(index) => self.events[index] = event,
+            None => self.events.push(event),
+        }
+    }
+
+    pub fn event_keys(&self) -> Vec<BroadcastEventKey> {
+        self.events.iter().map(|event| event.event_key()).collect()
+    }
+
+    /// The highest severity of the events in the digest
+    pub fn severity(&self) -> Severity {
+        self.events
+            .iter()
+            .map(|event| event.severity())
+            .max()
+            .unwrap_or(Severity::Info)
+    }
+
+    pub fn subject_and_body(&self) -> (String, Body) {
+        // a digest with a single event is sent as-is
+        if let [event] = self.events.as_slice() {
+            return event.subject_and_body();
+        }
+
+        let title = self
+            .events
+            .first()
+            .map(|event| event.event_type().title())
+            .unwrap_or("Pulse");
+
+        let (text_sections, html_sections): (Vec<String>, Vec<String>) = self
+            .events
+            .iter()
+            .map(|event| {
+                let (subject, body) = event.subject_and_body();
+                (
+                    format!("{}\n\n{}", subject.to_uppercase(), body.text),
+                    format!(
+                        include_str!("../../../resources/email/news/section.html"),
+                        section_title = subject,
+                        articles = body.html
+                    ),
+                )
+            })
+            .unzip();
+
+        (
+            format!("{} Digest ({} events)", title, self.events.len()),
+            Body {
+                text: text_sections.join("\n\n\n"),
+                html: format!(
+                    include_str!("../../../resources/email/news/outline.html"),
+                    title = format!("{} Digest", title),
+                    sections = html_sections.join("<br>"),
+                    css = include_str!("../../../resources/email/news/style.css")
+                ),
+                attachments: vec![],
+            },
+        )
+    }
+}
@@ -1,33 +1,62 @@
 use lettre::smtp::authentication::{Credentials, Mechanism};
-use lettre::{SmtpClient, Transport};
+use lettre::smtp::extension::ClientId;
+use lettre::{ClientSecurity, ClientTlsParameters, SmtpClient, Transport};
 use lettre_email::Email;
+use native_tls::{Protocol, TlsConnector};
 
-use crate::{config::EmailConfig, error::Result};
+use crate::{
+    config::{EmailConfig, SmtpTls},
+    error::{Error, Result},
+};
 
-pub fn send_email(config: &EmailConfig, subject: String, body: String) -> Result<()> {
+use super::{events::Body, oauth2::TokenSource};
+
+pub fn send_email(
+    config: &EmailConfig,
+    token_source: Option<&TokenSource>,
+    subject: String,
+    body: Body,
+) -> Result<()> {
     let mut email = Email::builder();
     for recipient in &config.recipients {
         email = email.to(recipient.clone())
     }
 
-    let email = email
+    email = email
         .from(config.username.clone())
         .subject(subject)
-        .html(body)
-        .build()
-        .unwrap();
+        .alternative(body.html, body.text);
+    for attachment in &body.attachments {
+        email = email.attachment(
+            &attachment.data,
+            &attachment.filename,
+            &attachment.content_type,
+        )?;
+    }
+    let email = email.build()?;
+
+    // Authenticate with an oauth2 access token if one is configured,
+    // otherwise with the password
+    let (secret, mechanism) = match (token_source, &config.password) {
+        (Some(token_source), _) => (token_source.access_token()?, Mechanism::Xoauth2),
+        (None, Some(password)) => (password.clone(), Mechanism::Plain),
+        (None, None) => return Err(Error::unconfigured_email()),
+    };
 
-    let mut mailer = SmtpClient::new_simple(&config.smtp_host)?
+    let mut client = smtp_client(config)?
         // Add credentials for authentication
-        .credentials(Credentials::new(
-            config.username.clone(),
-            config.password.clone(),
-        ))
+        .credentials(Credentials::new(config.username.clone(), secret))
         // Enable SMTPUTF8 if the server supports it
         .smtp_utf8(true)
         // Configure expected authentication mechanism
-        .authentication_mechanism(Mechanism::Plain)
-        .transport();
+        .authentication_mechanism(mechanism);
+    if let Some(hello_name) = &config.hello_name {
+        client = client.hello_name(ClientId::Domain(hello_name.clone()));
+    }
+    if let Some(timeout) = config.timeout {
+        client = client.timeout(Some(timeout));
+    }
+    let mut mailer = client.transport();
 
     // Send the email
     mailer.send(email.into()).map(|_| ()).map_err(|e| {
@@ -35,3 +64,28 @@ pub fn send_email(config: &EmailConfig, subject: String, body: String) -> Result
         Into::into(e)
     })
 }
+
+/// Connect to the configured SMTP server over the configured port and
+/// TLS mode
+fn smtp_client(config: &EmailConfig) -> Result<SmtpClient> {
+    let tls = config.tls.unwrap_or(SmtpTls::Implicit);
+    let port = config.port.unwrap_or_else(|| tls.default_port());
+
+    let security = match tls {
+        SmtpTls::None => ClientSecurity::None,
+        SmtpTls::Starttls => ClientSecurity::Required(tls_parameters(config)?),
+        SmtpTls::Implicit => ClientSecurity::Wrapper(tls_parameters(config)?),
+    };
+
+    SmtpClient::new((config.smtp_host.as_str(), port), security).map_err(Into::into)
+}
+
+fn tls_parameters(config: &EmailConfig) -> Result<ClientTlsParameters> {
+    let connector = TlsConnector::builder()
+        .min_protocol_version(Some(Protocol::Tlsv10))
+        .build()?;
+    Ok(ClientTlsParameters::new(
+        config.smtp_host.clone(),
+        connector,
+    ))
+}
@@ -1,6 +1,13 @@
+use std::{fmt, str::FromStr};
+
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
-use crate::{db::models::Tweet, services::news};
+use crate::{
+    db::models::Tweet,
+    error::{Error, Result},
+    services::{markets, news, twitter},
+};
 
 #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, Hash)]
 pub struct BroadcastEventKey(String);
@@ -11,15 +18,103 @@ impl From<String> for BroadcastEventKey {
     }
 }
 
+impl fmt::Display for BroadcastEventKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub enum BroadcastEventType {
     HighDiskUsage,
     Newscast,
     TwitterAlert,
+    TwitterSentiment,
+    WeatherWarning,
+    CalendarReminder,
+    StockMove,
+    CryptoPrice,
+    GithubRelease,
+    GithubNotification,
+    MailReceived,
+    UnreadMail,
+    PodcastEpisode,
+    YoutubeUpload,
+    GenericMessage,
+    CustomEvent,
+    DatabaseUnreachable,
+    TaskTimedOut,
+    TaskFailed,
+}
+
+impl BroadcastEventType {
+    /// Human readable name for this type of event
+    pub fn title(&self) -> &'static str {
+        match self {
+            BroadcastEventType::HighDiskUsage => "High Disk Usage",
+            BroadcastEventType::Newscast => "News",
+            BroadcastEventType::TwitterAlert => "Twitter Alert",
+            BroadcastEventType::TwitterSentiment => "Twitter Sentiment",
+            BroadcastEventType::WeatherWarning => "Weather Warning",
+            BroadcastEventType::CalendarReminder => "Calendar Reminder",
+            BroadcastEventType::StockMove => "Stock Move",
+            BroadcastEventType::CryptoPrice => "Crypto Price",
+            BroadcastEventType::GithubRelease => "GitHub Release",
+            BroadcastEventType::GithubNotification => "GitHub Notification",
+            BroadcastEventType::MailReceived => "Mail Received",
+            BroadcastEventType::UnreadMail => "Unread Mail",
+            BroadcastEventType::PodcastEpisode => "Podcast Episode",
+            BroadcastEventType::YoutubeUpload => "YouTube Upload",
+            BroadcastEventType::GenericMessage => "Message",
+            BroadcastEventType::CustomEvent => "Custom Event",
+            BroadcastEventType::DatabaseUnreachable => "Database Unreachable",
+            BroadcastEventType::TaskTimedOut => "Task Timed Out",
+            BroadcastEventType::TaskFailed => "Task Failed",
+        }
+    }
+}
+
+/// Displays the event type as it is written in the config, e.g.
+/// `high-disk-usage`
+impl fmt::Display for BroadcastEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        name.trim_matches('"').fmt(f)
+    }
+}
+
+impl FromStr for BroadcastEventType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(Into::into)
+    }
+}
+
+/// How urgently an event needs attention. Severities are ordered from
+/// least to most severe
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
 }
 
-#[derive(Clone, Debug)]
+/// Displays the severity as it is written in the config, e.g. `critical`
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        name.trim_matches('"').fmt(f)
+    }
+}
+
+/// Disk usage at or above this percentage is considered critical
+const CRITICAL_DISK_USAGE: f64 = 95.0;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum BroadcastEvent {
     HighDiskUsage {
         filesystem_mount: String,
@@ -32,13 +127,191 @@ pub enum BroadcastEvent {
         max_count: i64,
         tweets: Vec<Tweet>,
     },
+    TwitterSentiment {
+        group_name: String,
+        average_sentiment: f64,
+        threshold: f64,
+        sample_size: usize,
+    },
     Newscast {
-        new_york_times: Vec<news::ArticleSection>,
+        /// The sections from every news provider, in the order that
+        /// they're configured
+        #[serde(alias = "new_york_times")]
+        articles: Vec<news::ArticleSection>,
+        /// Today's forecast for each configured location
+        #[serde(default)]
+        weather: Vec<news::Forecast>,
+        /// Today's and tomorrow's calendar events
+        #[serde(default)]
+        calendar: Vec<news::CalendarEvent>,
+        /// The latest prices of the configured tickers
+        #[serde(default)]
+        stocks: Vec<markets::StockQuote>,
+        /// The last day's tweet activity in each group
+        #[serde(default)]
+        twitter: Vec<twitter::GroupActivity>,
+    },
+    WeatherWarning {
+        location: String,
+        event: String,
+        description: String,
+        sender: String,
+        starts_at: NaiveDateTime,
+        ends_at: NaiveDateTime,
+    },
+    CalendarReminder {
+        summary: String,
+        location: Option<String>,
+        starts_at: NaiveDateTime,
+    },
+    StockMove {
+        ticker: String,
+        price: f64,
+        previous_close: f64,
+        alert_percent: f64,
+    },
+    CryptoPrice {
+        asset: String,
+        currency: String,
+        price: f64,
+        percent_change_24h: Option<f64>,
+        trigger: PriceTrigger,
+    },
+    GithubRelease {
+        repository: String,
+        tag: String,
+        name: Option<String>,
+        url: String,
+    },
+    GithubNotification {
+        repository: String,
+        title: String,
+        reason: String,
+        url: String,
+    },
+    MailReceived {
+        mailbox: String,
+        /// The name of the watch the message matched
+        watch: String,
+        uid: u32,
+        from: String,
+        subject: String,
+    },
+    UnreadMail {
+        mailbox: String,
+        unread: usize,
+        flagged: usize,
+        max_unread: usize,
+    },
+    PodcastEpisode {
+        podcast: String,
+        title: String,
+        /// As given by the feed, e.g. `1:02:15`
+        duration: Option<String>,
+        url: String,
+    },
+    YoutubeUpload {
+        channel: String,
+        title: String,
+        url: String,
     },
+    /// A message from outside of pulse, e.g. from a script or another
+    /// monitoring system
+    GenericMessage {
+        /// Where the message came from, e.g. `alertmanager`
+        source: String,
+        title: String,
+        body: String,
+        severity: Severity,
+        /// Identifies repeats of the same message, if the title
+        /// doesn't
+        key: Option<String>,
+    },
+    /// An event pushed through the api by a script
+    CustomEvent {
+        title: String,
+        body: String,
+        severity: Severity,
+        /// Identifies repeats of the same event, for deduplication
+        key: String,
+    },
+    /// Pulse has been unable to reach its own database
+    DatabaseUnreachable { since: NaiveDateTime, error: String },
+    /// A scheduled task is still running past its timeout
+    TaskTimedOut {
+        task: String,
+        started_at: NaiveDateTime,
+        timeout: String,
+    },
+    /// A task runner failed a scheduled task on every attempt
+    TaskFailed {
+        task: String,
+        runner: String,
+        attempts: u32,
+        error: String,
+    },
+}
+
+/// The threshold a price alert fired for
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PriceTrigger {
+    /// The price is at or above this
+    Above(f64),
+    /// The price is at or below this
+    Below(f64),
+    /// The price changed by at least this percent in 24 hours
+    Change(f64),
+}
+
+impl PriceTrigger {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PriceTrigger::Above(_) => "above",
+            PriceTrigger::Below(_) => "below",
+            PriceTrigger::Change(_) => "change",
+        }
+    }
+}
+
+/// The content of a message, as both plain text and HTML so that it
+/// renders well in any email client
+#[derive(Clone, Debug, PartialEq)]
+pub struct Body {
+    pub text: String,
+    pub html: String,
+    pub attachments: Vec<Attachment>,
+}
+
+/// A file sent along with a message, e.g. a chart
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: mime::Mime,
+    pub data: Vec<u8>,
+}
+
+impl Body {
+    /// A body whose HTML part is the same as its text part
+    pub fn plain(text: String) -> Self {
+        Self {
+            html: text.replace("\n", "<br>"),
+            text,
+            attachments: vec![],
+        }
+    }
+
+    /// Append a paragraph to both parts of the body
+    pub fn push_paragraph(&mut self, paragraph: &str) {
+        self.text.push_str("\n\n");
+        self.text.push_str(paragraph);
+        self.html.push_str("<br><br>");
+        self.html.push_str(paragraph);
+    }
 }
 
 impl BroadcastEvent {
-    pub fn subject_and_body(&self) -> (String, String) {
+    pub fn subject_and_body(&self) -> (String, Body) {
         match self {
             BroadcastEvent::HighDiskUsage {
                 filesystem_mount,
@@ -46,12 +319,11 @@ impl BroadcastEvent {
                 max_usage,
             } => (
                 "High Disk Usage".to_string(),
-                format!(
+                Body::plain(format!(
                     "Filesystem mounted at {} has {:.2}% disk usage, \
                      which is above the max of {:.2}",
                     filesystem_mount, current_usage, max_usage
-                )
-                .to_string(),
+                )),
             ),
 
             BroadcastEvent::TwitterAlert {
@@ -67,49 +339,346 @@ impl BroadcastEvent {
                     .join("\n");
                 (
                     format!("Twitter Alert: {}", group_name),
-                    format!(
-                        "Group {} had a spike of {} tweets, which exceeds the max of {}.\n\n{:?}",
+                    Body::plain(format!(
+                        "Group {} had a spike of {} tweets, which exceeds the max of {}.\n\n{}",
                         group_name, current_count, max_count, formatted_tweets
-                    )
-                    .to_string(),
+                    )),
                 )
             }
 
-            BroadcastEvent::Newscast { new_york_times } => ("News".to_string(), {
-                let sections = new_york_times
-                    .iter()
-                    .map(|section| {
-                        let articles = section
-                            .articles
+            BroadcastEvent::TwitterSentiment {
+                group_name,
+                average_sentiment,
+                threshold,
+                sample_size,
+            } => (
+                format!("Twitter Sentiment: {}", group_name),
+                Body::plain(format!(
+                    "The average sentiment of the last {} tweets in group {} is {:.2}, below the threshold of {:.2}.",
+                    sample_size, group_name, average_sentiment, threshold
+                )),
+            ),
+
+            BroadcastEvent::WeatherWarning {
+                location,
+                event,
+                description,
+                sender,
+                starts_at,
+                ends_at,
+            } => (
+                format!("Weather Warning: {} in {}", event, location),
+                Body::plain(format!(
+                    "{} issued a {} warning for {} from {} until {} UTC.\n\n{}",
+                    sender, event, location, starts_at, ends_at, description
+                )),
+            ),
+
+            BroadcastEvent::CalendarReminder {
+                summary,
+                location,
+                starts_at,
+            } => (
+                format!("Reminder: {} at {}", summary, starts_at.format("%H:%M")),
+                Body::plain(match location {
+                    Some(location) => format!(
+                        "{} starts at {} in {}.",
+                        summary,
+                        starts_at.format("%H:%M on %a %b %e"),
+                        location
+                    ),
+                    None => format!(
+                        "{} starts at {}.",
+                        summary,
+                        starts_at.format("%H:%M on %a %b %e")
+                    ),
+                }),
+            ),
+
+            BroadcastEvent::StockMove {
+                ticker,
+                price,
+                previous_close,
+                alert_percent,
+            } => (
+                format!("Stock Move: {}", ticker),
+                Body::plain(format!(
+                    "{} is at {:.2}, {:+.2}% from its previous close of {:.2}, \
+                     which is more than {:.2}%",
+                    ticker,
+                    price,
+                    (price - previous_close) / previous_close * 100.0,
+                    previous_close,
+                    alert_percent
+                )),
+            ),
+
+            BroadcastEvent::CryptoPrice {
+                asset,
+                currency,
+                price,
+                percent_change_24h,
+                trigger,
+            } => {
+                let threshold = match trigger {
+                    PriceTrigger::Above(above) => format!("above {:.2} {}", above, currency),
+                    PriceTrigger::Below(below) => format!("below {:.2} {}", below, currency),
+                    PriceTrigger::Change(change) => {
+                        format!("changed by more than {:.2}% in 24 hours", change)
+                    }
+                };
+                let change = percent_change_24h
+                    .map(|change| format!(" ({:+.2}% in 24 hours)", change))
+                    .unwrap_or_default();
+                (
+                    format!("Crypto Price: {}", asset),
+                    Body::plain(format!(
+                        "{} is at {:.2} {}{}, which is {}",
+                        asset, price, currency, change, threshold
+                    )),
+                )
+            }
+
+            BroadcastEvent::GithubRelease {
+                repository,
+                tag,
+                name,
+                url,
+            } => (
+                format!("GitHub Release: {} {}", repository, tag),
+                Body::plain(format!(
+                    "{} released {}{}\n{}",
+                    repository,
+                    tag,
+                    name.as_ref()
+                        .filter(|name| *name != tag)
+                        .map(|name| format!(" ({})", name))
+                        .unwrap_or_default(),
+                    url
+                )),
+            ),
+
+            BroadcastEvent::GithubNotification {
+                repository,
+                title,
+                reason,
+                url,
+            } => (
+                format!("GitHub Notification: {}", title),
+                Body::plain(format!(
+                    "{} in {} ({})\n{}",
+                    title,
+                    repository,
+                    reason.replace('_', " "),
+                    url
+                )),
+            ),
+
+            BroadcastEvent::MailReceived {
+                mailbox,
+                watch,
+                from,
+                subject,
+                ..
+            } => (
+                format!("Mail Received: {}", subject),
+                Body::plain(format!(
+                    "A message matching {} arrived in {}.\n\nFrom: {}\nSubject: {}",
+                    watch, mailbox, from, subject
+                )),
+            ),
+
+            BroadcastEvent::UnreadMail {
+                mailbox,
+                unread,
+                flagged,
+                max_unread,
+            } => (
+                format!("Unread Mail: {} messages in {}", unread, mailbox),
+                Body::plain(format!(
+                    "{} has {} unread messages, more than the maximum of {}. {} messages are flagged.",
+                    mailbox, unread, max_unread, flagged
+                )),
+            ),
+
+            BroadcastEvent::PodcastEpisode {
+                podcast,
+                title,
+                duration,
+                url,
+            } => (
+                format!("New Episode: {}", title),
+                Body::plain(match duration {
+                    Some(duration) => format!("{}: {} ({})\n{}", podcast, title, duration, url),
+                    None => format!("{}: {}\n{}", podcast, title, url),
+                }),
+            ),
+
+            BroadcastEvent::YoutubeUpload {
+                channel,
+                title,
+                url,
+            } => (
+                format!("New Video: {}", title),
+                Body::plain(format!("{} uploaded {}\n{}", channel, title, url)),
+            ),
+
+            BroadcastEvent::GenericMessage {
+                source,
+                title,
+                body,
+                ..
+            } => (format!("{}: {}", source, title), Body::plain(body.clone())),
+
+            BroadcastEvent::CustomEvent { title, body, .. } => {
+                (title.clone(), Body::plain(body.clone()))
+            }
+
+            BroadcastEvent::DatabaseUnreachable { since, error } => (
+                "Database Unreachable".to_string(),
+                Body::plain(format!(
+                    "Pulse has been unable to reach its database since {} UTC. \
+                     Data recorded in the meantime may be lost.\n\n{}",
+                    since, error
+                )),
+            ),
+
+            BroadcastEvent::TaskTimedOut {
+                task,
+                started_at,
+                timeout,
+            } => (
+                format!("Task Timed Out: {}", task),
+                Body::plain(format!(
+                    "The {} task started at {} UTC is still running after {}. \
+                     It won't run again until it finishes.",
+                    task, started_at, timeout
+                )),
+            ),
+
+            BroadcastEvent::TaskFailed {
+                task,
+                runner,
+                attempts,
+                error,
+            } => (
+                format!("Task Failed: {}", task),
+                Body::plain(format!(
+                    "{} failed the {} task {} times, and won't retry it until its next run.\n\n{}",
+                    runner, task, attempts, error
+                )),
+            ),
+
+            BroadcastEvent::Newscast {
+                articles,
+                weather,
+                calendar,
+                stocks,
+                twitter,
+            } => {
+                // sections summarized in a line per item
+                let summaries: Vec<(&str, Vec<String>)> = vec![
+                    (
+                        "Weather",
+                        weather.iter().map(news::Forecast::describe).collect(),
+                    ),
+                    (
+                        "Calendar",
+                        calendar.iter().map(news::CalendarEvent::describe).collect(),
+                    ),
+                    (
+                        "Markets",
+                        stocks.iter().map(markets::StockQuote::describe).collect(),
+                    ),
+                    (
+                        "Twitter Activity",
+                        twitter
                             .iter()
-                            .map(|article| {
-                                format!(
-                                    include_str!("../../../resources/email/news/article.html"),
-                                    url = article.url,
-                                    title = article.title,
-                                    publish_date = article.published_date,
-                                    r#abstract = article.r#abstract
-                                )
-                            })
-                            .collect::<Vec<String>>()
-                            .join("<br>");
+                            .flat_map(twitter::GroupActivity::describe)
+                            .collect(),
+                    ),
+                ]
+                .into_iter()
+                .filter(|(_, lines)| !lines.is_empty())
+                .collect();
+                let article_sections = articles.iter();
 
+                let mut text_sections = summaries
+                    .iter()
+                    .map(|(section_title, lines)| {
+                        format!("{}\n\n{}", section_title.to_uppercase(), lines.join("\n"))
+                    })
+                    .collect::<Vec<String>>();
+                text_sections.extend(article_sections.clone().map(|section| {
+                    let articles = section
+                        .articles
+                        .iter()
+                        .map(|article| {
+                            format!(
+                                "{} ({})\n{}\n{}",
+                                article.title,
+                                article.published_date,
+                                article.r#abstract,
+                                article.url
+                            )
+                        })
+                        .collect::<Vec<String>>()
+                        .join("\n\n");
+
+                    format!("{}\n\n{}", section.section_title.to_uppercase(), articles)
+                }));
+                let text = text_sections.join("\n\n\n");
+
+                let mut html_sections = summaries
+                    .iter()
+                    .map(|(section_title, lines)| {
                         format!(
                             include_str!("../../../resources/email/news/section.html"),
-                            section_title = section.section_title,
-                            articles = articles
+                            section_title = section_title,
+                            articles = lines.join("<br>")
                         )
                     })
-                    .collect::<Vec<String>>()
-                    .join("<br>");
+                    .collect::<Vec<String>>();
+                html_sections.extend(article_sections.map(|section| {
+                    let articles = section
+                        .articles
+                        .iter()
+                        .map(|article| {
+                            format!(
+                                include_str!("../../../resources/email/news/article.html"),
+                                url = article.url,
+                                title = article.title,
+                                publish_date = article.published_date,
+                                r#abstract = article.r#abstract
+                            )
+                        })
+                        .collect::<Vec<String>>()
+                        .join("<br>");
 
-                format!(
+                    format!(
+                        include_str!("../../../resources/email/news/section.html"),
+                        section_title = section.section_title,
+                        articles = articles
+                    )
+                }));
+                let sections = html_sections.join("<br>");
+
+                let html = format!(
                     include_str!("../../../resources/email/news/outline.html"),
                     title = "Digest",
                     sections = sections,
                     css = include_str!("../../../resources/email/news/style.css")
+                );
+
+                (
+                    "News".to_string(),
+                    Body {
+                        text,
+                        html,
+                        attachments: vec![],
+                    },
                 )
-            }),
+            }
         }
     }
 
@@ -118,27 +687,137 @@ impl BroadcastEvent {
             BroadcastEvent::HighDiskUsage { .. } => BroadcastEventType::HighDiskUsage,
             BroadcastEvent::Newscast { .. } => BroadcastEventType::Newscast,
             BroadcastEvent::TwitterAlert { .. } => BroadcastEventType::TwitterAlert,
+            BroadcastEvent::TwitterSentiment { .. } => BroadcastEventType::TwitterSentiment,
+            BroadcastEvent::WeatherWarning { .. } => BroadcastEventType::WeatherWarning,
+            BroadcastEvent::CalendarReminder { .. } => BroadcastEventType::CalendarReminder,
+            BroadcastEvent::StockMove { .. } => BroadcastEventType::StockMove,
+            BroadcastEvent::CryptoPrice { .. } => BroadcastEventType::CryptoPrice,
+            BroadcastEvent::GithubRelease { .. } => BroadcastEventType::GithubRelease,
+            BroadcastEvent::GithubNotification { .. } => BroadcastEventType::GithubNotification,
+            BroadcastEvent::MailReceived { .. } => BroadcastEventType::MailReceived,
+            BroadcastEvent::UnreadMail { .. } => BroadcastEventType::UnreadMail,
+            BroadcastEvent::PodcastEpisode { .. } => BroadcastEventType::PodcastEpisode,
+            BroadcastEvent::YoutubeUpload { .. } => BroadcastEventType::YoutubeUpload,
+            BroadcastEvent::GenericMessage { .. } => BroadcastEventType::GenericMessage,
+            BroadcastEvent::CustomEvent { .. } => BroadcastEventType::CustomEvent,
+            BroadcastEvent::DatabaseUnreachable { .. } => BroadcastEventType::DatabaseUnreachable,
+            BroadcastEvent::TaskTimedOut { .. } => BroadcastEventType::TaskTimedOut,
+            BroadcastEvent::TaskFailed { .. } => BroadcastEventType::TaskFailed,
         }
     }
 
-    /// Unique identifier for this event
+    pub fn severity(&self) -> Severity {
+        match self {
+            BroadcastEvent::HighDiskUsage { current_usage, .. } => {
+                if *current_usage >= CRITICAL_DISK_USAGE {
+                    Severity::Critical
+                } else {
+                    Severity::Warning
+                }
+            }
+            BroadcastEvent::Newscast { .. } => Severity::Info,
+            BroadcastEvent::TwitterAlert { .. } => Severity::Warning,
+            BroadcastEvent::TwitterSentiment { .. } => Severity::Warning,
+            BroadcastEvent::WeatherWarning { .. } => Severity::Critical,
+            BroadcastEvent::CalendarReminder { .. } => Severity::Warning,
+            BroadcastEvent::StockMove { .. } => Severity::Warning,
+            BroadcastEvent::CryptoPrice { .. } => Severity::Warning,
+            BroadcastEvent::GithubRelease { .. } => Severity::Info,
+            BroadcastEvent::GithubNotification { .. } => Severity::Info,
+            BroadcastEvent::MailReceived { .. } => Severity::Warning,
+            BroadcastEvent::UnreadMail { .. } => Severity::Info,
+            BroadcastEvent::PodcastEpisode { .. } => Severity::Info,
+            BroadcastEvent::YoutubeUpload { .. } => Severity::Info,
+            BroadcastEvent::GenericMessage { severity, .. } => *severity,
+            BroadcastEvent::CustomEvent { severity, .. } => *severity,
+            BroadcastEvent::DatabaseUnreachable { .. } => Severity::Critical,
+            BroadcastEvent::TaskTimedOut { .. } => Severity::Warning,
+            BroadcastEvent::TaskFailed { .. } => Severity::Warning,
+        }
+    }
+
+    /// Unique identifier for this event, e.g. `high-disk-usage:/`
     pub fn event_key(&self) -> BroadcastEventKey {
         match self {
             BroadcastEvent::HighDiskUsage {
                 filesystem_mount, ..
-            } => (serde_json::to_string(&self.event_type()).unwrap() + filesystem_mount).into(),
-            BroadcastEvent::Newscast { .. } => {
-                serde_json::to_string(&self.event_type()).unwrap().into()
+            } => format!("{}:{}", self.event_type(), filesystem_mount).into(),
+            BroadcastEvent::Newscast { .. } => self.event_type().to_string().into(),
+            BroadcastEvent::TwitterAlert { .. } => self.event_type().to_string().into(),
+            BroadcastEvent::TwitterSentiment { group_name, .. } => {
+                format!("{}:{}", self.event_type(), group_name).into()
+            }
+            BroadcastEvent::WeatherWarning {
+                location, event, ..
+            } => format!("{}:{}:{}", self.event_type(), location, event).into(),
+            BroadcastEvent::CalendarReminder {
+                summary, starts_at, ..
+            } => format!("{}:{}:{}", self.event_type(), summary, starts_at).into(),
+            BroadcastEvent::StockMove { ticker, .. } => {
+                format!("{}:{}", self.event_type(), ticker).into()
+            }
+            BroadcastEvent::CryptoPrice { asset, trigger, .. } => {
+                format!("{}:{}:{}", self.event_type(), asset, trigger.name()).into()
+            }
+            BroadcastEvent::GithubRelease {
+                repository, tag, ..
+            } => format!("{}:{}:{}", self.event_type(), repository, tag).into(),
+            BroadcastEvent::GithubNotification {
+                repository, title, ..
+            } => format!("{}:{}:{}", self.event_type(), repository, title).into(),
+            BroadcastEvent::MailReceived { mailbox, uid, .. } => {
+                format!("{}:{}:{}", self.event_type(), mailbox, uid).into()
+            }
+            BroadcastEvent::UnreadMail { mailbox, .. } => {
+                format!("{}:{}", self.event_type(), mailbox).into()
+            }
+            BroadcastEvent::PodcastEpisode { podcast, url, .. } => {
+                format!("{}:{}:{}", self.event_type(), podcast, url).into()
+            }
+            BroadcastEvent::YoutubeUpload { channel, url, .. } => {
+                format!("{}:{}:{}", self.event_type(), channel, url).into()
+            }
+            BroadcastEvent::GenericMessage {
+                source, title, key, ..
+            } => format!(
+                "{}:{}:{}",
+                self.event_type(),
+                source,
+                key.as_ref().unwrap_or(title)
+            )
+            .into(),
+            BroadcastEvent::CustomEvent { key, .. } => {
+                format!("{}:{}", self.event_type(), key).into()
             }
-            BroadcastEvent::TwitterAlert { .. } => {
-                serde_json::to_string(&self.event_type()).unwrap().into()
+            BroadcastEvent::DatabaseUnreachable { .. } => self.event_type().to_string().into(),
+            BroadcastEvent::TaskTimedOut {
+                task, started_at, ..
+            } => format!("{}:{}:{}", self.event_type(), task, started_at).into(),
+            BroadcastEvent::TaskFailed { task, runner, .. } => {
+                format!("{}:{}:{}", self.event_type(), task, runner).into()
             }
         }
     }
 }
 
-#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub enum BroadcastMedium {
     Email,
 }
+
+/// Displays the medium as it is written in the config, e.g. `email`
+impl fmt::Display for BroadcastMedium {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        name.trim_matches('"').fmt(f)
+    }
+}
+
+impl FromStr for BroadcastMedium {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(Into::into)
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{BroadcastEvent, Severity};
+use crate::error::{Error, Result};
+
+/// A message posted by a script, e.g.
+/// `{"title": "backup failed", "severity": "critical"}`
+#[derive(Deserialize)]
+struct Message {
+    title: String,
+    body: Option<String>,
+    severity: Option<Severity>,
+    /// Messages with the same key are deduplicated, and resolve
+    /// together. The title is used if there is no key
+    key: Option<String>,
+}
+
+/// A webhook notification from Prometheus Alertmanager
+#[derive(Deserialize)]
+struct AlertmanagerNotification {
+    alerts: Vec<AlertmanagerAlert>,
+}
+
+#[derive(Deserialize)]
+struct AlertmanagerAlert {
+    status: String,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+    fingerprint: Option<String>,
+}
+
+/// Convert a payload posted to the ingest endpoint into generic
+/// messages. GitHub webhooks are recognized by their `event` (from the
+/// `X-GitHub-Event` header) and Alertmanager notifications by their
+/// shape. Anything else must be a message with a `title`
+pub fn ingest(
+    source: Option<String>,
+    github_event: Option<String>,
+    payload: Value,
+) -> Result<Vec<BroadcastEvent>> {
+    if let Some(event) = github_event {
+        return Ok(vec![github(
+            source.unwrap_or_else(|| "github".to_string()),
+            &event,
+            &payload,
+        )]);
+    }
+
+    if payload.get("alerts").is_some() && payload.get("receiver").is_some() {
+        let notification: AlertmanagerNotification = serde_json::from_value(payload)
+            .map_err(|e| Error::invalid_request(format!("invalid alertmanager payload: {}", e)))?;
+        return Ok(alertmanager(
+            source.unwrap_or_else(|| "alertmanager".to_string()),
+            notification,
+        ));
+    }
+
+    let message: Message = serde_json::from_value(payload).map_err(|e| {
+        Error::invalid_request(format!(
+            "expected a message with a title, an alertmanager notification or a github webhook: {}",
+            e
+        ))
+    })?;
+    Ok(vec![BroadcastEvent::GenericMessage {
+        source: source.unwrap_or_else(|| "webhook".to_string()),
+        title: message.title,
+        body: message.body.unwrap_or_default(),
+        severity: message.severity.unwrap_or(Severity::Warning),
+        key: message.key,
+    }])
+}
+
+/// A message for each firing alert. Resolved alerts are skipped, since
+/// pulse resolves alerts itself once they stop firing
+fn alertmanager(source: String, notification: AlertmanagerNotification) -> Vec<BroadcastEvent> {
+    notification
+        .alerts
+        .into_iter()
+        .filter(|alert| alert.status == "firing")
+        .map(|alert| {
+            let title = alert
+                .labels
+                .get("alertname")
+                .cloned()
+                .unwrap_or_else(|| "Alertmanager alert".to_string());
+            let body = ["summary", "description"]
+                .iter()
+                .filter_map(|annotation| alert.annotations.get(*annotation))
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let severity = alert
+                .labels
+                .get("severity")
+                .and_then(|severity| {
+                    serde_json::from_value(Value::String(severity.to_lowercase())).ok()
+                })
+                .unwrap_or(Severity::Warning);
+
+            BroadcastEvent::GenericMessage {
+                source: source.clone(),
+                key: alert.fingerprint.or_else(|| Some(title.clone())),
+                title,
+                body,
+                severity,
+            }
+        })
+        .collect()
+}
+
+/// A message describing a GitHub webhook, e.g. `opened pull_request in
+/// owner/repo`
+fn github(source: String, event: &str, payload: &Value) -> BroadcastEvent {
+    let field = |pointer: &str| payload.pointer(pointer).and_then(Value::as_str);
+    let repository = field("/repository/full_name").unwrap_or("github");
+    let title = match field("/action") {
+        Some(action) => format!("{} {} in {}", action, event, repository),
+        None => format!("{} in {}", event, repository),
+    };
+    let url = [
+        "/pull_request",
+        "/issue",
+        "/release",
+        "/comment",
+        "/repository",
+    ]
+    .iter()
+    .find_map(|object| field(&format!("{}/html_url", object)));
+    let body = vec![
+        field("/sender/login").map(|sender| format!("By {}", sender)),
+        url.map(ToString::to_string),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n");
+
+    BroadcastEvent::GenericMessage {
+        source,
+        key: url.map(|url| format!("{}:{}", event, url)),
+        title,
+        body,
+        severity: Severity::Info,
+    }
+}
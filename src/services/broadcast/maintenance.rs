@@ -0,0 +1,104 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveTime};
+use cron::Schedule as CronSchedule;
+
+use crate::{
+    config::MaintenanceWindowConfig,
+    error::{Error, Result},
+};
+
+use super::events::{BroadcastEvent, BroadcastEventType};
+
+enum Period {
+    /// A time range repeated every day. The range wraps past midnight
+    /// if it ends before it starts
+    Daily { start: NaiveTime, end: NaiveTime },
+    /// A window starting at every occurrence of a cron schedule
+    Cron {
+        schedule: CronSchedule,
+        duration: chrono::Duration,
+    },
+}
+
+/// A period of time during which alerts for matching events are
+/// suppressed
+pub struct MaintenanceWindow {
+    event: Option<BroadcastEventType>,
+    key: Option<String>,
+    period: Period,
+}
+
+impl MaintenanceWindow {
+    pub fn new(config: &MaintenanceWindowConfig) -> Result<Self> {
+        let period =
+            match config {
+                MaintenanceWindowConfig {
+                    start: Some(start),
+                    end: Some(end),
+                    cron: None,
+                    duration: None,
+                    ..
+                } => Period::Daily {
+                    start: *start,
+                    end: *end,
+                },
+                MaintenanceWindowConfig {
+                    start: None,
+                    end: None,
+                    cron: Some(cron),
+                    duration: Some(duration),
+                    ..
+                } => Period::Cron {
+                    schedule: CronSchedule::from_str(cron)?,
+                    duration: chrono::Duration::from_std(*duration).map_err(|_| {
+                        Error::invalid_config("maintenance window duration is out of range")
+                    })?,
+                },
+                _ => return Err(Error::invalid_config(
+                    "maintenance windows need either `start` and `end` or `cron` and `duration`",
+                )),
+            };
+
+        Ok(Self {
+            event: config.event.clone(),
+            key: config.key.clone(),
+            period,
+        })
+    }
+
+    /// Whether alerts for this event should be suppressed right now
+    pub fn suppresses(&self, event: &BroadcastEvent) -> bool {
+        self.applies_to(event) && self.is_active_at(Local::now())
+    }
+
+    fn applies_to(&self, event: &BroadcastEvent) -> bool {
+        self.event
+            .as_ref()
+            .map(|event_type| *event_type == event.event_type())
+            .unwrap_or(true)
+            && self
+                .key
+                .as_ref()
+                .map(|key| *key == event.event_key().to_string())
+                .unwrap_or(true)
+    }
+
+    fn is_active_at(&self, now: DateTime<Local>) -> bool {
+        match &self.period {
+            Period::Daily { start, end } => {
+                let time = now.time();
+                if start <= end {
+                    *start <= time && time < *end
+                } else {
+                    *start <= time || time < *end
+                }
+            }
+            Period::Cron { schedule, duration } => schedule
+                .after(&(now - *duration))
+                .next()
+                .map(|window_start| window_start <= now)
+                .unwrap_or(false),
+        }
+    }
+}
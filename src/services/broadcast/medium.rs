@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use crate::{
+    config::{BroadcastConfig, EmailConfig},
+    error::{Error, Result},
+};
+
+use super::{delivery::Delivery, email, events::BroadcastMedium, oauth2::TokenSource};
+
+/// A way of delivering alerts to people. Each medium is registered
+/// under the name used for it in alert configs
+pub trait Medium: Send + Sync {
+    fn deliver(&self, delivery: &Delivery) -> Result<()>;
+}
+
+pub struct EmailMedium {
+    config: EmailConfig,
+    token_source: Option<TokenSource>,
+}
+
+impl EmailMedium {
+    pub fn new(config: EmailConfig) -> Result<Self> {
+        if config.password.is_none() && config.oauth2.is_none() {
+            return Err(Error::invalid_config(
+                "email needs either a `password` or `oauth2` configuration",
+            ));
+        }
+
+        Ok(Self {
+            token_source: config.oauth2.clone().map(TokenSource::new),
+            config,
+        })
+    }
+}
+
+impl Medium for EmailMedium {
+    fn deliver(&self, delivery: &Delivery) -> Result<()> {
+        email::send_email(
+            &self.config,
+            self.token_source.as_ref(),
+            delivery.subject.clone(),
+            delivery.body.clone(),
+        )
+    }
+}
+
+/// The mediums alerts can be delivered over
+#[derive(Default)]
+pub struct Mediums {
+    mediums: HashMap<BroadcastMedium, Box<dyn Medium>>,
+}
+
+impl Mediums {
+    /// Register every medium that has been configured
+    pub fn from_config(config: &BroadcastConfig) -> Result<Self> {
+        let mut mediums = Self::default();
+        if let Some(email_config) = &config.email {
+            mediums.register(
+                BroadcastMedium::Email,
+                Box::new(EmailMedium::new(email_config.clone())?),
+            );
+        }
+
+        Ok(mediums)
+    }
+
+    pub fn register(&mut self, name: BroadcastMedium, medium: Box<dyn Medium>) {
+        self.mediums.insert(name, medium);
+    }
+
+    pub fn get(&self, name: &BroadcastMedium) -> Option<&dyn Medium> {
+        self.mediums.get(name).map(AsRef::as_ref)
+    }
+
+    /// Check that every medium used by an alert has been registered
+    pub fn validate(&self, config: &BroadcastConfig) -> Result<()> {
+        let used = config.alerts.iter().flat_map(|alert| {
+            alert.mediums.iter().chain(
+                alert
+                    .severity_routes
+                    .iter()
+                    .flatten()
+                    .flat_map(|route| route.mediums.iter()),
+            )
+        });
+        for name in used {
+            if !self.mediums.contains_key(name) {
+                return Err(Error::unconfigured_medium(name.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
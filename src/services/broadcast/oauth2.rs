@@ -0,0 +1,83 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    config::OAuth2Config,
+    error::{Error, Result},
+};
+
+/// Refresh access tokens this long before they expire, so that they
+/// don't expire mid-send
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+struct AccessToken {
+    token: String,
+    expires_at: Instant,
+}
+
+/// Provides OAuth2 access tokens for XOAUTH2 SMTP authentication,
+/// refreshing them with the configured refresh token when they expire
+pub struct TokenSource {
+    config: OAuth2Config,
+    access_token: Mutex<Option<AccessToken>>,
+}
+
+impl TokenSource {
+    pub fn new(config: OAuth2Config) -> Self {
+        Self {
+            config,
+            access_token: Mutex::new(None),
+        }
+    }
+
+    pub fn access_token(&self) -> Result<String> {
+        let mut access_token = self.access_token.lock().unwrap();
+        match &*access_token {
+            Some(current) if Instant::now() + EXPIRY_MARGIN < current.expires_at => {
+                Ok(current.token.clone())
+            }
+            _ => {
+                let refreshed = self.refresh()?;
+                let token = refreshed.token.clone();
+                *access_token = Some(refreshed);
+                Ok(token)
+            }
+        }
+    }
+
+    fn refresh(&self) -> Result<AccessToken> {
+        log::debug!("Refreshing oauth2 access token");
+        let response = ureq::post(&self.config.token_url).send_form(&[
+            ("client_id", self.config.client_id.as_str()),
+            ("client_secret", self.config.client_secret.as_str()),
+            ("refresh_token", self.config.refresh_token.as_str()),
+            ("grant_type", "refresh_token"),
+        ]);
+        if !response.ok() {
+            return Err(Error::oauth2(format!(
+                "token refresh failed with status {}: {}",
+                response.status(),
+                response.into_string().unwrap_or_default()
+            )));
+        }
+
+        let token_response: TokenResponse = response
+            .into_json_deserialize()
+            .map_err(|e| Error::oauth2(e.to_string()))?;
+
+        Ok(AccessToken {
+            token: token_response.access_token,
+            expires_at: Instant::now() + Duration::from_secs(token_response.expires_in),
+        })
+    }
+}
@@ -0,0 +1,357 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    mem,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use actix::{prelude::SendError, Message, Recipient};
+use futures::FutureExt;
+use serde::Serialize;
+
+use crate::{
+    config::{OutboxConfig, OverflowPolicy},
+    db::{self, database, models},
+    error::{Error, Result},
+};
+
+use super::{
+    events::{BroadcastEvent, Severity},
+    OUTBOX_CAPACITY,
+};
+
+/// An event waiting in the outbox, ordered so that more severe events
+/// come first, and events of the same severity in the order they were
+/// pushed
+struct Queued {
+    severity: Severity,
+    sequence: u64,
+    entry: OutboxEntry,
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.severity
+            .cmp(&other.severity)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+/// An event taken from the outbox. Events from a durable outbox have
+/// the id of their row in the `outbox` table, which should be marked
+/// delivered once the event has been broadcast
+#[derive(Clone, Debug)]
+pub struct OutboxEntry {
+    pub id: Option<i32>,
+    pub event: BroadcastEvent,
+}
+
+impl From<BroadcastEvent> for OutboxEntry {
+    fn from(event: BroadcastEvent) -> Self {
+        Self { id: None, event }
+    }
+}
+
+/// Sent to the outbox's subscriber whenever an event is pushed
+pub struct OutboxReady;
+
+impl Message for OutboxReady {
+    type Result = ();
+}
+
+/// Sent to the outbox's listeners with a copy of every pushed event
+pub struct EventPushed(pub BroadcastEvent);
+
+impl Message for EventPushed {
+    type Result = ();
+}
+
+/// The state of the outbox, for self-monitoring
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OutboxStats {
+    pub depth: usize,
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+    /// Events dropped to make room for newer events
+    pub dropped: u64,
+    /// Events replaced by a newer event with the same key
+    pub coalesced: u64,
+    /// New events refused because the outbox was full
+    pub rejected: u64,
+}
+
+struct OutboxInner {
+    queue: BinaryHeap<Queued>,
+    next_sequence: u64,
+    durable: bool,
+    /// Events of a durable outbox that are being written to the
+    /// database, and are queued once they have been
+    writing: usize,
+    subscriber: Option<Recipient<OutboxReady>>,
+    listeners: Vec<Recipient<EventPushed>>,
+    dropped: u64,
+    coalesced: u64,
+    rejected: u64,
+}
+
+/// A bounded queue of events waiting to be broadcast. When the queue
+/// backs up, critical events jump ahead of bulk events like newscasts,
+/// and once it is full new events are handled by the overflow policy.
+/// Pushing never waits for room, since events are pushed from actors
+/// that would stall their arbiter. Clones share the same queue
+#[derive(Clone)]
+pub struct Outbox {
+    capacity: usize,
+    overflow: OverflowPolicy,
+    inner: Arc<Mutex<OutboxInner>>,
+}
+
+impl Outbox {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            overflow: OverflowPolicy::Reject,
+            inner: Arc::new(Mutex::new(OutboxInner {
+                queue: BinaryHeap::new(),
+                next_sequence: 0,
+                durable: false,
+                writing: 0,
+                subscriber: None,
+                listeners: vec![],
+                dropped: 0,
+                coalesced: 0,
+                rejected: 0,
+            })),
+        }
+    }
+
+    pub fn from_config(config: &OutboxConfig) -> Self {
+        let outbox = Self::new(config.capacity.unwrap_or(OUTBOX_CAPACITY));
+        match config.overflow {
+            Some(overflow) => outbox.with_overflow(overflow),
+            None => outbox,
+        }
+    }
+
+    /// Handle new events with this policy once the outbox is full
+    pub fn with_overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Write every event pushed from now on to the database, so that
+    /// pending events survive a crash, and recover the events that
+    /// were still pending when pulse last stopped
+    pub fn make_durable(&self) -> Result<usize> {
+        let pending = database().pending_outbox_events()?;
+        let count = pending.len();
+        let mut inner = self.lock();
+        for outbox_event in pending {
+            let event = serde_json::from_str(&outbox_event.event)?;
+            Self::enqueue(
+                &mut inner,
+                OutboxEntry {
+                    id: Some(outbox_event.id),
+                    event,
+                },
+            );
+        }
+        inner.durable = true;
+
+        Ok(count)
+    }
+
+    /// Notify this recipient whenever an event is pushed
+    pub fn subscribe(&self, subscriber: Recipient<OutboxReady>) {
+        self.lock().subscriber = Some(subscriber);
+    }
+
+    /// Send this recipient a copy of every event pushed, until it
+    /// stops
+    pub fn listen(&self, listener: Recipient<EventPushed>) {
+        self.lock().listeners.push(listener);
+    }
+
+    pub fn push(&self, event: BroadcastEvent) -> Result<()> {
+        let mut inner = self.lock();
+        if Self::depth(&inner) >= self.capacity {
+            let (guard, made_room) = self.make_room(inner, &event);
+            inner = guard;
+            if !made_room {
+                inner.rejected += 1;
+                return Err(Error::outbox_full());
+            }
+        }
+
+        inner.listeners.retain(
+            |listener| match listener.do_send(EventPushed(event.clone())) {
+                Err(SendError::Closed(_)) => false,
+                _ => true,
+            },
+        );
+
+        if inner.durable {
+            let outbox_event = models::NewOutboxEvent {
+                event: serde_json::to_string(&event)?,
+            };
+            inner.writing += 1;
+            drop(inner);
+            self.write(outbox_event, event);
+        } else {
+            Self::enqueue(&mut inner, event.into());
+            Self::notify(&inner);
+        }
+        Ok(())
+    }
+
+    /// Write an event to the database in the background, and queue it
+    /// with the id of its row once it has been written. Events that
+    /// can't be written are still queued, but aren't recovered after a
+    /// restart
+    fn write(&self, outbox_event: models::NewOutboxEvent, event: BroadcastEvent) {
+        let outbox = self.clone();
+        let written = db::run(move |database| database.insert_outbox_event(outbox_event));
+        actix::spawn(written.map(move |written| {
+            let id = match written {
+                Ok(outbox_event) => Some(outbox_event.id),
+                Err(e) => {
+                    log::error!("Error writing outbox event: {}", e);
+                    None
+                }
+            };
+
+            let mut inner = outbox.lock();
+            inner.writing -= 1;
+            Self::enqueue(&mut inner, OutboxEntry { id, event });
+            Self::notify(&inner);
+        }));
+    }
+
+    /// Tell the subscriber that an event was queued. The event stays
+    /// queued even if the subscriber is gone
+    fn notify(inner: &OutboxInner) {
+        if let Some(subscriber) = &inner.subscriber {
+            subscriber
+                .do_send(OutboxReady)
+                .unwrap_or_else(|e| log::warn!("Error notifying outbox subscriber: {}", e));
+        }
+    }
+
+    /// The events in the outbox, counting those still being written
+    fn depth(inner: &OutboxInner) -> usize {
+        inner.queue.len() + inner.writing
+    }
+
+    /// Take the most severe event from the outbox
+    pub fn pop(&self) -> Option<OutboxEntry> {
+        self.lock().queue.pop().map(|queued| queued.entry)
+    }
+
+    pub fn stats(&self) -> OutboxStats {
+        let inner = self.lock();
+        OutboxStats {
+            depth: Self::depth(&inner),
+            capacity: self.capacity,
+            overflow: self.overflow,
+            dropped: inner.dropped,
+            coalesced: inner.coalesced,
+            rejected: inner.rejected,
+        }
+    }
+
+    /// Try to make room in a full outbox for a new event according to
+    /// the overflow policy
+    fn make_room<'a>(
+        &self,
+        mut inner: MutexGuard<'a, OutboxInner>,
+        event: &BroadcastEvent,
+    ) -> (MutexGuard<'a, OutboxInner>, bool) {
+        match self.overflow {
+            OverflowPolicy::Reject => (inner, false),
+            OverflowPolicy::DropOldest => {
+                let oldest = Self::remove(&mut inner, |queued| {
+                    queued
+                        .iter()
+                        .enumerate()
+                        .min_by(|(_, a), (_, b)| {
+                            a.severity
+                                .cmp(&b.severity)
+                                .then_with(|| a.sequence.cmp(&b.sequence))
+                        })
+                        .map(|(index, _)| index)
+                });
+                if let Some(oldest) = oldest {
+                    log::warn!(
+                        "Outbox full, dropped {} event {}",
+                        oldest.entry.event.event_type(),
+                        oldest.entry.event.event_key()
+                    );
+                    inner.dropped += 1;
+                }
+                (inner, true)
+            }
+            OverflowPolicy::CoalesceByKey => {
+                let (event_type, event_key) = (event.event_type(), event.event_key());
+                let matching = Self::remove(&mut inner, |queued| {
+                    queued.iter().position(|queued| {
+                        queued.entry.event.event_type() == event_type
+                            && queued.entry.event.event_key() == event_key
+                    })
+                });
+                let coalesced = matching.is_some();
+                if coalesced {
+                    inner.coalesced += 1;
+                }
+                (inner, coalesced)
+            }
+        }
+    }
+
+    /// Remove the queued event at the index chosen by `select`. Events
+    /// from a durable outbox are marked delivered so that they aren't
+    /// recovered after a restart
+    fn remove<F>(inner: &mut OutboxInner, select: F) -> Option<Queued>
+    where
+        F: FnOnce(&[Queued]) -> Option<usize>,
+    {
+        let mut queued = mem::take(&mut inner.queue).into_vec();
+        let removed = select(&queued).map(|index| queued.swap_remove(index));
+        inner.queue = queued.into();
+
+        if let Some(id) = removed.as_ref().and_then(|removed| removed.entry.id) {
+            db::spawn_write(
+                "marking a removed outbox event delivered",
+                move |database| database.mark_outbox_events_delivered(&[id]),
+            );
+        }
+        removed
+    }
+
+    fn enqueue(inner: &mut OutboxInner, entry: OutboxEntry) {
+        let sequence = inner.next_sequence;
+        inner.next_sequence += 1;
+        inner.queue.push(Queued {
+            severity: entry.event.severity(),
+            sequence,
+            entry,
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<OutboxInner> {
+        self.inner.lock().unwrap()
+    }
+}
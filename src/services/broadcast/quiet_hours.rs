@@ -0,0 +1,48 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+use crate::{
+    config::QuietHoursConfig,
+    error::{Error, Result},
+};
+
+use super::events::Severity;
+
+/// A daily period during which alerts that aren't urgent are held
+/// until morning
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+    timezone: Tz,
+    severities: Vec<Severity>,
+}
+
+impl QuietHours {
+    pub fn new(config: &QuietHoursConfig) -> Result<Self> {
+        Ok(Self {
+            start: config.start,
+            end: config.end,
+            timezone: config.timezone.parse().map_err(|e| {
+                Error::invalid_config(format!("invalid quiet hours timezone: {}", e))
+            })?,
+            severities: config
+                .severities
+                .clone()
+                .unwrap_or_else(|| vec![Severity::Info, Severity::Warning]),
+        })
+    }
+
+    /// Whether alerts of this severity should be held right now
+    pub fn holds(&self, severity: Severity) -> bool {
+        self.severities.contains(&severity) && self.is_active_at(Utc::now())
+    }
+
+    fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        let time = now.with_timezone(&self.timezone).time();
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use lazy_static::lazy_static;
+
+use super::events::{BroadcastEventType, Severity};
+
+/// Used when an alert has no `subject_template` configured
+pub const DEFAULT_SUBJECT_TEMPLATE: &str = "[PULSE] {retriggered}{subject}";
+
+lazy_static! {
+    static ref HOSTNAME: String = hostname::get()
+        .map(|hostname| hostname.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| "unknown".to_string());
+}
+
+/// Render a subject line template. Templates can use the variables
+/// `{hostname}`, `{event_type}`, `{severity}`, `{subject}` (the
+/// event's own subject) and `{retriggered}` (`Retriggered: ` if we've
+/// alerted for the event before, otherwise empty)
+pub fn render(
+    template: &str,
+    event_type: &BroadcastEventType,
+    severity: Severity,
+    subject: &str,
+    retriggered: bool,
+) -> String {
+    template
+        .replace("{hostname}", &HOSTNAME)
+        .replace("{event_type}", &event_type.to_string())
+        .replace("{severity}", &severity.to_string())
+        .replace(
+            "{retriggered}",
+            if retriggered { "Retriggered: " } else { "" },
+        )
+        .replace("{subject}", subject)
+}
@@ -0,0 +1,318 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use actix::{Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, WrapFuture};
+use actix_web::web;
+use futures::future::{BoxFuture, FutureExt};
+
+use crate::{
+    cache::cached,
+    config::{config, CryptoAssetConfig, CryptoConfig},
+    db::{self, models},
+    error::{Error, Result},
+    services::{
+        broadcast::{BroadcastEvent, BroadcastEventKey, Outbox, PriceTrigger},
+        http_get,
+    },
+};
+
+const SIMPLE_PRICE_URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+
+/// How often to check prices unless configured otherwise
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How long prices are cached for unless configured otherwise
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Prices are quoted in this currency unless configured otherwise
+const DEFAULT_CURRENCY: &str = "usd";
+
+trait CryptoPorts {
+    /// The current price and 24 hour change of each asset
+    fn fetch_prices(
+        &self,
+        assets: Vec<String>,
+        currency: String,
+    ) -> BoxFuture<'static, Result<Vec<models::NewCryptoPrice>>>;
+
+    fn record_price(&self, price: models::NewCryptoPrice) -> Result<()>;
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()>;
+}
+
+struct LiveCryptoPorts {
+    outbox: Outbox,
+}
+impl CryptoPorts for LiveCryptoPorts {
+    /// Prices are fetched on the blocking thread pool, so that a slow
+    /// api doesn't hold up the other actors
+    fn fetch_prices(
+        &self,
+        assets: Vec<String>,
+        currency: String,
+    ) -> BoxFuture<'static, Result<Vec<models::NewCryptoPrice>>> {
+        web::block(move || fetch_prices(&assets, &currency))
+            .map(|result| result.map_err(Into::into))
+            .boxed()
+    }
+
+    fn record_price(&self, price: models::NewCryptoPrice) -> Result<()> {
+        db::spawn_write("recording a crypto price", move |database| {
+            database.insert_crypto_price(price).map(|_| ())
+        });
+        Ok(())
+    }
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+        self.outbox.push(event)
+    }
+}
+
+fn fetch_prices(assets: &[String], currency: &str) -> Result<Vec<models::NewCryptoPrice>> {
+    let request = format!("{}:{}", assets.join(","), currency);
+    // e.g. {"bitcoin": {"usd": 9000.0, "usd_24h_change": 1.5}}
+    let prices: HashMap<String, HashMap<String, Option<f64>>> =
+        cached("crypto", &request, DEFAULT_CACHE_TTL, || {
+            let response = http_get(SIMPLE_PRICE_URL)
+                .query("ids", &assets.join(","))
+                .query("vs_currencies", currency)
+                .query("include_24hr_change", "true")
+                .call();
+            if !response.ok() {
+                return Err(Error::market_data(format!(
+                    "price request failed with status {}",
+                    response.status()
+                )));
+            }
+
+            response
+                .into_json_deserialize()
+                .map_err(|e| Error::market_data(e.to_string()))
+        })?;
+    Ok(prices
+        .into_iter()
+        .filter_map(|(asset, quote)| {
+            quote
+                .get(currency)
+                .cloned()
+                .flatten()
+                .map(|price| models::NewCryptoPrice {
+                    percent_change_24h: quote
+                        .get(&format!("{}_24h_change", currency))
+                        .cloned()
+                        .flatten(),
+                    asset,
+                    currency: currency.to_string(),
+                    price,
+                })
+        })
+        .collect())
+}
+
+/// Records cryptocurrency prices and alerts when they cross the
+/// configured thresholds
+pub struct Crypto {
+    config: CryptoConfig,
+    /// Alerts whose condition held as of the last check, so that each
+    /// only fires when its condition starts to hold
+    triggered: HashSet<BroadcastEventKey>,
+    ports: Box<dyn CryptoPorts>,
+}
+
+impl Crypto {
+    pub fn new(outbox: Outbox) -> Option<Self> {
+        config().crypto.map(|crypto_config| Self {
+            config: crypto_config,
+            triggered: HashSet::new(),
+            ports: Box::new(LiveCryptoPorts { outbox }),
+        })
+    }
+
+    #[cfg(test)]
+    fn test(config: CryptoConfig, ports: Box<dyn CryptoPorts>) -> Self {
+        Self {
+            config,
+            triggered: HashSet::new(),
+            ports,
+        }
+    }
+
+    fn currency(&self) -> &str {
+        self.config.currency.as_deref().unwrap_or(DEFAULT_CURRENCY)
+    }
+
+    /// Fetch the current prices in the background, then record them
+    fn check_prices(&mut self, ctx: &mut Context<Self>) {
+        let assets = self
+            .config
+            .assets
+            .iter()
+            .map(|asset| asset.id.clone())
+            .collect::<Vec<_>>();
+        self.ports
+            .fetch_prices(assets, self.currency().to_string())
+            .into_actor(self)
+            .map(|prices, this, _| {
+                prices
+                    .and_then(|prices| this.record_prices(prices))
+                    .unwrap_or_else(|e| log::error!("Error checking crypto prices: {}", e))
+            })
+            .spawn(ctx);
+    }
+
+    /// Record prices, alerting for the thresholds that they have just
+    /// crossed
+    fn record_prices(&mut self, prices: Vec<models::NewCryptoPrice>) -> Result<()> {
+        let mut triggered = HashSet::new();
+        for price in prices {
+            let asset_config = match self.config.assets.iter().find(|a| a.id == price.asset) {
+                Some(asset_config) => asset_config,
+                None => continue,
+            };
+
+            for trigger in triggers(asset_config, &price) {
+                let event = BroadcastEvent::CryptoPrice {
+                    asset: price.asset.clone(),
+                    currency: price.currency.clone(),
+                    price: price.price,
+                    percent_change_24h: price.percent_change_24h,
+                    trigger,
+                };
+                let event_key = event.event_key();
+                if !self.triggered.contains(&event_key) {
+                    self.ports.send_alert(event)?;
+                }
+                triggered.insert(event_key);
+            }
+
+            self.ports.record_price(price)?;
+        }
+        self.triggered = triggered;
+
+        Ok(())
+    }
+}
+
+/// The thresholds the price is past
+fn triggers(asset_config: &CryptoAssetConfig, price: &models::NewCryptoPrice) -> Vec<PriceTrigger> {
+    let mut triggers = vec![];
+    if let Some(above) = asset_config.above {
+        if price.price >= above {
+            triggers.push(PriceTrigger::Above(above));
+        }
+    }
+    if let Some(below) = asset_config.below {
+        if price.price <= below {
+            triggers.push(PriceTrigger::Below(below));
+        }
+    }
+    if let (Some(change), Some(percent_change)) =
+        (asset_config.change_alert_percent, price.percent_change_24h)
+    {
+        if percent_change.abs() >= change {
+            triggers.push(PriceTrigger::Change(change));
+        }
+    }
+    triggers
+}
+
+impl Actor for Crypto {
+    type Context = Context<Self>;
+
+    /// When the crypto actor is started, begin checking prices
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let interval = self.config.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+        ctx.run_interval(interval, |this, ctx| this.check_prices(ctx));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    struct TestCryptoPorts {
+        recorded_prices: Arc<Mutex<Vec<models::NewCryptoPrice>>>,
+        sent_alerts: Arc<Mutex<Vec<BroadcastEvent>>>,
+    }
+    impl CryptoPorts for TestCryptoPorts {
+        fn fetch_prices(
+            &self,
+            _: Vec<String>,
+            _: String,
+        ) -> BoxFuture<'static, Result<Vec<models::NewCryptoPrice>>> {
+            futures::future::ok(vec![]).boxed()
+        }
+
+        fn record_price(&self, price: models::NewCryptoPrice) -> Result<()> {
+            self.recorded_prices.lock().unwrap().push(price);
+            Ok(())
+        }
+
+        fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+            self.sent_alerts.lock().unwrap().push(event);
+            Ok(())
+        }
+    }
+
+    fn bitcoin(price: f64, percent_change_24h: f64) -> models::NewCryptoPrice {
+        models::NewCryptoPrice {
+            asset: "bitcoin".to_string(),
+            currency: "usd".to_string(),
+            price,
+            percent_change_24h: Some(percent_change_24h),
+        }
+    }
+
+    #[test]
+    fn crypto_alerts_when_prices_cross_thresholds() {
+        let recorded_prices = Arc::new(Mutex::new(vec![]));
+        let sent_alerts = Arc::new(Mutex::new(vec![]));
+        let config = CryptoConfig {
+            currency: None,
+            assets: vec![CryptoAssetConfig {
+                id: "bitcoin".to_string(),
+                above: Some(10_000.0),
+                below: Some(5_000.0),
+                change_alert_percent: Some(10.0),
+            }],
+            poll_interval: None,
+        };
+        let mut crypto = Crypto::test(
+            config,
+            Box::new(TestCryptoPorts {
+                recorded_prices: recorded_prices.clone(),
+                sent_alerts: sent_alerts.clone(),
+            }),
+        );
+
+        for price in vec![
+            bitcoin(8_000.0, 1.0),
+            bitcoin(11_000.0, 2.0),
+            bitcoin(12_000.0, 12.0),
+            bitcoin(9_000.0, 1.0),
+            bitcoin(4_000.0, -1.0),
+        ] {
+            crypto.record_prices(vec![price]).unwrap();
+        }
+
+        let alerted = sent_alerts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.event_key().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            alerted,
+            vec![
+                "crypto-price:bitcoin:above",
+                "crypto-price:bitcoin:change",
+                "crypto-price:bitcoin:below"
+            ]
+        );
+        assert_eq!(recorded_prices.lock().unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,250 @@
+use std::time::Duration;
+
+use actix::{Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, WrapFuture};
+use chrono::{NaiveDateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+
+use crate::{
+    config::{config, DatabaseConfig},
+    db,
+    error::Result,
+    services::broadcast::{BroadcastEvent, Outbox},
+};
+
+trait DatabaseMonitorPorts {
+    /// Check that the database is reachable, reconnecting if it isn't
+    fn check_connection(&self) -> BoxFuture<'static, Result<()>>;
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()>;
+}
+
+struct LiveDatabaseMonitorPorts {
+    outbox: Outbox,
+}
+impl DatabaseMonitorPorts for LiveDatabaseMonitorPorts {
+    fn check_connection(&self) -> BoxFuture<'static, Result<()>> {
+        db::run(|database| {
+            database.ping().or_else(|e| {
+                log::warn!("Database is unreachable, reconnecting: {}", e);
+                database.reconnect()?;
+                database.ping()
+            })
+        })
+        .boxed()
+    }
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+        self.outbox.push(event)
+    }
+}
+
+const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_ALERT_UNREACHABLE_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// The first wait before reconnecting, which doubles after each failed
+/// attempt
+const INITIAL_RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Checks the connection to the database, reconnects with a backoff
+/// while it is unreachable, and alerts if it stays unreachable
+pub struct DatabaseMonitor {
+    health_check_interval: Duration,
+    max_reconnect_backoff: Duration,
+    alert_unreachable_after: Duration,
+    /// When the database was first found to be unreachable, if it still
+    /// is
+    unreachable_since: Option<NaiveDateTime>,
+    failed_attempts: u32,
+    /// Whether the current outage has been alerted
+    alerted: bool,
+    ports: Box<dyn DatabaseMonitorPorts>,
+}
+impl DatabaseMonitor {
+    /// A monitor for the configured database. There is nothing to
+    /// monitor if data is kept in memory
+    pub fn new(outbox: Outbox) -> Option<Self> {
+        config().database.map(|database| {
+            Self::from_config(&database, Box::new(LiveDatabaseMonitorPorts { outbox }))
+        })
+    }
+
+    fn from_config(config: &DatabaseConfig, ports: Box<dyn DatabaseMonitorPorts>) -> Self {
+        Self {
+            health_check_interval: config
+                .health_check_interval
+                .unwrap_or(DEFAULT_HEALTH_CHECK_INTERVAL),
+            max_reconnect_backoff: config
+                .max_reconnect_backoff
+                .unwrap_or(DEFAULT_MAX_RECONNECT_BACKOFF),
+            alert_unreachable_after: config
+                .alert_unreachable_after
+                .unwrap_or(DEFAULT_ALERT_UNREACHABLE_AFTER),
+            unreachable_since: None,
+            failed_attempts: 0,
+            alerted: false,
+            ports,
+        }
+    }
+
+    fn check_connection(&self, ctx: &mut Context<Self>) {
+        self.ports
+            .check_connection()
+            .into_actor(self)
+            .map(|result, this, ctx| {
+                let delay = this.record_check(result, Utc::now().naive_utc());
+                ctx.run_later(delay, |this, ctx| this.check_connection(ctx));
+            })
+            .spawn(ctx);
+    }
+
+    /// Track an outage from the result of a check, alerting once it
+    /// has lasted long enough. Returns how long to wait before checking
+    /// again
+    fn record_check(&mut self, result: Result<()>, now: NaiveDateTime) -> Duration {
+        let error = match result {
+            Ok(()) => {
+                if let Some(since) = self.unreachable_since.take() {
+                    log::info!("Reconnected to the database, unreachable since {}", since);
+                }
+                self.failed_attempts = 0;
+                self.alerted = false;
+                return self.health_check_interval;
+            }
+            Err(e) => e,
+        };
+
+        let since = *self.unreachable_since.get_or_insert(now);
+        self.failed_attempts += 1;
+        log::error!(
+            "Failed to reconnect to the database ({} attempts): {}",
+            self.failed_attempts,
+            error
+        );
+
+        let unreachable_for = (now - since).to_std().unwrap_or_default();
+        if !self.alerted && unreachable_for >= self.alert_unreachable_after {
+            let event = BroadcastEvent::DatabaseUnreachable {
+                since,
+                error: error.to_string(),
+            };
+            match self.ports.send_alert(event) {
+                Ok(()) => self.alerted = true,
+                Err(e) => log::error!("Error sending database alert: {}", e),
+            }
+        }
+
+        let multiplier = 2_u32.saturating_pow(self.failed_attempts - 1);
+        INITIAL_RECONNECT_BACKOFF
+            .checked_mul(multiplier)
+            .map(|backoff| backoff.min(self.max_reconnect_backoff))
+            .unwrap_or(self.max_reconnect_backoff)
+    }
+}
+
+impl Actor for DatabaseMonitor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.run_later(self.health_check_interval, |this, ctx| {
+            this.check_connection(ctx)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::error::Error;
+
+    struct TestDatabaseMonitorPorts {
+        sent_alerts: Vec<BroadcastEvent>,
+    }
+    impl DatabaseMonitorPorts for Arc<Mutex<TestDatabaseMonitorPorts>> {
+        fn check_connection(&self) -> BoxFuture<'static, Result<()>> {
+            futures::future::ok(()).boxed()
+        }
+
+        fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+            self.lock().unwrap().sent_alerts.push(event);
+            Ok(())
+        }
+    }
+
+    fn monitor() -> (DatabaseMonitor, Arc<Mutex<TestDatabaseMonitorPorts>>) {
+        let ports = Arc::new(Mutex::new(TestDatabaseMonitorPorts {
+            sent_alerts: vec![],
+        }));
+        let mut config = crate::config::Config::default().database.unwrap();
+        config.health_check_interval = Some(Duration::from_secs(30));
+        config.max_reconnect_backoff = Some(Duration::from_secs(10));
+        config.alert_unreachable_after = Some(Duration::from_secs(60));
+        let monitor = DatabaseMonitor::from_config(&config, Box::new(ports.clone()));
+        (monitor, ports)
+    }
+
+    fn unreachable() -> Result<()> {
+        Err(Error::from(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "connection refused",
+        )))
+    }
+
+    #[test]
+    fn database_monitor_backs_off_while_unreachable() {
+        let (mut monitor, _) = monitor();
+        let now = Utc::now().naive_utc();
+
+        let delays = (0..6)
+            .map(|_| monitor.record_check(unreachable(), now))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            delays,
+            vec![1, 2, 4, 8, 10, 10]
+                .into_iter()
+                .map(Duration::from_secs)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(monitor.record_check(Ok(()), now), Duration::from_secs(30));
+        assert_eq!(
+            monitor.record_check(unreachable(), now),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn database_monitor_alerts_once_when_unreachable_too_long() {
+        let (mut monitor, ports) = monitor();
+        let start = Utc::now().naive_utc();
+
+        monitor.record_check(unreachable(), start);
+        monitor.record_check(unreachable(), start + chrono::Duration::seconds(30));
+        assert!(ports.lock().unwrap().sent_alerts.is_empty());
+
+        monitor.record_check(unreachable(), start + chrono::Duration::seconds(60));
+        monitor.record_check(unreachable(), start + chrono::Duration::seconds(90));
+        let alerts = ports.lock().unwrap().sent_alerts.clone();
+        assert_eq!(alerts.len(), 1);
+        match &alerts[0] {
+            BroadcastEvent::DatabaseUnreachable { since, .. } => assert_eq!(*since, start),
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn database_monitor_alerts_again_after_recovering() {
+        let (mut monitor, ports) = monitor();
+        let start = Utc::now().naive_utc();
+
+        monitor.record_check(unreachable(), start);
+        monitor.record_check(unreachable(), start + chrono::Duration::seconds(60));
+        monitor.record_check(Ok(()), start + chrono::Duration::seconds(90));
+        monitor.record_check(unreachable(), start + chrono::Duration::seconds(120));
+        monitor.record_check(unreachable(), start + chrono::Duration::seconds(180));
+
+        assert_eq!(ports.lock().unwrap().sent_alerts.len(), 2);
+    }
+}
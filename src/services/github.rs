@@ -0,0 +1,431 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use actix::{Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, WrapFuture};
+use actix_web::web;
+use futures::future::{BoxFuture, FutureExt};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    config::{config, GitHubConfig},
+    db::{self, models},
+    error::{Error, Result},
+    services::{
+        broadcast::{BroadcastEvent, Outbox},
+        http_get,
+    },
+};
+
+const API_URL: &str = "https://api.github.com";
+
+/// GitHub requires api clients to identify themselves
+const USER_AGENT: &str = concat!("pulse/", env!("CARGO_PKG_VERSION"));
+
+/// How often to poll github unless configured otherwise
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+const RELEASE: &str = "release";
+const TAG: &str = "tag";
+const NOTIFICATION: &str = "notification";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Release {
+    pub id: u64,
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub html_url: String,
+    pub draft: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tag {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub reason: String,
+    pub subject: NotificationSubject,
+    pub repository: NotificationRepository,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NotificationSubject {
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NotificationRepository {
+    pub full_name: String,
+    pub html_url: String,
+}
+
+/// The releases and tags of a watched repository
+#[derive(Clone, Debug, Default)]
+struct RepositoryItems {
+    repository: String,
+    releases: Vec<Release>,
+    tags: Vec<Tag>,
+}
+
+/// Everything fetched from github in a single poll
+#[derive(Clone, Debug, Default)]
+struct Fetched {
+    repositories: Vec<RepositoryItems>,
+    notifications: Vec<Notification>,
+}
+
+fn get<T: DeserializeOwned>(token: Option<&str>, path: &str) -> Result<T> {
+    let mut request = http_get(&format!("{}{}", API_URL, path));
+    request.set("User-Agent", USER_AGENT);
+    if let Some(token) = token {
+        request.set("Authorization", &format!("token {}", token));
+    }
+
+    let response = request.call();
+    if !response.ok() {
+        return Err(Error::github(format!(
+            "request for {} failed with status {}",
+            path,
+            response.status()
+        )));
+    }
+
+    response
+        .into_json_deserialize()
+        .map_err(|e| Error::github(e.to_string()))
+}
+
+/// Fetch the releases (and optionally tags) of each repository, and
+/// optionally the notifications of the authenticated user
+fn fetch(token: Option<&str>, config: &GitHubConfig) -> Result<Fetched> {
+    let mut fetched = Fetched::default();
+    for repository in &config.repositories {
+        let releases = get(token, &format!("/repos/{}/releases", repository))?;
+        let tags = if config.tags.unwrap_or(false) {
+            get(token, &format!("/repos/{}/tags", repository))?
+        } else {
+            vec![]
+        };
+        fetched.repositories.push(RepositoryItems {
+            repository: repository.clone(),
+            releases,
+            tags,
+        });
+    }
+
+    if config.notifications.unwrap_or(false) {
+        fetched.notifications = get(token, "/notifications")?;
+    }
+
+    Ok(fetched)
+}
+
+trait GitHubPorts {
+    fn fetch(&self, config: GitHubConfig) -> BoxFuture<'static, Result<Fetched>>;
+    fn seen(&self) -> BoxFuture<'static, Result<Vec<models::GitHubSeen>>>;
+    fn mark_seen(&self, seen: models::NewGitHubSeen) -> Result<()>;
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()>;
+}
+
+struct LiveGitHubPorts {
+    token: Option<String>,
+    outbox: Outbox,
+}
+impl GitHubPorts for LiveGitHubPorts {
+    /// Github is polled on the blocking thread pool, so that a slow api
+    /// doesn't hold up the other actors
+    fn fetch(&self, config: GitHubConfig) -> BoxFuture<'static, Result<Fetched>> {
+        let token = self.token.clone();
+        web::block(move || fetch(token.as_deref(), &config))
+            .map(|result| result.map_err(Into::into))
+            .boxed()
+    }
+
+    fn seen(&self) -> BoxFuture<'static, Result<Vec<models::GitHubSeen>>> {
+        db::run(|database| database.github_seen()).boxed()
+    }
+
+    fn mark_seen(&self, seen: models::NewGitHubSeen) -> Result<()> {
+        db::spawn_write("recording a seen github item", move |database| {
+            database.insert_github_seen(seen)
+        });
+        Ok(())
+    }
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+        self.outbox.push(event)
+    }
+}
+
+/// Polls github for new releases and tags in the configured
+/// repositories, and optionally for notifications, broadcasting each
+/// one once
+pub struct GitHub {
+    config: GitHubConfig,
+    /// Ids of the items already seen for each kind of item
+    seen: HashMap<&'static str, HashSet<String>>,
+    /// Repositories whose existing releases and tags have been
+    /// recorded, so that only releases published since are broadcast
+    watched: HashSet<String>,
+    ports: Box<dyn GitHubPorts>,
+}
+
+impl GitHub {
+    pub fn new(outbox: Outbox) -> Option<Self> {
+        config().github.map(|github_config| {
+            Self::with_ports(
+                github_config.clone(),
+                Box::new(LiveGitHubPorts {
+                    token: github_config.token,
+                    outbox,
+                }),
+            )
+        })
+    }
+
+    fn with_ports(config: GitHubConfig, ports: Box<dyn GitHubPorts>) -> Self {
+        Self {
+            config,
+            seen: HashMap::new(),
+            watched: HashSet::new(),
+            ports,
+        }
+    }
+
+    /// Load the items seen before pulse was last stopped
+    fn load_seen(&mut self, seen: Vec<models::GitHubSeen>) {
+        for seen in seen {
+            let kind = match seen.kind.as_str() {
+                RELEASE => RELEASE,
+                TAG => TAG,
+                _ => NOTIFICATION,
+            };
+            if kind != NOTIFICATION {
+                self.watched.insert(seen.repository);
+            }
+            self.seen.entry(kind).or_default().insert(seen.item_id);
+        }
+    }
+
+    /// Fetch from github in the background, then broadcast the new
+    /// items
+    fn poll(&mut self, ctx: &mut Context<Self>) {
+        self.ports
+            .fetch(self.config.clone())
+            .into_actor(self)
+            .map(|fetched, this, _| {
+                fetched
+                    .and_then(|fetched| this.broadcast_fetched(fetched))
+                    .unwrap_or_else(|e| log::error!("Error polling github: {}", e))
+            })
+            .spawn(ctx);
+    }
+
+    fn broadcast_fetched(&mut self, fetched: Fetched) -> Result<()> {
+        for RepositoryItems {
+            repository,
+            releases,
+            tags,
+        } in fetched.repositories
+        {
+            let release_tags = releases
+                .iter()
+                .map(|release| release.tag_name.clone())
+                .collect::<HashSet<_>>();
+            for release in releases.into_iter().filter(|release| !release.draft) {
+                let event = BroadcastEvent::GithubRelease {
+                    repository: repository.clone(),
+                    tag: release.tag_name,
+                    name: release.name,
+                    url: release.html_url,
+                };
+                self.broadcast_new(RELEASE, &repository, release.id.to_string(), event)?;
+            }
+
+            // tags that are part of a release were broadcast with it
+            for tag in tags
+                .into_iter()
+                .filter(|tag| !release_tags.contains(&tag.name))
+            {
+                let event = BroadcastEvent::GithubRelease {
+                    url: format!(
+                        "https://github.com/{}/releases/tag/{}",
+                        repository, tag.name
+                    ),
+                    repository: repository.clone(),
+                    tag: tag.name.clone(),
+                    name: None,
+                };
+                self.broadcast_new(TAG, &repository, tag.name, event)?;
+            }
+
+            self.watched.insert(repository);
+        }
+
+        for notification in fetched.notifications {
+            let event = BroadcastEvent::GithubNotification {
+                repository: notification.repository.full_name.clone(),
+                title: notification.subject.title,
+                reason: notification.reason,
+                url: notification.repository.html_url,
+            };
+            self.broadcast_new(
+                NOTIFICATION,
+                &notification.repository.full_name,
+                notification.id,
+                event,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Broadcast an item if it hasn't been seen before. Releases and
+    /// tags in a repository that is polled for the first time are
+    /// recorded without being broadcast
+    fn broadcast_new(
+        &mut self,
+        kind: &'static str,
+        repository: &str,
+        item_id: String,
+        event: BroadcastEvent,
+    ) -> Result<()> {
+        if self
+            .seen
+            .get(kind)
+            .map_or(false, |seen| seen.contains(&item_id))
+        {
+            return Ok(());
+        }
+
+        if kind == NOTIFICATION || self.watched.contains(repository) {
+            self.ports.send_alert(event)?;
+        }
+        self.ports.mark_seen(models::NewGitHubSeen {
+            kind: kind.to_string(),
+            repository: repository.to_string(),
+            item_id: item_id.clone(),
+        })?;
+        self.seen.entry(kind).or_default().insert(item_id);
+
+        Ok(())
+    }
+}
+
+impl Actor for GitHub {
+    type Context = Context<Self>;
+
+    /// When the github actor is started, begin polling
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        // polling waits until the seen items have loaded
+        self.ports
+            .seen()
+            .into_actor(self)
+            .map(|seen, this, _| match seen {
+                Ok(seen) => this.load_seen(seen),
+                Err(e) => log::error!("Error loading seen github items: {}", e),
+            })
+            .wait(ctx);
+
+        let interval = self.config.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+        ctx.run_interval(interval, |this, ctx| this.poll(ctx));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    struct TestGitHubPorts {
+        seen: Arc<Mutex<Vec<models::NewGitHubSeen>>>,
+        sent_alerts: Arc<Mutex<Vec<BroadcastEvent>>>,
+    }
+    impl GitHubPorts for TestGitHubPorts {
+        fn fetch(&self, _: GitHubConfig) -> BoxFuture<'static, Result<Fetched>> {
+            futures::future::ok(Fetched::default()).boxed()
+        }
+
+        fn seen(&self) -> BoxFuture<'static, Result<Vec<models::GitHubSeen>>> {
+            futures::future::ok(vec![]).boxed()
+        }
+
+        fn mark_seen(&self, seen: models::NewGitHubSeen) -> Result<()> {
+            self.seen.lock().unwrap().push(seen);
+            Ok(())
+        }
+
+        fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+            self.sent_alerts.lock().unwrap().push(event);
+            Ok(())
+        }
+    }
+
+    fn release(id: u64, tag_name: &str) -> Release {
+        Release {
+            id,
+            tag_name: tag_name.to_string(),
+            name: None,
+            html_url: format!(
+                "https://github.com/rust-lang/rust/releases/tag/{}",
+                tag_name
+            ),
+            draft: false,
+        }
+    }
+
+    fn fetched(releases: Vec<Release>) -> Fetched {
+        Fetched {
+            repositories: vec![RepositoryItems {
+                repository: "rust-lang/rust".to_string(),
+                releases,
+                tags: vec![],
+            }],
+            notifications: vec![],
+        }
+    }
+
+    #[test]
+    fn github_broadcasts_each_new_release_once() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let sent_alerts = Arc::new(Mutex::new(vec![]));
+        let config = GitHubConfig {
+            token: None,
+            repositories: vec!["rust-lang/rust".to_string()],
+            tags: None,
+            notifications: None,
+            poll_interval: None,
+        };
+        let mut github = GitHub::with_ports(
+            config,
+            Box::new(TestGitHubPorts {
+                seen: seen.clone(),
+                sent_alerts: sent_alerts.clone(),
+            }),
+        );
+
+        // existing releases are recorded without being broadcast
+        github
+            .broadcast_fetched(fetched(vec![release(1, "1.40.0")]))
+            .unwrap();
+        assert!(sent_alerts.lock().unwrap().is_empty());
+
+        let releases = vec![release(1, "1.40.0"), release(2, "1.41.0")];
+        github.broadcast_fetched(fetched(releases.clone())).unwrap();
+        github.broadcast_fetched(fetched(releases)).unwrap();
+
+        let alerted = sent_alerts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.event_key().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(alerted, vec!["github-release:rust-lang/rust:1.41.0"]);
+        assert_eq!(seen.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,373 @@
+use std::{
+    collections::HashSet,
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use actix::{Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, WrapFuture};
+use actix_web::web;
+use futures::future::{BoxFuture, FutureExt};
+use imap::{types::Fetch, Client, Session};
+use native_tls::TlsStream;
+
+use crate::{
+    config::{config, MailWatchConfig, MailboxConfig},
+    error::{Error, Result},
+    services::broadcast::{BroadcastEvent, Outbox},
+};
+
+/// How often to check the mailbox unless configured otherwise
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+const DEFAULT_PORT: u16 = 993;
+const DEFAULT_MAILBOX: &str = "INBOX";
+const DEFAULT_SEARCH: &str = "ALL";
+
+/// How long to wait on the IMAP server before giving up on a poll
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An unread message in the mailbox
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub uid: u32,
+    pub from: String,
+    pub subject: String,
+}
+
+/// The messages matching the configured search criteria
+#[derive(Clone, Debug)]
+pub struct MailboxStatus {
+    pub flagged: usize,
+    pub unread: Vec<Message>,
+}
+
+trait MailboxPorts {
+    fn fetch_status(&self) -> BoxFuture<'static, Result<MailboxStatus>>;
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()>;
+}
+
+struct LiveMailboxPorts {
+    config: MailboxConfig,
+    outbox: Outbox,
+}
+impl MailboxPorts for LiveMailboxPorts {
+    /// The mailbox is polled on the blocking thread pool, so that a
+    /// slow server doesn't hold up the other actors
+    fn fetch_status(&self) -> BoxFuture<'static, Result<MailboxStatus>> {
+        let config = self.config.clone();
+        web::block(move || fetch_status(&config))
+            .map(|result| result.map_err(Into::into))
+            .boxed()
+    }
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+        self.outbox.push(event)
+    }
+}
+
+fn fetch_status(config: &MailboxConfig) -> Result<MailboxStatus> {
+    let mut session = connect(config)?
+        .login(&config.username, &config.password)
+        .map_err(|(e, _)| Error::imap(e.to_string()))?;
+
+    let status = read_status(&mut session, config).map_err(|e| Error::imap(e.to_string()));
+
+    session
+        .logout()
+        .unwrap_or_else(|e| log::warn!("Error logging out of the mailbox: {}", e));
+    status
+}
+
+/// Connect to the IMAP server, timing out reads and writes so that an
+/// unresponsive server can't hold a blocking thread forever
+fn connect(config: &MailboxConfig) -> Result<Client<TlsStream<TcpStream>>> {
+    let imap_error = |e: &dyn std::fmt::Display| Error::imap(e.to_string());
+
+    let address = (config.host.as_str(), config.port.unwrap_or(DEFAULT_PORT))
+        .to_socket_addrs()
+        .map_err(|e| imap_error(&e))?
+        .next()
+        .ok_or_else(|| Error::imap(format!("could not resolve {}", config.host)))?;
+    let stream = TcpStream::connect_timeout(&address, TIMEOUT).map_err(|e| imap_error(&e))?;
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+        .map_err(|e| imap_error(&e))?;
+
+    let tls = native_tls::TlsConnector::new().map_err(|e| imap_error(&e))?;
+    let stream = tls
+        .connect(&config.host, stream)
+        .map_err(|e| imap_error(&e))?;
+
+    let mut client = Client::new(stream);
+    client.read_greeting().map_err(|e| imap_error(&e))?;
+    Ok(client)
+}
+
+/// Counts the flagged messages and fetches the unread ones, without
+/// marking them as read
+fn read_status(
+    session: &mut Session<TlsStream<TcpStream>>,
+    config: &MailboxConfig,
+) -> imap::error::Result<MailboxStatus> {
+    session.examine(config.mailbox.as_deref().unwrap_or(DEFAULT_MAILBOX))?;
+
+    let search = config.search.as_deref().unwrap_or(DEFAULT_SEARCH);
+    let flagged = session.uid_search(format!("FLAGGED {}", search))?.len();
+    let unread_uids = session
+        .uid_search(format!("UNSEEN {}", search))?
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>();
+    if unread_uids.is_empty() {
+        return Ok(MailboxStatus {
+            flagged,
+            unread: vec![],
+        });
+    }
+
+    let unread = session
+        .uid_fetch(unread_uids.join(","), "ENVELOPE")?
+        .iter()
+        .filter_map(message)
+        .collect();
+    Ok(MailboxStatus { flagged, unread })
+}
+
+/// The sender and subject of a fetched message
+fn message(fetch: &Fetch) -> Option<Message> {
+    let envelope = fetch.envelope()?;
+    let text = |bytes: Option<&[u8]>| {
+        bytes
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+            .unwrap_or_default()
+    };
+    let from = envelope
+        .from
+        .as_ref()
+        .and_then(|from| from.first())
+        .map(|address| format!("{}@{}", text(address.mailbox), text(address.host)))
+        .unwrap_or_default();
+
+    Some(Message {
+        uid: fetch.uid?,
+        from,
+        subject: text(envelope.subject),
+    })
+}
+
+/// Watches an IMAP mailbox, alerting when unread messages from watched
+/// senders or with watched subjects arrive and when the number of
+/// unread messages passes `max_unread`
+pub struct Mailbox {
+    config: MailboxConfig,
+    /// Uids of the messages that have already been alerted
+    alerted: HashSet<u32>,
+    /// Whether the unread count is over `max_unread`, so that it is
+    /// only alerted as it crosses
+    over_max_unread: bool,
+    ports: Box<dyn MailboxPorts>,
+}
+
+impl Mailbox {
+    pub fn new(outbox: Outbox) -> Option<Self> {
+        config().mailbox.map(|mailbox_config| {
+            Self::with_ports(
+                mailbox_config.clone(),
+                Box::new(LiveMailboxPorts {
+                    config: mailbox_config,
+                    outbox,
+                }),
+            )
+        })
+    }
+
+    fn with_ports(config: MailboxConfig, ports: Box<dyn MailboxPorts>) -> Self {
+        Self {
+            config,
+            alerted: HashSet::new(),
+            over_max_unread: false,
+            ports,
+        }
+    }
+
+    fn mailbox_name(&self) -> String {
+        format!(
+            "{}/{}",
+            self.config.username,
+            self.config.mailbox.as_deref().unwrap_or(DEFAULT_MAILBOX)
+        )
+    }
+
+    /// Fetch the mailbox status in the background, then alert on it
+    fn poll(&mut self, ctx: &mut Context<Self>) {
+        self.ports
+            .fetch_status()
+            .into_actor(self)
+            .map(|status, this, _| {
+                status
+                    .and_then(|status| this.check_status(status))
+                    .unwrap_or_else(|e| log::error!("Error polling mailbox: {}", e))
+            })
+            .spawn(ctx);
+    }
+
+    fn check_status(&mut self, status: MailboxStatus) -> Result<()> {
+        log::info!(
+            "{} has {} unread and {} flagged messages",
+            self.mailbox_name(),
+            status.unread.len(),
+            status.flagged
+        );
+
+        for message in &status.unread {
+            let watch = match self
+                .config
+                .watches
+                .iter()
+                .find(|watch| watch.matches(message))
+            {
+                Some(watch) => watch,
+                None => continue,
+            };
+            if self.alerted.insert(message.uid) {
+                self.ports.send_alert(BroadcastEvent::MailReceived {
+                    mailbox: self.mailbox_name(),
+                    watch: watch.name.clone(),
+                    uid: message.uid,
+                    from: message.from.clone(),
+                    subject: message.subject.clone(),
+                })?;
+            }
+        }
+        // messages that have been read may be alerted again if they are
+        // marked unread
+        let unread = status
+            .unread
+            .iter()
+            .map(|message| message.uid)
+            .collect::<HashSet<_>>();
+        self.alerted.retain(|uid| unread.contains(uid));
+
+        if let Some(max_unread) = self.config.max_unread {
+            let over_max_unread = status.unread.len() > max_unread;
+            if over_max_unread && !self.over_max_unread {
+                self.ports.send_alert(BroadcastEvent::UnreadMail {
+                    mailbox: self.mailbox_name(),
+                    unread: status.unread.len(),
+                    flagged: status.flagged,
+                    max_unread,
+                })?;
+            }
+            self.over_max_unread = over_max_unread;
+        }
+
+        Ok(())
+    }
+}
+
+impl MailWatchConfig {
+    /// Senders and subjects match if they contain the configured text,
+    /// ignoring case. Every configured field must match
+    fn matches(&self, message: &Message) -> bool {
+        let contains = |text: &str, pattern: &Option<String>| {
+            pattern.as_ref().map_or(true, |pattern| {
+                text.to_lowercase().contains(&pattern.to_lowercase())
+            })
+        };
+        contains(&message.from, &self.from) && contains(&message.subject, &self.subject)
+    }
+}
+
+impl Actor for Mailbox {
+    type Context = Context<Self>;
+
+    /// When the mailbox actor is started, begin polling
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let interval = self.config.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+        ctx.run_interval(interval, |this, ctx| this.poll(ctx));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    struct TestMailboxPorts {
+        sent_alerts: Arc<Mutex<Vec<BroadcastEvent>>>,
+    }
+    impl MailboxPorts for TestMailboxPorts {
+        fn fetch_status(&self) -> BoxFuture<'static, Result<MailboxStatus>> {
+            futures::future::ok(status(vec![])).boxed()
+        }
+
+        fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+            self.sent_alerts.lock().unwrap().push(event);
+            Ok(())
+        }
+    }
+
+    fn message(uid: u32, from: &str, subject: &str) -> Message {
+        Message {
+            uid,
+            from: from.to_string(),
+            subject: subject.to_string(),
+        }
+    }
+
+    fn status(unread: Vec<Message>) -> MailboxStatus {
+        MailboxStatus { flagged: 0, unread }
+    }
+
+    #[test]
+    fn mailbox_alerts_once_for_watched_messages() {
+        let mut unread = vec![
+            message(1, "news@example.com", "Weekly newsletter"),
+            message(2, "support@vps.example.com", "Incident: network outage"),
+        ];
+        let sent_alerts = Arc::new(Mutex::new(vec![]));
+        let config = MailboxConfig {
+            host: "imap.example.com".to_string(),
+            port: None,
+            username: "me@example.com".to_string(),
+            password: "password".to_string(),
+            mailbox: None,
+            search: None,
+            watches: vec![MailWatchConfig {
+                name: "vps incidents".to_string(),
+                from: Some("@vps.example.com".to_string()),
+                subject: Some("incident".to_string()),
+            }],
+            max_unread: Some(2),
+            poll_interval: None,
+        };
+        let mut mailbox = Mailbox::with_ports(
+            config,
+            Box::new(TestMailboxPorts {
+                sent_alerts: sent_alerts.clone(),
+            }),
+        );
+
+        mailbox.check_status(status(unread.clone())).unwrap();
+        mailbox.check_status(status(unread.clone())).unwrap();
+        unread.push(message(3, "friend@example.com", "Lunch?"));
+        mailbox.check_status(status(unread.clone())).unwrap();
+        mailbox.check_status(status(unread)).unwrap();
+
+        let alerted = sent_alerts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.event_key().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            alerted,
+            vec![
+                "mail-received:me@example.com/INBOX:2",
+                "unread-mail:me@example.com/INBOX",
+            ]
+        );
+    }
+}
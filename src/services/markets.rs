@@ -0,0 +1,341 @@
+use std::{collections::HashMap, time::Duration};
+
+use actix::{Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, WrapFuture};
+use actix_web::web;
+use chrono::{Local, NaiveDate};
+use futures::{
+    future::{self, BoxFuture, FutureExt},
+    Future,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::cached,
+    config::{config, MarketsConfig},
+    error::{Error, Result},
+    services::{
+        broadcast::{BroadcastEvent, Outbox},
+        http_get,
+    },
+};
+
+const QUOTE_URL: &str = "https://finnhub.io/api/v1/quote";
+
+/// How often to check for intraday moves unless configured otherwise
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How long quotes are cached for unless configured otherwise
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Serialize)]
+struct QuoteResponse {
+    /// Current price
+    c: f64,
+    /// Previous close
+    pc: f64,
+}
+
+/// The latest price of a stock, compared to its previous close
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct StockQuote {
+    pub ticker: String,
+    pub price: f64,
+    pub previous_close: f64,
+}
+
+impl StockQuote {
+    pub fn percent_change(&self) -> f64 {
+        (self.price - self.previous_close) / self.previous_close * 100.0
+    }
+
+    /// A one line description, e.g. `AAPL: 123.45 (+1.20%)`
+    pub fn describe(&self) -> String {
+        format!(
+            "{}: {:.2} ({:+.2}%)",
+            self.ticker,
+            self.price,
+            self.percent_change()
+        )
+    }
+}
+
+/// Fetch the latest quote for a ticker from Finnhub
+pub fn fetch_quote(config: &MarketsConfig, ticker: &str) -> Result<StockQuote> {
+    let request = format!("{}:{}", ticker, config.api_key);
+    let quote: QuoteResponse = cached("markets", &request, DEFAULT_CACHE_TTL, || {
+        let response = http_get(QUOTE_URL)
+            .query("symbol", ticker)
+            .query("token", &config.api_key)
+            .call();
+        if !response.ok() {
+            return Err(Error::market_data(format!(
+                "request for {} failed with status {}",
+                ticker,
+                response.status()
+            )));
+        }
+
+        response
+            .into_json_deserialize()
+            .map_err(|e| Error::market_data(e.to_string()))
+    })?;
+    // unknown tickers get an empty quote rather than an error
+    if quote.pc == 0.0 {
+        return Err(Error::market_data(format!("no quote for {}", ticker)));
+    }
+
+    Ok(StockQuote {
+        ticker: ticker.to_string(),
+        price: quote.c,
+        previous_close: quote.pc,
+    })
+}
+
+/// Quotes for every configured ticker, for the newscast, fetched on
+/// the blocking thread pool. Tickers that can't be fetched are skipped
+/// so that they don't hold up the rest of the newscast
+pub fn fetch_quotes(config: MarketsConfig) -> impl Future<Output = Vec<StockQuote>> {
+    web::block(move || {
+        Ok::<_, Error>(
+            config
+                .tickers
+                .iter()
+                .filter_map(|ticker| {
+                    fetch_quote(&config, ticker)
+                        .map_err(|e| log::warn!("Error fetching quote for {}: {}", ticker, e))
+                        .ok()
+                })
+                .collect(),
+        )
+    })
+    .map(|quotes| {
+        quotes.unwrap_or_else(|e| {
+            log::warn!("Error fetching quotes: {}", e);
+            vec![]
+        })
+    })
+}
+
+trait MarketsPorts {
+    fn fetch_quote(&self, ticker: String) -> BoxFuture<'static, Result<StockQuote>>;
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()>;
+}
+
+struct LiveMarketsPorts {
+    config: MarketsConfig,
+    outbox: Outbox,
+}
+impl MarketsPorts for LiveMarketsPorts {
+    /// Quotes are fetched on the blocking thread pool, so that a slow
+    /// api doesn't hold up the other actors
+    fn fetch_quote(&self, ticker: String) -> BoxFuture<'static, Result<StockQuote>> {
+        let config = self.config.clone();
+        web::block(move || fetch_quote(&config, &ticker))
+            .map(|result| result.map_err(Into::into))
+            .boxed()
+    }
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+        self.outbox.push(event)
+    }
+}
+
+/// Watches the configured tickers and alerts when one moves more than
+/// the configured percent from its previous close
+pub struct Markets {
+    config: MarketsConfig,
+    /// The day each ticker was last alerted for, so that each ticker
+    /// alerts at most once a day
+    alerted: HashMap<String, NaiveDate>,
+    ports: Box<dyn MarketsPorts>,
+}
+
+impl Markets {
+    pub fn new(outbox: Outbox) -> Option<Self> {
+        config().markets.map(|markets_config| Self {
+            ports: Box::new(LiveMarketsPorts {
+                config: markets_config.clone(),
+                outbox,
+            }),
+            config: markets_config,
+            alerted: HashMap::new(),
+        })
+    }
+
+    #[cfg(test)]
+    fn test(config: MarketsConfig, ports: Box<dyn MarketsPorts>) -> Self {
+        Self {
+            config,
+            alerted: HashMap::new(),
+            ports,
+        }
+    }
+
+    /// Fetch quotes for the tickers that haven't been alerted for
+    /// today in the background, then alert on their moves
+    fn check_moves(&mut self, ctx: &mut Context<Self>, alert_percent: f64, today: NaiveDate) {
+        let quotes = self
+            .config
+            .tickers
+            .iter()
+            .filter(|ticker| self.alerted.get(*ticker) != Some(&today))
+            .map(|ticker| {
+                let ticker = ticker.clone();
+                self.ports
+                    .fetch_quote(ticker.clone())
+                    .map(move |quote| (ticker, quote))
+            })
+            .collect::<Vec<_>>();
+
+        future::join_all(quotes)
+            .into_actor(self)
+            .map(move |quotes, this, _| this.alert_moves(alert_percent, today, quotes))
+            .spawn(ctx);
+    }
+
+    /// Alert for each ticker that has moved more than `alert_percent`,
+    /// at most once a day
+    fn alert_moves(
+        &mut self,
+        alert_percent: f64,
+        today: NaiveDate,
+        quotes: Vec<(String, Result<StockQuote>)>,
+    ) {
+        for (ticker, quote) in quotes {
+            if self.alerted.get(&ticker) == Some(&today) {
+                continue;
+            }
+
+            let quote = match quote {
+                Ok(quote) => quote,
+                Err(e) => {
+                    log::warn!("Error fetching quote for {}: {}", ticker, e);
+                    continue;
+                }
+            };
+
+            if quote.percent_change().abs() >= alert_percent {
+                let event = BroadcastEvent::StockMove {
+                    ticker: quote.ticker.clone(),
+                    price: quote.price,
+                    previous_close: quote.previous_close,
+                    alert_percent,
+                };
+                match self.ports.send_alert(event) {
+                    Ok(()) => {
+                        self.alerted.insert(ticker, today);
+                    }
+                    Err(e) => log::error!("Error sending stock move alert: {}", e),
+                }
+            }
+        }
+    }
+}
+
+impl Actor for Markets {
+    type Context = Context<Self>;
+
+    /// When the markets actor is started, begin checking for intraday
+    /// moves if they should be alerted
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        if let Some(alert_percent) = self.config.move_alert_percent {
+            let interval = self.config.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+            ctx.run_interval(interval, move |this, ctx| {
+                this.check_moves(ctx, alert_percent, Local::today().naive_local())
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    struct TestMarketsPorts {
+        quotes: HashMap<String, StockQuote>,
+        sent_alerts: Arc<Mutex<Vec<BroadcastEvent>>>,
+    }
+    impl MarketsPorts for TestMarketsPorts {
+        fn fetch_quote(&self, ticker: String) -> BoxFuture<'static, Result<StockQuote>> {
+            future::ready(quote_for(&self.quotes, &ticker)).boxed()
+        }
+
+        fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+            self.sent_alerts.lock().unwrap().push(event);
+            Ok(())
+        }
+    }
+
+    fn quote_for(quotes: &HashMap<String, StockQuote>, ticker: &str) -> Result<StockQuote> {
+        quotes
+            .get(ticker)
+            .cloned()
+            .ok_or_else(|| Error::market_data(format!("no quote for {}", ticker)))
+    }
+
+    fn quote(ticker: &str, price: f64) -> (String, StockQuote) {
+        (
+            ticker.to_string(),
+            StockQuote {
+                ticker: ticker.to_string(),
+                price,
+                previous_close: 100.0,
+            },
+        )
+    }
+
+    #[test]
+    fn markets_alert_once_a_day_on_large_moves() {
+        let sent_alerts = Arc::new(Mutex::new(vec![]));
+        let config = MarketsConfig {
+            api_key: "key".to_string(),
+            tickers: vec!["UP".to_string(), "DOWN".to_string(), "FLAT".to_string()],
+            include_in_digest: None,
+            move_alert_percent: Some(5.0),
+            poll_interval: None,
+        };
+        let quotes: HashMap<_, _> = vec![
+            quote("UP", 106.0),
+            quote("DOWN", 90.0),
+            quote("FLAT", 101.0),
+        ]
+        .into_iter()
+        .collect();
+        let fetched = || {
+            config
+                .tickers
+                .iter()
+                .map(|ticker| (ticker.clone(), quote_for(&quotes, ticker)))
+                .collect::<Vec<_>>()
+        };
+        let ports = TestMarketsPorts {
+            quotes: quotes.clone(),
+            sent_alerts: sent_alerts.clone(),
+        };
+        let mut markets = Markets::test(config.clone(), Box::new(ports));
+
+        let today = NaiveDate::from_ymd(2020, 3, 16);
+        markets.alert_moves(5.0, today, fetched());
+        markets.alert_moves(5.0, today, fetched());
+        markets.alert_moves(5.0, today.succ(), fetched());
+
+        let alerted = sent_alerts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.event_key().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            alerted,
+            vec![
+                "stock-move:UP",
+                "stock-move:DOWN",
+                "stock-move:UP",
+                "stock-move:DOWN"
+            ]
+        );
+    }
+}
@@ -1,23 +1,86 @@
+mod calendar;
+mod feeds;
+mod guardian;
+mod new_york_times;
+mod reddit;
+mod top_stories;
+mod weather;
+mod youtube;
+
+use std::{sync::Arc, time::Duration};
+
 use actix::prelude::*;
-use chrono::NaiveDate;
-use nytrs::NewYorkTimes;
+use actix_web::web;
+use chrono::{NaiveDate, Utc};
+use futures::{future, Future, FutureExt};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     config::{config, NewsConfig},
-    error::Result,
+    db::{self, models},
+    error::{Error, Result},
     services::{
-        broadcast::{BroadcastEvent, OUTBOX},
+        broadcast::{BroadcastEvent, Outbox},
+        markets,
         scheduler::ScheduledTaskMessage,
+        twitter, ReloadConfig,
     },
 };
+use calendar::Calendar;
+pub use calendar::{fetch_events, CalendarEvent};
+use feeds::Feeds;
+pub use weather::Forecast;
+use weather::Weather;
+use youtube::{YouTubeAlerts, YouTubeDigest};
+
+/// A source of articles for the newscast. Providers are fetched on the
+/// blocking thread pool
+pub trait NewsProvider: Send + Sync {
+    /// Identifies the provider in logs
+    fn name(&self) -> &str;
+
+    /// The sections of articles to include in the next newscast
+    fn fetch(&self) -> Result<Vec<ArticleSection>>;
+}
+
+/// The providers enabled in the config, in the order that their
+/// sections appear in the newscast
+fn providers(config: &NewsConfig) -> Vec<Box<dyn NewsProvider>> {
+    let mut providers: Vec<Box<dyn NewsProvider>> = vec![];
+    if let Some(new_york_times) = &config.new_york_times {
+        providers.push(Box::new(new_york_times.clone()));
+    }
+    if let Some(guardian) = &config.guardian {
+        providers.push(Box::new(guardian.clone()));
+    }
+    if let Some(feeds) = &config.feeds {
+        providers.push(Box::new(Feeds::new(feeds)));
+    }
+    if let Some(reddit) = &config.reddit {
+        providers.push(Box::new(reddit.clone()));
+    }
+    if let Some(youtube) = &config.youtube {
+        providers.push(Box::new(YouTubeDigest::new(youtube.clone())));
+    }
+    providers
+}
+
+/// How often to check for weather warnings unless configured otherwise
+const DEFAULT_WARNING_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// How often to check for calendar reminders unless configured otherwise
+const DEFAULT_REMINDER_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often to check for youtube uploads unless configured otherwise
+const DEFAULT_UPLOAD_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ArticleSection {
     pub section_title: String,
     pub articles: Vec<Article>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Article {
     pub url: String,
     pub published_date: NaiveDate,
@@ -26,75 +89,265 @@ pub struct Article {
     pub metric: String,
 }
 
+/// Record the articles in a newscast in the background, so that past
+/// newscasts can be browsed
+fn archive(sections: &[ArticleSection]) {
+    let articles = sections
+        .iter()
+        .flat_map(|section| {
+            section
+                .articles
+                .iter()
+                .map(move |article| models::NewArticle {
+                    url: article.url.clone(),
+                    title: article.title.clone(),
+                    source: section.section_title.clone(),
+                    summary: article.r#abstract.clone(),
+                    published_date: article.published_date,
+                })
+        })
+        .collect::<Vec<_>>();
+
+    db::spawn_write("archiving newscast articles", move |database| {
+        database.insert_digest(articles).map(|_| ())
+    });
+}
+
+/// The last day's tweet activity in each group, if configured.
+/// Activity that can't be loaded is skipped so that it doesn't hold up
+/// the rest of the newscast
+fn twitter_activity() -> impl Future<Output = Vec<twitter::GroupActivity>> {
+    if !config()
+        .twitter
+        .map_or(false, |twitter| twitter.include_in_digest.unwrap_or(false))
+    {
+        return future::ready(vec![]).left_future();
+    }
+
+    twitter::activity_since(Utc::now().naive_utc() - chrono::Duration::days(1))
+        .map(|activity| {
+            activity.unwrap_or_else(|e| {
+                log::warn!("Error loading twitter activity: {}", e);
+                vec![]
+            })
+        })
+        .right_future()
+}
+
+/// The sections from every provider. Providers that fail are skipped so
+/// that they don't hold up the rest of the newscast
+fn fetch_articles(providers: &[Box<dyn NewsProvider>]) -> Vec<ArticleSection> {
+    providers
+        .iter()
+        .flat_map(|provider| {
+            provider.fetch().unwrap_or_else(|e| {
+                log::error!("Error fetching news from {}: {}", provider.name(), e);
+                vec![]
+            })
+        })
+        .collect()
+}
+
 pub struct News {
     config: NewsConfig,
+    providers: Arc<Vec<Box<dyn NewsProvider>>>,
+    weather: Option<Weather>,
+    calendar: Option<Calendar>,
+    youtube_alerts: Option<YouTubeAlerts>,
+    outbox: Outbox,
 }
 
 impl News {
-    pub fn new() -> Self {
+    pub fn new(outbox: Outbox) -> Self {
         let config = config().news.unwrap();
+        let providers = Arc::new(providers(&config));
+        let weather = config.weather.clone().map(Weather::new);
+        let calendar = config.calendar.clone().map(Calendar::new);
+        let youtube_alerts = config.youtube.clone().map(YouTubeAlerts::new);
 
-        Self { config }
+        Self {
+            config,
+            providers,
+            weather,
+            calendar,
+            youtube_alerts,
+            outbox,
+        }
     }
 
-    fn build_new_york_times_articles(&self) -> Result<Vec<ArticleSection>> {
-        self.config
-            .new_york_times
-            .iter()
-            .flat_map(|nyt_config| {
-                let new_york_times = NewYorkTimes::new(nyt_config.api_key.clone());
-
-                nyt_config
-                    .most_popular_viewed_period
-                    .iter()
-                    .map(move |period| {
-                        new_york_times
-                            .most_popular_viewed(period.clone())
-                            .map(|response| ArticleSection {
-                                section_title: "Most Viewed".to_string(),
-                                articles: response
-                                    .results
-                                    .into_iter()
-                                    .map(|article| Article {
-                                        url: article.url,
-                                        published_date: NaiveDate::parse_from_str(
-                                            &article.published_date,
-                                            "%Y-%m-%d",
-                                        )
-                                        .unwrap(),
-                                        title: article.title,
-                                        r#abstract: article.r#abstract,
-                                        metric: format!("{:?} views", article.views),
-                                    })
-                                    .collect(),
-                            })
-                            .map_err(Into::into)
-                    })
+    /// The sections from every provider, fetched on the blocking
+    /// thread pool so that slow providers don't hold up the news actor
+    fn fetch_articles(&self) -> impl Future<Output = Vec<ArticleSection>> {
+        let providers = self.providers.clone();
+        web::block(move || Ok::<_, Error>(fetch_articles(&providers))).map(|articles| {
+            articles.unwrap_or_else(|e| {
+                log::error!("Error fetching news: {}", e);
+                vec![]
             })
-            .collect()
+        })
+    }
+
+    /// Build the newscast and queue it for broadcast, once the parts
+    /// loaded in the background have arrived
+    fn build_newscast(&mut self) -> impl Future<Output = Result<()>> {
+        let articles = self.fetch_articles().map(|articles| {
+            archive(&articles);
+            articles
+        });
+
+        let weather = match &self.weather {
+            Some(weather) => weather.forecasts().left_future(),
+            None => future::ready(vec![]).right_future(),
+        };
+        let calendar = match &self.calendar {
+            Some(calendar) => calendar.upcoming().left_future(),
+            None => future::ready(vec![]).right_future(),
+        };
+        let stocks = match config()
+            .markets
+            .filter(|markets| markets.include_in_digest.unwrap_or(false))
+        {
+            Some(markets) => markets::fetch_quotes(markets).left_future(),
+            None => future::ready(vec![]).right_future(),
+        };
+
+        let outbox = self.outbox.clone();
+        future::join5(articles, weather, calendar, stocks, twitter_activity()).map(
+            move |(articles, weather, calendar, stocks, twitter)| {
+                outbox.push(BroadcastEvent::Newscast {
+                    articles,
+                    weather,
+                    calendar,
+                    stocks,
+                    twitter,
+                })
+            },
+        )
     }
 
-    fn build_newscast(&self) -> Result<()> {
-        let message = BroadcastEvent::Newscast {
-            new_york_times: self.build_new_york_times_articles()?,
+    /// Broadcast weather warnings as soon as they are issued
+    fn check_weather_warnings(&mut self, ctx: &mut Context<Self>) {
+        let warnings = match &self.weather {
+            Some(weather) => weather.fetch_warnings(),
+            None => return,
         };
+        warnings
+            .into_actor(self)
+            .map(|warnings, this, _| {
+                let warnings = match &mut this.weather {
+                    Some(weather) => weather.new_warnings(warnings),
+                    None => return,
+                };
+                for warning in warnings {
+                    this.outbox
+                        .push(warning)
+                        .unwrap_or_else(|e| log::error!("Error queueing weather warning: {}", e));
+                }
+            })
+            .spawn(ctx);
+    }
 
-        OUTBOX.push(message)?;
+    /// Broadcast reminders shortly before calendar events start
+    fn check_calendar_reminders(&mut self, ctx: &mut Context<Self>) {
+        let due = match &self.calendar {
+            Some(calendar) => calendar.fetch_due(),
+            None => return,
+        };
+        due.into_actor(self)
+            .map(|due, this, _| {
+                let reminders = match &mut this.calendar {
+                    Some(calendar) => calendar.due_reminders(due),
+                    None => return,
+                };
+                for reminder in reminders {
+                    this.outbox
+                        .push(reminder)
+                        .unwrap_or_else(|e| log::error!("Error queueing calendar reminder: {}", e));
+                }
+            })
+            .spawn(ctx);
+    }
 
-        Ok(())
+    /// Broadcast uploads to the alerted youtube channels as they appear
+    fn check_youtube_uploads(&mut self, ctx: &mut Context<Self>) {
+        let uploads = match &self.youtube_alerts {
+            Some(youtube_alerts) => youtube_alerts.fetch_uploads(),
+            None => return,
+        };
+        uploads
+            .into_actor(self)
+            .map(|uploads, this, _| {
+                let uploads = match &mut this.youtube_alerts {
+                    Some(youtube_alerts) => youtube_alerts.new_uploads(uploads),
+                    None => return,
+                };
+                for upload in uploads {
+                    this.outbox
+                        .push(upload)
+                        .unwrap_or_else(|e| log::error!("Error queueing youtube upload: {}", e));
+                }
+            })
+            .spawn(ctx);
     }
 }
 
 impl Actor for News {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        if let Some(weather_config) = &self.config.weather {
+            if weather_config.alert_on_warnings.unwrap_or(false) {
+                let interval = weather_config
+                    .warning_check_interval
+                    .unwrap_or(DEFAULT_WARNING_CHECK_INTERVAL);
+                ctx.run_interval(interval, |this, ctx| this.check_weather_warnings(ctx));
+            }
+        }
+
+        if let Some(calendar_config) = &self.config.calendar {
+            if calendar_config.remind_minutes_before.is_some() {
+                let interval = calendar_config
+                    .reminder_check_interval
+                    .unwrap_or(DEFAULT_REMINDER_CHECK_INTERVAL);
+                ctx.run_interval(interval, |this, ctx| this.check_calendar_reminders(ctx));
+            }
+        }
+
+        if let Some(youtube_config) = &self.config.youtube {
+            if youtube_config.alert_channels.is_some() {
+                let interval = youtube_config
+                    .poll_interval
+                    .unwrap_or(DEFAULT_UPLOAD_CHECK_INTERVAL);
+                // record the existing uploads so that only new ones are
+                // broadcast
+                self.check_youtube_uploads(ctx);
+                ctx.run_interval(interval, |this, ctx| this.check_youtube_uploads(ctx));
+            }
+        }
+    }
 }
 
 impl Handler<ScheduledTaskMessage> for News {
-    type Result = Result<()>;
+    type Result = ResponseFuture<Result<()>>;
 
     fn handle(&mut self, msg: ScheduledTaskMessage, _ctx: &mut Context<Self>) -> Self::Result {
         match msg {
-            ScheduledTaskMessage::FetchNews => self.build_newscast(),
+            ScheduledTaskMessage::FetchNews => Box::pin(self.build_newscast()),
+        }
+    }
+}
+
+/// Apply new news sources. Weather, calendar and YouTube checks are only
+/// scheduled on start, so changes to them need a restart
+impl Handler<ReloadConfig> for News {
+    type Result = Result<()>;
+
+    fn handle(&mut self, ReloadConfig(config): ReloadConfig, _: &mut Context<Self>) -> Result<()> {
+        if let Some(news) = config.news {
+            self.providers = Arc::new(providers(&news));
+            log::info!("News sources reloaded");
         }
+
+        Ok(())
     }
 }
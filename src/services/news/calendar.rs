@@ -0,0 +1,222 @@
+use std::{collections::HashSet, io::BufReader};
+
+use actix_web::web;
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use futures::{future, Future, FutureExt};
+use ical::{parser::ical::component::IcalEvent, property::Property, IcalParser};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::CalendarConfig,
+    error::{Error, Result},
+    services::{
+        broadcast::{BroadcastEvent, BroadcastEventKey},
+        http_get,
+    },
+};
+
+/// An event from one of the configured calendars, in local time
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CalendarEvent {
+    pub summary: String,
+    pub location: Option<String>,
+    pub starts_at: NaiveDateTime,
+    pub all_day: bool,
+}
+
+impl CalendarEvent {
+    /// A one line description, e.g. `Fri Oct 16 09:30: Standup (Room 4)`
+    pub fn describe(&self) -> String {
+        let starts_at = if self.all_day {
+            self.starts_at.format("%a %b %e").to_string()
+        } else {
+            self.starts_at.format("%a %b %e %H:%M").to_string()
+        };
+        match &self.location {
+            Some(location) => format!("{}: {} ({})", starts_at, self.summary, location),
+            None => format!("{}: {}", starts_at, self.summary),
+        }
+    }
+}
+
+/// Fetches events from the configured iCal urls. Recurring events are
+/// only included on their first occurrence
+pub struct Calendar {
+    config: CalendarConfig,
+    /// Keys of the events that have already been reminded of
+    reminded: HashSet<BroadcastEventKey>,
+}
+
+impl Calendar {
+    pub fn new(config: CalendarConfig) -> Self {
+        Self {
+            config,
+            reminded: HashSet::new(),
+        }
+    }
+
+    /// Today's and tomorrow's events, in the order that they start
+    pub fn upcoming(&self) -> impl Future<Output = Vec<CalendarEvent>> {
+        let today = Local::now().naive_local().date().and_hms(0, 0, 0);
+        self.events_between(today, today + Duration::days(2))
+    }
+
+    /// The events starting within the next `remind_minutes_before`
+    /// minutes
+    pub fn fetch_due(&self) -> impl Future<Output = Vec<CalendarEvent>> {
+        match self.config.remind_minutes_before {
+            Some(minutes_before) => {
+                let now = Local::now().naive_local();
+                self.events_between(now, now + Duration::minutes(minutes_before))
+                    .left_future()
+            }
+            None => future::ready(vec![]).right_future(),
+        }
+    }
+
+    /// Reminders for the selected events among the fetched due events.
+    /// Each event is only reminded of once
+    pub fn due_reminders(&mut self, due: Vec<CalendarEvent>) -> Vec<BroadcastEvent> {
+        let mut reminders = vec![];
+        for event in due {
+            if event.all_day || !self.is_selected(&event) {
+                continue;
+            }
+
+            let reminder = BroadcastEvent::CalendarReminder {
+                summary: event.summary,
+                location: event.location,
+                starts_at: event.starts_at,
+            };
+            if self.reminded.insert(reminder.event_key()) {
+                reminders.push(reminder);
+            }
+        }
+
+        reminders
+    }
+
+    /// Events are selected for reminders if their summary contains one
+    /// of `remind_of`, or if `remind_of` isn't configured
+    fn is_selected(&self, event: &CalendarEvent) -> bool {
+        let summary = event.summary.to_lowercase();
+        match &self.config.remind_of {
+            Some(remind_of) => remind_of
+                .iter()
+                .any(|pattern| summary.contains(&pattern.to_lowercase())),
+            None => true,
+        }
+    }
+
+    /// Events starting between `start` (inclusive) and `end`
+    /// (exclusive), fetched on the blocking thread pool so that a slow
+    /// calendar doesn't hold up the news actor
+    fn events_between(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> impl Future<Output = Vec<CalendarEvent>> {
+        let urls = self.config.urls.clone();
+        web::block(move || Ok::<_, Error>(events_between(&urls, start, end))).map(|events| {
+            events.unwrap_or_else(|e| {
+                log::warn!("Error fetching calendars: {}", e);
+                vec![]
+            })
+        })
+    }
+}
+
+/// Events in the calendars at `urls` starting between `start` and
+/// `end`. Calendars that can't be fetched are skipped so that they
+/// don't hold up the others
+fn events_between(urls: &[String], start: NaiveDateTime, end: NaiveDateTime) -> Vec<CalendarEvent> {
+    let mut events = vec![];
+    for url in urls {
+        match fetch_events(url) {
+            Ok(calendar_events) => events.extend(
+                calendar_events
+                    .into_iter()
+                    .filter(|event| event.starts_at >= start && event.starts_at < end),
+            ),
+            Err(e) => log::warn!("Error fetching calendar {}: {}", url, e),
+        }
+    }
+    events.sort_by_key(|event| event.starts_at);
+    events
+}
+
+/// Every event in the iCal calendar at `url`
+pub fn fetch_events(url: &str) -> Result<Vec<CalendarEvent>> {
+    let response = http_get(url).call();
+    if !response.ok() {
+        return Err(Error::calendar(format!(
+            "request failed with status {}",
+            response.status()
+        )));
+    }
+
+    let mut events = vec![];
+    for calendar in IcalParser::new(BufReader::new(response.into_reader())) {
+        let calendar = calendar.map_err(|e| Error::calendar(e.to_string()))?;
+        events.extend(calendar.events.iter().filter_map(calendar_event));
+    }
+    Ok(events)
+}
+
+/// Events without a start are skipped
+fn calendar_event(event: &IcalEvent) -> Option<CalendarEvent> {
+    let property = |name: &str| {
+        event
+            .properties
+            .iter()
+            .find(|property| property.name == name)
+    };
+    let value = |name: &str| property(name).and_then(|property| property.value.clone());
+
+    let dtstart = property("DTSTART")?;
+    let (starts_at, all_day) = start_time(dtstart)?;
+    Some(CalendarEvent {
+        summary: value("SUMMARY").unwrap_or_default(),
+        location: value("LOCATION").filter(|location| !location.is_empty()),
+        starts_at,
+        all_day,
+    })
+}
+
+/// The local start time of an event, and whether it is an all day
+/// event. Start times are either dates, UTC (`20201016T093000Z`), in a
+/// `TZID` timezone, or floating and taken to already be local
+fn start_time(dtstart: &Property) -> Option<(NaiveDateTime, bool)> {
+    let value = dtstart.value.as_ref()?;
+    let param = |name: &str| {
+        dtstart
+            .params
+            .iter()
+            .flatten()
+            .find(|(param, _)| param == name)
+            .and_then(|(_, values)| values.first())
+    };
+
+    if value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((date.and_hms(0, 0, 0), true));
+    }
+
+    let starts_at =
+        NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()?;
+    let starts_at = if value.ends_with('Z') {
+        Utc.from_utc_datetime(&starts_at)
+            .with_timezone(&Local)
+            .naive_local()
+    } else if let Some(timezone) = param("TZID").and_then(|tzid| tzid.parse::<Tz>().ok()) {
+        timezone
+            .from_local_datetime(&starts_at)
+            .earliest()?
+            .with_timezone(&Local)
+            .naive_local()
+    } else {
+        starts_at
+    };
+    Some((starts_at, false))
+}
@@ -0,0 +1,124 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use chrono::Utc;
+use feed_rs::model::{Entry, Feed};
+
+use crate::{
+    error::{Error, Result},
+    services::http_get,
+};
+
+use super::{Article, ArticleSection, NewsProvider};
+
+/// Fetches RSS and Atom feeds, keeping track of the entries that have
+/// already been included in a newscast so that they aren't repeated
+pub struct Feeds {
+    urls: Vec<String>,
+    /// The urls of the entries in each feed as of its last fetch
+    seen: Mutex<HashMap<String, HashSet<String>>>,
+}
+
+impl Feeds {
+    pub fn new(urls: &[String]) -> Self {
+        let mut unique = HashSet::new();
+        Self {
+            urls: urls
+                .iter()
+                .filter(|url| unique.insert(url.as_str()))
+                .cloned()
+                .collect(),
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A section for every feed with entries that haven't been seen
+    /// before. Feeds that can't be fetched are skipped so that they
+    /// don't hold up the rest of the newscast
+    pub fn fetch_new_entries(&self) -> Vec<ArticleSection> {
+        let mut seen_by_feed = self.seen.lock().unwrap();
+        let mut included = HashSet::new();
+        let mut sections = vec![];
+        for url in &self.urls {
+            let feed = match fetch(url) {
+                Ok(feed) => feed,
+                Err(e) => {
+                    log::warn!("Error fetching feed {}: {}", url, e);
+                    continue;
+                }
+            };
+
+            let section_title = feed
+                .title
+                .map(|title| title.content)
+                .unwrap_or_else(|| url.clone());
+            let previously_seen = seen_by_feed.remove(url).unwrap_or_default();
+            let mut seen = HashSet::new();
+            let mut articles = vec![];
+            for article in feed.entries.into_iter().map(article) {
+                seen.insert(article.url.clone());
+                // the same entry may be in more than one feed
+                if !previously_seen.contains(&article.url) && included.insert(article.url.clone()) {
+                    articles.push(article);
+                }
+            }
+            seen_by_feed.insert(url.clone(), seen);
+
+            if !articles.is_empty() {
+                sections.push(ArticleSection {
+                    section_title,
+                    articles,
+                });
+            }
+        }
+
+        sections
+    }
+}
+
+impl NewsProvider for Feeds {
+    fn name(&self) -> &str {
+        "feeds"
+    }
+
+    fn fetch(&self) -> Result<Vec<ArticleSection>> {
+        Ok(self.fetch_new_entries())
+    }
+}
+
+fn fetch(url: &str) -> Result<Feed> {
+    let response = http_get(url).call();
+    if !response.ok() {
+        return Err(Error::feed(format!(
+            "request failed with status {}",
+            response.status()
+        )));
+    }
+
+    feed_rs::parser::parse(response.into_reader()).map_err(|e| Error::feed(e.to_string()))
+}
+
+fn article(entry: Entry) -> Article {
+    Article {
+        url: entry
+            .links
+            .into_iter()
+            .next()
+            .map(|link| link.href)
+            .unwrap_or(entry.id),
+        published_date: entry
+            .published
+            .or(entry.updated)
+            .unwrap_or_else(Utc::now)
+            .naive_utc()
+            .date(),
+        title: entry.title.map(|title| title.content).unwrap_or_default(),
+        r#abstract: entry
+            .summary
+            .map(|summary| summary.content)
+            .unwrap_or_default(),
+        metric: String::new(),
+    }
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::cached,
+    config::GuardianConfig,
+    error::{Error, Result},
+    services::http_get,
+};
+
+use super::{Article, ArticleSection, NewsProvider};
+
+const SEARCH_URL: &str = "https://content.guardianapis.com/search";
+
+/// The number of articles in each section unless configured otherwise
+const DEFAULT_PAGE_SIZE: u32 = 10;
+
+/// How long searches are cached for unless configured otherwise
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize, Serialize)]
+struct SearchResponse {
+    response: SearchResults,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchResults {
+    results: Vec<Content>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    web_title: String,
+    web_url: String,
+    web_publication_date: DateTime<Utc>,
+    fields: Option<Fields>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Fields {
+    trail_text: Option<String>,
+}
+
+impl NewsProvider for GuardianConfig {
+    fn name(&self) -> &str {
+        "the guardian"
+    }
+
+    /// A section of the latest articles for each configured section, or
+    /// a single section of the latest articles if none are configured.
+    /// Sections that can't be fetched are skipped so that they don't
+    /// hold up the rest of the newscast
+    fn fetch(&self) -> Result<Vec<ArticleSection>> {
+        Ok(fetch_sections(self))
+    }
+}
+
+fn fetch_sections(config: &GuardianConfig) -> Vec<ArticleSection> {
+    let sections = match &config.sections {
+        Some(sections) => sections
+            .iter()
+            .map(|section| Some(section.as_str()))
+            .collect(),
+        None => vec![None],
+    };
+
+    sections
+        .into_iter()
+        .filter_map(|section| match search(config, section) {
+            Ok(articles) => Some(ArticleSection {
+                section_title: match section {
+                    Some(section) => format!("The Guardian: {}", section),
+                    None => "The Guardian".to_string(),
+                },
+                articles,
+            }),
+            Err(e) => {
+                log::warn!("Error fetching guardian articles: {}", e);
+                None
+            }
+        })
+        .filter(|section| !section.articles.is_empty())
+        .collect()
+}
+
+fn search(config: &GuardianConfig, section: Option<&str>) -> Result<Vec<Article>> {
+    let request = format!(
+        "{:?}:{:?}:{:?}:{}",
+        section, config.tags, config.page_size, config.api_key
+    );
+    let search: SearchResponse = cached("guardian", &request, DEFAULT_CACHE_TTL, || {
+        let mut request = http_get(SEARCH_URL);
+        request
+            .query("api-key", &config.api_key)
+            .query("order-by", "newest")
+            .query("show-fields", "trailText")
+            .query(
+                "page-size",
+                &config.page_size.unwrap_or(DEFAULT_PAGE_SIZE).to_string(),
+            );
+        if let Some(section) = section {
+            request.query("section", section);
+        }
+        if let Some(tags) = &config.tags {
+            // articles must have every tag
+            request.query("tag", &tags.join(","));
+        }
+
+        let response = request.call();
+        if !response.ok() {
+            return Err(Error::guardian(format!(
+                "request failed with status {}",
+                response.status()
+            )));
+        }
+
+        response
+            .into_json_deserialize()
+            .map_err(|e| Error::guardian(e.to_string()))
+    })?;
+    Ok(search
+        .response
+        .results
+        .into_iter()
+        .map(|content| Article {
+            url: content.web_url,
+            published_date: content.web_publication_date.naive_utc().date(),
+            title: content.web_title,
+            r#abstract: content
+                .fields
+                .and_then(|fields| fields.trail_text)
+                .unwrap_or_default(),
+            metric: String::new(),
+        })
+        .collect())
+}
@@ -0,0 +1,104 @@
+use chrono::NaiveDate;
+use nytrs::NewYorkTimes;
+
+use crate::{config::NewYorkTimesConfig, error::Result};
+
+use super::{top_stories, Article, ArticleSection, NewsProvider};
+
+impl NewsProvider for NewYorkTimesConfig {
+    fn name(&self) -> &str {
+        "new york times"
+    }
+
+    /// The configured most popular lists followed by the configured
+    /// top stories sections
+    fn fetch(&self) -> Result<Vec<ArticleSection>> {
+        let mut sections = vec![];
+        let new_york_times = NewYorkTimes::new(self.api_key.clone());
+
+        if let Some(period) = &self.most_popular_viewed_period {
+            let response = new_york_times.most_popular_viewed(period.clone())?;
+            sections.push(ArticleSection {
+                section_title: "Most Viewed".to_string(),
+                articles: response
+                    .results
+                    .into_iter()
+                    .map(|article| {
+                        most_popular_article(
+                            article.url,
+                            &article.published_date,
+                            article.title,
+                            article.r#abstract,
+                            format!("{:?} views", article.views),
+                        )
+                    })
+                    .collect(),
+            });
+        }
+
+        if let Some(period) = &self.most_popular_emailed_days {
+            let response = new_york_times.most_popular_emailed(period.clone())?;
+            sections.push(ArticleSection {
+                section_title: "Most Emailed".to_string(),
+                articles: response
+                    .results
+                    .into_iter()
+                    .map(|article| {
+                        most_popular_article(
+                            article.url,
+                            &article.published_date,
+                            article.title,
+                            article.r#abstract,
+                            String::new(),
+                        )
+                    })
+                    .collect(),
+            });
+        }
+
+        if let Some(period) = &self.most_popular_shared_period {
+            let response = new_york_times
+                .most_popular_shared(period.clone(), self.most_popular_shared_mediums.clone())?;
+            sections.push(ArticleSection {
+                section_title: "Most Shared".to_string(),
+                articles: response
+                    .results
+                    .into_iter()
+                    .map(|article| {
+                        most_popular_article(
+                            article.url,
+                            &article.published_date,
+                            article.title,
+                            article.r#abstract,
+                            String::new(),
+                        )
+                    })
+                    .collect(),
+            });
+        }
+
+        for section in self.top_stories_sections.iter().flatten() {
+            sections.push(top_stories::fetch(&self.api_key, section)?);
+        }
+
+        Ok(sections)
+    }
+}
+
+/// An article from one of the NYT most popular lists, which all date
+/// articles as `YYYY-MM-DD`
+fn most_popular_article(
+    url: String,
+    published_date: &str,
+    title: String,
+    r#abstract: String,
+    metric: String,
+) -> Article {
+    Article {
+        url,
+        published_date: NaiveDate::parse_from_str(published_date, "%Y-%m-%d").unwrap(),
+        title,
+        r#abstract,
+        metric,
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::cached,
+    config::{RedditConfig, RedditSort},
+    error::{Error, Result},
+    services::http_get,
+};
+
+use super::{Article, ArticleSection, NewsProvider};
+
+/// Reddit asks that api clients identify themselves
+const USER_AGENT: &str = concat!("pulse/", env!("CARGO_PKG_VERSION"));
+
+/// The number of posts fetched from each subreddit unless configured
+/// otherwise
+const DEFAULT_LIMIT: u32 = 10;
+
+/// How long listings are cached for unless configured otherwise
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize, Serialize)]
+struct Listing {
+    data: ListingData,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ListingData {
+    children: Vec<Child>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Child {
+    data: Post,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Post {
+    title: String,
+    permalink: String,
+    score: i64,
+    created_utc: f64,
+    selftext: String,
+}
+
+impl NewsProvider for RedditConfig {
+    fn name(&self) -> &str {
+        "reddit"
+    }
+
+    /// A section for every subreddit with posts at or above the minimum
+    /// score. Subreddits that can't be fetched are skipped so that they
+    /// don't hold up the rest of the newscast
+    fn fetch(&self) -> Result<Vec<ArticleSection>> {
+        Ok(fetch_subreddits(self))
+    }
+}
+
+fn fetch_subreddits(config: &RedditConfig) -> Vec<ArticleSection> {
+    let sort = config.sort.unwrap_or(RedditSort::Hot);
+    let limit = config.limit.unwrap_or(DEFAULT_LIMIT);
+
+    config
+        .subreddits
+        .iter()
+        .filter_map(|subreddit| match fetch(subreddit, sort, limit) {
+            Ok(posts) => Some((subreddit, posts)),
+            Err(e) => {
+                log::warn!("Error fetching r/{}: {}", subreddit, e);
+                None
+            }
+        })
+        .map(|(subreddit, posts)| ArticleSection {
+            section_title: format!("r/{}", subreddit),
+            articles: posts
+                .into_iter()
+                .filter(|post| config.min_score.map_or(true, |min| post.score >= min))
+                .map(article)
+                .collect(),
+        })
+        .filter(|section| !section.articles.is_empty())
+        .collect()
+}
+
+fn fetch(subreddit: &str, sort: RedditSort, limit: u32) -> Result<Vec<Post>> {
+    let request = format!("{}:{}:{}", subreddit, sort, limit);
+    let listing: Listing = cached("reddit", &request, DEFAULT_CACHE_TTL, || {
+        let response = http_get(&format!(
+            "https://www.reddit.com/r/{}/{}.json",
+            subreddit, sort
+        ))
+        .set("User-Agent", USER_AGENT)
+        .query("limit", &limit.to_string())
+        // top posts of the day, to match the daily newscast
+        .query("t", "day")
+        .call();
+        if !response.ok() {
+            return Err(Error::reddit(format!(
+                "request failed with status {}",
+                response.status()
+            )));
+        }
+
+        response
+            .into_json_deserialize()
+            .map_err(|e| Error::reddit(e.to_string()))
+    })?;
+    Ok(listing
+        .data
+        .children
+        .into_iter()
+        .map(|child| child.data)
+        .collect())
+}
+
+fn article(post: Post) -> Article {
+    Article {
+        url: format!("https://www.reddit.com{}", post.permalink),
+        published_date: NaiveDateTime::from_timestamp(post.created_utc as i64, 0).date(),
+        title: post.title,
+        r#abstract: post.selftext,
+        metric: format!("{} points", post.score),
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::cached,
+    error::{Error, Result},
+    services::http_get,
+};
+
+use super::{Article, ArticleSection};
+
+const TOP_STORIES_URL: &str = "https://api.nytimes.com/svc/topstories/v2";
+
+/// How long sections are cached for unless configured otherwise
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize, Serialize)]
+struct TopStoriesResponse {
+    results: Vec<Story>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Story {
+    title: String,
+    r#abstract: String,
+    url: String,
+    published_date: DateTime<FixedOffset>,
+}
+
+/// Fetch the top stories in a section of the New York Times, e.g.
+/// `technology`
+pub fn fetch(api_key: &str, section: &str) -> Result<ArticleSection> {
+    let request = format!("top-stories:{}:{}", section, api_key);
+    let top_stories: TopStoriesResponse =
+        cached("new-york-times", &request, DEFAULT_CACHE_TTL, || {
+            let response = http_get(&format!("{}/{}.json", TOP_STORIES_URL, section))
+                .query("api-key", api_key)
+                .call();
+            if !response.ok() {
+                return Err(Error::new_york_times(format!(
+                    "top stories request for {} failed with status {}",
+                    section,
+                    response.status()
+                )));
+            }
+
+            response
+                .into_json_deserialize()
+                .map_err(|e| Error::new_york_times(e.to_string()))
+        })?;
+
+    Ok(ArticleSection {
+        section_title: format!("Top Stories: {}", title_case(section)),
+        articles: top_stories
+            .results
+            .into_iter()
+            // the api includes placeholders for sections without stories
+            .filter(|story| !story.title.is_empty())
+            .map(|story| Article {
+                url: story.url,
+                published_date: story.published_date.naive_local().date(),
+                title: story.title,
+                r#abstract: story.r#abstract,
+                metric: String::new(),
+            })
+            .collect(),
+    })
+}
+
+/// e.g. `realestate` -> `Realestate`, `us` -> `US`
+fn title_case(section: &str) -> String {
+    if section.len() <= 2 {
+        return section.to_uppercase();
+    }
+
+    let mut chars = section.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
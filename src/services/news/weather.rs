@@ -0,0 +1,243 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use actix_web::web;
+use chrono::NaiveDateTime;
+use futures::{Future, FutureExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::cached,
+    config::{WeatherConfig, WeatherLocationConfig, WeatherUnits},
+    error::{Error, Result},
+    services::{
+        broadcast::{BroadcastEvent, BroadcastEventKey},
+        http_get,
+    },
+};
+
+const ONE_CALL_URL: &str = "https://api.openweathermap.org/data/2.5/onecall";
+
+/// How long forecasts are cached for unless configured otherwise
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize, Serialize)]
+struct OneCall {
+    daily: Vec<Daily>,
+    #[serde(default)]
+    alerts: Vec<WeatherAlert>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Daily {
+    temp: Temperature,
+    weather: Vec<Condition>,
+    /// Probability of precipitation, from 0 to 1
+    #[serde(default)]
+    pop: f64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Temperature {
+    min: f64,
+    max: f64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Condition {
+    description: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WeatherAlert {
+    sender_name: String,
+    event: String,
+    start: i64,
+    end: i64,
+    description: String,
+}
+
+/// Today's weather for a location
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Forecast {
+    pub location: String,
+    pub summary: String,
+    pub temperature_min: f64,
+    pub temperature_max: f64,
+    pub units: WeatherUnits,
+    /// Percent chance of precipitation
+    pub precipitation_chance: f64,
+}
+
+impl Forecast {
+    /// A one line description, e.g. `Boston: light rain, 8°C to 14°C,
+    /// 60% chance of precipitation`
+    pub fn describe(&self) -> String {
+        let symbol = self.units.temperature_symbol();
+        format!(
+            "{}: {}, {:.0}{} to {:.0}{}, {:.0}% chance of precipitation",
+            self.location,
+            self.summary,
+            self.temperature_min,
+            symbol,
+            self.temperature_max,
+            symbol,
+            self.precipitation_chance
+        )
+    }
+}
+
+/// Fetches forecasts and weather warnings for the configured locations
+/// from OpenWeatherMap
+pub struct Weather {
+    config: WeatherConfig,
+    /// Keys of the warnings in effect for each location as of the
+    /// last check, so that each warning is only alerted once
+    active_warnings: HashMap<String, HashSet<BroadcastEventKey>>,
+}
+
+impl Weather {
+    pub fn new(config: WeatherConfig) -> Self {
+        Self {
+            config,
+            active_warnings: HashMap::new(),
+        }
+    }
+
+    /// Today's forecast for every location. Locations that can't be
+    /// fetched are skipped so that they don't hold up the rest of the
+    /// newscast
+    pub fn forecasts(&self) -> impl Future<Output = Vec<Forecast>> {
+        let units = units(&self.config);
+        fetch_all(self.config.clone()).map(move |one_calls| {
+            one_calls
+                .into_iter()
+                .filter_map(|(location, one_call)| match one_call {
+                    Ok(one_call) => one_call.daily.into_iter().next().map(|today| Forecast {
+                        location,
+                        summary: today
+                            .weather
+                            .into_iter()
+                            .map(|condition| condition.description)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        temperature_min: today.temp.min,
+                        temperature_max: today.temp.max,
+                        units,
+                        precipitation_chance: today.pop * 100.0,
+                    }),
+                    Err(e) => {
+                        log::warn!("Error fetching forecast for {}: {}", location, e);
+                        None
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// The warnings in effect for every location
+    pub fn fetch_warnings(&self) -> impl Future<Output = Vec<(String, Result<Vec<WeatherAlert>>)>> {
+        fetch_all(self.config.clone()).map(|one_calls| {
+            one_calls
+                .into_iter()
+                .map(|(location, one_call)| (location, one_call.map(|one_call| one_call.alerts)))
+                .collect()
+        })
+    }
+
+    /// The fetched warnings that have been issued since the last check
+    pub fn new_warnings(
+        &mut self,
+        warnings: Vec<(String, Result<Vec<WeatherAlert>>)>,
+    ) -> Vec<BroadcastEvent> {
+        let mut new_warnings = vec![];
+        for (location, alerts) in warnings {
+            // the location's warnings are assumed unchanged if they
+            // can't be fetched
+            let alerts = match alerts {
+                Ok(alerts) => alerts,
+                Err(e) => {
+                    log::warn!("Error fetching warnings for {}: {}", location, e);
+                    continue;
+                }
+            };
+
+            let previously_active = self.active_warnings.remove(&location).unwrap_or_default();
+            let mut active = HashSet::new();
+            for alert in alerts {
+                let warning = BroadcastEvent::WeatherWarning {
+                    location: location.clone(),
+                    event: alert.event,
+                    description: alert.description,
+                    sender: alert.sender_name,
+                    starts_at: NaiveDateTime::from_timestamp(alert.start, 0),
+                    ends_at: NaiveDateTime::from_timestamp(alert.end, 0),
+                };
+                let event_key = warning.event_key();
+                if !previously_active.contains(&event_key) {
+                    new_warnings.push(warning);
+                }
+                active.insert(event_key);
+            }
+            self.active_warnings.insert(location, active);
+        }
+
+        new_warnings
+    }
+}
+
+fn units(config: &WeatherConfig) -> WeatherUnits {
+    config.units.unwrap_or(WeatherUnits::Metric)
+}
+
+/// The forecast and warnings for every location, by location name,
+/// fetched on the blocking thread pool so that a slow api doesn't hold
+/// up the news actor
+fn fetch_all(config: WeatherConfig) -> impl Future<Output = Vec<(String, Result<OneCall>)>> {
+    web::block(move || {
+        Ok::<_, Error>(
+            config
+                .locations
+                .iter()
+                .map(|location| (location.name.clone(), fetch(&config, location)))
+                .collect::<Vec<_>>(),
+        )
+    })
+    .map(|one_calls| {
+        one_calls.unwrap_or_else(|e| {
+            log::warn!("Error fetching weather: {}", e);
+            vec![]
+        })
+    })
+}
+
+fn fetch(config: &WeatherConfig, location: &WeatherLocationConfig) -> Result<OneCall> {
+    let request = format!(
+        "{}:{}:{}:{}",
+        location.latitude,
+        location.longitude,
+        units(config),
+        config.api_key
+    );
+    cached("weather", &request, DEFAULT_CACHE_TTL, || {
+        let response = http_get(ONE_CALL_URL)
+            .query("lat", &location.latitude.to_string())
+            .query("lon", &location.longitude.to_string())
+            .query("exclude", "current,minutely,hourly")
+            .query("units", &units(config).to_string())
+            .query("appid", &config.api_key)
+            .call();
+        if !response.ok() {
+            return Err(Error::weather(format!(
+                "request failed with status {}",
+                response.status()
+            )));
+        }
+
+        response
+            .into_json_deserialize()
+            .map_err(|e| Error::weather(e.to_string()))
+    })
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::Duration,
+};
+
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use futures::{Future, FutureExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    cache::cached,
+    config::YouTubeConfig,
+    error::{Error, Result},
+    services::{broadcast::BroadcastEvent, http_get},
+};
+
+use super::{Article, ArticleSection, NewsProvider};
+
+const API_URL: &str = "https://www.googleapis.com/youtube/v3";
+
+/// The number of recent uploads fetched from each channel
+const MAX_RESULTS: u32 = 10;
+
+/// How long responses are cached for unless configured otherwise
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize, Serialize)]
+struct Items<T> {
+    #[serde(default)]
+    items: Vec<T>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Channel {
+    snippet: ChannelSnippet,
+    content_details: ChannelContentDetails,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ChannelSnippet {
+    title: String,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChannelContentDetails {
+    related_playlists: RelatedPlaylists,
+}
+
+#[derive(Deserialize, Serialize)]
+struct RelatedPlaylists {
+    uploads: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct PlaylistItem {
+    snippet: PlaylistItemSnippet,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaylistItemSnippet {
+    title: String,
+    description: String,
+    published_at: DateTime<Utc>,
+    resource_id: ResourceId,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceId {
+    video_id: String,
+}
+
+/// A video uploaded to a channel
+pub struct Upload {
+    title: String,
+    description: String,
+    published_at: DateTime<Utc>,
+    url: String,
+}
+
+/// The uploads from the channels in `channels` since the last newscast
+/// (or the last day, for the first newscast)
+pub struct YouTubeDigest {
+    config: YouTubeConfig,
+    since: Mutex<DateTime<Utc>>,
+}
+
+impl YouTubeDigest {
+    pub fn new(config: YouTubeConfig) -> Self {
+        Self {
+            config,
+            since: Mutex::new(Utc::now() - chrono::Duration::days(1)),
+        }
+    }
+}
+
+impl NewsProvider for YouTubeDigest {
+    fn name(&self) -> &str {
+        "youtube"
+    }
+
+    /// A section for every channel with new uploads. Channels that
+    /// can't be fetched are skipped so that they don't hold up the rest
+    /// of the newscast
+    fn fetch(&self) -> Result<Vec<ArticleSection>> {
+        let mut since = self.since.lock().unwrap();
+        let now = Utc::now();
+        let mut sections = vec![];
+        for channel in &self.config.channels {
+            let (channel_title, uploads) = match uploads(&self.config.api_key, channel) {
+                Ok(uploads) => uploads,
+                Err(e) => {
+                    log::warn!("Error fetching youtube channel {}: {}", channel, e);
+                    continue;
+                }
+            };
+
+            let articles = uploads
+                .into_iter()
+                .filter(|upload| upload.published_at >= *since)
+                .map(|upload| Article {
+                    url: upload.url,
+                    published_date: upload.published_at.naive_utc().date(),
+                    title: upload.title,
+                    r#abstract: upload.description,
+                    metric: String::new(),
+                })
+                .collect::<Vec<_>>();
+            if !articles.is_empty() {
+                sections.push(ArticleSection {
+                    section_title: channel_title,
+                    articles,
+                });
+            }
+        }
+        *since = now;
+
+        Ok(sections)
+    }
+}
+
+/// Watches the channels in `alert_channels` for uploads to broadcast as
+/// soon as they appear
+pub struct YouTubeAlerts {
+    config: YouTubeConfig,
+    /// Urls of the uploads already seen in each channel. Uploads in a
+    /// channel that is checked for the first time are recorded without
+    /// being broadcast
+    seen: HashMap<String, HashSet<String>>,
+}
+
+impl YouTubeAlerts {
+    pub fn new(config: YouTubeConfig) -> Self {
+        Self {
+            config,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// The title and recent uploads of each alerted channel, by channel
+    /// id, fetched on the blocking thread pool so that a slow api
+    /// doesn't hold up the news actor
+    pub fn fetch_uploads(
+        &self,
+    ) -> impl Future<Output = Vec<(String, Result<(String, Vec<Upload>)>)>> {
+        let api_key = self.config.api_key.clone();
+        let channels = self.config.alert_channels.clone().unwrap_or_default();
+        web::block(move || {
+            Ok::<_, Error>(
+                channels
+                    .into_iter()
+                    .map(|channel| {
+                        let uploads = uploads(&api_key, &channel);
+                        (channel, uploads)
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .map(|uploads| {
+            uploads.unwrap_or_else(|e| {
+                log::warn!("Error fetching youtube channels: {}", e);
+                vec![]
+            })
+        })
+    }
+
+    /// The fetched uploads that have appeared since the last check
+    pub fn new_uploads(
+        &mut self,
+        fetched: Vec<(String, Result<(String, Vec<Upload>)>)>,
+    ) -> Vec<BroadcastEvent> {
+        let mut new_uploads = vec![];
+        for (channel, uploads) in fetched {
+            let (channel_title, uploads) = match uploads {
+                Ok(uploads) => uploads,
+                Err(e) => {
+                    log::warn!("Error fetching youtube channel {}: {}", channel, e);
+                    continue;
+                }
+            };
+
+            let first_check = !self.seen.contains_key(&channel);
+            let seen = self.seen.entry(channel).or_default();
+            for upload in uploads {
+                if seen.insert(upload.url.clone()) && !first_check {
+                    new_uploads.push(BroadcastEvent::YoutubeUpload {
+                        channel: channel_title.clone(),
+                        title: upload.title,
+                        url: upload.url,
+                    });
+                }
+            }
+        }
+
+        new_uploads
+    }
+}
+
+/// The channel's title and its most recent uploads
+fn uploads(api_key: &str, channel_id: &str) -> Result<(String, Vec<Upload>)> {
+    let channel = get::<Channel>(
+        "channels",
+        &[
+            ("part", "snippet,contentDetails"),
+            ("id", channel_id),
+            ("key", api_key),
+        ],
+    )?
+    .into_iter()
+    .next()
+    .ok_or_else(|| Error::youtube(format!("no channel with id {}", channel_id)))?;
+
+    let uploads = get::<PlaylistItem>(
+        "playlistItems",
+        &[
+            ("part", "snippet"),
+            (
+                "playlistId",
+                &channel.content_details.related_playlists.uploads,
+            ),
+            ("maxResults", &MAX_RESULTS.to_string()),
+            ("key", api_key),
+        ],
+    )?
+    .into_iter()
+    .map(|item| Upload {
+        url: format!(
+            "https://www.youtube.com/watch?v={}",
+            item.snippet.resource_id.video_id
+        ),
+        title: item.snippet.title,
+        description: item.snippet.description,
+        published_at: item.snippet.published_at,
+    })
+    .collect();
+
+    Ok((channel.snippet.title, uploads))
+}
+
+fn get<T: DeserializeOwned + Serialize>(resource: &str, query: &[(&str, &str)]) -> Result<Vec<T>> {
+    let request = format!("{}:{:?}", resource, query);
+    cached("youtube", &request, DEFAULT_CACHE_TTL, || {
+        let mut request = http_get(&format!("{}/{}", API_URL, resource));
+        for (param, value) in query {
+            request.query(param, value);
+        }
+
+        let response = request.call();
+        if !response.ok() {
+            return Err(Error::youtube(format!(
+                "request for {} failed with status {}",
+                resource,
+                response.status()
+            )));
+        }
+
+        response
+            .into_json_deserialize::<Items<T>>()
+            .map(|items| items.items)
+            .map_err(|e| Error::youtube(e.to_string()))
+    })
+}
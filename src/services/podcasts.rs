@@ -0,0 +1,297 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use actix::{Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, WrapFuture};
+use actix_web::web;
+use feed_rs::model::Entry;
+use futures::future::{self, BoxFuture, FutureExt};
+
+use crate::{
+    config::{config, PodcastsConfig},
+    error::{Error, Result},
+    services::{
+        broadcast::{BroadcastEvent, Outbox},
+        http_get,
+    },
+};
+
+/// How often to check the feeds unless configured otherwise
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// An episode in a podcast feed, identified by its url (or the url of
+/// its audio if it has none)
+#[derive(Clone, Debug)]
+pub struct Episode {
+    pub title: String,
+    pub duration: Option<String>,
+    pub url: String,
+}
+
+/// A podcast's title and episodes
+#[derive(Clone, Debug)]
+pub struct Podcast {
+    pub title: String,
+    pub episodes: Vec<Episode>,
+}
+
+trait PodcastsPorts {
+    fn fetch_podcast(&self, url: String) -> BoxFuture<'static, Result<Podcast>>;
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()>;
+}
+
+struct LivePodcastsPorts {
+    outbox: Outbox,
+}
+impl PodcastsPorts for LivePodcastsPorts {
+    /// Feeds are fetched on the blocking thread pool, so that a slow
+    /// feed doesn't hold up the other actors
+    fn fetch_podcast(&self, url: String) -> BoxFuture<'static, Result<Podcast>> {
+        web::block(move || fetch_podcast(&url))
+            .map(|result| result.map_err(Into::into))
+            .boxed()
+    }
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+        self.outbox.push(event)
+    }
+}
+
+fn fetch_podcast(url: &str) -> Result<Podcast> {
+    let response = http_get(url).call();
+    if !response.ok() {
+        return Err(Error::podcast(format!(
+            "request for {} failed with status {}",
+            url,
+            response.status()
+        )));
+    }
+
+    let feed = feed_rs::parser::parse(response.into_reader())
+        .map_err(|e| Error::podcast(e.to_string()))?;
+    Ok(Podcast {
+        title: feed
+            .title
+            .map(|title| title.content)
+            .unwrap_or_else(|| url.to_string()),
+        episodes: feed.entries.into_iter().filter_map(episode).collect(),
+    })
+}
+
+/// The episode in a feed entry. Enclosures and `itunes:duration` are
+/// parsed as media, and entries without audio aren't episodes
+fn episode(entry: Entry) -> Option<Episode> {
+    let media = entry
+        .media
+        .iter()
+        .find(|media| media.content.iter().any(|content| content.url.is_some()))?;
+    let audio_url = media
+        .content
+        .iter()
+        .find_map(|content| content.url.as_ref())?
+        .to_string();
+    let duration = media
+        .duration
+        .or_else(|| media.content.iter().find_map(|content| content.duration));
+
+    Some(Episode {
+        title: entry.title.map(|title| title.content).unwrap_or_default(),
+        duration: duration.map(format_duration),
+        url: entry
+            .links
+            .into_iter()
+            .next()
+            .map(|link| link.href)
+            .unwrap_or(audio_url),
+    })
+}
+
+/// Format an episode's duration the way podcast feeds do, as
+/// `h:mm:ss`, or `m:ss` for episodes shorter than an hour
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Polls podcast feeds, broadcasting each new episode once
+pub struct Podcasts {
+    config: PodcastsConfig,
+    /// Urls of the episodes already seen in each feed. Episodes in a
+    /// feed that is polled for the first time are recorded without
+    /// being broadcast
+    seen: HashMap<String, HashSet<String>>,
+    ports: Box<dyn PodcastsPorts>,
+}
+
+impl Podcasts {
+    pub fn new(outbox: Outbox) -> Option<Self> {
+        config().podcasts.map(|podcasts_config| {
+            Self::with_ports(podcasts_config, Box::new(LivePodcastsPorts { outbox }))
+        })
+    }
+
+    fn with_ports(config: PodcastsConfig, ports: Box<dyn PodcastsPorts>) -> Self {
+        Self {
+            config,
+            seen: HashMap::new(),
+            ports,
+        }
+    }
+
+    /// Fetch every feed in the background, then broadcast the new
+    /// episodes
+    fn poll(&mut self, ctx: &mut Context<Self>) {
+        let podcasts = self
+            .config
+            .feeds
+            .iter()
+            .map(|feed| {
+                let feed = feed.clone();
+                self.ports
+                    .fetch_podcast(feed.clone())
+                    .map(move |podcast| (feed, podcast))
+            })
+            .collect::<Vec<_>>();
+
+        future::join_all(podcasts)
+            .into_actor(self)
+            .map(|podcasts, this, _| {
+                this.broadcast_new_episodes(podcasts)
+                    .unwrap_or_else(|e| log::error!("Error polling podcasts: {}", e))
+            })
+            .spawn(ctx);
+    }
+
+    /// Broadcast the episodes not seen before. Feeds that couldn't be
+    /// fetched are skipped so that they don't hold up the others
+    fn broadcast_new_episodes(&mut self, podcasts: Vec<(String, Result<Podcast>)>) -> Result<()> {
+        for (feed, podcast) in podcasts {
+            let podcast = match podcast {
+                Ok(podcast) => podcast,
+                Err(e) => {
+                    log::warn!("Error fetching podcast {}: {}", feed, e);
+                    continue;
+                }
+            };
+
+            let first_poll = !self.seen.contains_key(&feed);
+            let seen = self.seen.entry(feed).or_default();
+            for episode in podcast.episodes {
+                if seen.insert(episode.url.clone()) && !first_poll {
+                    self.ports.send_alert(BroadcastEvent::PodcastEpisode {
+                        podcast: podcast.title.clone(),
+                        title: episode.title,
+                        duration: episode.duration,
+                        url: episode.url,
+                    })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Actor for Podcasts {
+    type Context = Context<Self>;
+
+    /// When the podcasts actor is started, begin polling
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let interval = self.config.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL);
+        ctx.run_interval(interval, |this, ctx| this.poll(ctx));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    struct TestPodcastsPorts {
+        sent_alerts: Arc<Mutex<Vec<BroadcastEvent>>>,
+    }
+    impl PodcastsPorts for TestPodcastsPorts {
+        fn fetch_podcast(&self, _: String) -> BoxFuture<'static, Result<Podcast>> {
+            futures::future::ok(podcast(vec![])).boxed()
+        }
+
+        fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+            self.sent_alerts.lock().unwrap().push(event);
+            Ok(())
+        }
+    }
+
+    fn episode(number: u32) -> Episode {
+        Episode {
+            title: format!("Episode {}", number),
+            duration: Some("45:00".to_string()),
+            url: format!("https://newrustacean.com/episodes/{}", number),
+        }
+    }
+
+    fn podcast(episodes: Vec<Episode>) -> Podcast {
+        Podcast {
+            title: "New Rustacean".to_string(),
+            episodes,
+        }
+    }
+
+    fn fetched(episodes: Vec<Episode>) -> Vec<(String, Result<Podcast>)> {
+        vec![(
+            "https://newrustacean.com/feed.xml".to_string(),
+            Ok(podcast(episodes)),
+        )]
+    }
+
+    #[test]
+    fn podcasts_broadcast_each_new_episode_once() {
+        let sent_alerts = Arc::new(Mutex::new(vec![]));
+        let config = PodcastsConfig {
+            feeds: vec!["https://newrustacean.com/feed.xml".to_string()],
+            poll_interval: None,
+        };
+        let mut podcasts = Podcasts::with_ports(
+            config,
+            Box::new(TestPodcastsPorts {
+                sent_alerts: sent_alerts.clone(),
+            }),
+        );
+
+        // existing episodes are recorded without being broadcast
+        podcasts
+            .broadcast_new_episodes(fetched(vec![episode(1)]))
+            .unwrap();
+        assert!(sent_alerts.lock().unwrap().is_empty());
+
+        let episodes = vec![episode(2), episode(1)];
+        podcasts
+            .broadcast_new_episodes(fetched(episodes.clone()))
+            .unwrap();
+        podcasts.broadcast_new_episodes(fetched(episodes)).unwrap();
+
+        let alerted = sent_alerts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.event_key().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            alerted,
+            vec!["podcast-episode:New Rustacean:https://newrustacean.com/episodes/2"]
+        );
+    }
+
+    #[test]
+    fn podcasts_format_episode_durations_like_feeds() {
+        assert_eq!(format_duration(Duration::from_secs(45 * 60)), "45:00");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1:05");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+    }
+}
@@ -1,24 +1,163 @@
+mod blackout;
 mod messages;
 pub use messages::*;
 
-use actix::{fut::wrap_future, Actor, AsyncContext, Context, Recipient};
-use futures::FutureExt;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use actix::{
+    fut::wrap_future, Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, Handler,
+    Message, Recipient, ResponseActFuture, SpawnHandle, WrapFuture,
+};
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use futures::{
+    future::{self, BoxFuture, FutureExt, LocalBoxFuture},
+    Future,
+};
+use rand::Rng;
+use serde::Serialize;
 
+use self::blackout::{Blackout, CalendarDays};
 use crate::{
-    config::{config, ScheduledTaskConfig},
-    db::{database, models},
+    config::{config, format_short_duration, CatchUpPolicy, ScheduledTaskConfig, TaskRetryConfig},
+    db::{self, models},
     error::{Error, Result},
+    services::broadcast::{BroadcastEvent, Outbox},
 };
 
+/// How far back the task history is searched for each task's last run
+const LAST_RUN_LOOKBACK_DAYS: i64 = 7;
+
+/// How often a paused or blacked out one-shot task whose time has come
+/// checks whether it can run
+const PAUSED_ONE_SHOT_RETRY: Duration = Duration::from_secs(1);
+
 trait SchedulerPorts {
     fn insert_task(&self, task: models::NewTask) -> Result<()>;
+
+    fn record_run(&self, run: models::NewTaskRun) -> Result<()>;
+
+    fn mark_completed(&self, task: models::NewCompletedTask) -> Result<()>;
+
+    fn completed_tasks(&self) -> BoxFuture<'static, Result<Vec<models::CompletedTask>>>;
+
+    fn tasks_since(&self, since: NaiveDateTime) -> BoxFuture<'static, Result<Vec<models::Task>>>;
+
+    fn last_run(&self, task_key: &str) -> BoxFuture<'static, Result<Option<NaiveDateTime>>>;
+
+    fn latest_task_runs(&self) -> BoxFuture<'static, Result<Vec<models::TaskRun>>>;
+
+    fn save_next_run(&self, schedule: models::TaskSchedule) -> Result<()>;
+
+    fn task_schedules(&self) -> BoxFuture<'static, Result<Vec<models::TaskSchedule>>>;
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()>;
+
+    fn register_task(&self, task: models::NewRegisteredTask) -> BoxFuture<'static, Result<()>>;
+
+    fn registered_tasks(&self) -> BoxFuture<'static, Result<Vec<models::RegisteredTask>>>;
 }
 
-struct LiveSchedulerPorts;
+struct LiveSchedulerPorts {
+    outbox: Outbox,
+}
 impl SchedulerPorts for LiveSchedulerPorts {
     fn insert_task(&self, task: models::NewTask) -> Result<()> {
-        database().insert_task(task).map(|_| ())
+        db::spawn_write("recording a task", move |database| {
+            database.insert_task(task).map(|_| ())
+        });
+        Ok(())
+    }
+
+    fn record_run(&self, run: models::NewTaskRun) -> Result<()> {
+        db::spawn_write("recording a task run", move |database| {
+            database.insert_task_run(run).map(|_| ())
+        });
+        Ok(())
+    }
+
+    fn mark_completed(&self, task: models::NewCompletedTask) -> Result<()> {
+        db::spawn_write("recording a completed task", move |database| {
+            database.insert_completed_task(task)
+        });
+        Ok(())
+    }
+
+    fn completed_tasks(&self) -> BoxFuture<'static, Result<Vec<models::CompletedTask>>> {
+        db::run(|database| database.completed_tasks()).boxed()
+    }
+
+    fn tasks_since(&self, since: NaiveDateTime) -> BoxFuture<'static, Result<Vec<models::Task>>> {
+        db::run(move |database| database.tasks_since(since)).boxed()
+    }
+
+    fn last_run(&self, task_key: &str) -> BoxFuture<'static, Result<Option<NaiveDateTime>>> {
+        let task_key = task_key.to_string();
+        db::run(move |database| {
+            database
+                .last_task(&task_key)
+                .map(|task| task.map(|task| task.sent_at))
+        })
+        .boxed()
+    }
+
+    fn latest_task_runs(&self) -> BoxFuture<'static, Result<Vec<models::TaskRun>>> {
+        db::run(|database| database.latest_task_runs()).boxed()
+    }
+
+    fn save_next_run(&self, schedule: models::TaskSchedule) -> Result<()> {
+        db::spawn_write("recording a task schedule", move |database| {
+            database.upsert_task_schedule(schedule)
+        });
+        Ok(())
+    }
+
+    fn task_schedules(&self) -> BoxFuture<'static, Result<Vec<models::TaskSchedule>>> {
+        db::run(|database| database.task_schedules()).boxed()
+    }
+
+    fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+        self.outbox.push(event)
     }
+
+    fn register_task(&self, task: models::NewRegisteredTask) -> BoxFuture<'static, Result<()>> {
+        db::run(move |database| database.insert_registered_task(task).map(|_| ())).boxed()
+    }
+
+    fn registered_tasks(&self) -> BoxFuture<'static, Result<Vec<models::RegisteredTask>>> {
+        db::run(|database| database.registered_tasks()).boxed()
+    }
+}
+
+/// A service that runs scheduled tasks, named in the runs it records
+struct TaskRunner {
+    name: String,
+    recipient: Recipient<ScheduledTaskMessage>,
+}
+
+/// A task runner's attempt at a run of a task
+#[derive(Clone)]
+struct Attempt {
+    id: usize,
+    task: ScheduledTaskConfig,
+    /// The task message, as it is recorded in the db
+    message: String,
+    /// What the task's runs are recorded under
+    key: String,
+    /// The position of the task runner in `task_runners`
+    runner: usize,
+    /// When the run started, which identifies it
+    started: Instant,
+    number: u32,
+}
+
+/// A run of a task that hasn't finished yet
+struct RunningTask {
+    started: Instant,
+    /// How many task runners haven't replied yet
+    remaining: usize,
 }
 
 /// The scheduler is responsible for kicking off configured tasks at
@@ -26,15 +165,41 @@ impl SchedulerPorts for LiveSchedulerPorts {
 /// actually perform those tasks
 pub struct Scheduler {
     tasks: Vec<ScheduledTaskConfig>,
-    task_runners: Vec<Recipient<ScheduledTaskMessage>>,
+    task_runners: Vec<TaskRunner>,
     ports: Box<dyn SchedulerPorts>,
+    /// How late the most recent task ran
+    lag: Option<Duration>,
+    /// While paused, no tasks are run
+    paused: bool,
+    /// Ids (positions in `tasks`) of individually paused tasks
+    paused_tasks: HashSet<usize>,
+    /// When each task is next scheduled to run, by id. One-shot tasks
+    /// that have run are never run again
+    next_runs: HashMap<usize, DateTime<Local>>,
+    /// Tasks that are still running, by id. A task isn't run again
+    /// until its previous run has finished
+    running: HashMap<usize, RunningTask>,
+    /// The pending run of each task, cancelled if the task is disabled
+    timers: HashMap<usize, SpawnHandle>,
+    /// One-shot tasks that have run, loaded when the scheduler starts
+    completed: Vec<models::CompletedTask>,
+    blackout: Option<Blackout>,
 }
 impl Scheduler {
-    pub fn new() -> Self {
+    pub fn new(outbox: Outbox) -> Self {
+        let config = config();
         Self {
-            tasks: config().tasks,
+            tasks: config.tasks,
             task_runners: vec![],
-            ports: Box::new(LiveSchedulerPorts),
+            ports: Box::new(LiveSchedulerPorts { outbox }),
+            lag: None,
+            paused: false,
+            paused_tasks: HashSet::new(),
+            next_runs: HashMap::new(),
+            running: HashMap::new(),
+            timers: HashMap::new(),
+            completed: vec![],
+            blackout: config.blackout.map(Blackout::new),
         }
     }
 
@@ -44,36 +209,549 @@ impl Scheduler {
             tasks,
             task_runners: vec![],
             ports: test_ports,
+            lag: None,
+            paused: false,
+            paused_tasks: HashSet::new(),
+            next_runs: HashMap::new(),
+            running: HashMap::new(),
+            timers: HashMap::new(),
+            completed: vec![],
+            blackout: None,
         }
     }
 
     /// Add a service to the scheduler
-    pub fn add_task_runner(&mut self, task_runner: Recipient<ScheduledTaskMessage>) {
-        self.task_runners.push(task_runner)
+    pub fn add_task_runner<S: Into<String>>(
+        &mut self,
+        name: S,
+        recipient: Recipient<ScheduledTaskMessage>,
+    ) {
+        self.task_runners.push(TaskRunner {
+            name: name.into(),
+            recipient,
+        })
+    }
+
+    /// Run a task at its next scheduled time, delayed by up to its
+    /// jitter
+    fn schedule_next(&mut self, ctx: &mut Context<Self>, id: usize, task: ScheduledTaskConfig) {
+        let jitter = task
+            .jitter
+            .filter(|jitter| jitter.as_millis() > 0)
+            .map(|jitter| {
+                let millis = rand::thread_rng().gen_range(0, jitter.as_millis() as u64 + 1);
+                chrono::Duration::milliseconds(millis as i64)
+            })
+            .unwrap_or_else(chrono::Duration::zero);
+        let next_run = task.next_run() + jitter;
+        self.next_runs.insert(id, next_run);
+        let schedule = models::TaskSchedule {
+            task_key: task.key(),
+            next_run: next_run.with_timezone(&Utc).naive_utc(),
+        };
+        self.ports
+            .save_next_run(schedule)
+            .unwrap_or_else(|e| log::error!("Error recording next run: {}", e));
+        let delay = next_run
+            .signed_duration_since(Local::now())
+            .to_std()
+            .unwrap_or_default();
+        let timer = ctx.run_later(delay, move |this, ctx| {
+            this.schedule_task(ctx, id, task, next_run)
+        });
+        self.timers.insert(id, timer);
+    }
+
+    fn schedule_task(
+        &mut self,
+        ctx: &mut Context<Self>,
+        id: usize,
+        task: ScheduledTaskConfig,
+        scheduled_for: DateTime<Local>,
+    ) {
+        // paused and blacked out tasks keep their schedule, but are
+        // skipped until resumed or the blackout is over. One-shot tasks
+        // run once they can
+        let paused = self.paused || self.paused_tasks.contains(&id);
+        if paused || self.is_blacked_out(&task) {
+            if task.is_one_shot() {
+                let timer = ctx.run_later(PAUSED_ONE_SHOT_RETRY, move |this, ctx| {
+                    this.schedule_task(ctx, id, task, scheduled_for)
+                });
+                self.timers.insert(id, timer);
+            } else {
+                let reason = if paused { "paused" } else { "blacked out" };
+                log::info!("Skipping {} task {:?}", reason, task.message);
+                self.schedule_next(ctx, id, task);
+            }
+            return;
+        }
+
+        self.lag = Some(
+            Local::now()
+                .signed_duration_since(scheduled_for)
+                .to_std()
+                .unwrap_or_default(),
+        );
+
+        self.run_task(ctx, id, &task)
+            .unwrap_or_else(|e| log::error!("Error running task {:?}: {}", task.message, e));
+
+        // one-shot tasks are done, other tasks are scheduled to run
+        // again based on their cron schedule
+        match completed_task(&task) {
+            Some(completed) => {
+                self.next_runs.remove(&id);
+                self.timers.remove(&id);
+                self.completed.push(models::CompletedTask {
+                    task: completed.task.clone(),
+                    scheduled_for: completed.scheduled_for,
+                    completed_at: Utc::now().naive_utc(),
+                });
+                self.ports
+                    .mark_completed(completed)
+                    .unwrap_or_else(|e| log::error!("Error completing task: {}", e));
+            }
+            None => self.schedule_next(ctx, id, task),
+        }
     }
 
-    fn schedule_task(&self, ctx: &mut Context<Self>, task: ScheduledTaskConfig) {
+    /// Record a run of a task in the db and send its message to the
+    /// task runners, unless its previous run hasn't finished
+    fn run_task(
+        &mut self,
+        ctx: &mut Context<Self>,
+        id: usize,
+        task: &ScheduledTaskConfig,
+    ) -> Result<()> {
+        if let Some(running) = self.running.get(&id) {
+            log::warn!(
+                "Skipping task {:?}, its previous run started {}s ago hasn't finished",
+                task.message,
+                running.started.elapsed().as_secs()
+            );
+            return Ok(());
+        }
+
         // record this message in the db
-        serde_json::to_string(&task.message)
-            .map_err(Into::into)
-            .and_then(|t| self.ports.insert_task(models::NewTask::new(t)))
-            .unwrap_or_else(|e| log::error!("{}", Into::<Error>::into(e)));
-
-        // send this message to configured task_runners
-        for runner in &self.task_runners {
-            let task = task.clone();
-            ctx.spawn(wrap_future(runner.send(task.clone().message).map(
-                move |response| match response.unwrap() {
-                    Err(e) => log::error!("Error sending message {:?}: {:?}", task.message, e),
-                    Ok(_) => (),
+        let message = serde_json::to_string(&task.message)?;
+        self.ports
+            .insert_task(models::NewTask::new(message.clone(), task.key()))
+            .unwrap_or_else(|e| log::error!("{}", e));
+
+        // send this message to configured task_runners, and record how
+        // each of them handled it
+        let started_at = Utc::now().naive_utc();
+        let started = Instant::now();
+        let runners = self.routes(task);
+        if !runners.is_empty() {
+            self.running.insert(
+                id,
+                RunningTask {
+                    started,
+                    remaining: runners.len(),
                 },
-            )));
+            );
+        }
+        for runner in runners {
+            let attempt = Attempt {
+                id,
+                task: task.clone(),
+                message: message.clone(),
+                key: task.key(),
+                runner,
+                started,
+                number: 1,
+            };
+            self.send_attempt(ctx, attempt);
+        }
+
+        // alert if this run is still going once it times out
+        if let Some(timeout) = task.timeout {
+            ctx.run_later(timeout, move |this, _| {
+                if this.is_running(id, started) {
+                    this.alert_timeout(&message, started_at, timeout)
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The positions in `task_runners` of the runners a task is sent
+    /// to, which is every runner unless the task names its runners
+    fn routes(&self, task: &ScheduledTaskConfig) -> Vec<usize> {
+        self.task_runners
+            .iter()
+            .enumerate()
+            .filter(|(_, runner)| {
+                task.runners
+                    .as_ref()
+                    .map_or(true, |names| names.contains(&runner.name))
+            })
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    /// Check that every runner a task is sent to has been added
+    fn check_routes(&self, task: &ScheduledTaskConfig) -> Result<()> {
+        let unknown = task
+            .runners
+            .iter()
+            .flatten()
+            .find(|name| !self.task_runners.iter().any(|runner| &&runner.name == name));
+        match unknown {
+            Some(name) => Err(Error::invalid_config(format!(
+                "task {:?} is sent to unknown runner {:?}",
+                task.message, name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Send a task's message to a task runner and record how it
+    /// handled it. Failed attempts are retried if the task retries
+    fn send_attempt(&self, ctx: &mut Context<Self>, attempt: Attempt) {
+        let runner = &self.task_runners[attempt.runner];
+        let name = runner.name.clone();
+        let started_at = Utc::now().naive_utc();
+        let started = Instant::now();
+        ctx.spawn(
+            wrap_future::<_, Self>(runner.recipient.send(attempt.task.message.clone())).map(
+                move |response, this, ctx| {
+                    let error = match response {
+                        Ok(Ok(())) => None,
+                        Ok(Err(e)) => Some(e.to_string()),
+                        Err(e) => Some(e.to_string()),
+                    };
+                    if let Some(error) = &error {
+                        log::error!(
+                            "Error running task {:?} with {} (attempt {}): {}",
+                            attempt.task.message,
+                            name,
+                            attempt.number,
+                            error
+                        );
+                    }
+                    this.ports
+                        .record_run(models::NewTaskRun {
+                            task: attempt.message.clone(),
+                            runner: name.clone(),
+                            succeeded: error.is_none(),
+                            error: error.clone(),
+                            duration_ms: started.elapsed().as_millis() as i64,
+                            started_at,
+                            task_key: Some(attempt.key.clone()),
+                        })
+                        .unwrap_or_else(|e| log::error!("Error recording task run: {}", e));
+
+                    let (error, retry) = match (error, attempt.task.retry.as_ref()) {
+                        (Some(error), Some(retry)) => (error, retry),
+                        _ => return this.finish_run(attempt.id, attempt.started),
+                    };
+                    match retry_delay(retry, attempt.number) {
+                        Some(delay) => {
+                            log::info!(
+                                "Retrying task {:?} with {} in {:?}",
+                                attempt.task.message,
+                                name,
+                                delay
+                            );
+                            let retry = Attempt {
+                                number: attempt.number + 1,
+                                ..attempt
+                            };
+                            ctx.run_later(delay, move |this, ctx| this.send_attempt(ctx, retry));
+                        }
+                        None => {
+                            this.finish_run(attempt.id, attempt.started);
+                            let event = BroadcastEvent::TaskFailed {
+                                task: attempt.message.trim_matches('"').to_string(),
+                                runner: name,
+                                attempts: attempt.number,
+                                error,
+                            };
+                            this.ports.send_alert(event).unwrap_or_else(|e| {
+                                log::error!("Error sending task failure alert: {}", e)
+                            });
+                        }
+                    }
+                },
+            ),
+        );
+    }
+
+    /// Whether the run of a task that started at `started` is still
+    /// going
+    fn is_running(&self, id: usize, started: Instant) -> bool {
+        self.running
+            .get(&id)
+            .map_or(false, |running| running.started == started)
+    }
+
+    /// Note that a task runner has finished a run of a task. The run is
+    /// finished once every task runner has replied
+    fn finish_run(&mut self, id: usize, started: Instant) {
+        if let Some(running) = self.running.get_mut(&id) {
+            if running.started == started {
+                running.remaining -= 1;
+                if running.remaining == 0 {
+                    self.running.remove(&id);
+                }
+            }
+        }
+    }
+
+    fn alert_timeout(&self, message: &str, started_at: NaiveDateTime, timeout: Duration) {
+        log::warn!("Task {} timed out after {:?}", message, timeout);
+        let event = BroadcastEvent::TaskTimedOut {
+            task: message.trim_matches('"').to_string(),
+            started_at,
+            timeout: format_short_duration(timeout),
+        };
+        self.ports
+            .send_alert(event)
+            .unwrap_or_else(|e| log::error!("Error sending task timeout alert: {}", e));
+    }
+
+    /// Whether a recurring task missed a run while pulse was down: the
+    /// run it was scheduled for when pulse stopped has passed, or, if
+    /// that wasn't recorded, a run was due since it last ran. Tasks
+    /// that have never been scheduled haven't missed a run
+    fn missed_run(
+        task: &ScheduledTaskConfig,
+        schedules: &[models::TaskSchedule],
+        last_runs: &HashMap<String, NaiveDateTime>,
+    ) -> bool {
+        let key = task.key();
+        if let Some(schedule) = schedules.iter().find(|schedule| schedule.task_key == key) {
+            return schedule.next_run < Utc::now().naive_utc();
+        }
+
+        last_runs
+            .get(&key)
+            .and_then(|last_run| task.run_after(DateTime::from_utc(*last_run, Utc)))
+            .map_or(false, |missed| missed < Utc::now())
+    }
+
+    /// Whether a one-shot task has already run
+    fn is_completed(task: &ScheduledTaskConfig, completed: &[models::CompletedTask]) -> bool {
+        completed_task(task).map_or(false, |task| {
+            completed.iter().any(|completed| {
+                completed.task == task.task && completed.scheduled_for == task.scheduled_for
+            })
+        })
+    }
+
+    /// When each task was last run, by key, from the task history
+    fn last_runs(tasks: Result<Vec<models::Task>>) -> HashMap<String, DateTime<Local>> {
+        let tasks = tasks.unwrap_or_else(|e| {
+            log::warn!("Error loading task history: {}", e);
+            vec![]
+        });
+
+        // tasks are oldest first, so later runs replace earlier ones
+        tasks
+            .into_iter()
+            .filter_map(|task| {
+                let sent_at = DateTime::<Utc>::from_utc(task.sent_at, Utc);
+                task.task_key
+                    .map(|key| (key, sent_at.with_timezone(&Local)))
+            })
+            .collect()
+    }
+
+    /// The blackout calendars, fetched without holding up the
+    /// scheduler. There are none if no calendars are configured
+    fn fetch_blackout_calendars(&self) -> LocalBoxFuture<'static, Result<CalendarDays>> {
+        match &self.blackout {
+            Some(blackout) if blackout.has_calendars() => blackout.fetch_calendars().boxed_local(),
+            _ => future::ok(CalendarDays::new()).boxed_local(),
+        }
+    }
+
+    fn refresh_blackout(&self, ctx: &mut Context<Self>) {
+        self.fetch_blackout_calendars()
+            .into_actor(self)
+            .map(|calendar_days, this, _| this.update_blackout(calendar_days))
+            .spawn(ctx);
+    }
+
+    fn update_blackout(&mut self, calendar_days: Result<CalendarDays>) {
+        match (calendar_days, self.blackout.as_mut()) {
+            (Ok(calendar_days), Some(blackout)) => blackout.update(calendar_days),
+            (Err(e), _) => log::warn!("Error fetching blackout calendars: {}", e),
+            (Ok(_), None) => {}
         }
+    }
+
+    /// Whether a task is skipped today because it's a blackout day
+    fn is_blacked_out(&self, task: &ScheduledTaskConfig) -> bool {
+        task.blackout == Some(true)
+            && self.blackout.as_ref().map_or(false, |blackout| {
+                blackout.contains(Local::today().naive_local())
+            })
+    }
+
+    /// How a task runner handled the latest run of each task, by key
+    fn last_results(runs: Result<Vec<models::TaskRun>>) -> HashMap<String, TaskResult> {
+        let runs = runs.unwrap_or_else(|e| {
+            log::warn!("Error loading the last task results: {}", e);
+            vec![]
+        });
+
+        runs.into_iter()
+            .filter_map(|run| {
+                let started_at = DateTime::<Utc>::from_utc(run.started_at, Utc);
+                run.task_key.map(|key| {
+                    let result = TaskResult {
+                        runner: run.runner,
+                        succeeded: run.succeeded,
+                        error: run.error,
+                        started_at: started_at.with_timezone(&Local),
+                    };
+                    (key, result)
+                })
+            })
+            .collect()
+    }
+
+    /// Load the tasks registered at runtime and what was recorded about
+    /// each task, then start every enabled task
+    fn start_tasks(&self) -> impl ActorFuture<Output = (), Actor = Self> {
+        future::join3(
+            self.ports.registered_tasks(),
+            self.ports.completed_tasks(),
+            self.ports.task_schedules(),
+        )
+        .into_actor(self)
+        .then(|(registered, completed, schedules), this, _| {
+            this.add_registered_tasks(registered);
+            this.completed = completed.unwrap_or_else(|e| {
+                log::warn!("Error loading completed tasks: {}", e);
+                vec![]
+            });
+            let schedules = schedules.unwrap_or_else(|e| {
+                log::warn!("Error loading task schedules: {}", e);
+                vec![]
+            });
+            this.load_last_runs(&schedules)
+                .into_actor(this)
+                .map(move |last_runs, this, ctx| this.run_tasks(ctx, &schedules, &last_runs))
+        })
+    }
 
-        // schedule the next run of this task based on its cron schedule
-        ctx.run_later(task.duration_until_next(), |this, ctx| {
-            this.schedule_task(ctx, task)
+    /// Tasks registered at runtime follow the configured tasks, in the
+    /// order they were registered
+    fn add_registered_tasks(&mut self, registered: Result<Vec<models::RegisteredTask>>) {
+        let registered = registered.unwrap_or_else(|e| {
+            log::warn!("Error loading registered tasks: {}", e);
+            vec![]
         });
+        for registered in registered {
+            let task = serde_json::from_str::<ScheduledTaskConfig>(&registered.config)
+                .map_err(Error::from)
+                .and_then(|task| task.validate().map(|_| task));
+            match task {
+                Ok(task) => self.tasks.push(task),
+                Err(e) => log::error!("Error loading registered task {}: {}", registered.id, e),
+            }
+        }
+    }
+
+    /// When each task that catches up on missed runs last ran, by key,
+    /// for the tasks whose next run wasn't recorded
+    fn load_last_runs(
+        &self,
+        schedules: &[models::TaskSchedule],
+    ) -> impl Future<Output = HashMap<String, NaiveDateTime>> {
+        let last_runs = self
+            .tasks
+            .iter()
+            .filter(|task| task.catch_up == Some(CatchUpPolicy::RunOnce))
+            .map(ScheduledTaskConfig::key)
+            .filter(|key| !schedules.iter().any(|schedule| &schedule.task_key == key))
+            .map(|key| {
+                self.ports
+                    .last_run(&key)
+                    .map(move |last_run| (key, last_run))
+            })
+            .collect::<Vec<_>>();
+        future::join_all(last_runs).map(|last_runs| {
+            last_runs
+                .into_iter()
+                .filter_map(|(key, last_run)| match last_run {
+                    Ok(last_run) => last_run.map(|last_run| (key, last_run)),
+                    Err(e) => {
+                        log::warn!("Error loading the last run of {}: {}", key, e);
+                        None
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Start enabled tasks, except one-shot tasks that have already
+    /// run. Tasks that run on start, and tasks that catch up on a
+    /// missed run, run right away too
+    fn run_tasks(
+        &mut self,
+        ctx: &mut Context<Self>,
+        schedules: &[models::TaskSchedule],
+        last_runs: &HashMap<String, NaiveDateTime>,
+    ) {
+        for (id, task) in self.tasks.clone().into_iter().enumerate() {
+            if !task.is_enabled() || Self::is_completed(&task, &self.completed) {
+                continue;
+            }
+            if let Err(e) = self.check_routes(&task) {
+                log::warn!("{}", e);
+            }
+            let run_now = if self.is_blacked_out(&task) {
+                false
+            } else if task.run_on_start == Some(true) {
+                log::info!("Running task {:?} on start", task.message);
+                true
+            } else if task.catch_up == Some(CatchUpPolicy::RunOnce)
+                && Self::missed_run(&task, schedules, last_runs)
+            {
+                log::info!("Catching up on a missed run of {:?}", task.message);
+                true
+            } else {
+                false
+            };
+            if run_now {
+                self.run_task(ctx, id, &task).unwrap_or_else(|e| {
+                    log::error!("Error running task {:?}: {}", task.message, e)
+                });
+            }
+            self.schedule_next(ctx, id, task);
+        }
+    }
+
+    /// Check a task that is being registered, and serialize it to be
+    /// saved
+    fn check_new_task(&self, task: &ScheduledTaskConfig) -> Result<models::NewRegisteredTask> {
+        task.validate()?;
+        self.check_routes(task)?;
+        if self
+            .tasks
+            .iter()
+            .any(|existing| existing.key() == task.key())
+        {
+            return Err(Error::invalid_config(format!(
+                "a task is already recorded as {:?}, give the new task a distinct name",
+                task.key()
+            )));
+        }
+        Ok(models::NewRegisteredTask {
+            config: serde_json::to_string(task)?,
+        })
+    }
+
+    /// Check that `id` is one of the configured tasks
+    fn task(&self, id: usize) -> Result<&ScheduledTaskConfig> {
+        self.tasks.get(id).ok_or_else(|| Error::unknown_task(id))
     }
 }
 
@@ -84,13 +762,260 @@ impl Actor for Scheduler {
     /// context to send the configured messages to task_runners on the
     /// configured schedule
     fn started(&mut self, ctx: &mut Context<Self>) {
-        // start tasks
-        for task in &self.tasks {
-            let task = task.clone();
-            ctx.run_later(task.duration_until_next(), move |this, ctx| {
-                this.schedule_task(ctx, task.clone())
-            });
+        // blackout calendars are fetched before tasks start, so that no
+        // task runs on start on a blackout day, and then periodically.
+        // Messages wait until the tasks have started
+        if let Some(blackout) = &self.blackout {
+            if blackout.has_calendars() {
+                ctx.run_interval(blackout.refresh_interval(), |this, ctx| {
+                    this.refresh_blackout(ctx)
+                });
+            }
+        }
+        self.fetch_blackout_calendars()
+            .into_actor(self)
+            .then(|calendar_days, this, _| {
+                this.update_blackout(calendar_days);
+                this.start_tasks()
+            })
+            .wait(ctx);
+    }
+}
+
+/// How long to wait before retrying a task that failed on attempt
+/// `attempt`, or `None` if it has made all of its attempts
+fn retry_delay(retry: &TaskRetryConfig, attempt: u32) -> Option<Duration> {
+    if attempt >= retry.max_attempts {
+        return None;
+    }
+    Some(retry.backoff(attempt))
+}
+
+/// The record of a one-shot task having run, or `None` if the task
+/// isn't a one-shot task
+fn completed_task(task: &ScheduledTaskConfig) -> Option<models::NewCompletedTask> {
+    let at = task.at?;
+    serde_json::to_string(&task.message)
+        .ok()
+        .map(|message| models::NewCompletedTask {
+            task: message,
+            scheduled_for: at.naive_utc(),
+        })
+}
+
+/// How late the most recent task ran, if any have run
+pub struct GetLag;
+impl Message for GetLag {
+    type Result = Option<Duration>;
+}
+
+impl Handler<GetLag> for Scheduler {
+    type Result = Option<Duration>;
+
+    fn handle(&mut self, _: GetLag, _: &mut Context<Self>) -> Self::Result {
+        self.lag
+    }
+}
+
+/// Stop running a task, or every task if no task id is given, until
+/// it is resumed
+pub struct Pause(pub Option<usize>);
+impl Message for Pause {
+    type Result = Result<()>;
+}
+
+impl Handler<Pause> for Scheduler {
+    type Result = Result<()>;
+
+    fn handle(&mut self, Pause(id): Pause, _: &mut Context<Self>) -> Self::Result {
+        match id {
+            Some(id) => {
+                self.task(id)?;
+                self.paused_tasks.insert(id);
+            }
+            None => self.paused = true,
         }
+        Ok(())
+    }
+}
+
+/// Run a paused task again, or the whole scheduler if no task id is
+/// given. Tasks paused individually stay paused when the whole
+/// scheduler is resumed
+pub struct Resume(pub Option<usize>);
+impl Message for Resume {
+    type Result = Result<()>;
+}
+
+impl Handler<Resume> for Scheduler {
+    type Result = Result<()>;
+
+    fn handle(&mut self, Resume(id): Resume, _: &mut Context<Self>) -> Self::Result {
+        match id {
+            Some(id) => {
+                self.task(id)?;
+                self.paused_tasks.remove(&id);
+            }
+            None => self.paused = false,
+        }
+        Ok(())
+    }
+}
+
+/// Turn a task on or off. Unlike paused tasks, disabled tasks aren't
+/// scheduled at all, and start over from their next scheduled run when
+/// enabled again
+pub struct SetEnabled {
+    pub id: usize,
+    pub enabled: bool,
+}
+impl Message for SetEnabled {
+    type Result = Result<()>;
+}
+
+impl Handler<SetEnabled> for Scheduler {
+    type Result = Result<()>;
+
+    fn handle(
+        &mut self,
+        SetEnabled { id, enabled }: SetEnabled,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        if self.task(id)?.is_enabled() == enabled {
+            return Ok(());
+        }
+        self.tasks[id].enabled = Some(enabled);
+
+        if enabled {
+            let task = self.tasks[id].clone();
+            if !Self::is_completed(&task, &self.completed) {
+                self.schedule_next(ctx, id, task);
+            }
+        } else {
+            if let Some(timer) = self.timers.remove(&id) {
+                ctx.cancel_future(timer);
+            }
+            self.next_runs.remove(&id);
+        }
+        Ok(())
+    }
+}
+
+/// Add a task to the running scheduler. Registered tasks are saved,
+/// and scheduled again after a restart. Replies with the new task's id
+pub struct RegisterTask(pub ScheduledTaskConfig);
+impl Message for RegisterTask {
+    type Result = Result<usize>;
+}
+
+impl Handler<RegisterTask> for Scheduler {
+    type Result = ResponseActFuture<Self, Result<usize>>;
+
+    fn handle(&mut self, RegisterTask(task): RegisterTask, _: &mut Context<Self>) -> Self::Result {
+        let registered = match self.check_new_task(&task) {
+            Ok(registered) => registered,
+            Err(e) => return Box::new(future::err(e).into_actor(self)),
+        };
+
+        Box::new(self.ports.register_task(registered).into_actor(self).map(
+            move |registered, this, ctx| {
+                registered?;
+                let id = this.tasks.len();
+                this.tasks.push(task.clone());
+                if task.is_enabled() {
+                    this.schedule_next(ctx, id, task);
+                }
+                log::info!("Registered task {}", id);
+                Ok(id)
+            },
+        ))
+    }
+}
+
+/// How a task runner handled the latest run of a task
+#[derive(Serialize)]
+pub struct TaskResult {
+    pub runner: String,
+    pub succeeded: bool,
+    pub error: Option<String>,
+    pub started_at: DateTime<Local>,
+}
+
+#[derive(Serialize)]
+pub struct ScheduledTaskStatus {
+    pub id: usize,
+    pub cron: Option<String>,
+    pub at: Option<DateTime<Utc>>,
+    /// The interval between runs, e.g. `15m`
+    pub every: Option<String>,
+    /// The most each run is delayed by
+    pub jitter: Option<String>,
+    /// How long a run can take before alerting
+    pub timeout: Option<String>,
+    pub message: ScheduledTaskMessage,
+    /// One-shot tasks that have run won't run again
+    pub next_run: Option<DateTime<Local>>,
+    pub last_run: Option<DateTime<Local>>,
+    pub last_result: Option<TaskResult>,
+    pub paused: bool,
+    pub enabled: bool,
+    /// Whether a run of this task hasn't finished yet
+    pub running: bool,
+}
+
+#[derive(Serialize)]
+pub struct Schedule {
+    pub paused: bool,
+    pub tasks: Vec<ScheduledTaskStatus>,
+}
+
+/// The configured tasks, when they next and last ran, how their last
+/// run went and whether they are paused
+pub struct GetSchedule;
+impl Message for GetSchedule {
+    type Result = Schedule;
+}
+
+impl Handler<GetSchedule> for Scheduler {
+    type Result = ResponseActFuture<Self, Schedule>;
+
+    fn handle(&mut self, _: GetSchedule, _: &mut Context<Self>) -> Self::Result {
+        let since = Utc::now().naive_utc() - chrono::Duration::days(LAST_RUN_LOOKBACK_DAYS);
+        let history = future::join(self.ports.tasks_since(since), self.ports.latest_task_runs());
+
+        Box::new(
+            history
+                .into_actor(self)
+                .map(|(history, latest_task_runs), this, _| {
+                    let last_runs = Self::last_runs(history);
+                    let mut last_results = Self::last_results(latest_task_runs);
+                    let tasks = this
+                        .tasks
+                        .iter()
+                        .enumerate()
+                        .map(|(id, task)| ScheduledTaskStatus {
+                            id,
+                            cron: task.cron.clone(),
+                            at: task.at,
+                            every: task.every.map(format_short_duration),
+                            jitter: task.jitter.map(format_short_duration),
+                            timeout: task.timeout.map(format_short_duration),
+                            message: task.message.clone(),
+                            next_run: this.next_runs.get(&id).cloned(),
+                            last_run: last_runs.get(&task.key()).cloned(),
+                            last_result: last_results.remove(&task.key()),
+                            paused: this.paused_tasks.contains(&id),
+                            enabled: task.is_enabled(),
+                            running: this.running.contains_key(&id),
+                        })
+                        .collect();
+
+                    Schedule {
+                        paused: this.paused,
+                        tasks,
+                    }
+                }),
+        )
     }
 }
 
@@ -103,7 +1028,10 @@ mod test {
         thread, time,
     };
 
-    use crate::{config::ScheduledTaskConfig, error::Result};
+    use crate::{
+        config::{BlackoutConfig, ScheduledTaskConfig},
+        error::Result,
+    };
 
     struct TestActor {
         pub messages_recieved: Arc<Mutex<Vec<ScheduledTaskMessage>>>,
@@ -121,13 +1049,57 @@ mod test {
         }
     }
 
+    struct FailingActor;
+    impl Actor for FailingActor {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<ScheduledTaskMessage> for FailingActor {
+        type Result = Result<()>;
+
+        fn handle(&mut self, _: ScheduledTaskMessage, _ctx: &mut Context<Self>) -> Self::Result {
+            Err(Error::unknown_task(0))
+        }
+    }
+
+    /// Never finishes a task
+    struct HangingActor {
+        pub messages_recieved: Arc<Mutex<Vec<ScheduledTaskMessage>>>,
+    }
+    impl Actor for HangingActor {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<ScheduledTaskMessage> for HangingActor {
+        type Result = ResponseFuture<Result<()>>;
+
+        fn handle(&mut self, msg: ScheduledTaskMessage, _ctx: &mut Context<Self>) -> Self::Result {
+            self.messages_recieved.lock().unwrap().push(msg);
+            Box::pin(futures::future::pending())
+        }
+    }
+
     struct TestSchedulerPorts {
         inserted_tasks: Vec<models::NewTask>,
+        recorded_runs: Vec<models::NewTaskRun>,
+        marked_completed: Vec<models::NewCompletedTask>,
+        completed_tasks: Vec<models::CompletedTask>,
+        task_history: Vec<models::Task>,
+        sent_alerts: Vec<BroadcastEvent>,
+        registered_tasks: Vec<models::RegisteredTask>,
+        task_schedules: Vec<models::TaskSchedule>,
     }
     impl TestSchedulerPorts {
         pub fn new() -> Self {
             Self {
                 inserted_tasks: vec![],
+                recorded_runs: vec![],
+                marked_completed: vec![],
+                completed_tasks: vec![],
+                task_history: vec![],
+                sent_alerts: vec![],
+                registered_tasks: vec![],
+                task_schedules: vec![],
             }
         }
     }
@@ -136,6 +1108,106 @@ mod test {
             self.lock().unwrap().inserted_tasks.push(task);
             Ok(())
         }
+
+        fn record_run(&self, run: models::NewTaskRun) -> Result<()> {
+            self.lock().unwrap().recorded_runs.push(run);
+            Ok(())
+        }
+
+        fn mark_completed(&self, task: models::NewCompletedTask) -> Result<()> {
+            self.lock().unwrap().marked_completed.push(task);
+            Ok(())
+        }
+
+        fn completed_tasks(&self) -> BoxFuture<'static, Result<Vec<models::CompletedTask>>> {
+            futures::future::ok(self.lock().unwrap().completed_tasks.clone()).boxed()
+        }
+
+        fn last_run(&self, task_key: &str) -> BoxFuture<'static, Result<Option<NaiveDateTime>>> {
+            let last_run = self
+                .lock()
+                .unwrap()
+                .task_history
+                .iter()
+                .filter(|run| run.task_key.as_deref() == Some(task_key))
+                .map(|run| run.sent_at)
+                .max();
+            futures::future::ok(last_run).boxed()
+        }
+
+        fn latest_task_runs(&self) -> BoxFuture<'static, Result<Vec<models::TaskRun>>> {
+            // runs are recorded in order, so later runs replace earlier
+            // ones
+            let latest_task_runs = self
+                .lock()
+                .unwrap()
+                .recorded_runs
+                .iter()
+                .enumerate()
+                .filter_map(|(id, run)| run.task_key.as_ref().map(|task_key| (task_key, (id, run))))
+                .collect::<HashMap<_, _>>()
+                .into_iter()
+                .map(|(_, (id, run))| models::TaskRun {
+                    id: id as i32 + 1,
+                    task: run.task.clone(),
+                    runner: run.runner.clone(),
+                    succeeded: run.succeeded,
+                    error: run.error.clone(),
+                    duration_ms: run.duration_ms,
+                    started_at: run.started_at,
+                    task_key: run.task_key.clone(),
+                })
+                .collect();
+            futures::future::ok(latest_task_runs).boxed()
+        }
+
+        fn save_next_run(&self, schedule: models::TaskSchedule) -> Result<()> {
+            let mut ports = self.lock().unwrap();
+            ports
+                .task_schedules
+                .retain(|saved| saved.task_key != schedule.task_key);
+            ports.task_schedules.push(schedule);
+            Ok(())
+        }
+
+        fn task_schedules(&self) -> BoxFuture<'static, Result<Vec<models::TaskSchedule>>> {
+            futures::future::ok(self.lock().unwrap().task_schedules.clone()).boxed()
+        }
+
+        fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+            self.lock().unwrap().sent_alerts.push(event);
+            Ok(())
+        }
+
+        fn register_task(&self, task: models::NewRegisteredTask) -> BoxFuture<'static, Result<()>> {
+            let mut ports = self.lock().unwrap();
+            let id = ports.registered_tasks.len() as i32 + 1;
+            ports.registered_tasks.push(models::RegisteredTask {
+                id,
+                config: task.config,
+                registered_at: Utc::now().naive_utc(),
+            });
+            futures::future::ok(()).boxed()
+        }
+
+        fn registered_tasks(&self) -> BoxFuture<'static, Result<Vec<models::RegisteredTask>>> {
+            futures::future::ok(self.lock().unwrap().registered_tasks.clone()).boxed()
+        }
+
+        fn tasks_since(
+            &self,
+            since: NaiveDateTime,
+        ) -> BoxFuture<'static, Result<Vec<models::Task>>> {
+            let tasks = self
+                .lock()
+                .unwrap()
+                .task_history
+                .iter()
+                .filter(|task| task.sent_at >= since)
+                .cloned()
+                .collect();
+            futures::future::ok(tasks).boxed()
+        }
     }
 
     #[test]
@@ -154,12 +1226,23 @@ mod test {
 
         let mut scheduler = Scheduler::test(
             vec![ScheduledTaskConfig {
-                cron: "* * * * * * *".to_string(),
+                name: None,
+                cron: Some("* * * * * * *".to_string()),
+                at: None,
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
                 message: ScheduledTaskMessage::FetchNews,
             }],
             Box::new(Arc::clone(&ports)),
         );
-        scheduler.add_task_runner(recipient);
+        scheduler.add_task_runner("test", recipient);
         scheduler.start();
 
         let current = System::current();
@@ -193,7 +1276,18 @@ mod test {
 
         Scheduler::test(
             vec![ScheduledTaskConfig {
-                cron: "* * * * * * *".to_string(),
+                name: None,
+                cron: Some("* * * * * * *".to_string()),
+                at: None,
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
                 message: ScheduledTaskMessage::FetchNews,
             }],
             Box::new(Arc::new(Mutex::new(TestSchedulerPorts::new()))),
@@ -209,4 +1303,950 @@ mod test {
 
         system.run().unwrap();
     }
+
+    #[test]
+    fn scheduler_skips_tasks_while_paused() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+
+        let test_actor = TestActor {
+            messages_recieved: Arc::clone(&messages_received),
+        };
+        let recipient = Addr::recipient(test_actor.start());
+
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: Some("* * * * * * *".to_string()),
+                at: None,
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::clone(&ports)),
+        );
+        scheduler.add_task_runner("test", recipient);
+        scheduler.paused = true;
+        let scheduler = scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(1500));
+            assert!(messages_received.lock().unwrap().is_empty());
+
+            let unknown = futures::executor::block_on(scheduler.send(Resume(Some(1)))).unwrap();
+            assert!(unknown.is_err());
+
+            current.stop();
+        });
+
+        system.run().unwrap();
+        assert!(ports.lock().unwrap().inserted_tasks.is_empty());
+    }
+
+    #[test]
+    fn schedule_includes_the_last_run_of_each_task() {
+        let system = System::new("test");
+
+        let now = Utc::now().naive_utc();
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        ports.lock().unwrap().task_history = vec![
+            models::Task {
+                id: 1,
+                task: "\"fetch-news\"".to_string(),
+                sent_at: now - chrono::Duration::days(30),
+                task_key: Some("fetch-news cron:0 0 0 1 1 * 2100".to_string()),
+            },
+            models::Task {
+                id: 2,
+                task: "\"fetch-news\"".to_string(),
+                sent_at: now - chrono::Duration::hours(2),
+                task_key: Some("fetch-news cron:0 0 0 1 1 * 2100".to_string()),
+            },
+            models::Task {
+                id: 3,
+                task: "\"fetch-news\"".to_string(),
+                sent_at: now - chrono::Duration::hours(1),
+                task_key: Some("fetch-news cron:0 0 0 1 1 * 2100".to_string()),
+            },
+        ];
+
+        let task = ScheduledTaskConfig {
+            name: None,
+            cron: Some("0 0 0 1 1 * 2100".to_string()),
+            at: None,
+            every: None,
+            jitter: None,
+            timeout: None,
+            catch_up: None,
+            run_on_start: None,
+            runners: None,
+            blackout: None,
+            retry: None,
+            enabled: None,
+            message: ScheduledTaskMessage::FetchNews,
+        };
+        let other_task = ScheduledTaskConfig {
+            cron: Some("0 0 0 1 2 * 2100".to_string()),
+            ..task.clone()
+        };
+        let scheduler =
+            Scheduler::test(vec![task, other_task], Box::new(Arc::clone(&ports))).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            let schedule = futures::executor::block_on(scheduler.send(GetSchedule)).unwrap();
+            let last_run = schedule.tasks[0].last_run.unwrap();
+            assert_eq!(
+                last_run.with_timezone(&Utc).naive_utc(),
+                now - chrono::Duration::hours(1)
+            );
+            assert!(schedule.tasks[1].last_run.is_none());
+
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn scheduler_records_how_each_runner_handled_a_task() {
+        let system = System::new("test");
+
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: Some("* * * * * * *".to_string()),
+                at: None,
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::clone(&ports)),
+        );
+        let succeeding = TestActor {
+            messages_recieved: Arc::new(Mutex::new(vec![])),
+        };
+        scheduler.add_task_runner("succeeding", Addr::recipient(succeeding.start()));
+        scheduler.add_task_runner("failing", Addr::recipient(FailingActor.start()));
+        scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(1500));
+            current.stop();
+        });
+
+        system.run().unwrap();
+
+        let runs = ports.lock().unwrap().recorded_runs.clone();
+        let succeeded = runs.iter().find(|run| run.runner == "succeeding").unwrap();
+        assert!(succeeded.succeeded);
+        assert_eq!(succeeded.task, "\"fetch-news\"");
+        assert_eq!(
+            succeeded.task_key,
+            Some("fetch-news cron:* * * * * * *".to_string())
+        );
+        assert_eq!(succeeded.error, None);
+        let failed = runs.iter().find(|run| run.runner == "failing").unwrap();
+        assert!(!failed.succeeded);
+        assert!(failed.error.is_some());
+    }
+
+    #[test]
+    fn scheduler_runs_one_shot_tasks_once() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let test_actor = TestActor {
+            messages_recieved: Arc::clone(&messages_received),
+        };
+        let recipient = Addr::recipient(test_actor.start());
+
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        let at = Utc::now() - chrono::Duration::minutes(1);
+
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: None,
+                at: Some(at),
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::clone(&ports)),
+        );
+        scheduler.add_task_runner("test", recipient);
+        let scheduler = scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(500));
+            assert_eq!(messages_received.lock().unwrap().len(), 1);
+
+            let schedule = futures::executor::block_on(scheduler.send(GetSchedule)).unwrap();
+            assert!(schedule.tasks[0].next_run.is_none());
+
+            current.stop();
+        });
+
+        system.run().unwrap();
+        let marked_completed = ports.lock().unwrap().marked_completed.clone();
+        assert_eq!(marked_completed.len(), 1);
+        assert_eq!(marked_completed[0].scheduled_for, at.naive_utc());
+    }
+
+    #[test]
+    fn scheduler_skips_completed_one_shot_tasks() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let test_actor = TestActor {
+            messages_recieved: Arc::clone(&messages_received),
+        };
+        let recipient = Addr::recipient(test_actor.start());
+
+        let at = Utc::now() - chrono::Duration::minutes(1);
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        ports.lock().unwrap().completed_tasks = vec![models::CompletedTask {
+            task: "\"fetch-news\"".to_string(),
+            scheduled_for: at.naive_utc(),
+            completed_at: at.naive_utc(),
+        }];
+
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: None,
+                at: Some(at),
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::clone(&ports)),
+        );
+        scheduler.add_task_runner("test", recipient);
+        scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(500));
+            assert!(messages_received.lock().unwrap().is_empty());
+            current.stop();
+        });
+
+        system.run().unwrap();
+        assert!(ports.lock().unwrap().marked_completed.is_empty());
+    }
+
+    #[test]
+    fn scheduler_runs_tasks_at_an_interval() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let test_actor = TestActor {
+            messages_recieved: Arc::clone(&messages_received),
+        };
+        let recipient = Addr::recipient(test_actor.start());
+
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: None,
+                at: None,
+                every: Some(time::Duration::from_millis(200)),
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::clone(&ports)),
+        );
+        scheduler.add_task_runner("test", recipient);
+        scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(700));
+            let messages = messages_received.lock().unwrap().len();
+            assert!(messages >= 2 && messages <= 3);
+            current.stop();
+        });
+
+        system.run().unwrap();
+        assert!(!ports.lock().unwrap().inserted_tasks.is_empty());
+    }
+
+    #[test]
+    fn scheduler_delays_runs_by_up_to_their_jitter() {
+        let system = System::new("test");
+
+        let task = ScheduledTaskConfig {
+            name: None,
+            cron: Some("0 0 0 1 1 * 2100".to_string()),
+            at: None,
+            every: None,
+            jitter: Some(time::Duration::from_secs(30)),
+            timeout: None,
+            catch_up: None,
+            run_on_start: None,
+            runners: None,
+            blackout: None,
+            retry: None,
+            enabled: None,
+            message: ScheduledTaskMessage::FetchNews,
+        };
+        let scheduled_for = task.next_run();
+        let scheduler = Scheduler::test(
+            vec![task],
+            Box::new(Arc::new(Mutex::new(TestSchedulerPorts::new()))),
+        )
+        .start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            let schedule = futures::executor::block_on(scheduler.send(GetSchedule)).unwrap();
+            let next_run = schedule.tasks[0].next_run.unwrap();
+            assert!(next_run >= scheduled_for);
+            assert!(next_run <= scheduled_for + chrono::Duration::seconds(30));
+
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    fn catch_up_scheduler(
+        catch_up: Option<CatchUpPolicy>,
+        messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>>,
+    ) -> Scheduler {
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        ports.lock().unwrap().task_history = vec![models::Task {
+            id: 1,
+            task: "\"fetch-news\"".to_string(),
+            sent_at: Utc::now().naive_utc() - chrono::Duration::days(2),
+            task_key: Some("morning-news".to_string()),
+        }];
+        catch_up_scheduler_with_ports(catch_up, messages_received, ports)
+    }
+
+    fn catch_up_scheduler_with_ports(
+        catch_up: Option<CatchUpPolicy>,
+        messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>>,
+        ports: Arc<Mutex<TestSchedulerPorts>>,
+    ) -> Scheduler {
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: Some("morning-news".to_string()),
+                cron: Some("0 0 9 * * * *".to_string()),
+                at: None,
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(ports),
+        );
+        let test_actor = TestActor {
+            messages_recieved: messages_received,
+        };
+        scheduler.add_task_runner("test", Addr::recipient(test_actor.start()));
+        scheduler
+    }
+
+    #[test]
+    fn scheduler_catches_up_on_missed_runs() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        catch_up_scheduler(Some(CatchUpPolicy::RunOnce), Arc::clone(&messages_received)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(300));
+            assert_eq!(messages_received.lock().unwrap().len(), 1);
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn scheduler_doesnt_catch_up_on_runs_of_other_tasks() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        ports.lock().unwrap().task_history = vec![models::Task {
+            id: 1,
+            task: "\"fetch-news\"".to_string(),
+            sent_at: Utc::now().naive_utc() - chrono::Duration::days(2),
+            task_key: Some("evening-news".to_string()),
+        }];
+        catch_up_scheduler_with_ports(
+            Some(CatchUpPolicy::RunOnce),
+            Arc::clone(&messages_received),
+            ports,
+        )
+        .start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(300));
+            assert!(messages_received.lock().unwrap().is_empty());
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn scheduler_skips_missed_runs_by_default() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        catch_up_scheduler(None, Arc::clone(&messages_received)).start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(300));
+            assert!(messages_received.lock().unwrap().is_empty());
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn scheduler_skips_and_alerts_on_tasks_that_run_too_long() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: None,
+                at: None,
+                every: Some(time::Duration::from_millis(100)),
+                jitter: None,
+                timeout: Some(time::Duration::from_millis(250)),
+                catch_up: None,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::clone(&ports)),
+        );
+        let hanging_actor = HangingActor {
+            messages_recieved: Arc::clone(&messages_received),
+        };
+        scheduler.add_task_runner("test", Addr::recipient(hanging_actor.start()));
+        scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(600));
+            assert_eq!(messages_received.lock().unwrap().len(), 1);
+            current.stop();
+        });
+
+        system.run().unwrap();
+        let alerts = ports.lock().unwrap().sent_alerts.clone();
+        assert_eq!(alerts.len(), 1);
+        match &alerts[0] {
+            BroadcastEvent::TaskTimedOut { task, .. } => assert_eq!(task, "fetch-news"),
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn scheduler_only_runs_enabled_tasks() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let test_actor = TestActor {
+            messages_recieved: Arc::clone(&messages_received),
+        };
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: None,
+                at: None,
+                every: Some(time::Duration::from_millis(100)),
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: None,
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: Some(false),
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::new(Mutex::new(TestSchedulerPorts::new()))),
+        );
+        scheduler.add_task_runner("test", Addr::recipient(test_actor.start()));
+        let scheduler = scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(300));
+            assert!(messages_received.lock().unwrap().is_empty());
+
+            let enable = SetEnabled {
+                id: 0,
+                enabled: true,
+            };
+            futures::executor::block_on(scheduler.send(enable))
+                .unwrap()
+                .unwrap();
+            thread::sleep(time::Duration::from_millis(350));
+            assert!(!messages_received.lock().unwrap().is_empty());
+
+            let disable = SetEnabled {
+                id: 0,
+                enabled: false,
+            };
+            futures::executor::block_on(scheduler.send(disable))
+                .unwrap()
+                .unwrap();
+            let received = messages_received.lock().unwrap().len();
+            thread::sleep(time::Duration::from_millis(300));
+            assert_eq!(messages_received.lock().unwrap().len(), received);
+
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn scheduler_runs_and_restores_registered_tasks() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let test_actor = TestActor {
+            messages_recieved: Arc::clone(&messages_received),
+        };
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        let mut scheduler = Scheduler::test(vec![], Box::new(Arc::clone(&ports)));
+        scheduler.add_task_runner("test", Addr::recipient(test_actor.start()));
+        let scheduler = scheduler.start();
+
+        let task = ScheduledTaskConfig {
+            name: None,
+            cron: Some("* * * * * * *".to_string()),
+            at: None,
+            every: None,
+            jitter: None,
+            timeout: None,
+            catch_up: None,
+            run_on_start: None,
+            runners: None,
+            blackout: None,
+            retry: None,
+            enabled: None,
+            message: ScheduledTaskMessage::FetchNews,
+        };
+        let invalid = ScheduledTaskConfig {
+            cron: None,
+            ..task.clone()
+        };
+
+        let current = System::current();
+        thread::spawn(move || {
+            let registered = futures::executor::block_on(scheduler.send(RegisterTask(invalid)));
+            assert!(registered.unwrap().is_err());
+            let registered =
+                futures::executor::block_on(scheduler.send(RegisterTask(task.clone())));
+            assert_eq!(registered.unwrap().unwrap(), 0);
+            let duplicate = futures::executor::block_on(scheduler.send(RegisterTask(task)));
+            assert!(duplicate.unwrap().is_err());
+
+            thread::sleep(time::Duration::from_millis(1500));
+            assert!(!messages_received.lock().unwrap().is_empty());
+            current.stop();
+        });
+
+        system.run().unwrap();
+
+        // a new scheduler picks up the registered task
+        let system = System::new("test");
+        let restored = Scheduler::test(vec![], Box::new(Arc::clone(&ports))).start();
+        let current = System::current();
+        thread::spawn(move || {
+            let schedule = futures::executor::block_on(restored.send(GetSchedule)).unwrap();
+            assert_eq!(schedule.tasks.len(), 1);
+            assert_eq!(schedule.tasks[0].cron, Some("* * * * * * *".to_string()));
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn scheduler_runs_tasks_on_start() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let test_actor = TestActor {
+            messages_recieved: Arc::clone(&messages_received),
+        };
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: Some("0 0 0 1 1 * 2100".to_string()),
+                at: None,
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: Some(true),
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::new(Mutex::new(TestSchedulerPorts::new()))),
+        );
+        scheduler.add_task_runner("test", Addr::recipient(test_actor.start()));
+        scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(300));
+            assert_eq!(messages_received.lock().unwrap().len(), 1);
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn retry_delay_backs_off_until_attempts_run_out() {
+        let retry = TaskRetryConfig {
+            max_attempts: 4,
+            initial_backoff: Some(time::Duration::from_secs(10)),
+            max_backoff: Some(time::Duration::from_secs(30)),
+        };
+        let delays = (1..=4)
+            .map(|attempt| retry_delay(&retry, attempt))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            vec![
+                Some(time::Duration::from_secs(10)),
+                Some(time::Duration::from_secs(20)),
+                Some(time::Duration::from_secs(30)),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn scheduler_retries_failed_tasks_and_alerts_when_out_of_attempts() {
+        let system = System::new("test");
+
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: Some("0 0 0 1 1 * 2100".to_string()),
+                at: None,
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: Some(true),
+                runners: None,
+                blackout: None,
+                retry: Some(TaskRetryConfig {
+                    max_attempts: 3,
+                    initial_backoff: Some(time::Duration::from_millis(50)),
+                    max_backoff: Some(time::Duration::from_millis(100)),
+                }),
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::clone(&ports)),
+        );
+        scheduler.add_task_runner("failing", Addr::recipient(FailingActor.start()));
+        scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(500));
+            current.stop();
+        });
+
+        system.run().unwrap();
+        let ports = ports.lock().unwrap();
+        assert_eq!(ports.recorded_runs.len(), 3);
+        assert!(ports.recorded_runs.iter().all(|run| !run.succeeded));
+        assert_eq!(ports.sent_alerts.len(), 1);
+        match &ports.sent_alerts[0] {
+            BroadcastEvent::TaskFailed {
+                runner, attempts, ..
+            } => {
+                assert_eq!(runner, "failing");
+                assert_eq!(*attempts, 3);
+            }
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn scheduler_sends_tasks_to_their_runners() {
+        let system = System::new("test");
+
+        let news_messages: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let other_messages: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let news = TestActor {
+            messages_recieved: Arc::clone(&news_messages),
+        };
+        let other = TestActor {
+            messages_recieved: Arc::clone(&other_messages),
+        };
+        let task = ScheduledTaskConfig {
+            name: None,
+            cron: Some("0 0 0 1 1 * 2100".to_string()),
+            at: None,
+            every: None,
+            jitter: None,
+            timeout: None,
+            catch_up: None,
+            run_on_start: Some(true),
+            runners: Some(vec!["news".to_string()]),
+            blackout: None,
+            retry: None,
+            enabled: None,
+            message: ScheduledTaskMessage::FetchNews,
+        };
+        let unknown_runner = ScheduledTaskConfig {
+            runners: Some(vec!["podcasts".to_string()]),
+            run_on_start: None,
+            ..task.clone()
+        };
+        let mut scheduler = Scheduler::test(
+            vec![task],
+            Box::new(Arc::new(Mutex::new(TestSchedulerPorts::new()))),
+        );
+        scheduler.add_task_runner("news", Addr::recipient(news.start()));
+        scheduler.add_task_runner("other", Addr::recipient(other.start()));
+        let scheduler = scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            let registered =
+                futures::executor::block_on(scheduler.send(RegisterTask(unknown_runner)));
+            assert!(registered.unwrap().is_err());
+
+            thread::sleep(time::Duration::from_millis(300));
+            assert_eq!(news_messages.lock().unwrap().len(), 1);
+            assert!(other_messages.lock().unwrap().is_empty());
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn schedule_includes_the_last_result_of_each_task() {
+        let system = System::new("test");
+
+        let mut scheduler = Scheduler::test(
+            vec![ScheduledTaskConfig {
+                name: None,
+                cron: Some("0 0 0 1 1 * 2100".to_string()),
+                at: None,
+                every: None,
+                jitter: None,
+                timeout: None,
+                catch_up: None,
+                run_on_start: Some(true),
+                runners: None,
+                blackout: None,
+                retry: None,
+                enabled: None,
+                message: ScheduledTaskMessage::FetchNews,
+            }],
+            Box::new(Arc::new(Mutex::new(TestSchedulerPorts::new()))),
+        );
+        scheduler.add_task_runner("failing", Addr::recipient(FailingActor.start()));
+        let scheduler = scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(300));
+            let schedule = futures::executor::block_on(scheduler.send(GetSchedule)).unwrap();
+            let last_result = schedule.tasks[0].last_result.as_ref().unwrap();
+            assert_eq!(last_result.runner, "failing");
+            assert!(!last_result.succeeded);
+            assert!(last_result.error.is_some());
+
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
+
+    #[test]
+    fn scheduler_catches_up_on_the_recorded_next_run() {
+        let system = System::new("test");
+
+        // the task never ran, but was due an hour ago
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        ports.lock().unwrap().task_schedules = vec![models::TaskSchedule {
+            task_key: "morning-news".to_string(),
+            next_run: Utc::now().naive_utc() - chrono::Duration::hours(1),
+        }];
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        catch_up_scheduler_with_ports(
+            Some(CatchUpPolicy::RunOnce),
+            Arc::clone(&messages_received),
+            Arc::clone(&ports),
+        )
+        .start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(300));
+            assert_eq!(messages_received.lock().unwrap().len(), 1);
+            current.stop();
+        });
+
+        system.run().unwrap();
+
+        // the next run is recorded in place of the missed one
+        let schedules = ports.lock().unwrap().task_schedules.clone();
+        assert_eq!(schedules.len(), 1);
+        assert!(schedules[0].next_run > Utc::now().naive_utc());
+    }
+
+    #[test]
+    fn scheduler_ignores_the_recorded_next_run_of_other_tasks() {
+        let system = System::new("test");
+
+        let ports = Arc::new(Mutex::new(TestSchedulerPorts::new()));
+        ports.lock().unwrap().task_schedules = vec![models::TaskSchedule {
+            task_key: "evening-news".to_string(),
+            next_run: Utc::now().naive_utc() - chrono::Duration::hours(1),
+        }];
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        catch_up_scheduler_with_ports(
+            Some(CatchUpPolicy::RunOnce),
+            Arc::clone(&messages_received),
+            Arc::clone(&ports),
+        )
+        .start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(300));
+            assert!(messages_received.lock().unwrap().is_empty());
+            current.stop();
+        });
+
+        system.run().unwrap();
+
+        let schedules = ports.lock().unwrap().task_schedules.clone();
+        assert_eq!(schedules.len(), 2);
+    }
+
+    #[test]
+    fn scheduler_skips_blacked_out_tasks() {
+        let system = System::new("test");
+
+        let messages_received: Arc<Mutex<Vec<ScheduledTaskMessage>>> = Arc::new(Mutex::new(vec![]));
+        let test_actor = TestActor {
+            messages_recieved: Arc::clone(&messages_received),
+        };
+        let task = ScheduledTaskConfig {
+            name: None,
+            cron: None,
+            at: None,
+            every: Some(time::Duration::from_millis(100)),
+            jitter: None,
+            timeout: None,
+            catch_up: None,
+            run_on_start: Some(true),
+            runners: None,
+            blackout: Some(true),
+            retry: None,
+            enabled: None,
+            message: ScheduledTaskMessage::FetchNews,
+        };
+        let mut scheduler = Scheduler::test(
+            vec![task],
+            Box::new(Arc::new(Mutex::new(TestSchedulerPorts::new()))),
+        );
+        scheduler.blackout = Some(Blackout::new(BlackoutConfig {
+            dates: Some(vec![Local::today().naive_local()]),
+            calendar_urls: None,
+            refresh_interval: None,
+        }));
+        scheduler.add_task_runner("test", Addr::recipient(test_actor.start()));
+        scheduler.start();
+
+        let current = System::current();
+        thread::spawn(move || {
+            thread::sleep(time::Duration::from_millis(350));
+            assert!(messages_received.lock().unwrap().is_empty());
+            current.stop();
+        });
+
+        system.run().unwrap();
+    }
 }
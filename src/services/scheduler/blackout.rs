@@ -0,0 +1,85 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use actix_web::web;
+use chrono::NaiveDate;
+use futures::{Future, FutureExt};
+
+use crate::{
+    config::BlackoutConfig,
+    error::{Error, Result},
+    services::news::fetch_events,
+};
+
+/// How often blackout calendars are fetched again, if not configured
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The days of the events in each blackout calendar, by url
+pub type CalendarDays = HashMap<String, HashSet<NaiveDate>>;
+
+/// Days on which tasks that observe the blackout are skipped, either
+/// listed in the config or the days that events in the blackout
+/// calendars start on
+pub struct Blackout {
+    dates: HashSet<NaiveDate>,
+    calendar_urls: Vec<String>,
+    /// The days of the events in each calendar, as of the last time it
+    /// was fetched
+    calendar_days: CalendarDays,
+    refresh_interval: Duration,
+}
+
+impl Blackout {
+    pub fn new(config: BlackoutConfig) -> Self {
+        Self {
+            dates: config.dates.into_iter().flatten().collect(),
+            calendar_urls: config.calendar_urls.unwrap_or_default(),
+            calendar_days: HashMap::new(),
+            refresh_interval: config.refresh_interval.unwrap_or(DEFAULT_REFRESH_INTERVAL),
+        }
+    }
+
+    pub fn has_calendars(&self) -> bool {
+        !self.calendar_urls.is_empty()
+    }
+
+    pub fn refresh_interval(&self) -> Duration {
+        self.refresh_interval
+    }
+
+    /// Fetch the blackout calendars on the blocking thread pool, so
+    /// that the scheduler isn't held up waiting on them. Calendars that
+    /// can't be fetched are left out
+    pub fn fetch_calendars(&self) -> impl Future<Output = Result<CalendarDays>> {
+        let urls = self.calendar_urls.clone();
+        web::block(move || Ok::<_, Error>(fetch_calendar_days(&urls)))
+            .map(|result| result.map_err(Into::into))
+    }
+
+    /// Use the days of freshly fetched calendars. Calendars that
+    /// couldn't be fetched keep the days they had
+    pub fn update(&mut self, calendar_days: CalendarDays) {
+        self.calendar_days.extend(calendar_days);
+    }
+
+    pub fn contains(&self, day: NaiveDate) -> bool {
+        self.dates.contains(&day) || self.calendar_days.values().any(|days| days.contains(&day))
+    }
+}
+
+fn fetch_calendar_days(urls: &[String]) -> CalendarDays {
+    urls.iter()
+        .filter_map(|url| match fetch_events(url) {
+            Ok(events) => {
+                let days = events.iter().map(|event| event.starts_at.date()).collect();
+                Some((url.clone(), days))
+            }
+            Err(e) => {
+                log::warn!("Error fetching blackout calendar {}: {}", url, e);
+                None
+            }
+        })
+        .collect()
+}
@@ -1,50 +1,101 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
-use actix::{Actor, AsyncContext, Context, Handler, Message, Recipient};
+use actix::{
+    Actor, ActorFuture, AsyncContext, Context, ContextFutureSpawner, Handler, Message, Recipient,
+    WrapFuture,
+};
+use chrono::{NaiveDateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
 use systemstat::{Filesystem, Platform, System as LocalSystem};
 
 use crate::{
-    config::{config, FilesystemConfig, ScheduledStreamConfig, SystemMonitorConfig},
-    db::{database, models},
+    config::{
+        config, FilesystemConfig, ScheduledStreamConfig, SystemMonitorConfig, TweetRetentionConfig,
+    },
+    db::{self, models},
     error::{Error, Result},
     services::{
-        broadcast::{BroadcastEvent, OUTBOX},
+        broadcast::{BroadcastEvent, Outbox},
         scheduler::ScheduledStreamMessage,
+        Ping, ReloadConfig,
     },
 };
 
 trait SystemMonitorPorts {
-    fn record_disk_usage(&self, disk_usage: models::NewDiskUsage) -> Result<models::DiskUsage>;
+    fn record_disk_usage(
+        &self,
+        disk_usage: models::NewDiskUsage,
+    ) -> BoxFuture<'static, Result<models::DiskUsage>>;
 
     fn send_alert(&self, event: BroadcastEvent) -> Result<()>;
+
+    fn roll_up_disk_usage(&self, before: NaiveDateTime) -> Result<()>;
+
+    fn delete_old_tweets(&self, before: Option<NaiveDateTime>, keep: Option<i64>) -> Result<()>;
 }
 
-struct LiveSystemMonitorPorts;
+struct LiveSystemMonitorPorts {
+    outbox: Outbox,
+}
 impl SystemMonitorPorts for LiveSystemMonitorPorts {
-    fn record_disk_usage(&self, disk_usage: models::NewDiskUsage) -> Result<models::DiskUsage> {
-        database().insert_disk_usage(disk_usage)
+    fn record_disk_usage(
+        &self,
+        disk_usage: models::NewDiskUsage,
+    ) -> BoxFuture<'static, Result<models::DiskUsage>> {
+        db::run(move |database| database.insert_disk_usage(disk_usage)).boxed()
     }
 
     fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
-        OUTBOX.push(event).map_err(Into::into)
+        self.outbox.push(event)
+    }
+
+    fn roll_up_disk_usage(&self, before: NaiveDateTime) -> Result<()> {
+        db::spawn_write("rolling up disk usage", move |database| {
+            let samples = database.roll_up_disk_usage(before)?;
+            log::info!(
+                "Rolled up {} disk usage samples from before {}",
+                samples,
+                before
+            );
+            Ok(())
+        });
+        Ok(())
+    }
+
+    fn delete_old_tweets(&self, before: Option<NaiveDateTime>, keep: Option<i64>) -> Result<()> {
+        db::spawn_write("deleting old tweets", move |database| {
+            let deleted = database.delete_old_tweets(before, keep)?;
+            log::info!("Deleted {} tweets past their retention", deleted);
+            Ok(())
+        });
+        Ok(())
     }
 }
 
+/// How often old disk usage is rolled up, and old tweets are deleted
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 pub struct SystemMonitor {
     system: LocalSystem,
     config: SystemMonitorConfig,
     streams: Vec<ScheduledStreamConfig>,
-    subscribers: HashMap<usize, Subscriber>,
+    tweet_retention: Option<TweetRetentionConfig>,
+    subscribers: HashMap<usize, Subscription>,
     ports: Box<dyn SystemMonitorPorts>,
 }
 impl SystemMonitor {
-    pub fn new() -> Self {
+    pub fn new(outbox: Outbox) -> Self {
         Self {
             system: LocalSystem::new(),
             config: config().system_monitor.unwrap(),
             streams: config().streams,
+            tweet_retention: config().twitter.and_then(|twitter| twitter.retention),
             subscribers: HashMap::new(),
-            ports: Box::new(LiveSystemMonitorPorts),
+            ports: Box::new(LiveSystemMonitorPorts { outbox }),
         }
     }
 
@@ -58,6 +109,7 @@ impl SystemMonitor {
             system: LocalSystem::new(),
             config,
             streams,
+            tweet_retention: None,
             subscribers: HashMap::new(),
             ports,
         }
@@ -85,50 +137,89 @@ impl SystemMonitor {
             .and_then(|path| self.system.mount_at(path).map_err(Into::into))
     }
 
-    fn check_all_filesystems_usage(&self) -> Result<()> {
+    /// Send an update to every subscriber of its stream
+    fn publish(&self, update: MonitorUpdate) -> Result<()> {
+        let stream = update.stream();
+        self.subscribers
+            .values()
+            .filter(|subscription| subscription.streams.contains(&stream))
+            .map(|subscription| {
+                subscription
+                    .subscriber
+                    .do_send(update.clone())
+                    .map_err(Into::into)
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|_| ())
+    }
+
+    fn check_all_filesystems_usage(&self, ctx: &mut Context<Self>) -> Result<()> {
         self.filesystems()
             .iter()
-            .map(|fs| self.check_filesystem_usage(fs))
+            .map(|fs| self.check_filesystem_usage(fs, ctx))
             .collect::<Result<Vec<_>>>()
             .map(|_| ())
     }
 
-    fn check_filesystem_usage(&self, filesystem_config: &FilesystemConfig) -> Result<()> {
-        self.get_mount(filesystem_config)
-            .and_then(|filesystem| {
-                let disk_usage = ((filesystem.total.as_u64() - filesystem.avail.as_u64()) as f64
-                    / filesystem.total.as_u64() as f64)
-                    * 100_f64;
-                let disk_usage =
-                    models::NewDiskUsage::new(filesystem.fs_mounted_on.clone(), disk_usage);
+    fn check_filesystem_usage(
+        &self,
+        filesystem_config: &FilesystemConfig,
+        ctx: &mut Context<Self>,
+    ) -> Result<()> {
+        let filesystem = self.get_mount(filesystem_config)?;
+        let percent_disk_used = ((filesystem.total.as_u64() - filesystem.avail.as_u64()) as f64
+            / filesystem.total.as_u64() as f64)
+            * 100_f64;
+
+        // if the current usage exceeds the threshold, send an alert
+        if percent_disk_used > filesystem_config.available_space_alert_above {
+            let message = BroadcastEvent::HighDiskUsage {
+                filesystem_mount: filesystem.fs_mounted_on.clone(),
+                current_usage: percent_disk_used,
+                max_usage: filesystem_config.available_space_alert_above,
+            };
+
+            self.ports.send_alert(message)?
+        }
 
-                // record current usage in the database
-                self.ports
-                    .record_disk_usage(disk_usage.clone())
-                    .map(|disk_usage| (filesystem, disk_usage))
-            })
-            .and_then(|(filesystem, disk_usage)| {
-                // send filesystem updates to all subscribers
-                self.subscribers
-                    .values()
-                    .map(|subscriber| subscriber.do_send(disk_usage.clone()).map_err(Into::into))
-                    .collect::<Result<Vec<_>>>()
-                    .map(|_| (filesystem, disk_usage))
+        // record current usage in the database without waiting on it,
+        // then send it to subscribers
+        let disk_usage = models::NewDiskUsage::new(filesystem.fs_mounted_on, percent_disk_used);
+        self.ports
+            .record_disk_usage(disk_usage)
+            .into_actor(self)
+            .map(|result, this, _| {
+                result
+                    .and_then(|disk_usage| this.publish(MonitorUpdate::DiskUsage(disk_usage)))
+                    .unwrap_or_else(|e| log::error!("Error recording disk usage: {}", e))
             })
-            .and_then(|(filesystem, disk_usage)| {
-                // if the current usage exceeds the threshold, send an alert
-                if disk_usage.percent_disk_used > filesystem_config.available_space_alert_above {
-                    let message = BroadcastEvent::HighDiskUsage {
-                        filesystem_mount: filesystem.fs_mounted_on,
-                        current_usage: disk_usage.percent_disk_used,
-                        max_usage: filesystem_config.available_space_alert_above,
-                    };
-
-                    self.ports.send_alert(message)?
-                }
+            .spawn(ctx);
 
-                Ok(())
-            })
+        Ok(())
+    }
+
+    /// Roll up disk usage older than the configured age, if any
+    fn roll_up_disk_usage(&self) -> Result<()> {
+        if let Some(days) = self.config.rollup_after_days {
+            let before = Utc::now().naive_utc() - chrono::Duration::days(days);
+            self.ports.roll_up_disk_usage(before)?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete tweets past the configured retention, if any
+    fn delete_old_tweets(&self) -> Result<()> {
+        if let Some(retention) = &self.tweet_retention {
+            let before = retention
+                .max_age_days
+                .map(|days| Utc::now().naive_utc() - chrono::Duration::days(days));
+            if before.is_some() || retention.max_rows.is_some() {
+                self.ports.delete_old_tweets(before, retention.max_rows)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -140,11 +231,11 @@ impl Actor for SystemMonitor {
     fn started(&mut self, ctx: &mut Context<Self>) {
         ctx.run_interval(
             Duration::from_millis(self.config.tick_ms),
-            move |this, _ctx| {
+            move |this, ctx| {
                 for stream in &this.streams {
                     match stream.message {
                         ScheduledStreamMessage::CheckDiskUsage => this
-                            .check_all_filesystems_usage()
+                            .check_all_filesystems_usage(ctx)
                             .or_else::<Error, _>(|e| {
                                 log::error!("Error encountered checking filesystem usage: {:?}", e);
                                 Ok(())
@@ -154,11 +245,53 @@ impl Actor for SystemMonitor {
                 }
             },
         );
+
+        ctx.run_interval(MAINTENANCE_INTERVAL, |this, _ctx| {
+            this.roll_up_disk_usage()
+                .unwrap_or_else(|e| log::error!("Error rolling up disk usage: {}", e));
+            this.delete_old_tweets()
+                .unwrap_or_else(|e| log::error!("Error deleting old tweets: {}", e));
+        });
+    }
+}
+
+/// An update streamed to subscribers, tagged with its stream, e.g.
+/// `{"type": "disk_usage", "data": {...}}`
+#[derive(Clone, Debug, Message, Serialize, Deserialize)]
+#[rtype(result = "()")]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum MonitorUpdate {
+    DiskUsage(models::DiskUsage),
+}
+
+impl MonitorUpdate {
+    pub fn stream(&self) -> MonitorStream {
+        match self {
+            MonitorUpdate::DiskUsage(_) => MonitorStream::DiskUsage,
+        }
     }
 }
 
 /// Subscribe to system updates
-type Subscriber = Recipient<models::DiskUsage>;
+type Subscriber = Recipient<MonitorUpdate>;
+
+/// The kinds of updates streamed to subscribers
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorStream {
+    DiskUsage,
+}
+
+impl MonitorStream {
+    pub const ALL: &'static [MonitorStream] = &[MonitorStream::DiskUsage];
+}
+
+/// A subscriber and the streams it receives, every stream until it
+/// chooses otherwise
+struct Subscription {
+    subscriber: Subscriber,
+    streams: HashSet<MonitorStream>,
+}
 
 #[derive(Message)]
 #[rtype(result = "usize")]
@@ -168,16 +301,60 @@ pub struct Subscribe(pub Subscriber);
 #[rtype(result = "()")]
 pub struct Unsubscribe(pub usize);
 
+/// Choose which streams a subscriber receives
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetStreams {
+    pub subscriber_id: usize,
+    pub streams: HashSet<MonitorStream>,
+}
+
 impl Handler<Subscribe> for SystemMonitor {
     type Result = usize;
 
     fn handle(&mut self, msg: Subscribe, _: &mut Self::Context) -> Self::Result {
         let id = self.next_subscriber_id();
-        self.subscribers.insert(id, msg.0);
+        self.subscribers.insert(
+            id,
+            Subscription {
+                subscriber: msg.0,
+                streams: MonitorStream::ALL.iter().cloned().collect(),
+            },
+        );
         id
     }
 }
 
+impl Handler<SetStreams> for SystemMonitor {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetStreams, _: &mut Self::Context) {
+        if let Some(subscription) = self.subscribers.get_mut(&msg.subscriber_id) {
+            subscription.streams = msg.streams;
+        }
+    }
+}
+
+impl Handler<Ping> for SystemMonitor {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Self::Context) {}
+}
+
+/// Apply new disk usage thresholds. The streams are only scheduled on
+/// start, so changes to them need a restart
+impl Handler<ReloadConfig> for SystemMonitor {
+    type Result = Result<()>;
+
+    fn handle(&mut self, ReloadConfig(config): ReloadConfig, _: &mut Self::Context) -> Result<()> {
+        if let Some(system_monitor) = config.system_monitor {
+            self.config = system_monitor;
+        }
+
+        Ok(())
+    }
+}
+
 impl Handler<Unsubscribe> for SystemMonitor {
     type Result = ();
 
@@ -205,7 +382,7 @@ mod test {
     }
 
     struct TestSubscriber {
-        updates: Vec<models::DiskUsage>,
+        updates: Vec<MonitorUpdate>,
     }
     impl TestSubscriber {
         pub fn new() -> Self {
@@ -215,10 +392,10 @@ mod test {
     impl Actor for TestSubscriber {
         type Context = Context<Self>;
     }
-    impl Handler<models::DiskUsage> for TestSubscriber {
+    impl Handler<MonitorUpdate> for TestSubscriber {
         type Result = ();
 
-        fn handle(&mut self, update: models::DiskUsage, _: &mut Self::Context) {
+        fn handle(&mut self, update: MonitorUpdate, _: &mut Self::Context) {
             self.updates.push(update)
         }
     }
@@ -233,33 +410,55 @@ mod test {
     struct TestSystemMonitorPorts {
         recorded_disk_usage: Vec<models::NewDiskUsage>,
         sent_alerts: Vec<BroadcastEvent>,
+        rolled_up_before: Vec<NaiveDateTime>,
+        deleted_tweets: Vec<(Option<NaiveDateTime>, Option<i64>)>,
     }
     impl TestSystemMonitorPorts {
         pub fn new() -> Self {
             Self {
                 recorded_disk_usage: vec![],
                 sent_alerts: vec![],
+                rolled_up_before: vec![],
+                deleted_tweets: vec![],
             }
         }
     }
     impl SystemMonitorPorts for Arc<Mutex<TestSystemMonitorPorts>> {
-        fn record_disk_usage(&self, disk_usage: models::NewDiskUsage) -> Result<models::DiskUsage> {
+        fn record_disk_usage(
+            &self,
+            disk_usage: models::NewDiskUsage,
+        ) -> BoxFuture<'static, Result<models::DiskUsage>> {
             self.lock()
                 .unwrap()
                 .recorded_disk_usage
                 .push(disk_usage.clone());
-            Ok(models::DiskUsage {
+            futures::future::ok(models::DiskUsage {
                 id: 0,
                 mount: disk_usage.mount,
                 percent_disk_used: disk_usage.percent_disk_used,
                 recorded_at: chrono::NaiveDateTime::from_timestamp(0, 0),
             })
+            .boxed()
         }
 
         fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
             self.lock().unwrap().sent_alerts.push(event);
             Ok(())
         }
+
+        fn roll_up_disk_usage(&self, before: NaiveDateTime) -> Result<()> {
+            self.lock().unwrap().rolled_up_before.push(before);
+            Ok(())
+        }
+
+        fn delete_old_tweets(
+            &self,
+            before: Option<NaiveDateTime>,
+            keep: Option<i64>,
+        ) -> Result<()> {
+            self.lock().unwrap().deleted_tweets.push((before, keep));
+            Ok(())
+        }
     }
 
     fn test_monitor(ports: Arc<Mutex<TestSystemMonitorPorts>>) -> SystemMonitor {
@@ -270,6 +469,8 @@ mod test {
                     available_space_alert_above: 0.0,
                 }],
                 tick_ms: 10,
+                backfill_minutes: None,
+                rollup_after_days: Some(30),
             },
             vec![ScheduledStreamConfig {
                 message: ScheduledStreamMessage::CheckDiskUsage,
@@ -329,7 +530,7 @@ mod test {
                 delay_for(Duration::from_millis(30)).await;
                 let msg = subscriber.send(GetState).await.unwrap();
 
-                let updates: Vec<models::DiskUsage> = serde_json::from_str(&msg).unwrap();
+                let updates: Vec<MonitorUpdate> = serde_json::from_str(&msg).unwrap();
                 assert!(updates.len() == 3);
 
                 System::current().stop();
@@ -337,4 +538,87 @@ mod test {
         })
         .unwrap()
     }
+
+    #[test]
+    fn system_monitor_only_sends_chosen_streams() {
+        System::run(|| {
+            let monitor = test_monitor(Arc::new(Mutex::new(TestSystemMonitorPorts::new()))).start();
+            let subscriber = TestSubscriber::new().start();
+
+            actix_rt::spawn(async move {
+                let subscriber_id = monitor
+                    .send(Subscribe(Addr::recipient(subscriber.clone())))
+                    .await
+                    .unwrap();
+                monitor
+                    .send(SetStreams {
+                        subscriber_id,
+                        streams: HashSet::new(),
+                    })
+                    .await
+                    .unwrap();
+                let before = subscriber.send(GetState).await.unwrap();
+
+                delay_for(Duration::from_millis(30)).await;
+                let after = subscriber.send(GetState).await.unwrap();
+                assert_eq!(before, after);
+
+                System::current().stop();
+            })
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn system_monitor_rolls_up_disk_usage_older_than_configured() {
+        let ports = Arc::new(Mutex::new(TestSystemMonitorPorts::new()));
+        let monitor = test_monitor(Arc::clone(&ports));
+
+        monitor.roll_up_disk_usage().unwrap();
+
+        let expected = Utc::now().naive_utc() - chrono::Duration::days(30);
+        let rolled_up_before = ports.lock().unwrap().rolled_up_before.clone();
+        assert_eq!(rolled_up_before.len(), 1);
+        assert!((expected - rolled_up_before[0]).num_seconds().abs() < 5);
+    }
+
+    #[test]
+    fn system_monitor_keeps_disk_usage_without_rollup_age() {
+        let ports = Arc::new(Mutex::new(TestSystemMonitorPorts::new()));
+        let mut monitor = test_monitor(Arc::clone(&ports));
+        monitor.config.rollup_after_days = None;
+
+        monitor.roll_up_disk_usage().unwrap();
+
+        assert!(ports.lock().unwrap().rolled_up_before.is_empty());
+    }
+
+    #[test]
+    fn system_monitor_deletes_tweets_past_retention() {
+        let ports = Arc::new(Mutex::new(TestSystemMonitorPorts::new()));
+        let mut monitor = test_monitor(Arc::clone(&ports));
+        monitor.tweet_retention = Some(TweetRetentionConfig {
+            max_age_days: Some(30),
+            max_rows: Some(1_000_000),
+        });
+
+        monitor.delete_old_tweets().unwrap();
+
+        let expected = Utc::now().naive_utc() - chrono::Duration::days(30);
+        let deleted_tweets = ports.lock().unwrap().deleted_tweets.clone();
+        assert_eq!(deleted_tweets.len(), 1);
+        let (before, keep) = deleted_tweets[0];
+        assert!((expected - before.unwrap()).num_seconds().abs() < 5);
+        assert_eq!(keep, Some(1_000_000));
+    }
+
+    #[test]
+    fn system_monitor_keeps_tweets_without_retention() {
+        let ports = Arc::new(Mutex::new(TestSystemMonitorPorts::new()));
+        let monitor = test_monitor(Arc::clone(&ports));
+
+        monitor.delete_old_tweets().unwrap();
+
+        assert!(ports.lock().unwrap().deleted_tweets.is_empty());
+    }
 }
@@ -1,33 +1,162 @@
-use std::collections::{HashMap, VecDeque};
+mod sentiment;
+
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
+use std::time::Duration;
 
-use actix::{Actor, Context};
-use chrono::NaiveDateTime;
+use actix::{Actor, Context, Handler};
+use chrono::{NaiveDateTime, Utc};
 use egg_mode::{stream::TwitterStream, KeyPair, Token};
+use futures::Future;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    config::{config, TwitterConfig},
-    db::{database, models},
+    config::{config, TwitterConfig, TwitterTerms},
+    db::{self, models},
     error::Result,
-    services::broadcast::{BroadcastEvent, OUTBOX},
+    services::{
+        broadcast::{BroadcastEvent, Outbox},
+        Ping,
+    },
 };
 
 const MAX_TWEETS_TO_SEND: usize = 100;
 
+/// The languages that tweets are streamed in unless configured otherwise
+const DEFAULT_LANGUAGES: &[&str] = &["en"];
+
+/// The window that tweets are counted in unless configured otherwise
+const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+/// The number of tweets whose sentiment is averaged unless configured
+/// otherwise
+const DEFAULT_SENTIMENT_SAMPLE_SIZE: usize = 20;
+
+/// The number of users and tweets included in each group's activity
+const TOP_USERS: usize = 3;
+const SAMPLE_TWEETS: usize = 3;
+
+/// Tweet volume in a group over a period, for the digest
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GroupActivity {
+    pub group_name: String,
+    pub tweet_count: usize,
+    /// The users who tweeted the most, and how many times
+    pub top_users: Vec<(String, usize)>,
+    /// The most favorited and retweeted tweets
+    pub sample_tweets: Vec<String>,
+}
+
+impl GroupActivity {
+    /// A summary line, e.g. `rust: 120 tweets, most from @rustlang (12)`,
+    /// followed by a line for each sample tweet
+    pub fn describe(&self) -> Vec<String> {
+        let mut summary = format!("{}: {} tweets", self.group_name, self.tweet_count);
+        if !self.top_users.is_empty() {
+            let top_users = self
+                .top_users
+                .iter()
+                .map(|(username, count)| format!("@{} ({})", username, count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            summary = format!("{}, most from {}", summary, top_users);
+        }
+
+        let mut lines = vec![summary];
+        lines.extend(
+            self.sample_tweets
+                .iter()
+                .map(|text| format!("\"{}\"", text)),
+        );
+        lines
+    }
+}
+
+/// The activity in each configured group since `since`, from the
+/// recorded tweets
+pub fn activity_since(since: NaiveDateTime) -> impl Future<Output = Result<Vec<GroupActivity>>> {
+    let now = Utc::now().naive_utc();
+    let group_names = config()
+        .twitter
+        .map(|twitter| {
+            twitter
+                .terms
+                .into_iter()
+                .map(|terms| terms.group_name)
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+
+    db::run(move |database| {
+        let mut tweets = vec![];
+        for group_name in group_names {
+            tweets.extend(database.tweets_for_group_between(&group_name, since, now)?);
+        }
+        Ok(activity(tweets))
+    })
+}
+
+/// The activity in each group, in order of group name
+fn activity(tweets: Vec<models::Tweet>) -> Vec<GroupActivity> {
+    let mut groups: HashMap<String, Vec<models::Tweet>> = HashMap::new();
+    for tweet in tweets {
+        groups
+            .entry(tweet.group_name.clone())
+            .or_default()
+            .push(tweet);
+    }
+
+    let mut activity = groups
+        .into_iter()
+        .map(|(group_name, mut tweets)| {
+            let mut users: HashMap<&str, usize> = HashMap::new();
+            for username in tweets.iter().filter_map(|tweet| tweet.username.as_deref()) {
+                *users.entry(username).or_default() += 1;
+            }
+            let mut top_users = users
+                .into_iter()
+                .map(|(username, count)| (username.to_string(), count))
+                .collect::<Vec<_>>();
+            top_users.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            top_users.truncate(TOP_USERS);
+
+            let tweet_count = tweets.len();
+            tweets.sort_by_key(|tweet| -(tweet.favorite_count + tweet.retweet_count));
+            GroupActivity {
+                group_name,
+                tweet_count,
+                top_users,
+                sample_tweets: tweets
+                    .into_iter()
+                    .take(SAMPLE_TWEETS)
+                    .map(|tweet| tweet.text)
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    activity.sort_by(|a, b| a.group_name.cmp(&b.group_name));
+    activity
+}
+
 trait TwitterPorts {
-    fn record_tweet(&self, tweet: models::NewTweet) -> Result<models::Tweet>;
+    fn record_tweet(&self, tweet: models::NewTweet) -> Result<()>;
 
     fn send_alert(&self, event: BroadcastEvent) -> Result<()>;
 }
 
-struct LiveTwitterPorts;
+struct LiveTwitterPorts {
+    outbox: Outbox,
+}
 impl TwitterPorts for LiveTwitterPorts {
-    fn record_tweet(&self, tweet: models::NewTweet) -> Result<models::Tweet> {
-        database().insert_tweet(tweet)
+    fn record_tweet(&self, tweet: models::NewTweet) -> Result<()> {
+        db::spawn_write("recording a tweet", move |database| {
+            database.insert_tweet(tweet).map(|_| ())
+        });
+        Ok(())
     }
 
     fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
-        OUTBOX.push(event).map_err(Into::into)
+        self.outbox.push(event)
     }
 }
 
@@ -35,17 +164,154 @@ impl TwitterPorts for LiveTwitterPorts {
 pub struct Twitter {
     config: TwitterConfig,
     ports: Arc<Box<dyn TwitterPorts>>,
-    popular_tweets: HashMap<String, Vec<models::NewTweet>>,
-    tweets_per_second: HashMap<String, VecDeque<NaiveDateTime>>,
+    /// The tweets in each group within the group's window, oldest
+    /// first
+    recent_tweets: HashMap<String, VecDeque<models::Tweet>>,
+    /// Groups that are over their max count, so that each spike is
+    /// only alerted once
+    spiking: HashSet<String>,
+    /// The sentiment of each group's most recent tweets, oldest first
+    recent_sentiments: HashMap<String, VecDeque<f64>>,
+    /// Groups whose average sentiment is below their threshold, so
+    /// that each negative turn is only alerted once
+    negative: HashSet<String>,
 }
 
 impl Twitter {
-    pub fn new() -> Option<Self> {
-        config().twitter.map(|twitter_config| Self {
-            config: twitter_config,
-            ports: Arc::new(Box::new(LiveTwitterPorts)),
-            popular_tweets: HashMap::new(),
-            tweets_per_second: HashMap::new(),
+    pub fn new(outbox: Outbox) -> Option<Self> {
+        config().twitter.map(|twitter_config| {
+            Self::with_ports(
+                twitter_config,
+                Arc::new(Box::new(LiveTwitterPorts { outbox })),
+            )
+        })
+    }
+
+    fn with_ports(config: TwitterConfig, ports: Arc<Box<dyn TwitterPorts>>) -> Self {
+        Self {
+            config,
+            ports,
+            recent_tweets: HashMap::new(),
+            spiking: HashSet::new(),
+            recent_sentiments: HashMap::new(),
+            negative: HashSet::new(),
+        }
+    }
+
+    /// Record a tweet, scoring its sentiment if configured to, and
+    /// check whether its group is spiking or turning negative. Tweets
+    /// outside of the group's bounding box are ignored
+    pub fn handle_tweet(&mut self, mut tweet: models::NewTweet) -> Result<()> {
+        let terms = match self
+            .config
+            .terms
+            .iter()
+            .find(|terms| terms.group_name == tweet.group_name)
+        {
+            Some(terms) => terms.clone(),
+            None => return Ok(()),
+        };
+        if let Some(bounding_box) = terms.bounding_box {
+            let inside = match (tweet.latitude, tweet.longitude) {
+                (Some(latitude), Some(longitude)) => bounding_box.contains(latitude, longitude),
+                _ => false,
+            };
+            if !inside {
+                return Ok(());
+            }
+        }
+        if self.config.score_sentiment.unwrap_or(false) || terms.sentiment_alert_below.is_some() {
+            tweet.sentiment = Some(sentiment::score(&tweet.text));
+        }
+
+        self.ports.record_tweet(tweet.clone())?;
+        let tweet = unsaved(tweet);
+        self.check_sentiment(&terms, &tweet)?;
+        self.check_spike(&terms, tweet)
+    }
+
+    /// Count the tweets in the group within the group's window,
+    /// alerting with the most recent of them when the count first goes
+    /// over the group's max count
+    fn check_spike(&mut self, terms: &TwitterTerms, tweet: models::Tweet) -> Result<()> {
+        let max_count = match terms.max_count {
+            Some(max_count) => max_count,
+            None => return Ok(()),
+        };
+
+        let window = chrono::Duration::from_std(window(terms)).unwrap();
+        let group_name = tweet.group_name.clone();
+        let window_start = tweet.tweeted_at - window;
+        let recent_tweets = self.recent_tweets.entry(group_name.clone()).or_default();
+        recent_tweets.push_back(tweet);
+        while recent_tweets
+            .front()
+            .map_or(false, |oldest| oldest.tweeted_at < window_start)
+        {
+            recent_tweets.pop_front();
+        }
+
+        let current_count = recent_tweets.len() as i64;
+        if current_count <= max_count {
+            self.spiking.remove(&group_name);
+            return Ok(());
+        }
+        if !self.spiking.insert(group_name.clone()) {
+            return Ok(());
+        }
+
+        let tweets = recent_tweets
+            .iter()
+            .rev()
+            .take(MAX_TWEETS_TO_SEND)
+            .cloned()
+            .collect();
+        self.ports.send_alert(BroadcastEvent::TwitterAlert {
+            group_name,
+            current_count,
+            max_count,
+            tweets,
+        })
+    }
+
+    /// Average the sentiment of the group's most recent tweets,
+    /// alerting when a full sample first falls below the group's
+    /// threshold
+    fn check_sentiment(&mut self, terms: &TwitterTerms, tweet: &models::Tweet) -> Result<()> {
+        let (threshold, sentiment) = match (terms.sentiment_alert_below, tweet.sentiment) {
+            (Some(threshold), Some(sentiment)) => (threshold, sentiment),
+            _ => return Ok(()),
+        };
+
+        let sample_size = terms
+            .sentiment_sample_size
+            .unwrap_or(DEFAULT_SENTIMENT_SAMPLE_SIZE);
+        let sentiments = self
+            .recent_sentiments
+            .entry(terms.group_name.clone())
+            .or_default();
+        sentiments.push_back(sentiment);
+        while sentiments.len() > sample_size {
+            sentiments.pop_front();
+        }
+        if sentiments.len() < sample_size {
+            return Ok(());
+        }
+
+        let average_sentiment = sentiments.iter().sum::<f64>() / sentiments.len() as f64;
+        if average_sentiment >= threshold {
+            self.negative.remove(&terms.group_name);
+            return Ok(());
+        }
+        if !self.negative.insert(terms.group_name.clone()) {
+            return Ok(());
+        }
+
+        self.ports.send_alert(BroadcastEvent::TwitterSentiment {
+            group_name: terms.group_name.clone(),
+            average_sentiment,
+            threshold,
+            sample_size,
         })
     }
 
@@ -65,19 +331,60 @@ impl Twitter {
         }
     }
 
+    /// The group's languages, falling back to the languages for every
+    /// group
+    fn languages(&self, terms: &TwitterTerms) -> Vec<String> {
+        terms
+            .languages
+            .as_ref()
+            .or_else(|| self.config.languages.as_ref())
+            .cloned()
+            .unwrap_or_else(|| {
+                DEFAULT_LANGUAGES
+                    .iter()
+                    .map(|language| language.to_string())
+                    .collect()
+            })
+    }
+
     pub fn filter_streams(&self) -> impl Iterator<Item = (String, TwitterStream)> + '_ {
         self.config.terms.iter().map(move |terms| {
             (
                 terms.group_name.clone(),
                 egg_mode::stream::filter()
+                    .follow(&terms.follow)
                     .track(&terms.terms)
-                    .language(&["en"])
+                    .language(&self.languages(terms))
                     .start(&self.get_token()),
             )
         })
     }
 }
 
+/// A tweet as it is counted while it is being written in the
+/// background, before it has been given an id
+fn unsaved(tweet: models::NewTweet) -> models::Tweet {
+    models::Tweet {
+        id: 0,
+        twitter_tweet_id: tweet.twitter_tweet_id,
+        group_name: tweet.group_name,
+        latitude: tweet.latitude,
+        longitude: tweet.longitude,
+        favorite_count: tweet.favorite_count,
+        retweet_count: tweet.retweet_count,
+        username: tweet.username,
+        lang: tweet.lang,
+        text: tweet.text,
+        tweeted_at: tweet.tweeted_at,
+        matched_by: tweet.matched_by,
+        sentiment: tweet.sentiment,
+    }
+}
+
+fn window(terms: &TwitterTerms) -> Duration {
+    terms.window.unwrap_or(DEFAULT_WINDOW)
+}
+
 impl Actor for Twitter {
     type Context = Context<Self>;
 
@@ -85,6 +392,12 @@ impl Actor for Twitter {
     /// streaming websocket
     fn started(&mut self, ctx: &mut Context<Self>) {
         // let twitter = self.clone();
+        // let follow_by_group = self
+        //     .config
+        //     .terms
+        //     .iter()
+        //     .map(|terms| (terms.group_name.clone(), terms.follow.clone()))
+        //     .collect::<HashMap<_, _>>();
         // self.filter_streams().for_each(move |(group_name, stream)| {
         //     let twitter = twitter.clone();
         //     let group_name_clone = group_name.clone();
@@ -102,14 +415,11 @@ impl Actor for Twitter {
         //                 if let StreamMessage::Tweet(egg_mode_tweet) = message {
         //                     let tweet = models::NewTweet::from_egg_mode_tweet(
         //                         group_name.clone(),
+        //                         &follow_by_group[&group_name],
         //                         egg_mode_tweet,
         //                     );
-        //                     if len(twitter.most_popular_tweets) >= MAX_TWEETS_TO_SEND {
-        //                         let least_popular =
-        //                     }
-
-        //                     if let Err(e) = twitter.ports.record_tweet(tweet) {
-        //                         log::error!("Error encountered when recording tweet: {:?}", e)
+        //                     if let Err(e) = twitter.handle_tweet(tweet) {
+        //                         log::error!("Error encountered when handling tweet: {:?}", e)
         //                     }
         //                 }
 
@@ -119,3 +429,293 @@ impl Actor for Twitter {
         // });
     }
 }
+
+impl Handler<Ping> for Twitter {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Context<Self>) {}
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::config::BoundingBox;
+
+    struct TestTwitterPorts {
+        recorded_tweets: Arc<Mutex<Vec<models::NewTweet>>>,
+        sent_alerts: Arc<Mutex<Vec<BroadcastEvent>>>,
+    }
+    impl TwitterPorts for TestTwitterPorts {
+        fn record_tweet(&self, tweet: models::NewTweet) -> Result<()> {
+            self.recorded_tweets.lock().unwrap().push(tweet);
+            Ok(())
+        }
+
+        fn send_alert(&self, event: BroadcastEvent) -> Result<()> {
+            self.sent_alerts.lock().unwrap().push(event);
+            Ok(())
+        }
+    }
+
+    fn tweet(seconds: u32) -> models::NewTweet {
+        models::NewTweet {
+            twitter_tweet_id: seconds.to_string(),
+            group_name: "rust".to_string(),
+            latitude: None,
+            longitude: None,
+            favorite_count: 0,
+            retweet_count: 0,
+            username: None,
+            lang: Some("en".to_string()),
+            text: "rust 1.42 is out".to_string(),
+            tweeted_at: NaiveDate::from_ymd(2020, 3, 12).and_hms(12, 0, seconds),
+            matched_by: "keyword".to_string(),
+            sentiment: None,
+        }
+    }
+
+    #[test]
+    fn twitter_alerts_once_when_a_group_spikes() {
+        let sent_alerts = Arc::new(Mutex::new(vec![]));
+        let config = TwitterConfig {
+            consumer_key: String::new(),
+            consumer_secret: String::new(),
+            access_key: String::new(),
+            access_secret: String::new(),
+            languages: None,
+            score_sentiment: None,
+            include_in_digest: None,
+            retention: None,
+            terms: vec![TwitterTerms {
+                group_name: "rust".to_string(),
+                terms: vec!["rust".to_string()],
+                follow: vec![],
+                languages: None,
+                max_count: Some(2),
+                window: Some(Duration::from_secs(10)),
+                sentiment_alert_below: None,
+                sentiment_sample_size: None,
+                bounding_box: None,
+            }],
+        };
+        let mut twitter = Twitter::with_ports(
+            config,
+            Arc::new(Box::new(TestTwitterPorts {
+                recorded_tweets: Arc::new(Mutex::new(vec![])),
+                sent_alerts: sent_alerts.clone(),
+            })),
+        );
+
+        // spread out tweets stay under the max count
+        for seconds in &[0, 20, 40] {
+            twitter.handle_tweet(tweet(*seconds)).unwrap();
+        }
+        assert!(sent_alerts.lock().unwrap().is_empty());
+
+        for seconds in &[50, 51, 52, 53] {
+            twitter.handle_tweet(tweet(*seconds)).unwrap();
+        }
+
+        let sent_alerts = sent_alerts.lock().unwrap();
+        assert_eq!(sent_alerts.len(), 1);
+        match &sent_alerts[0] {
+            BroadcastEvent::TwitterAlert {
+                current_count,
+                tweets,
+                ..
+            } => {
+                assert_eq!(*current_count, 3);
+                assert_eq!(tweets.len(), 3);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn twitter_alerts_once_when_a_group_turns_negative() {
+        let sent_alerts = Arc::new(Mutex::new(vec![]));
+        let config = TwitterConfig {
+            consumer_key: String::new(),
+            consumer_secret: String::new(),
+            access_key: String::new(),
+            access_secret: String::new(),
+            languages: None,
+            score_sentiment: None,
+            include_in_digest: None,
+            retention: None,
+            terms: vec![TwitterTerms {
+                group_name: "rust".to_string(),
+                terms: vec!["rust".to_string()],
+                follow: vec![],
+                languages: None,
+                max_count: None,
+                window: None,
+                sentiment_alert_below: Some(-0.5),
+                sentiment_sample_size: Some(2),
+                bounding_box: None,
+            }],
+        };
+        let mut twitter = Twitter::with_ports(
+            config,
+            Arc::new(Box::new(TestTwitterPorts {
+                recorded_tweets: Arc::new(Mutex::new(vec![])),
+                sent_alerts: sent_alerts.clone(),
+            })),
+        );
+
+        let texts = &[
+            "the new release is great",
+            "the build is broken",
+            "this outage is terrible",
+            "still down, awful",
+        ];
+        for (seconds, text) in texts.iter().enumerate() {
+            let mut tweet = tweet(seconds as u32);
+            tweet.text = text.to_string();
+            twitter.handle_tweet(tweet).unwrap();
+        }
+
+        let sent_alerts = sent_alerts.lock().unwrap();
+        assert_eq!(sent_alerts.len(), 1);
+        match &sent_alerts[0] {
+            BroadcastEvent::TwitterSentiment {
+                average_sentiment, ..
+            } => assert_eq!(*average_sentiment, -1.0),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn twitter_scores_tweets_when_configured_to() {
+        let recorded_tweets = Arc::new(Mutex::new(vec![]));
+        let config = TwitterConfig {
+            consumer_key: String::new(),
+            consumer_secret: String::new(),
+            access_key: String::new(),
+            access_secret: String::new(),
+            languages: None,
+            score_sentiment: Some(true),
+            include_in_digest: None,
+            retention: None,
+            terms: vec![TwitterTerms {
+                group_name: "rust".to_string(),
+                terms: vec!["rust".to_string()],
+                follow: vec![],
+                languages: None,
+                max_count: None,
+                window: None,
+                sentiment_alert_below: None,
+                sentiment_sample_size: None,
+                bounding_box: None,
+            }],
+        };
+        let mut twitter = Twitter::with_ports(
+            config,
+            Arc::new(Box::new(TestTwitterPorts {
+                recorded_tweets: recorded_tweets.clone(),
+                sent_alerts: Arc::new(Mutex::new(vec![])),
+            })),
+        );
+
+        let mut tweet = tweet(0);
+        tweet.text = "the new release is great".to_string();
+        twitter.handle_tweet(tweet).unwrap();
+
+        let recorded_tweets = recorded_tweets.lock().unwrap();
+        assert_eq!(recorded_tweets.len(), 1);
+        assert_eq!(recorded_tweets[0].sentiment, Some(1.0));
+    }
+
+    #[test]
+    fn twitter_ignores_tweets_outside_of_the_bounding_box() {
+        let sent_alerts = Arc::new(Mutex::new(vec![]));
+        let config = TwitterConfig {
+            consumer_key: String::new(),
+            consumer_secret: String::new(),
+            access_key: String::new(),
+            access_secret: String::new(),
+            languages: None,
+            score_sentiment: None,
+            include_in_digest: None,
+            retention: None,
+            terms: vec![TwitterTerms {
+                group_name: "rust".to_string(),
+                terms: vec!["rust".to_string()],
+                follow: vec![],
+                languages: None,
+                max_count: Some(1),
+                window: None,
+                sentiment_alert_below: None,
+                sentiment_sample_size: None,
+                bounding_box: Some(BoundingBox {
+                    west: -71.2,
+                    south: 42.2,
+                    east: -70.9,
+                    north: 42.5,
+                }),
+            }],
+        };
+        let mut twitter = Twitter::with_ports(
+            config,
+            Arc::new(Box::new(TestTwitterPorts {
+                recorded_tweets: Arc::new(Mutex::new(vec![])),
+                sent_alerts: sent_alerts.clone(),
+            })),
+        );
+
+        let located = |seconds, latitude, longitude| models::NewTweet {
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            ..tweet(seconds)
+        };
+        twitter.handle_tweet(tweet(0)).unwrap();
+        twitter.handle_tweet(located(1, 40.7, -74.0)).unwrap();
+        twitter.handle_tweet(located(2, 42.36, -71.06)).unwrap();
+        assert!(sent_alerts.lock().unwrap().is_empty());
+
+        twitter.handle_tweet(located(3, 42.35, -71.05)).unwrap();
+        assert_eq!(sent_alerts.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn activity_counts_tweets_and_top_users_per_group() {
+        let recorded = |seconds, group_name: &str, username: &str, favorite_count| models::Tweet {
+            id: 0,
+            twitter_tweet_id: seconds.to_string(),
+            group_name: group_name.to_string(),
+            latitude: None,
+            longitude: None,
+            favorite_count,
+            retweet_count: 0,
+            username: Some(username.to_string()),
+            lang: Some("en".to_string()),
+            text: format!("tweet {}", seconds),
+            tweeted_at: NaiveDate::from_ymd(2020, 3, 12).and_hms(12, 0, seconds),
+            matched_by: "keyword".to_string(),
+            sentiment: None,
+        };
+        let tweets = vec![
+            recorded(0, "rust", "ferris", 1),
+            recorded(1, "rust", "ferris", 10),
+            recorded(2, "rust", "bors", 5),
+            recorded(3, "go", "gopher", 0),
+        ];
+
+        let activity = activity(tweets);
+        assert_eq!(activity.len(), 2);
+        assert_eq!(activity[0].group_name, "go");
+        assert_eq!(activity[1].tweet_count, 3);
+        assert_eq!(
+            activity[1].top_users,
+            vec![("ferris".to_string(), 2), ("bors".to_string(), 1)]
+        );
+        assert_eq!(
+            activity[1].sample_tweets,
+            vec!["tweet 1", "tweet 2", "tweet 0"]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+const POSITIVE: &[&str] = &[
+    "amazing",
+    "awesome",
+    "best",
+    "better",
+    "love",
+    "loved",
+    "loves",
+    "great",
+    "good",
+    "happy",
+    "excellent",
+    "fantastic",
+    "fast",
+    "fixed",
+    "glad",
+    "impressive",
+    "like",
+    "nice",
+    "perfect",
+    "recommend",
+    "resolved",
+    "restored",
+    "smooth",
+    "thanks",
+    "thank",
+    "win",
+    "wonderful",
+    "works",
+    "working",
+    "stable",
+    "reliable",
+    "helpful",
+    "fun",
+    "cool",
+    "beautiful",
+    "success",
+];
+
+const NEGATIVE: &[&str] = &[
+    "awful",
+    "bad",
+    "broken",
+    "bug",
+    "crash",
+    "crashed",
+    "dead",
+    "delay",
+    "delayed",
+    "disaster",
+    "down",
+    "fail",
+    "failed",
+    "failing",
+    "failure",
+    "hate",
+    "horrible",
+    "issue",
+    "lost",
+    "outage",
+    "outages",
+    "poor",
+    "problem",
+    "slow",
+    "terrible",
+    "unreliable",
+    "unusable",
+    "worse",
+    "worst",
+    "angry",
+    "annoying",
+    "sad",
+    "sucks",
+    "wrong",
+    "error",
+    "blackout",
+    "scam",
+];
+
+/// Words that flip the sentiment of the word after them
+const NEGATIONS: &[&str] = &[
+    "not", "no", "never", "isn't", "wasn't", "don't", "doesn't", "can't",
+];
+
+/// A lexicon based sentiment score for short texts like tweets, from
+/// -1 (entirely negative) to 1 (entirely positive), or 0 if none of its
+/// words carry a sentiment
+pub fn score(text: &str) -> f64 {
+    let mut positive = 0.0;
+    let mut negative = 0.0;
+    let mut negated = false;
+    for word in text.split_whitespace().map(|word| {
+        word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+            .to_lowercase()
+    }) {
+        let sentiment = if POSITIVE.contains(&word.as_str()) {
+            1.0
+        } else if NEGATIVE.contains(&word.as_str()) {
+            -1.0
+        } else {
+            negated = NEGATIONS.contains(&word.as_str());
+            continue;
+        };
+
+        let sentiment = if negated { -sentiment } else { sentiment };
+        if sentiment > 0.0 {
+            positive += 1.0;
+        } else {
+            negative += 1.0;
+        }
+        negated = false;
+    }
+
+    if positive + negative == 0.0 {
+        0.0
+    } else {
+        (positive - negative) / (positive + negative)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn score_is_positive_for_positive_text() {
+        assert_eq!(score("Love the new release, it works great!"), 1.0);
+    }
+
+    #[test]
+    fn score_is_negative_for_negative_text() {
+        assert_eq!(score("The site is DOWN again. Terrible outage."), -1.0);
+    }
+
+    #[test]
+    fn score_is_neutral_without_sentiment_words() {
+        assert_eq!(score("rust 1.42 is out"), 0.0);
+        assert_eq!(score(""), 0.0);
+    }
+
+    #[test]
+    fn score_balances_mixed_text() {
+        assert_eq!(score("great release but a bad bug"), -1.0 / 3.0);
+    }
+
+    #[test]
+    fn score_flips_negated_words() {
+        assert_eq!(score("this is not good"), -1.0);
+        assert_eq!(score("it doesn't crash anymore"), 1.0);
+        // a negation only applies to the word right after it
+        assert_eq!(score("not really good"), 1.0);
+    }
+}
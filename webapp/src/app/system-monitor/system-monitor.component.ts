@@ -91,9 +91,12 @@ export class SystemMonitorComponent implements OnInit {
     ngOnInit() {
         this.socket = new WebSocket(WEBSOCKET_URL);
         this.socket.onmessage = (data: MessageEvent) => {
-            let message = new Message().deserialize(
-                JSON.parse(data.data)
-            );
+            let update = JSON.parse(data.data);
+            if (update.type !== 'disk_usage') {
+                return;
+            }
+
+            let message = new Message().deserialize(update.data);
             this.mounts.add(message.mount);
 
             this.adjustPlot(message.mount, message);